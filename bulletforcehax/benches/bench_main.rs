@@ -1,12 +1,11 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 use photon_core::*;
-use std::collections::HashMap;
 use std::convert::{TryFrom, TryInto};
 
 fn bench_photon_base(c: &mut Criterion) {
     c.bench_function("serialize OperationRequest with float value", |b| {
         b.iter(|| {
-            let mut params = HashMap::new();
+            let mut params = ParameterTable::new();
             params.insert(0x42, ProtocolValue::Float(13.37));
             let packet = PhotonPacket::OperationRequest(0x20, black_box(params));
             let _bytes: Vec<u8> = packet.try_into().unwrap();
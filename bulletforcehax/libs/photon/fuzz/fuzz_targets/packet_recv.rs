@@ -0,0 +1,9 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use photon::{Direction, Packet, Protocol};
+
+// Reading may fail, but must never panic
+fuzz_target!(|data: &[u8]| {
+    let _ = Packet::read_with_protocol(data, Direction::Recv, Protocol::GpBinaryV16);
+    let _ = Packet::read_with_protocol(data, Direction::Recv, Protocol::GpBinaryV18);
+});
@@ -0,0 +1,249 @@
+use super::*;
+use indexmap::IndexMap;
+use std::borrow::Cow;
+
+/// Generates a getter and a builder method for fields that are `Copy`.
+macro_rules! gen_copy_accessors {
+    ($struct_name:ident { $($(#[$attr:meta])* $field:ident: $type:ty),* $(,)? }) => {
+        paste::item! {
+            impl<'a> $struct_name<'a> {
+                $(
+                    $(#[$attr])*
+                    pub fn $field(&self) -> $type {
+                        self.$field
+                    }
+
+                    pub fn [<with_ $field>](mut self, $field: $type) -> Self {
+                        self.$field = $field;
+                        self
+                    }
+                )*
+            }
+        }
+    };
+}
+
+/// A value that can be read from a custom property using `get_custom`.
+pub trait FromProtocolValue: Sized {
+    /// Returns `None` if the value has a different type.
+    fn from_protocol_value(value: &ProtocolValue) -> Option<Self>;
+}
+
+macro_rules! impl_from_protocol_value {
+    ($type:ty, $protocol_type:path) => {
+        impl FromProtocolValue for $type {
+            fn from_protocol_value(value: &ProtocolValue) -> Option<Self> {
+                match value {
+                    $protocol_type(x) => Some(x.clone()),
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+impl_from_protocol_value!(bool, ProtocolValue::Bool);
+impl_from_protocol_value!(u8, ProtocolValue::Byte);
+impl_from_protocol_value!(i16, ProtocolValue::Short);
+impl_from_protocol_value!(i32, ProtocolValue::Integer);
+impl_from_protocol_value!(i64, ProtocolValue::Long);
+impl_from_protocol_value!(f32, ProtocolValue::Float);
+impl_from_protocol_value!(f64, ProtocolValue::Double);
+impl_from_protocol_value!(Vec<u8>, ProtocolValue::ByteArray);
+impl_from_protocol_value!(Vec<i32>, ProtocolValue::IntegerArray);
+
+impl FromProtocolValue for String {
+    fn from_protocol_value(value: &ProtocolValue) -> Option<Self> {
+        match value {
+            ProtocolValue::String(x) => Some(x.to_string()),
+            _ => None,
+        }
+    }
+}
+
+fn get_custom<T: FromProtocolValue>(custom_properties: &IndexMap<Cow<str>, ProtocolValue>, key: &str) -> Option<T> {
+    custom_properties.get(key).and_then(T::from_protocol_value)
+}
+
+gen_copy_accessors!(RoomInfo {
+    /// Max players that fit in this room. 0 for unlimited.
+    max_players: u8,
+    /// Allow other players to join
+    is_open: bool,
+    /// Does this room show in the lobby
+    is_visible: bool,
+    /// Current players in the room
+    player_count: u8,
+    cleanup_cache_on_leave: bool,
+    master_client_id: Option<i32>,
+    empty_room_ttl: i32,
+    player_ttl: i32,
+});
+
+impl<'a> RoomInfo<'a> {
+    /// Creates an empty room with the same defaults as Photon, which is open and visible.
+    pub fn new() -> Self {
+        RoomInfo {
+            max_players: 0,
+            is_open: true,
+            is_visible: true,
+            player_count: 0,
+            cleanup_cache_on_leave: true,
+            master_client_id: None,
+            custom_properties_lobby: vec![],
+            expected_users: vec![],
+            empty_room_ttl: 0,
+            player_ttl: 0,
+            custom_properties: IndexMap::new(),
+        }
+    }
+
+    /// The custom properties that are shown in the lobby
+    pub fn custom_properties_lobby(&self) -> &[Cow<'a, str>] {
+        &self.custom_properties_lobby
+    }
+
+    pub fn with_custom_properties_lobby(mut self, custom_properties_lobby: Vec<Cow<'a, str>>) -> Self {
+        self.custom_properties_lobby = custom_properties_lobby;
+        self
+    }
+
+    pub fn expected_users(&self) -> &[Cow<'a, str>] {
+        &self.expected_users
+    }
+
+    pub fn with_expected_users(mut self, expected_users: Vec<Cow<'a, str>>) -> Self {
+        self.expected_users = expected_users;
+        self
+    }
+
+    pub fn custom_properties(&self) -> &IndexMap<Cow<'a, str>, ProtocolValue<'a>> {
+        &self.custom_properties
+    }
+
+    /// Gets a custom property, or `None` if it is not present or has a different type.
+    pub fn get_custom<T: FromProtocolValue>(&self, key: &str) -> Option<T> {
+        get_custom(&self.custom_properties, key)
+    }
+
+    /// Adds or replaces a custom property
+    pub fn with_custom(mut self, key: impl Into<Cow<'a, str>>, value: ProtocolValue<'a>) -> Self {
+        self.custom_properties.insert(key.into(), value);
+        self
+    }
+}
+
+impl Default for RoomInfo<'_> {
+    fn default() -> Self {
+        RoomInfo::new()
+    }
+}
+
+gen_copy_accessors!(RoomOptions {
+    /// Max players that fit in this room. 0 for unlimited.
+    max_players: u8,
+    /// Allow other players to join
+    is_open: bool,
+    /// Does this room show in the lobby
+    is_visible: bool,
+    cleanup_cache_on_leave: bool,
+});
+
+impl<'a> RoomOptions<'a> {
+    /// Creates options with the same defaults as Photon, for a room that is open and visible.
+    pub fn new() -> Self {
+        RoomOptions {
+            max_players: 0,
+            is_open: true,
+            is_visible: true,
+            cleanup_cache_on_leave: true,
+            custom_properties_lobby: vec![],
+            custom_properties: IndexMap::new(),
+        }
+    }
+
+    /// The custom properties that are shown in the lobby
+    pub fn custom_properties_lobby(&self) -> &[Cow<'a, str>] {
+        &self.custom_properties_lobby
+    }
+
+    pub fn with_custom_properties_lobby(mut self, custom_properties_lobby: Vec<Cow<'a, str>>) -> Self {
+        self.custom_properties_lobby = custom_properties_lobby;
+        self
+    }
+
+    pub fn custom_properties(&self) -> &IndexMap<Cow<'a, str>, ProtocolValue<'a>> {
+        &self.custom_properties
+    }
+
+    /// Gets a custom property, or `None` if it is not present or has a different type.
+    pub fn get_custom<T: FromProtocolValue>(&self, key: &str) -> Option<T> {
+        get_custom(&self.custom_properties, key)
+    }
+
+    /// Adds or replaces a custom property
+    pub fn with_custom(mut self, key: impl Into<Cow<'a, str>>, value: ProtocolValue<'a>) -> Self {
+        self.custom_properties.insert(key.into(), value);
+        self
+    }
+}
+
+impl Default for RoomOptions<'_> {
+    fn default() -> Self {
+        RoomOptions::new()
+    }
+}
+
+gen_copy_accessors!(Player { is_inactive: Option<bool> });
+
+impl<'a> Player<'a> {
+    /// Creates a player without any properties
+    pub fn new() -> Self {
+        Player {
+            name: None,
+            user_id: None,
+            is_inactive: None,
+            custom_properties: IndexMap::new(),
+        }
+    }
+
+    /// The nickname of this player
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn with_name(mut self, name: impl Into<Cow<'a, str>>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    pub fn user_id(&self) -> Option<&str> {
+        self.user_id.as_deref()
+    }
+
+    pub fn with_user_id(mut self, user_id: impl Into<Cow<'a, str>>) -> Self {
+        self.user_id = Some(user_id.into());
+        self
+    }
+
+    pub fn custom_properties(&self) -> &IndexMap<Cow<'a, str>, ProtocolValue<'a>> {
+        &self.custom_properties
+    }
+
+    /// Gets a custom property, or `None` if it is not present or has a different type.
+    pub fn get_custom<T: FromProtocolValue>(&self, key: &str) -> Option<T> {
+        get_custom(&self.custom_properties, key)
+    }
+
+    /// Adds or replaces a custom property
+    pub fn with_custom(mut self, key: impl Into<Cow<'a, str>>, value: ProtocolValue<'a>) -> Self {
+        self.custom_properties.insert(key.into(), value);
+        self
+    }
+}
+
+impl Default for Player<'_> {
+    fn default() -> Self {
+        Player::new()
+    }
+}
@@ -0,0 +1,193 @@
+use super::*;
+use indexmap::IndexMap;
+
+/// One websocket connection of the client. The client keeps a connection to each server it is on, so a `Session` can be spread over
+/// multiple connections.
+///
+/// ```rust
+/// # use photon::*;
+/// let mut connections = Connections::new();
+/// let id = connections.open("wss://ns.exitgames.com:19093/photon/n", &Session::new());
+///
+/// let connection = connections.get_mut(id).unwrap();
+/// connection.record_frame(&[0xf3, 0x0, 0x1, 0x8, 0x0], Direction::Send);
+/// assert_eq!(connection.server(), Some(ServerType::NameServer));
+/// assert_eq!(connection.protocol(), Protocol::GpBinaryV18);
+/// assert_eq!(connection.frames_sent(), 1);
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Connection {
+    url: String,
+    /// The server on the other side, if known
+    server: Option<ServerType>,
+    /// The serialization protocol, detected from the `Init` packet
+    protocol: Protocol,
+    frames_sent: u64,
+    frames_received: u64,
+    bytes_sent: u64,
+    bytes_received: u64,
+    /// Frames that could not be read or written
+    errors: u64,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    encryption: Option<MitmEncryption>,
+}
+
+/// The open connections of the client, by id.
+#[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Connections {
+    next_id: u32,
+    connections: IndexMap<u32, Connection>,
+}
+
+impl Connection {
+    /// Creates a connection to `url`. The server is inferred from the address the session was sent to, or from the host name of the
+    /// NameServer.
+    pub fn new(url: impl Into<String>, session: &Session) -> Self {
+        let url = url.into();
+        let server = match session.next_server() {
+            Some((server, address)) if url.starts_with(address) => Some(server),
+            _ if Connection::host(&url).starts_with("ns.") => Some(ServerType::NameServer),
+            _ => None,
+        };
+
+        Connection {
+            url,
+            server,
+            protocol: Protocol::default(),
+            frames_sent: 0,
+            frames_received: 0,
+            bytes_sent: 0,
+            bytes_received: 0,
+            errors: 0,
+            encryption: None,
+        }
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// The server on the other side, if known
+    pub fn server(&self) -> Option<ServerType> {
+        self.server
+    }
+
+    /// The serialization protocol to read and write the packets of this connection with
+    pub fn protocol(&self) -> Protocol {
+        self.protocol
+    }
+
+    pub fn frames_sent(&self) -> u64 {
+        self.frames_sent
+    }
+
+    pub fn frames_received(&self) -> u64 {
+        self.frames_received
+    }
+
+    pub fn bytes_sent(&self) -> u64 {
+        self.bytes_sent
+    }
+
+    pub fn bytes_received(&self) -> u64 {
+        self.bytes_received
+    }
+
+    /// Frames that could not be read or written
+    pub fn errors(&self) -> u64 {
+        self.errors
+    }
+
+    /// Our own key exchange with both sides, if enabled
+    pub fn encryption(&self) -> Option<&MitmEncryption> {
+        self.encryption.as_ref()
+    }
+
+    pub fn encryption_mut(&mut self) -> Option<&mut MitmEncryption> {
+        self.encryption.as_mut()
+    }
+
+    /// Intercepts the key exchange of this connection. See `MitmEncryption::new`.
+    pub fn enable_encryption(&mut self, client_side_secret: &[u8], server_side_secret: &[u8]) {
+        self.encryption = Some(MitmEncryption::new(client_side_secret, server_side_secret));
+    }
+
+    /// Counts a frame as it passes through, and detects the protocol if it is an `Init` packet.
+    pub fn record_frame(&mut self, data: &[u8], direction: Direction) {
+        match direction {
+            Direction::Send => {
+                self.frames_sent += 1;
+                self.bytes_sent += data.len() as u64;
+            }
+            Direction::Recv => {
+                self.frames_received += 1;
+                self.bytes_received += data.len() as u64;
+            }
+        }
+
+        if let Some(protocol) = Protocol::detect(data) {
+            self.protocol = protocol;
+        }
+    }
+
+    pub fn record_error(&mut self) {
+        self.errors += 1;
+    }
+
+    /// Learns the server from the authentication flow. This should be called after `session` handled the packet.
+    pub fn handle(&mut self, packet: &Packet, session: &Session) {
+        if self.server.is_none() {
+            if let Packet::OperationRequest(Operation::AuthenticateRequestNoToken { .. }, _)
+            | Packet::OperationRequest(Operation::AuthenticateRequestToken { .. }, _) = packet
+            {
+                self.server = Some(session.server());
+            }
+        }
+    }
+
+    fn host(url: &str) -> &str {
+        let url = url.split("://").nth(1).unwrap_or(url);
+        url.split(['/', ':', '?']).next().unwrap_or(url)
+    }
+}
+
+impl Connections {
+    pub fn new() -> Self {
+        Connections::default()
+    }
+
+    /// Registers a new connection, and returns its id. Ids are not reused.
+    pub fn open(&mut self, url: impl Into<String>, session: &Session) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.connections.insert(id, Connection::new(url, session));
+        id
+    }
+
+    pub fn close(&mut self, id: u32) -> Option<Connection> {
+        self.connections.shift_remove(&id)
+    }
+
+    pub fn get(&self, id: u32) -> Option<&Connection> {
+        self.connections.get(&id)
+    }
+
+    pub fn get_mut(&mut self, id: u32) -> Option<&mut Connection> {
+        self.connections.get_mut(&id)
+    }
+
+    /// All open connections, in the order they were opened
+    pub fn iter(&self) -> impl Iterator<Item = (u32, &Connection)> {
+        self.connections.iter().map(|(id, connection)| (*id, connection))
+    }
+
+    pub fn len(&self) -> usize {
+        self.connections.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.connections.is_empty()
+    }
+}
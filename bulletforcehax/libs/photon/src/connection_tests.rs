@@ -0,0 +1,89 @@
+#![cfg(test)]
+
+use super::*;
+use crate::session_tests::authenticate;
+
+#[test]
+fn server_from_url() {
+    let mut session = Session::new();
+    assert_eq!(
+        Connection::new("wss://ns.exitgames.com:19093/photon/n", &session).server(),
+        Some(ServerType::NameServer)
+    );
+    assert_eq!(Connection::new("wss://somewhere:19090", &session).server(), None);
+
+    session.handle(&Packet::OperationResponse {
+        parameters: Operation::AuthenticateResponseName {
+            user_id: None,
+            nickname: None,
+            encryption_data: None,
+            custom_data: None,
+            cluster: None,
+            address: "wss://master:19090".into(),
+        },
+        return_code: 0,
+        debug_string: None,
+        secret: None,
+        order: ParameterOrder::default(),
+    });
+    assert_eq!(
+        Connection::new("wss://master:19090/?libversion=4.1.6.10", &session).server(),
+        Some(ServerType::MasterServer)
+    );
+}
+
+#[test]
+fn server_from_authentication() {
+    let mut session = Session::new();
+    let mut connection = Connection::new("wss://somewhere:19090", &session);
+
+    connection.handle(
+        &Packet::OperationRequest(Operation::LeaveRequest { is_inactive: None }, ParameterOrder::default()),
+        &session,
+    );
+    assert_eq!(connection.server(), None);
+
+    session.handle(&authenticate());
+    connection.handle(&authenticate(), &session);
+    assert_eq!(connection.server(), Some(ServerType::NameServer));
+}
+
+#[test]
+fn counters_and_protocol() {
+    let mut connection = Connection::new("wss://somewhere", &Session::new());
+
+    connection.record_frame(&[0xf3, 0x0, 0x1, 0x8, 0x0], Direction::Send);
+    connection.record_frame(&[0xf3, 0x1, 0x0, 0x1], Direction::Recv);
+    connection.record_frame(&[0xf3, 0x6, 0x1, 0x0, 0x1, 0x1, 0x69, 0x0, 0x0, 0x2d, 0x51], Direction::Send);
+    connection.record_error();
+
+    assert_eq!(connection.protocol(), Protocol::GpBinaryV18);
+    assert_eq!(connection.frames_sent(), 2);
+    assert_eq!(connection.bytes_sent(), 16);
+    assert_eq!(connection.frames_received(), 1);
+    assert_eq!(connection.bytes_received(), 4);
+    assert_eq!(connection.errors(), 1);
+}
+
+#[test]
+fn open_and_close() {
+    let session = Session::new();
+    let mut connections = Connections::new();
+
+    let first = connections.open("wss://first", &session);
+    let second = connections.open("wss://second", &session);
+    assert_ne!(first, second);
+    assert_eq!(connections.len(), 2);
+
+    assert_eq!(
+        connections.close(first).map(|connection| connection.url().to_string()),
+        Some("wss://first".into())
+    );
+    assert!(connections.close(first).is_none());
+    assert!(connections.get(first).is_none());
+
+    // ids are not reused
+    let third = connections.open("wss://third", &session);
+    assert_ne!(third, first);
+    assert_eq!(connections.iter().map(|(id, _)| id).collect::<Vec<_>>(), [second, third]);
+}
@@ -0,0 +1,272 @@
+//! Photon's payload encryption. Both sides send a Diffie-Hellman public key using `InternalOperation::InitEncryptionRequest` and
+//! `InternalOperation::InitEncryptionResponse`, and hash the shared secret with SHA-256 to get the key for AES-256-CBC with an IV of
+//! zeroes. Encrypted messages keep their 2 byte header, with the highest bit of the message type set.
+
+use super::*;
+use aes::cipher::block_padding::Pkcs7;
+use aes::cipher::{BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use num_bigint::BigUint;
+use sha2::{Digest, Sha256};
+
+/// The 768-bit prime from the first Oakley group (RFC 2409), used by Photon for the key exchange.
+pub const OAKLEY_PRIME_768: [u8; 96] = [
+    0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xC9, 0x0F, 0xDA, 0xA2, 0x21, 0x68, 0xC2, 0x34, 0xC4, 0xC6, 0x62, 0x8B, 0x80, 0xDC, 0x1C, 0xD1,
+    0x29, 0x02, 0x4E, 0x08, 0x8A, 0x67, 0xCC, 0x74, 0x02, 0x0B, 0xBE, 0xA6, 0x3B, 0x13, 0x9B, 0x22, 0x51, 0x4A, 0x08, 0x79, 0x8E, 0x34, 0x04, 0xDD,
+    0xEF, 0x95, 0x19, 0xB3, 0xCD, 0x3A, 0x43, 0x1B, 0x30, 0x2B, 0x0A, 0x6D, 0xF2, 0x5F, 0x14, 0x37, 0x4F, 0xE1, 0x35, 0x6D, 0x6D, 0x51, 0xC2, 0x45,
+    0xE4, 0x85, 0xB5, 0x76, 0x62, 0x5E, 0x7E, 0xC6, 0xF4, 0x4C, 0x42, 0xE9, 0xA6, 0x3A, 0x36, 0x20, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+];
+
+/// The generator used together with `OAKLEY_PRIME_768`.
+pub const PRIMITIVE_ROOT: u32 = 22;
+
+/// Set in the message type of encrypted messages
+const ENCRYPTED_FLAG: u8 = 0x80;
+
+type Aes256CbcEnc = cbc::Encryptor<aes::Aes256>;
+type Aes256CbcDec = cbc::Decryptor<aes::Aes256>;
+
+/// One side of a Diffie-Hellman key exchange.
+#[derive(Debug, PartialEq, Clone)]
+pub struct DiffieHellman {
+    secret: BigUint,
+    public_key: Vec<u8>,
+}
+
+/// Encrypts and decrypts the payloads of one connection, using the key derived from a key exchange.
+#[derive(Debug, PartialEq, Clone)]
+pub struct PayloadCipher {
+    key: [u8; 32],
+}
+
+/// Sits between a client and a server that both want to encrypt their traffic, and does a separate key exchange with each of them.
+/// This allows reading and changing packets that are encrypted, by decrypting them with the key of one side and encrypting them again
+/// with the key of the other.
+///
+/// ```rust
+/// # use photon::*;
+/// let client = DiffieHellman::new(&[1; 20]);
+/// let server = DiffieHellman::new(&[2; 20]);
+/// let mut mitm = MitmEncryption::new(&[3; 20], &[4; 20]);
+///
+/// // the client sends its key, which we swap out for ours before it reaches the server
+/// let request = Packet::InternalOperationRequest(InternalOperation::InitEncryptionRequest { public_key: client.public_key().to_vec() }, ParameterOrder::default());
+/// let request = mitm.rekey(request);
+/// let server_cipher = match &request {
+///     Packet::InternalOperationRequest(InternalOperation::InitEncryptionRequest { public_key }, _) => server.derive_cipher(public_key),
+///     _ => unreachable!(),
+/// };
+///
+/// let response = Packet::InternalOperationResponse(InternalOperation::InitEncryptionResponse { public_key: server.public_key().to_vec() }, 0, None, ParameterOrder::default());
+/// let response = mitm.rekey(response);
+/// let client_cipher = match &response {
+///     Packet::InternalOperationResponse(InternalOperation::InitEncryptionResponse { public_key }, _, _, _) => client.derive_cipher(public_key),
+///     _ => unreachable!(),
+/// };
+///
+/// // messages encrypted by the client can now be read, and are encrypted again for the server
+/// let message = client_cipher.encrypt_message(&[0xf3, 0x2, 0xfe, 0x0, 0x0]);
+/// let decrypted = mitm.decrypt(&message, Direction::Send).unwrap();
+/// assert_eq!(decrypted, [0xf3, 0x2, 0xfe, 0x0, 0x0]);
+/// let forwarded = mitm.encrypt(&decrypted, Direction::Send).unwrap();
+/// assert_eq!(server_cipher.decrypt_message(&forwarded).unwrap(), decrypted);
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct MitmEncryption {
+    /// Our key pair for the connection with the client
+    client_side: DiffieHellman,
+    /// Our key pair for the connection with the server
+    server_side: DiffieHellman,
+    client_cipher: Option<PayloadCipher>,
+    server_cipher: Option<PayloadCipher>,
+}
+
+impl DiffieHellman {
+    /// Creates a key pair from a random secret. The official client uses a secret of 160 bits.
+    pub fn new(secret: &[u8]) -> Self {
+        let secret = BigUint::from_bytes_be(secret);
+        let public_key = BigUint::from(PRIMITIVE_ROOT)
+            .modpow(&secret, &BigUint::from_bytes_be(&OAKLEY_PRIME_768))
+            .to_bytes_be();
+        DiffieHellman { secret, public_key }
+    }
+
+    /// The public key to send to the other side, in big-endian order
+    pub fn public_key(&self) -> &[u8] {
+        &self.public_key
+    }
+
+    /// Computes the secret shared with the other side, from their public key
+    pub fn shared_secret(&self, other_public_key: &[u8]) -> Vec<u8> {
+        BigUint::from_bytes_be(other_public_key)
+            .modpow(&self.secret, &BigUint::from_bytes_be(&OAKLEY_PRIME_768))
+            .to_bytes_be()
+    }
+
+    /// Creates the cipher used after exchanging keys with the other side
+    pub fn derive_cipher(&self, other_public_key: &[u8]) -> PayloadCipher {
+        PayloadCipher::from_shared_secret(&self.shared_secret(other_public_key))
+    }
+}
+
+impl PayloadCipher {
+    pub fn new(key: [u8; 32]) -> Self {
+        PayloadCipher { key }
+    }
+
+    /// Creates a cipher from the shared secret of a key exchange, by hashing it.
+    pub fn from_shared_secret(shared_secret: &[u8]) -> Self {
+        PayloadCipher::new(Sha256::digest(shared_secret).into())
+    }
+
+    /// The AES key
+    pub fn key(&self) -> &[u8; 32] {
+        &self.key
+    }
+
+    pub fn encrypt(&self, data: &[u8]) -> Vec<u8> {
+        Aes256CbcEnc::new(&self.key.into(), &[0; 16].into()).encrypt_padded_vec_mut::<Pkcs7>(data)
+    }
+
+    pub fn decrypt(&self, data: &[u8]) -> PacketReadResult<Vec<u8>> {
+        Aes256CbcDec::new(&self.key.into(), &[0; 16].into())
+            .decrypt_padded_vec_mut::<Pkcs7>(data)
+            .map_err(|_| PacketReadError::DecryptionFailed)
+    }
+
+    /// Encrypts a message, keeping its header. Messages that are already encrypted are returned as-is.
+    pub fn encrypt_message(&self, data: &[u8]) -> Vec<u8> {
+        match data {
+            [magic, message_type, payload @ ..] if !is_encrypted(data) => {
+                let mut vec = vec![*magic, message_type | ENCRYPTED_FLAG];
+                vec.extend(self.encrypt(payload));
+                vec
+            }
+            _ => data.to_vec(),
+        }
+    }
+
+    /// Decrypts an encrypted message into one that can be read using `Packet::read`. Messages that are not encrypted are returned
+    /// as-is.
+    pub fn decrypt_message(&self, data: &[u8]) -> PacketReadResult<Vec<u8>> {
+        match data {
+            [magic, message_type, payload @ ..] if is_encrypted(data) => {
+                let mut vec = vec![*magic, message_type & !ENCRYPTED_FLAG];
+                vec.extend(self.decrypt(payload)?);
+                Ok(vec)
+            }
+            _ => Ok(data.to_vec()),
+        }
+    }
+}
+
+/// Returns true if this message has an encrypted payload
+pub fn is_encrypted(data: &[u8]) -> bool {
+    matches!(data, [0xF3, message_type, ..] if message_type & ENCRYPTED_FLAG != 0)
+}
+
+impl MitmEncryption {
+    /// Creates new key pairs for both sides, from random secrets.
+    pub fn new(client_side_secret: &[u8], server_side_secret: &[u8]) -> Self {
+        MitmEncryption {
+            client_side: DiffieHellman::new(client_side_secret),
+            server_side: DiffieHellman::new(server_side_secret),
+            client_cipher: None,
+            server_cipher: None,
+        }
+    }
+
+    /// The cipher for the connection with the client, once it sent its public key
+    pub fn client_cipher(&self) -> Option<&PayloadCipher> {
+        self.client_cipher.as_ref()
+    }
+
+    /// The cipher for the connection with the server, once it sent its public key
+    pub fn server_cipher(&self) -> Option<&PayloadCipher> {
+        self.server_cipher.as_ref()
+    }
+
+    /// Replaces the public key in a key exchange with ours, and derives the cipher for the side that sent it. Other packets are
+    /// returned unchanged.
+    pub fn rekey<'a>(&mut self, packet: Packet<'a>) -> Packet<'a> {
+        match packet {
+            Packet::InternalOperationRequest(InternalOperation::InitEncryptionRequest { public_key }, order) => Packet::InternalOperationRequest(
+                InternalOperation::InitEncryptionRequest {
+                    public_key: self.rekey_client(&public_key),
+                },
+                order,
+            ),
+            Packet::InternalOperationResponse(InternalOperation::InitEncryptionResponse { public_key }, return_code, debug_string, order) => {
+                Packet::InternalOperationResponse(
+                    InternalOperation::InitEncryptionResponse {
+                        public_key: self.rekey_server(&public_key),
+                    },
+                    return_code,
+                    debug_string,
+                    order,
+                )
+            }
+            Packet::OperationRequest(Operation::ExchangeKeysForEncryptionRequest { public_key }, order) => Packet::OperationRequest(
+                Operation::ExchangeKeysForEncryptionRequest {
+                    public_key: self.rekey_client(&public_key),
+                },
+                order,
+            ),
+            Packet::OperationResponse {
+                parameters: Operation::ExchangeKeysForEncryptionResponse { public_key },
+                return_code,
+                debug_string,
+                secret,
+                order,
+            } => Packet::OperationResponse {
+                parameters: Operation::ExchangeKeysForEncryptionResponse {
+                    public_key: self.rekey_server(&public_key),
+                },
+                return_code,
+                debug_string,
+                secret,
+                order,
+            },
+            packet => packet,
+        }
+    }
+
+    /// Derives the cipher for the client from its public key, and returns the public key to send to the server instead
+    pub fn rekey_client(&mut self, client_public_key: &[u8]) -> Vec<u8> {
+        self.client_cipher = Some(self.client_side.derive_cipher(client_public_key));
+        self.server_side.public_key().to_vec()
+    }
+
+    /// Derives the cipher for the server from its public key, and returns the public key to send to the client instead
+    pub fn rekey_server(&mut self, server_public_key: &[u8]) -> Vec<u8> {
+        self.server_cipher = Some(self.server_side.derive_cipher(server_public_key));
+        self.client_side.public_key().to_vec()
+    }
+
+    /// Decrypts a message coming from the client if `direction` is `Send`, or from the server if it is `Recv`. Messages that are not
+    /// encrypted are returned as-is.
+    pub fn decrypt(&self, data: &[u8], direction: Direction) -> PacketReadResult<Vec<u8>> {
+        match (is_encrypted(data), self.cipher(direction)) {
+            (false, _) => Ok(data.to_vec()),
+            (true, Some(cipher)) => cipher.decrypt_message(data),
+            (true, None) => Err(PacketReadError::DecryptionFailed),
+        }
+    }
+
+    /// Encrypts a message going to the server if `direction` is `Send`, or to the client if it is `Recv`. Returns `None` if the keys
+    /// for that side have not been exchanged yet.
+    pub fn encrypt(&self, data: &[u8], direction: Direction) -> Option<Vec<u8>> {
+        let cipher = match direction {
+            Direction::Send => self.server_cipher.as_ref(),
+            Direction::Recv => self.client_cipher.as_ref(),
+        };
+        cipher.map(|cipher| cipher.encrypt_message(data))
+    }
+
+    /// The cipher used by the side that sends messages in `direction`
+    fn cipher(&self, direction: Direction) -> Option<&PayloadCipher> {
+        match direction {
+            Direction::Send => self.client_cipher.as_ref(),
+            Direction::Recv => self.server_cipher.as_ref(),
+        }
+    }
+}
@@ -0,0 +1,145 @@
+#![cfg(test)]
+
+use super::*;
+
+// known vectors, computed independently from this implementation
+const CLIENT_SECRET: [u8; 20] = [1; 20];
+const SERVER_SECRET: [u8; 20] = [2; 20];
+const CLIENT_PUBLIC_KEY: [u8; 96] = [
+    0xe5, 0x89, 0xbe, 0x03, 0xd1, 0x26, 0x6c, 0xa5, 0x4e, 0x32, 0xda, 0x2b, 0xcf, 0x36, 0xca, 0xd8, 0x38, 0xb3, 0x52, 0xff, 0x6a, 0x69, 0x75, 0x58,
+    0x2a, 0x4b, 0x58, 0x2b, 0xdd, 0x42, 0x10, 0x87, 0x15, 0x31, 0x4d, 0xb1, 0x13, 0xc4, 0xb7, 0x47, 0x74, 0xca, 0xf1, 0xc1, 0xda, 0x72, 0x4a, 0x7c,
+    0xae, 0x54, 0x47, 0x0c, 0xa5, 0x1d, 0x2f, 0x99, 0x8a, 0x76, 0xd7, 0x73, 0x1a, 0xbf, 0x07, 0x7b, 0x0a, 0x0c, 0x77, 0x93, 0x40, 0x99, 0x68, 0x82,
+    0x82, 0x8e, 0x6f, 0x3a, 0xd1, 0xd9, 0xef, 0xa6, 0x5a, 0xc8, 0x03, 0x90, 0x1d, 0xba, 0x05, 0x12, 0xa5, 0xd2, 0x56, 0x00, 0x4f, 0xca, 0xd0, 0xad,
+];
+const SERVER_PUBLIC_KEY: [u8; 96] = [
+    0x02, 0x32, 0xc0, 0xf0, 0x85, 0x81, 0x73, 0x9f, 0x3f, 0x10, 0xcf, 0x00, 0xc2, 0xc0, 0x22, 0xf5, 0x7e, 0x77, 0x7b, 0xf0, 0xde, 0xd3, 0xe0, 0x23,
+    0x3d, 0x04, 0x42, 0x45, 0x3a, 0x97, 0x44, 0xe4, 0x21, 0xe2, 0xfb, 0xac, 0x3d, 0xaf, 0xf8, 0x21, 0x68, 0x11, 0xf1, 0x4e, 0x0f, 0xb7, 0x3b, 0x2c,
+    0x67, 0xd4, 0x21, 0x62, 0x34, 0x9b, 0x90, 0x9b, 0xa3, 0xd6, 0xee, 0xd3, 0xdf, 0xaa, 0x98, 0x1c, 0x6b, 0x8d, 0x4c, 0x8e, 0x5f, 0x41, 0x20, 0x62,
+    0xd1, 0xd9, 0x4f, 0x0a, 0x86, 0x5e, 0x85, 0x47, 0x1a, 0x25, 0xd3, 0xf4, 0x08, 0xc5, 0x6e, 0x0a, 0x48, 0x16, 0x5e, 0xf7, 0xc8, 0xb1, 0x41, 0x44,
+];
+const SHARED_KEY: [u8; 32] = [
+    0xff, 0x62, 0xb8, 0x52, 0xa3, 0xb1, 0x09, 0xee, 0xb6, 0xbe, 0xdd, 0x50, 0x2f, 0x4c, 0xfb, 0x66, 0xd5, 0x1d, 0x76, 0xf1, 0xc4, 0x28, 0x4c, 0xf9,
+    0xdc, 0x90, 0x86, 0xd9, 0xb3, 0x38, 0xb2, 0xdd,
+];
+/// `InternalOperation::PingRequest`, as in the `ping_request` test
+const PING: [u8; 11] = [0xf3, 0x6, 0x1, 0x0, 0x1, 0x1, 0x69, 0x0, 0x0, 0x2d, 0x51];
+/// `PING`, encrypted with `SHARED_KEY`
+const ENCRYPTED_PING: [u8; 18] = [
+    0xf3, 0x86, 0x09, 0xf6, 0x34, 0xd6, 0x7e, 0xbf, 0x50, 0xcb, 0x95, 0xe7, 0xbc, 0xd9, 0xc5, 0x48, 0x2e, 0x02,
+];
+
+#[test]
+fn key_exchange() {
+    let client = DiffieHellman::new(&CLIENT_SECRET);
+    let server = DiffieHellman::new(&SERVER_SECRET);
+
+    assert_eq!(client.public_key(), CLIENT_PUBLIC_KEY);
+    assert_eq!(server.public_key(), SERVER_PUBLIC_KEY);
+    assert_eq!(client.shared_secret(&SERVER_PUBLIC_KEY), server.shared_secret(&CLIENT_PUBLIC_KEY));
+    assert_eq!(client.derive_cipher(&SERVER_PUBLIC_KEY).key(), &SHARED_KEY);
+    assert_eq!(server.derive_cipher(&CLIENT_PUBLIC_KEY).key(), &SHARED_KEY);
+}
+
+#[test]
+fn encrypt_message() {
+    let cipher = PayloadCipher::new(SHARED_KEY);
+
+    assert!(!is_encrypted(&PING));
+    assert!(is_encrypted(&ENCRYPTED_PING));
+    assert_eq!(cipher.encrypt_message(&PING), ENCRYPTED_PING);
+    assert_eq!(cipher.decrypt_message(&ENCRYPTED_PING).unwrap(), PING);
+    assert_eq!(
+        Packet::read(&cipher.decrypt_message(&ENCRYPTED_PING).unwrap(), Direction::Send).unwrap(),
+        Packet::InternalOperationRequest(InternalOperation::PingRequest { local_time: 11601 }, ParameterOrder::default())
+    );
+
+    // already encrypted or not encrypted messages are left alone
+    assert_eq!(cipher.encrypt_message(&ENCRYPTED_PING), ENCRYPTED_PING);
+    assert_eq!(cipher.decrypt_message(&PING).unwrap(), PING);
+}
+
+#[test]
+fn decrypt_with_wrong_key() {
+    let cipher = PayloadCipher::new([0; 32]);
+
+    match cipher.decrypt_message(&ENCRYPTED_PING) {
+        Err(PacketReadError::DecryptionFailed) => (),
+        other => panic!("Expected DecryptionFailed, got {:?}", other),
+    }
+    // not a multiple of the block size
+    assert!(cipher.decrypt(&[0; 5]).is_err());
+}
+
+#[test]
+fn mitm_rekeys_both_sides() {
+    let client = DiffieHellman::new(&CLIENT_SECRET);
+    let server = DiffieHellman::new(&SERVER_SECRET);
+    let mut mitm = MitmEncryption::new(&[3; 20], &[4; 20]);
+
+    let request = mitm.rekey(Packet::InternalOperationRequest(
+        InternalOperation::InitEncryptionRequest {
+            public_key: CLIENT_PUBLIC_KEY.to_vec(),
+        },
+        ParameterOrder::default(),
+    ));
+    let server_cipher = match request {
+        Packet::InternalOperationRequest(InternalOperation::InitEncryptionRequest { public_key }, _) => {
+            assert_ne!(public_key, CLIENT_PUBLIC_KEY);
+            server.derive_cipher(&public_key)
+        }
+        other => panic!("Expected InitEncryptionRequest, got {:?}", other),
+    };
+
+    let response = mitm.rekey(Packet::OperationResponse {
+        parameters: Operation::ExchangeKeysForEncryptionResponse {
+            public_key: SERVER_PUBLIC_KEY.to_vec(),
+        },
+        return_code: 0,
+        debug_string: None,
+        secret: None,
+        order: ParameterOrder::default(),
+    });
+    let client_cipher = match response {
+        Packet::OperationResponse {
+            parameters: Operation::ExchangeKeysForEncryptionResponse { public_key },
+            ..
+        } => {
+            assert_ne!(public_key, SERVER_PUBLIC_KEY);
+            client.derive_cipher(&public_key)
+        }
+        other => panic!("Expected ExchangeKeysForEncryptionResponse, got {:?}", other),
+    };
+
+    // each side has its own key, and neither is the one they would have shared
+    assert_ne!(client_cipher, server_cipher);
+    assert_ne!(client_cipher.key(), &SHARED_KEY);
+    assert_eq!(mitm.client_cipher(), Some(&client_cipher));
+    assert_eq!(mitm.server_cipher(), Some(&server_cipher));
+
+    let sent = client_cipher.encrypt_message(&PING);
+    let forwarded = mitm.encrypt(&mitm.decrypt(&sent, Direction::Send).unwrap(), Direction::Send).unwrap();
+    assert_eq!(server_cipher.decrypt_message(&forwarded).unwrap(), PING);
+
+    let received = server_cipher.encrypt_message(&[0xf3, 0x3, 0xfe, 0x0, 0x0, 0x2a, 0x0, 0x0]);
+    let forwarded = mitm.encrypt(&mitm.decrypt(&received, Direction::Recv).unwrap(), Direction::Recv).unwrap();
+    assert_eq!(
+        client_cipher.decrypt_message(&forwarded).unwrap(),
+        [0xf3, 0x3, 0xfe, 0x0, 0x0, 0x2a, 0x0, 0x0]
+    );
+}
+
+#[test]
+fn mitm_before_key_exchange() {
+    let mitm = MitmEncryption::new(&[3; 20], &[4; 20]);
+
+    assert_eq!(mitm.decrypt(&PING, Direction::Send).unwrap(), PING);
+    assert!(mitm.decrypt(&ENCRYPTED_PING, Direction::Send).is_err());
+    assert!(mitm.encrypt(&PING, Direction::Recv).is_none());
+    assert_eq!(
+        mitm.clone().rekey(Packet::OperationRequest(
+            Operation::LeaveRequest { is_inactive: None },
+            ParameterOrder::default()
+        )),
+        Packet::OperationRequest(Operation::LeaveRequest { is_inactive: None }, ParameterOrder::default())
+    );
+}
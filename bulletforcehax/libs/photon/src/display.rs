@@ -0,0 +1,141 @@
+use super::*;
+use std::fmt::{self, Display, Formatter};
+
+/// Names the codes used by PUN, so they can be shown when printing packets.
+///
+/// ```rust
+/// # use photon::*;
+/// # use photon_core::PhotonPacket;
+/// let data = vec![0xf3, 0x6, 0x1, 0x0, 0x1, 0x1, 0x69, 0x0, 0x0, 0x2d, 0x51];
+/// let packet = PhotonPacket::read(&data, Protocol::GpBinaryV16).unwrap();
+/// assert_eq!(
+///     packet.display_with(&Names).to_string(),
+///     "InternalOperationRequest 1 Ping { 1 LocalTime: Integer(11601) }"
+/// );
+/// ```
+pub struct Names;
+
+impl CodeNames for Names {
+    fn packet_code(&self, packet: &PhotonPacket) -> Option<&'static str> {
+        match packet {
+            PhotonPacket::OperationRequest(code, _) | PhotonPacket::OperationResponse(code, _, _, _) => operation_name(*code),
+            PhotonPacket::Event(code, _) => event_name(*code),
+            PhotonPacket::InternalOperationRequest(0, _) | PhotonPacket::InternalOperationResponse(0, _, _, _) => Some("InitEncryption"),
+            PhotonPacket::InternalOperationRequest(1, _) | PhotonPacket::InternalOperationResponse(1, _, _, _) => Some("Ping"),
+            _ => None,
+        }
+    }
+
+    fn parameter(&self, packet: &PhotonPacket, code: u8) -> Option<&'static str> {
+        match (packet, code) {
+            (PhotonPacket::InternalOperationRequest(1, _), 1) | (PhotonPacket::InternalOperationResponse(1, _, _, _), 1) => Some("LocalTime"),
+            (PhotonPacket::InternalOperationResponse(1, _, _, _), 2) => Some("ServerTime"),
+            (PhotonPacket::InternalOperationRequest(_, _), _) | (PhotonPacket::InternalOperationResponse(_, _, _, _), _) => None,
+            _ => ParameterCode::name(code),
+        }
+    }
+
+    fn property(&self, packet: &PhotonPacket, parameter: u8, key: u8) -> Option<&'static str> {
+        match parameter {
+            ParameterCode::GameProperties | ParameterCode::GameList => GamePropertyKey::name(key),
+            ParameterCode::PlayerProperties => ActorProperties::name(key),
+            // changes to player properties target an actor, and room properties do not
+            ParameterCode::Properties | ParameterCode::ExpectedValues => {
+                let params = packet.parameters()?;
+                let targets_actor = match packet {
+                    PhotonPacket::OperationRequest(252, _) => params.contains_key(&ParameterCode::ActorNr),
+                    PhotonPacket::Event(253, _) => matches!(params.get(&ParameterCode::TargetActorNr), Some(ProtocolValue::Integer(1..))),
+                    _ => false,
+                };
+                if targets_actor {
+                    ActorProperties::name(key)
+                } else {
+                    GamePropertyKey::name(key)
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+fn operation_name(code: u8) -> Option<&'static str> {
+    match code {
+        217 => Some("GetGameList"),
+        218 => Some("ServerSettings"),
+        219 => Some("WebRpc"),
+        220 => Some("GetRegions"),
+        221 => Some("GetLobbyStats"),
+        222 => Some("FindFriends"),
+        224 => Some("CancelJoinRandom"),
+        225 => Some("JoinRandomGame"),
+        226 => Some("JoinGame"),
+        227 => Some("CreateGame"),
+        228 => Some("LeaveLobby"),
+        229 => Some("JoinLobby"),
+        230 => Some("Authenticate"),
+        231 => Some("AuthenticateOnce"),
+        248 => Some("ChangeGroups"),
+        250 => Some("ExchangeKeysForEncryption"),
+        251 => Some("GetProperties"),
+        252 => Some("SetProperties"),
+        253 => Some("RaiseEvent"),
+        254 => Some("Leave"),
+        255 => Some("Join"),
+        _ => None,
+    }
+}
+
+fn event_name(code: u8) -> Option<&'static str> {
+    match code {
+        210 => Some("AzureNodeInfo"),
+        223 => Some("AuthEvent"),
+        224 => Some("LobbyStats"),
+        226 => Some("AppStats"),
+        227 => Some("Match"),
+        228 => Some("QueueState"),
+        229 => Some("GameListUpdate"),
+        230 => Some("GameList"),
+        250 => Some("CacheSliceChanged"),
+        251 => Some("ErrorInfo"),
+        253 => Some("PropertiesChanged"),
+        254 => Some("Leave"),
+        255 => Some("Join"),
+        _ => None,
+    }
+}
+
+/// Shows the packet as it is serialized, with the names of its codes. Use `{:#}` to print it on multiple lines.
+///
+/// Packets that can not be serialized are shown using their `Debug` impl instead. This formats a converted copy of the packet, so
+/// avoid formatting it where the output is not used, such as in a disabled log statement.
+impl Display for Packet<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self.clone().into_photon_packet() {
+            Ok(packet) => packet.display_with(&Names).fmt(f),
+            Err(_) => write!(f, "{:?}", self),
+        }
+    }
+}
+
+/// Shows the packet like `Packet` does, followed by why it could not be read as one. Frames that could not be read at all are shown
+/// as their bytes.
+impl Display for PacketView<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            PacketView::Packet(packet) => packet.fmt(f),
+            PacketView::Raw(packet, error) => write!(f, "{} ({:?})", packet.display_with(&Names), error),
+            PacketView::Invalid(data, error) => write!(f, "{:?} ({:?})", data, error),
+        }
+    }
+}
+
+impl Packet<'_> {
+    /// Lists the bytes of a serialized packet next to the fields they decode into, with the names of PUN codes.
+    ///
+    /// See [`PhotonPacket::hex_dump`] for the format.
+    ///
+    /// [`PhotonPacket::hex_dump`]: ../photon_core/enum.PhotonPacket.html#method.hex_dump
+    pub fn hex_dump(data: &[u8], protocol: Protocol) -> PhotonReadResult<String> {
+        PhotonPacket::hex_dump(data, protocol, &Names)
+    }
+}
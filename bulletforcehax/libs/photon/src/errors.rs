@@ -12,8 +12,15 @@ pub enum PacketReadError {
     UnimplementedOperationType(Operation<'static>),
     UnimplementedInternalOperationType(InternalOperation),
     UnexpectedProtocolValue,
+    InvalidInitLength(usize),
     CouldNotFindKey(u8),
     CouldNotFindKeyProtocolValue(ProtocolValue<'static>),
+    /// A parameter has the right type, but its value is out of range. Contains the parameter code.
+    InvalidParameterValue(u8),
+    /// The data of a PUN event has an unexpected shape, or contains values that would not be written back. Contains the event code.
+    InvalidPunData(u8),
+    /// An encrypted payload could not be decrypted, because the key is wrong or not known yet
+    DecryptionFailed,
     PhotonError(PhotonReadError),
 }
 
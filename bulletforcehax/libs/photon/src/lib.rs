@@ -1,44 +1,110 @@
 use bitflags::bitflags;
+use indexmap::IndexMap;
 use num_derive::FromPrimitive;
-use std::collections::HashMap;
+use std::borrow::Cow;
 
-pub use photon_core::ProtocolValue;
 use photon_core::*;
+pub use photon_core::{Protocol, ProtocolValue};
 
+mod accessors;
+pub use accessors::*;
+mod connection;
+pub use connection::*;
+mod connection_tests;
+mod crypto;
+pub use crypto::*;
+mod crypto_tests;
+mod display;
+pub use display::*;
 mod errors;
 pub use errors::*;
+mod lobby;
+pub use lobby::*;
+mod lobby_tests;
 mod macros;
 mod packets_impl;
 mod payloads_impl;
 mod payloads_tests;
+mod proptests;
+mod pun;
+pub use pun::*;
+mod pun_tests;
+mod session;
+pub use session::*;
+mod session_tests;
 mod tests;
+mod view;
+pub use view::*;
+mod view_tests;
 
-#[derive(Debug, PartialEq)]
+/// A packet read into typed operations and events.
+///
+/// Packets with parameters remember the order they were read in, so an unmodified packet is written back byte for byte. Packets
+/// created from scratch are written in the order the official client and servers use.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Packet<'a> {
-    OperationRequest(Operation<'a>),
+    /// Connection handshake sent by the client, if it is not passed through the websocket url instead.
+    Init {
+        /// The serialization protocol version, `(1, 6)` for GpBinary and `(1, 8)` for GpBinaryV18
+        protocol_version: (u8, u8),
+        client_sdk_id: u8,
+        /// The client library version. The first 2 parts are sent as a nibble each.
+        client_version: (u8, u8, u8, u8),
+        /// Connection flags, such as IPv6 support
+        flags: u8,
+        /// The application id. Like in the official client, only the first 32 bytes are sent.
+        app_id: Cow<'a, str>,
+    },
+    /// Response to `Init`, sent by the server
+    InitResponse(Cow<'a, [u8]>),
+    OperationRequest(Operation<'a>, ParameterOrder),
     OperationResponse {
         parameters: Operation<'a>,
         return_code: i16,
-        debug_string: Option<&'a str>,
-        secret: Option<&'a str>, // note: not present in OperationRequest on purpose. See CreateGame
+        debug_string: Option<Cow<'a, str>>,
+        secret: Option<Cow<'a, str>>, // note: not present in OperationRequest on purpose. See CreateGame
+        order: ParameterOrder,
     },
     Event {
         parameters: Event<'a>,
         custom_data: Option<ProtocolValue<'a>>,
         sender: Option<i32>,
+        order: ParameterOrder,
     },
-    InternalOperationRequest(InternalOperation),
-    InternalOperationResponse(InternalOperation, i16, Option<&'a str>),
+    InternalOperationRequest(InternalOperation, ParameterOrder),
+    InternalOperationResponse(InternalOperation, i16, Option<Cow<'a, str>>, ParameterOrder),
+    /// A single value, sent outside of operations and events
+    Message(ProtocolValue<'a>),
+    /// Raw bytes, sent outside of operations and events
+    RawMessage(Cow<'a, [u8]>),
 }
 
-#[derive(Debug, PartialEq)]
+/// The order the parameters of a packet were read in. Parameters that are not in it, such as ones added after reading, are written
+/// after the others.
+///
+/// Like the order of a `ParameterTable`, it does not affect equality: all orders are equal.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ParameterOrder(Vec<u8>);
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Event<'a> {
     /// Only when hosted with Azure, now obsolete
     AzureNodeInfo,
     /// Sent to update token
-    AuthEvent,
-    /// Contains list of lobbies with player and game counts
-    LobbyStats,
+    AuthEvent {
+        /// The new token, used like the `secret` of an `OperationResponse`
+        token: Cow<'a, str>,
+    },
+    /// Contains list of lobbies with player and game counts. All lists have the same length, with an entry for each lobby.
+    LobbyStats {
+        names: Vec<Cow<'a, str>>,
+        types: Vec<u8>,
+        peer_counts: Vec<i32>,
+        game_counts: Vec<i32>,
+    },
     /// Stats such as game, peer and master peer count. Sent every minute by master server.
     AppStats {
         game_count: i32,
@@ -50,15 +116,31 @@ pub enum Event<'a> {
     /// Unused
     QueueState,
     /// Initial game list. Contains all current games.
-    GameList(HashMap<&'a str, RoomInfo<'a>>),
+    GameList(IndexMap<Cow<'a, str>, RoomInfo<'a>>),
     /// Update to game list. Contains `Option`s which are `None` if the game was removed, and `Some` if it was added or updated.
-    GameListUpdate(HashMap<&'a str, Option<RoomInfo<'a>>>),
-    CacheSliceChanged,
-    ErrorInfo,
-    /// Used to update broadcasted properties
-    PropertiesChanged,
-    /// Player leaves the game
-    Leave,
+    GameListUpdate(IndexMap<Cow<'a, str>, Option<RoomInfo<'a>>>),
+    /// The index of the current event cache slice changed
+    CacheSliceChanged { index: i32 },
+    /// An error reported by the server, such as a failing plugin
+    ErrorInfo { info: Cow<'a, str> },
+    /// Used to update broadcasted properties. The actor that changed them is the `sender` of the packet.
+    PropertiesChanged {
+        /// The actor whose properties changed, or 0 if the properties of the room changed
+        target_actor: i32,
+        /// Only the properties that changed. Properties set to `ProtocolValue::Null` were removed.
+        properties: PropertiesChange<'a>,
+    },
+    /// Player leaves the game. The `sender` of the packet is always `None`, the leaving actor is `actor_nr` instead.
+    Leave {
+        /// The actor that left
+        actor_nr: i32,
+        /// All players still in the room
+        actor_list: Option<Vec<i32>>,
+        /// If the player may still rejoin, because the room has a player TTL
+        is_inactive: Option<bool>,
+        /// Present if the leaving player was the master client
+        new_master_client_id: Option<i32>,
+    },
     /// Player joins the game. If `actor_nr` is 1, we may be creating the game.
     Join {
         player_properties: Player<'a>,
@@ -67,50 +149,146 @@ pub enum Event<'a> {
     },
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Operation<'a> {
     /// Used to get game list with SQL filter
-    GetGameList,
+    GetGameListRequest {
+        lobby_name: Cow<'a, str>,
+        lobby_type: u8,
+        /// The SQL filter the rooms must match
+        sql_filter: Cow<'a, str>,
+    },
+    GetGameListResponse {
+        games: IndexMap<Cow<'a, str>, RoomInfo<'a>>,
+    },
     /// Changes server settings
     ServerSettings,
     WebRpc,
-    /// Gets a list of region servers
-    GetRegions,
-    GetLobbyStats,
+    /// Gets a list of region servers, sent to the NameServer
+    GetRegionsRequest {
+        app_id: Cow<'a, str>,
+    },
+    /// The available regions, with the address of their MasterServer at the same index
+    GetRegionsResponse {
+        regions: Vec<Cow<'a, str>>,
+        addresses: Vec<Cow<'a, str>>,
+    },
+    GetLobbyStatsRequest {
+        /// The lobbies to get the stats of, along with their types at the same index. Not present if all lobbies are requested.
+        lobby_names: Option<Vec<Cow<'a, str>>>,
+        lobby_types: Option<Vec<u8>>,
+    },
+    /// Same as `Event::LobbyStats`. All lists have the same length, with an entry for each lobby.
+    GetLobbyStatsResponse {
+        names: Vec<Cow<'a, str>>,
+        types: Vec<u8>,
+        peer_counts: Vec<i32>,
+        game_counts: Vec<i32>,
+    },
     /// Request room and online status from friend by name
-    FindFriends,
+    FindFriendsRequest {
+        user_ids: Vec<Cow<'a, str>>,
+        options: Option<FindFriendsOptions>,
+    },
+    /// The status of each requested friend, at the same index as in the request
+    FindFriendsResponse {
+        /// Never empty, since at least 1 friend has to be requested
+        online: Vec<bool>,
+        /// The room of each friend, or an empty string if they are not in a room
+        room_ids: Vec<Cow<'a, str>>,
+    },
     CancelJoinRandom,
-    JoinRandomGame,
-    JoinGame,
+    /// JoinRandomGame on MasterServer
+    JoinRandomGameRequest {
+        /// Properties the room must have, such as custom properties and `GamePropertyKey::MaxPlayers` if it is above 0
+        #[cfg_attr(feature = "serde", serde(with = "photon_core::serde_pairs::option"))]
+        expected_properties: Option<IndexMap<ProtocolValue<'a>, ProtocolValue<'a>>>, // not present if empty
+        matchmaking_mode: Option<MatchmakingMode>, // not present if FillRoom
+        lobby_name: Option<Cow<'a, str>>,          // if lobby name not null or empty
+        lobby_type: Option<u8>,                    // if lobby name not null or empty
+        /// Only used in SQL lobbies
+        sql_filter: Option<Cow<'a, str>>,
+        expected_users: Vec<Cow<'a, str>>, // not present if null or empty
+    },
+    /// JoinRandomGame on MasterServer. The `Secret` variable for the GameServer can be found in `OperationResponse`.
+    JoinRandomGameResponse {
+        room_name: Option<Cow<'a, str>>,
+        address: Option<Cow<'a, str>>, // not present if no room could be joined
+    },
+    /// JoinGame on MasterServer. Also used on GameServer if there are no player properties and the room is not created.
+    JoinGameRequestMaster {
+        room_name: Cow<'a, str>,
+        join_mode: Option<JoinMode>,       // not present if default
+        lobby_name: Option<Cow<'a, str>>,  // only if the room may be created
+        lobby_type: Option<u8>,            // only if the room may be created
+        expected_users: Vec<Cow<'a, str>>, // not present if null or empty
+    },
+    /// JoinGame on GameServer, has extra options compared to `JoinGameRequestMaster`
+    JoinGameRequestGame {
+        room_name: Cow<'a, str>,
+        join_mode: Option<JoinMode>,       // not present if default
+        lobby_name: Option<Cow<'a, str>>,  // only if the room may be created
+        lobby_type: Option<u8>,            // only if the room may be created
+        expected_users: Vec<Cow<'a, str>>, // not present if null or empty
+
+        /// Player struct, but with only custom properties and nick
+        player_properties: Option<Player<'a>>, // only present if not null and not empty
+        broadcast: Option<bool>, // present+true if playerproperties is present
+
+        /// Only present if the room may be created, like in `CreateGameRequestGame`
+        game_properties: Option<RoomOptions<'a>>,
+        player_ttl: Option<i32>,
+        empty_room_ttl: Option<i32>,
+        plugins: Option<Vec<Cow<'a, str>>>,
+        room_option_flags: Option<RoomOptionsFlags>,
+        /// The room options that were sent as separate parameters. Clients that send `room_option_flags` usually send some of
+        /// these as well, so a change to one should be made to the other too.
+        room_option_params: RoomOptionParams,
+    },
+    /// JoinGame on MasterServer. The `Secret` variable for the GameServer can be found in `OperationResponse`.
+    JoinGameResponseMaster {
+        room_name: Option<Cow<'a, str>>,
+        address: Option<Cow<'a, str>>, // not present if no room could be joined
+    },
+    /// JoinGame on GameServer
+    JoinGameResponseGame {
+        /// our actor number
+        actor_nr: i32,
+        actor_list: Option<Vec<i32>>,
+        game_properties: RoomInfo<'a>,
+        /// A list of all actors in the room.
+        player_properties: IndexMap<i32, Player<'a>>,
+    },
     /// CreateGame on MasterServer
     CreateGameRequestMaster {
-        room_name: Option<&'a str>,   // can be null, not present then
-        lobby_name: Option<&'a str>,  // if lobby not null and not default
-        lobby_type: Option<bool>,     // if lobby not null and not default
-        expected_users: Vec<&'a str>, // not present if null or empty
+        room_name: Option<Cow<'a, str>>,   // can be null, not present then
+        lobby_name: Option<Cow<'a, str>>,  // if lobby not null and not default
+        lobby_type: Option<bool>,          // if lobby not null and not default
+        expected_users: Vec<Cow<'a, str>>, // not present if null or empty
     },
     /// CreateGame on GameServer, has extra options compared to `CreateGameRequestMaster`
     CreateGameRequestGame {
-        room_name: Option<&'a str>,   // can be null, not present then
-        lobby_name: Option<&'a str>,  // if lobby not null and not default
-        lobby_type: Option<bool>,     // if lobby not null and not default
-        expected_users: Vec<&'a str>, // not present if null or empty
+        room_name: Option<Cow<'a, str>>,   // can be null, not present then
+        lobby_name: Option<Cow<'a, str>>,  // if lobby not null and not default
+        lobby_type: Option<bool>,          // if lobby not null and not default
+        expected_users: Vec<Cow<'a, str>>, // not present if null or empty
 
         /// Player struct, but with only custom properties and nick
         player_properties: Option<Player<'a>>, // only present if not null and not empty?
         broadcast: Option<bool>, // present+true if playerproperties is present
 
-        game_properties: RoomOptions<'a>, // always present, so can be used to differentiate master and game packet
-        player_ttl: i32,                  // only present if ttl > 0 or == -1
-        empty_room_ttl: i32,              // only present if ttl > 0
-        plugins: Option<Vec<&'a str>>,    // not present if null
+        game_properties: RoomOptions<'a>,   // always present, so can be used to differentiate master and game packet
+        player_ttl: i32,                    // only present if ttl > 0 or == -1
+        empty_room_ttl: i32,                // only present if ttl > 0
+        plugins: Option<Vec<Cow<'a, str>>>, // not present if null
 
         room_option_flags: RoomOptionsFlags, // note: assuming always present,  so that other bool flags don't need to be present
     },
     /// CreateGame in MasterServer. The `Secret` variable is can be found in `OperationResponse`.
     CreateGameResponseMaster {
-        room_name: Option<&'a str>,
-        address: &'a str,
+        room_name: Option<Cow<'a, str>>,
+        address: Cow<'a, str>,
     },
     /// CreateGame on GameServer
     CreateGameResponseGame {
@@ -119,73 +297,85 @@ pub enum Operation<'a> {
         actor_list: Option<Vec<i32>>,
         game_properties: RoomInfo<'a>,
         /// A list of all actors in the room.
-        player_properties: HashMap<i32, Player<'a>>, // Should correspond to `actor_list`?
+        player_properties: IndexMap<i32, Player<'a>>, // Should correspond to `actor_list`?
     },
     LeaveLobby,
     JoinLobby(),
     /// Full authentication request to request a token
     AuthenticateRequestNoToken {
         lobby_stats: bool, // not present if false
-        app_version: &'a str,
-        app_id: &'a str, // could be parsed as u128 since it's a guid
-        region: Option<&'a str>,
-        user_id: Option<&'a str>,
+        app_version: Cow<'a, str>,
+        app_id: Cow<'a, str>, // could be parsed as u128 since it's a guid
+        region: Option<Cow<'a, str>>,
+        user_id: Option<Cow<'a, str>>,
         client_auth_type: Option<u8>,
         /// Only if `client_auth_type` is not 255
-        client_auth_params: Option<&'a str>, // not present if not null or empty
-        client_auth_data: Option<&'a str>, // can be empty
+        client_auth_params: Option<Cow<'a, str>>, // not present if not null or empty
+        client_auth_data: Option<Cow<'a, str>>, // can be empty
     },
     /// Authenticate if we already have a token. Since this is sent, `secret` is a payload parameter.
     AuthenticateRequestToken {
         lobby_stats: bool, // not present if false
-        secret: &'a str,
+        secret: Cow<'a, str>,
     },
     /// The Authenticate response on NameServer
     /// The `Secret` variable is can be found in `OperationResponse`.
     AuthenticateResponseName {
-        user_id: Option<&'a str>,
-        nickname: Option<&'a str>,
-        encryption_data: Option<HashMap<u8, ProtocolValue<'a>>>, // probably not used in websocket connections
-        custom_data: Option<HashMap<&'a str, ProtocolValue<'a>>>,
+        user_id: Option<Cow<'a, str>>,
+        nickname: Option<Cow<'a, str>>,
+        encryption_data: Option<IndexMap<u8, ProtocolValue<'a>>>, // probably not used in websocket connections
+        custom_data: Option<IndexMap<Cow<'a, str>, ProtocolValue<'a>>>,
 
         // unique
-        cluster: Option<&'a str>,
-        address: &'a str,
+        cluster: Option<Cow<'a, str>>,
+        address: Cow<'a, str>,
     },
     /// The Authenticate response on MasterServer or GameServer
     /// The `Secret` variable is can be found in `OperationResponse`.
     AuthenticateResponseMasterOrGame {
         /// Only on MasterServer
-        user_id: Option<&'a str>,
+        user_id: Option<Cow<'a, str>>,
         /// Only on MasterServer
-        nickname: Option<&'a str>,
+        nickname: Option<Cow<'a, str>>,
         /// Only on MasterServer
-        encryption_data: Option<HashMap<u8, ProtocolValue<'a>>>, // probably not used in websocket connections
-        custom_data: Option<HashMap<&'a str, ProtocolValue<'a>>>,
+        encryption_data: Option<IndexMap<u8, ProtocolValue<'a>>>, // probably not used in websocket connections
+        custom_data: Option<IndexMap<Cow<'a, str>, ProtocolValue<'a>>>,
         /// Unused field, here for completeness. Seems to be 0 (meaning no waitlist to join?) for MasterServer
         position: Option<i32>,
     },
     AuthenticateOnce,
     ChangeGroups,
-    ExchangeKeysForEncryption,
+    /// Starts encrypting the connection, like `InternalOperation::InitEncryptionRequest`. See the `crypto` module.
+    ExchangeKeysForEncryptionRequest {
+        /// The Diffie-Hellman public key of the client
+        public_key: Vec<u8>,
+    },
+    ExchangeKeysForEncryptionResponse {
+        /// The Diffie-Hellman public key of the server
+        public_key: Vec<u8>,
+    },
     GetProperties,
     // send only
-    // TODO: how to handle this? add fn to RoomInfo to apply this update?
+    /// Can be applied to a room using `RoomInfo::apply_properties`
     SetPropertiesGame {
         /// The added/changed properties of this room
-        properties: HashMap<ProtocolValue<'a>, ProtocolValue<'a>>,
-        expected_properties: Option<HashMap<ProtocolValue<'a>, ProtocolValue<'a>>>,
+        #[cfg_attr(feature = "serde", serde(with = "photon_core::serde_pairs"))]
+        properties: IndexMap<ProtocolValue<'a>, ProtocolValue<'a>>,
+        #[cfg_attr(feature = "serde", serde(with = "photon_core::serde_pairs::option"))]
+        expected_properties: Option<IndexMap<ProtocolValue<'a>, ProtocolValue<'a>>>,
         broadcast: bool,     // always true
         event_forward: bool, // not present if false
     },
     // send only
-    // TODO: how to handle this? add fn to Player to apply this update?
+    /// Can be applied to a player using `Player::apply_properties`
     SetPropertiesActor {
         /// The actor to update
         actor_nr: i32,
         /// The added/changed properties of this actor
-        properties: HashMap<ProtocolValue<'a>, ProtocolValue<'a>>,
-        expected_properties: Option<HashMap<ProtocolValue<'a>, ProtocolValue<'a>>>,
+        #[cfg_attr(feature = "serde", serde(with = "photon_core::serde_pairs"))]
+        properties: IndexMap<ProtocolValue<'a>, ProtocolValue<'a>>,
+        #[cfg_attr(feature = "serde", serde(with = "photon_core::serde_pairs::option"))]
+        expected_properties: Option<IndexMap<ProtocolValue<'a>, ProtocolValue<'a>>>,
         broadcast: bool,     // always true
         event_forward: bool, // not present if false
     },
@@ -210,24 +400,45 @@ pub enum Operation<'a> {
         /// Custom data associated to this event
         data: Option<ProtocolValue<'a>>,
     },
-    Leave,
+    /// Leave the current room, after which the client goes back to the master server
+    LeaveRequest {
+        /// Whether to stay in the room as an inactive actor, so the room can be rejoined later
+        is_inactive: Option<bool>,
+    },
+    LeaveResponse,
     Join,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum InternalOperation {
-    InitEncryption, // TODO: has property public_key
+    /// Starts encrypting the connection. See the `crypto` module.
+    InitEncryptionRequest {
+        /// The Diffie-Hellman public key of the client
+        public_key: Vec<u8>,
+    },
+    InitEncryptionResponse {
+        /// The Diffie-Hellman public key of the server
+        public_key: Vec<u8>,
+    },
     // TODO: server_time and local_time should be u32
-    PingRequest { local_time: i32 },
-    PingResponse { local_time: i32, server_time: i32 },
+    PingRequest {
+        local_time: i32,
+    },
+    PingResponse {
+        local_time: i32,
+        server_time: i32,
+    },
 }
 
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum Direction {
     Send,
     Recv,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 // note: removed_from_list is not present here
 // TODO: which of these are not always present for default values
 /// A room containing players. You can receive a list by joining the lobby on the master server.
@@ -244,18 +455,19 @@ pub struct RoomInfo<'a> {
     player_count: u8,
     cleanup_cache_on_leave: bool, // defaults to true
     master_client_id: Option<i32>,
-    custom_properties_lobby: Vec<&'a str>,
-    expected_users: Vec<&'a str>,
+    custom_properties_lobby: Vec<Cow<'a, str>>,
+    expected_users: Vec<Cow<'a, str>>,
     empty_room_ttl: i32,
     player_ttl: i32,
 
     /// all other string-indexed properties
-    custom_properties: HashMap<&'a str, ProtocolValue<'a>>,
+    custom_properties: IndexMap<Cow<'a, str>, ProtocolValue<'a>>,
 }
 
 // Used in CreateGame, JoinGame and JoinRandomGame
 /// Info used when creating a room or when filtering. Very similar to `RoomInfo`
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RoomOptions<'a> {
     /// Max players that fit in this room. 0 for unlimited.
     max_players: u8,
@@ -265,23 +477,75 @@ pub struct RoomOptions<'a> {
     is_visible: bool,
     /// Should be the same as the parent!
     cleanup_cache_on_leave: bool, // included if false, but always included in parent
-    custom_properties_lobby: Vec<&'a str>, // always present, even if empty
+    custom_properties_lobby: Vec<Cow<'a, str>>, // always present, even if empty
+
+    /// all other string-indexed properties
+    custom_properties: IndexMap<Cow<'a, str>, ProtocolValue<'a>>,
+}
+
+/// Room options that are sent as separate parameters when joining a room, each `None` if its parameter is not present. Newer
+/// clients also send them in `RoomOptionsFlags`.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RoomOptionParams {
+    pub suppress_room_events: Option<bool>,
+    pub cleanup_cache_on_leave: Option<bool>,
+    pub check_user_on_join: Option<bool>,
+    pub publish_user_id: Option<bool>,
+}
+
+/// The room properties that changed, as sent in `Event::PropertiesChanged`. Properties that did not change are `None`.
+#[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RoomPropertiesChange<'a> {
+    max_players: Option<u8>,
+    is_open: Option<bool>,
+    is_visible: Option<bool>,
+    player_count: Option<u8>,
+    cleanup_cache_on_leave: Option<bool>,
+    master_client_id: Option<i32>,
+    custom_properties_lobby: Option<Vec<Cow<'a, str>>>,
+    expected_users: Option<Vec<Cow<'a, str>>>,
+    empty_room_ttl: Option<i32>,
+    player_ttl: Option<i32>,
 
     /// all other string-indexed properties
-    custom_properties: HashMap<&'a str, ProtocolValue<'a>>,
+    custom_properties: IndexMap<Cow<'a, str>, ProtocolValue<'a>>,
 }
 
-#[derive(Debug, PartialEq)]
+/// Properties that changed on either the room or a player. Players use the same struct as when joining, since all of its
+/// properties are optional.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PropertiesChange<'a> {
+    Room(RoomPropertiesChange<'a>),
+    Player(Player<'a>),
+}
+
+/// Changed properties that can be applied to a `RoomInfo`, `RoomOptions` or `Player` using `apply_properties`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct PropertiesDelta<'a> {
+    /// The added/changed properties
+    pub properties: IndexMap<ProtocolValue<'a>, ProtocolValue<'a>>,
+    /// If present, the properties are only changed if all of these match their current values
+    pub expected_properties: Option<IndexMap<ProtocolValue<'a>, ProtocolValue<'a>>>,
+    /// Removes custom properties that are set to `ProtocolValue::Null`, see `RoomOptionsFlags::DELETE_NULL_PROPS`
+    pub delete_null_props: bool,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Player<'a> {
-    name: Option<&'a str>,
-    user_id: Option<&'a str>,
+    name: Option<Cow<'a, str>>,
+    user_id: Option<Cow<'a, str>>,
     is_inactive: Option<bool>,
 
     /// all other string-indexed properties
-    custom_properties: HashMap<&'a str, ProtocolValue<'a>>,
+    custom_properties: IndexMap<Cow<'a, str>, ProtocolValue<'a>>,
 }
 
 bitflags! {
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
     pub struct RoomOptionsFlags: u32 {
         const NONE = 0;
         /// toggles a check of the UserId when joining (enabling returning to a game)
@@ -299,9 +563,25 @@ bitflags! {
     }
 }
 
+bitflags! {
+    /// Filters the rooms returned by `Operation::FindFriendsRequest`
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct FindFriendsOptions: u32 {
+        const NONE = 0;
+        /// only return rooms that were created and confirmed on the GameServer
+        const CREATED_ON_GS = 0x01;
+        /// only return rooms that are visible
+        const VISIBLE = 0x02;
+        /// only return rooms that are open
+        const OPEN = 0x04;
+    }
+}
+
 // This would be an enum, but Rust does not allow multiple enum members with the same value
 #[allow(dead_code, non_upper_case_globals, non_snake_case)]
 pub mod ParameterCode {
+    pub const ClientKey: u8 = 1;
+    pub const ServerKey: u8 = 1;
     pub const FindFriendsResponseOnlineList: u8 = 1;
     pub const FindFriendsRequestList: u8 = 1;
     pub const FindFriendsResponseRoomIdList: u8 = 2;
@@ -372,6 +652,73 @@ pub mod ParameterCode {
     pub const TargetActorNr: u8 = 253;
     pub const ActorNr: u8 = 254;
     pub const RoomName: u8 = 255;
+
+    /// Gets the name of a parameter code. Codes that have multiple names get all of them, separated by a `/`.
+    pub fn name(code: u8) -> Option<&'static str> {
+        match code {
+            1 => Some("ClientKey/ServerKey/FindFriendsRequestList/FindFriendsResponseOnlineList"),
+            2 => Some("FindFriendsOptions/FindFriendsResponseRoomIdList"),
+            RoomOptionFlags => Some("RoomOptionFlags"),
+            EncryptionData => Some("EncryptionData"),
+            EncryptionMode => Some("EncryptionMode"),
+            CustomInitData => Some("CustomInitData"),
+            ExpectedProtocol => Some("ExpectedProtocol"),
+            Cluster => Some("Cluster"),
+            PluginVersion => Some("PluginVersion"),
+            PluginName => Some("PluginName"),
+            NickName => Some("NickName"),
+            MasterClientId => Some("MasterClientId"),
+            Plugins => Some("Plugins"),
+            CacheSliceIndex => Some("CacheSliceIndex"),
+            WebRpcReturnMessage => Some("WebRpcReturnMessage"),
+            WebRpcReturnCode => Some("WebRpcReturnCode"),
+            208 => Some("AzureMasterNodeId/WebRpcParameters"),
+            209 => Some("AzureLocalNodeId/UriPath"),
+            210 => Some("AzureNodeInfo/Region"),
+            LobbyStats => Some("LobbyStats"),
+            LobbyType => Some("LobbyType"),
+            LobbyName => Some("LobbyName"),
+            ClientAuthenticationData => Some("ClientAuthenticationData"),
+            215 => Some("CreateIfNotExists/JoinMode"),
+            ClientAuthenticationParams => Some("ClientAuthenticationParams"),
+            ClientAuthenticationType => Some("ClientAuthenticationType"),
+            Info => Some("Info"),
+            AppVersion => Some("AppVersion"),
+            Secret => Some("Secret"),
+            GameList => Some("GameList"),
+            223 => Some("Position/MatchMakingType"),
+            ApplicationId => Some("ApplicationId"),
+            UserId => Some("UserId"),
+            MasterPeerCount => Some("MasterPeerCount"),
+            GameCount => Some("GameCount"),
+            PeerCount => Some("PeerCount"),
+            Address => Some("Address"),
+            ExpectedValues => Some("ExpectedValues"),
+            CheckUserOnJoin => Some("CheckUserOnJoin"),
+            233 => Some("IsInactive/IsComingBack"),
+            EventForward => Some("EventForward"),
+            PlayerTTL => Some("PlayerTTL"),
+            EmptyRoomTTL => Some("EmptyRoomTTL"),
+            SuppressRoomEvents => Some("SuppressRoomEvents"),
+            Add => Some("Add"),
+            239 => Some("Remove/PublishUserId"),
+            Group => Some("Group"),
+            CleanupCacheOnLeave => Some("CleanupCacheOnLeave"),
+            Code => Some("Code"),
+            Data => Some("Data"),
+            ReceiverGroup => Some("ReceiverGroup"),
+            Cache => Some("Cache"),
+            GameProperties => Some("GameProperties"),
+            PlayerProperties => Some("PlayerProperties"),
+            Broadcast => Some("Broadcast"),
+            Properties => Some("Properties"),
+            ActorList => Some("ActorList"),
+            TargetActorNr => Some("TargetActorNr"),
+            ActorNr => Some("ActorNr"),
+            RoomName => Some("RoomName"),
+            _ => None,
+        }
+    }
 }
 
 // TODO: check if enum is better
@@ -388,6 +735,23 @@ pub mod GamePropertyKey {
     pub const ExpectedUsers: u8 = 247;
     pub const PlayerTtl: u8 = 246;
     pub const EmptyRoomTtl: u8 = 245;
+
+    pub fn name(key: u8) -> Option<&'static str> {
+        match key {
+            MaxPlayers => Some("MaxPlayers"),
+            IsVisible => Some("IsVisible"),
+            IsOpen => Some("IsOpen"),
+            PlayerCount => Some("PlayerCount"),
+            Removed => Some("Removed"),
+            PropsListedInLobby => Some("PropsListedInLobby"),
+            CleanupCacheOnLeave => Some("CleanupCacheOnLeave"),
+            MasterClientId => Some("MasterClientId"),
+            ExpectedUsers => Some("ExpectedUsers"),
+            PlayerTtl => Some("PlayerTtl"),
+            EmptyRoomTtl => Some("EmptyRoomTtl"),
+            _ => None,
+        }
+    }
 }
 
 #[allow(dead_code, non_upper_case_globals, non_snake_case)]
@@ -395,10 +759,20 @@ pub mod ActorProperties {
     pub const PlayerName: u8 = 255;
     pub const IsInactive: u8 = 254;
     pub const UserId: u8 = 253;
+
+    pub fn name(key: u8) -> Option<&'static str> {
+        match key {
+            PlayerName => Some("PlayerName"),
+            IsInactive => Some("IsInactive"),
+            UserId => Some("UserId"),
+            _ => None,
+        }
+    }
 }
 
 #[allow(dead_code, non_snake_case)]
 #[derive(Debug, PartialEq, Clone, Copy, FromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum EventCaching {
     DoNotCache = 0,
     /// obsolete
@@ -417,8 +791,37 @@ pub enum EventCaching {
     SlicePurgeUpToIndex = 13,
 }
 
+/// How JoinGame should handle a room that does not exist, or that we have been in before.
 #[allow(dead_code, non_snake_case)]
-#[derive(Debug, PartialEq, FromPrimitive)]
+#[derive(Debug, PartialEq, Clone, Copy, FromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum JoinMode {
+    /// Fails if the room does not exist
+    Default = 0,
+    /// Creates the room if it does not exist
+    CreateIfNotExists = 1,
+    /// Rejoins as an inactive player if we were in the room before, otherwise joins normally
+    JoinOrRejoin = 2,
+    /// Only rejoins as an inactive player, fails otherwise
+    RejoinOnly = 3,
+}
+
+/// How JoinRandomGame picks a room out of the rooms that match.
+#[allow(dead_code, non_snake_case)]
+#[derive(Debug, PartialEq, Clone, Copy, FromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum MatchmakingMode {
+    /// Fills up the oldest rooms first
+    FillRoom = 0,
+    /// Spreads players over the rooms in order
+    SerialMatching = 1,
+    /// Picks a random room
+    RandomMatching = 2,
+}
+
+#[allow(dead_code, non_snake_case)]
+#[derive(Debug, PartialEq, Clone, Copy, FromPrimitive)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ReceiverGroup {
     /// For everyone
     Others = 0,
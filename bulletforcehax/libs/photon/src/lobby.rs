@@ -0,0 +1,189 @@
+use super::*;
+use indexmap::IndexMap;
+
+/// The rooms listed in the lobby of the MasterServer, kept up to date using `Event::GameList` and `Event::GameListUpdate`.
+///
+/// ```rust
+/// # use photon::*;
+/// # use indexmap::indexmap;
+/// let mut lobby = Lobby::new();
+/// lobby.apply_game_list(&indexmap! {
+///     "full".into() => RoomInfo::new().with_max_players(2).with_player_count(2),
+///     "tdm".into() => RoomInfo::new().with_max_players(12).with_custom("gameMode", ProtocolValue::Integer(1)),
+/// });
+///
+/// let filter = LobbyFilter::default().not_full().with_custom("gameMode", ProtocolValue::Integer(1));
+/// let rooms: Vec<&str> = lobby.filter(&filter).map(|(name, _)| name).collect();
+/// assert_eq!(rooms, ["tdm"]);
+/// ```
+#[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Lobby {
+    rooms: IndexMap<String, RoomInfo<'static>>,
+}
+
+/// The rooms that changed after applying a game list to a `Lobby`, by name.
+#[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LobbyDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+}
+
+/// Selects rooms in a `Lobby`. The default filter matches every room.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct LobbyFilter {
+    pub min_players: Option<u8>,
+    pub max_players: Option<u8>,
+    /// Only rooms that can be joined, because they are open and not full
+    pub joinable: bool,
+    pub not_full: bool,
+    /// Custom properties that must have these values
+    pub custom_properties: IndexMap<String, ProtocolValue<'static>>,
+}
+
+impl Lobby {
+    pub fn new() -> Self {
+        Lobby { rooms: IndexMap::new() }
+    }
+
+    /// All rooms, in the order they were listed
+    pub fn rooms(&self) -> &IndexMap<String, RoomInfo<'static>> {
+        &self.rooms
+    }
+
+    pub fn get(&self, name: &str) -> Option<&RoomInfo<'static>> {
+        self.rooms.get(name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.rooms.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rooms.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.rooms.clear();
+    }
+
+    /// Applies `Event::GameList` or `Event::GameListUpdate`, and returns which rooms changed. Other events are ignored.
+    pub fn apply_event(&mut self, event: &Event) -> LobbyDiff {
+        match event {
+            Event::GameList(games) => self.apply_game_list(games),
+            Event::GameListUpdate(games) => self.apply_game_list_update(games),
+            _ => LobbyDiff::default(),
+        }
+    }
+
+    /// Replaces all rooms with the initial game list.
+    pub fn apply_game_list(&mut self, games: &IndexMap<Cow<str>, RoomInfo>) -> LobbyDiff {
+        let mut diff = LobbyDiff {
+            removed: self.rooms.keys().filter(|name| !games.contains_key(name.as_str())).cloned().collect(),
+            ..LobbyDiff::default()
+        };
+
+        let old_rooms = std::mem::take(&mut self.rooms);
+        for (name, info) in games {
+            match old_rooms.get(name.as_ref()) {
+                None => diff.added.push(name.to_string()),
+                Some(old_info) if old_info != info => diff.changed.push(name.to_string()),
+                Some(_) => (),
+            }
+            self.rooms.insert(name.to_string(), info.clone().into_owned());
+        }
+
+        diff
+    }
+
+    /// Applies an update to the game list, where rooms that are `None` were removed.
+    pub fn apply_game_list_update(&mut self, games: &IndexMap<Cow<str>, Option<RoomInfo>>) -> LobbyDiff {
+        let mut diff = LobbyDiff::default();
+
+        for (name, info) in games {
+            match info {
+                Some(info) => match self.rooms.insert(name.to_string(), info.clone().into_owned()) {
+                    None => diff.added.push(name.to_string()),
+                    Some(old_info) if &old_info != info => diff.changed.push(name.to_string()),
+                    Some(_) => (),
+                },
+                None => {
+                    if self.rooms.shift_remove(name.as_ref()).is_some() {
+                        diff.removed.push(name.to_string());
+                    }
+                }
+            }
+        }
+
+        diff
+    }
+
+    /// Returns the rooms that match `filter`, in the order they were listed.
+    pub fn filter<'l>(&'l self, filter: &'l LobbyFilter) -> impl Iterator<Item = (&'l str, &'l RoomInfo<'static>)> + 'l {
+        self.rooms
+            .iter()
+            .filter(move |(_, info)| filter.matches(info))
+            .map(|(name, info)| (name.as_str(), info))
+    }
+
+    /// Returns all rooms, sorted by `key`. Rooms with the same key stay in the order they were listed.
+    pub fn sorted_by_key<K: Ord>(&self, mut key: impl FnMut(&RoomInfo<'static>) -> K) -> Vec<(&str, &RoomInfo<'static>)> {
+        let mut rooms: Vec<_> = self.rooms.iter().map(|(name, info)| (name.as_str(), info)).collect();
+        rooms.sort_by_key(|(_, info)| key(info));
+        rooms
+    }
+
+    /// Returns all rooms, with the most players first.
+    pub fn sorted_by_player_count(&self) -> Vec<(&str, &RoomInfo<'static>)> {
+        self.sorted_by_key(|info| std::cmp::Reverse(info.player_count()))
+    }
+}
+
+impl LobbyDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl LobbyFilter {
+    pub fn min_players(mut self, min_players: u8) -> Self {
+        self.min_players = Some(min_players);
+        self
+    }
+
+    pub fn max_players(mut self, max_players: u8) -> Self {
+        self.max_players = Some(max_players);
+        self
+    }
+
+    pub fn joinable(mut self) -> Self {
+        self.joinable = true;
+        self
+    }
+
+    pub fn not_full(mut self) -> Self {
+        self.not_full = true;
+        self
+    }
+
+    pub fn with_custom(mut self, key: impl Into<String>, value: ProtocolValue<'static>) -> Self {
+        self.custom_properties.insert(key.into(), value);
+        self
+    }
+
+    /// Returns true if the room matches this filter
+    pub fn matches(&self, info: &RoomInfo) -> bool {
+        let is_full = info.max_players() != 0 && info.player_count() >= info.max_players();
+
+        self.min_players.is_none_or(|min| info.player_count() >= min)
+            && self.max_players.is_none_or(|max| info.player_count() <= max)
+            && !(self.joinable && (!info.is_open() || is_full))
+            && !(self.not_full && is_full)
+            && self
+                .custom_properties
+                .iter()
+                .all(|(key, value)| info.custom_properties().get(key.as_str()) == Some(value))
+    }
+}
@@ -0,0 +1,128 @@
+#![cfg(test)]
+
+use super::*;
+use indexmap::indexmap;
+
+fn lobby() -> Lobby {
+    let mut lobby = Lobby::new();
+    lobby.apply_game_list(&indexmap! {
+        "empty".into() => RoomInfo::new().with_max_players(12),
+        "closed".into() => RoomInfo::new().with_max_players(12).with_player_count(3).with_is_open(false),
+        "full".into() => RoomInfo::new().with_max_players(2).with_player_count(2).with_custom("gameMode", ProtocolValue::Integer(0)),
+        "tdm".into() => RoomInfo::new().with_max_players(12).with_player_count(5).with_custom("gameMode", ProtocolValue::Integer(0)),
+    });
+    lobby
+}
+
+fn names<'l>(rooms: impl IntoIterator<Item = (&'l str, &'l RoomInfo<'static>)>) -> Vec<&'l str> {
+    rooms.into_iter().map(|(name, _)| name).collect()
+}
+
+#[test]
+fn game_list_replaces_rooms() {
+    let mut lobby = lobby();
+    let diff = lobby.apply_game_list(&indexmap! {
+        "empty".into() => RoomInfo::new().with_max_players(12),
+        "tdm".into() => RoomInfo::new().with_max_players(12).with_player_count(6),
+        "new".into() => RoomInfo::new(),
+    });
+
+    assert_eq!(
+        diff,
+        LobbyDiff {
+            added: vec!["new".into()],
+            removed: vec!["closed".into(), "full".into()],
+            changed: vec!["tdm".into()],
+        }
+    );
+    assert_eq!(lobby.rooms().keys().collect::<Vec<_>>(), ["empty", "tdm", "new"]);
+}
+
+#[test]
+fn game_list_update() {
+    let mut lobby = lobby();
+    let diff = lobby.apply_game_list_update(&indexmap! {
+        "empty".into() => Some(RoomInfo::new().with_max_players(12).with_player_count(1)),
+        "closed".into() => None,
+        "unknown".into() => None,
+        "new".into() => Some(RoomInfo::new()),
+        "tdm".into() => Some(lobby.get("tdm").unwrap().clone()),
+    });
+
+    assert_eq!(
+        diff,
+        LobbyDiff {
+            added: vec!["new".into()],
+            removed: vec!["closed".into()],
+            changed: vec!["empty".into()],
+        }
+    );
+    assert_eq!(lobby.len(), 4);
+    assert_eq!(lobby.get("empty").map(RoomInfo::player_count), Some(1));
+    assert!(lobby.get("closed").is_none());
+}
+
+#[test]
+fn other_events_are_ignored() {
+    let mut lobby = lobby();
+    let diff = lobby.apply_event(&Event::AppStats {
+        master_peer_count: 1,
+        peer_count: 2,
+        game_count: 3,
+    });
+
+    assert!(diff.is_empty());
+    assert_eq!(lobby.len(), 4);
+}
+
+#[test]
+fn filter() {
+    let lobby = lobby();
+
+    assert_eq!(names(lobby.filter(&LobbyFilter::default())), ["empty", "closed", "full", "tdm"]);
+    assert_eq!(names(lobby.filter(&LobbyFilter::default().joinable())), ["empty", "tdm"]);
+    assert_eq!(names(lobby.filter(&LobbyFilter::default().not_full())), ["empty", "closed", "tdm"]);
+    assert_eq!(
+        names(lobby.filter(&LobbyFilter::default().min_players(2).max_players(4))),
+        ["closed", "full"]
+    );
+    assert_eq!(
+        names(lobby.filter(&LobbyFilter::default().with_custom("gameMode", ProtocolValue::Integer(0)))),
+        ["full", "tdm"]
+    );
+    assert!(names(lobby.filter(&LobbyFilter::default().with_custom("gameMode", ProtocolValue::Byte(0)))).is_empty());
+}
+
+#[test]
+fn sort() {
+    let lobby = lobby();
+
+    assert_eq!(names(lobby.sorted_by_player_count()), ["tdm", "closed", "full", "empty"]);
+    assert_eq!(
+        names(lobby.sorted_by_key(|info| info.get_custom::<i32>("gameMode"))),
+        ["empty", "closed", "full", "tdm"]
+    );
+}
+
+#[test]
+fn malformed_game_list() {
+    // removed rooms are only allowed in updates
+    let params = indexmap! {
+        ParameterCode::GameList => ProtocolValue::Hashtable(indexmap! {
+            ProtocolValue::String("room".into()) => ProtocolValue::Hashtable(indexmap! {
+                ProtocolValue::Byte(GamePropertyKey::Removed) => ProtocolValue::Bool(true),
+            }),
+        }),
+    };
+    match Event::read(230, params) {
+        Err(PacketReadError::InvalidParameterValue(ParameterCode::GameList)) => (),
+        other => panic!("Expected InvalidParameterValue, got {:?}", other),
+    }
+
+    let params = indexmap! {
+        ParameterCode::GameList => ProtocolValue::Hashtable(indexmap! {
+            ProtocolValue::String("room".into()) => ProtocolValue::Integer(1),
+        }),
+    };
+    assert!(Event::read(229, params).is_err());
+}
@@ -1,14 +1,17 @@
 use super::*;
-use std::collections::HashMap;
+use indexmap::IndexMap;
 
 macro_rules! gen_protocol_type_functions {
     ($type_name:ident, $type:ty, $protocol_type:path) => {
+        gen_protocol_type_functions!($type_name, $type, i, $protocol_type(i));
+    };
+    ($type_name:ident, $type:ty, $value:ident, $pattern:pat) => {
         paste::item! {
             /// "Unwraps" a `ProtocolValue`, returning the internal type or an error if it doesn't match.
             #[allow(clippy::needless_lifetimes)]
             pub fn [<unwrap_protocol_ $type_name>]<'a>(protocol_type: ProtocolValue<'a>) -> PacketReadResult<$type> {
                 match protocol_type {
-                    $protocol_type(i) => Ok(i),
+                    $pattern => Ok($value),
                     _ => Err(PacketReadError::UnexpectedProtocolValue),
                 }
             }
@@ -17,49 +20,53 @@ macro_rules! gen_protocol_type_functions {
             /// Returns an error if a value could not be unwrapped.
             #[allow(dead_code)]
             pub fn [<get_protocol_ $type_name _opt>]<'a>(
-                map: &mut HashMap<ProtocolValue<'a>, ProtocolValue<'a>>,
+                map: &mut IndexMap<ProtocolValue<'a>, ProtocolValue<'a>>,
                 key: ProtocolValue<'static>,
             ) -> PacketReadResult<Option<$type>> {
-                map.remove(&key).and_then(|x| Some([<unwrap_protocol_ $type_name>](x))).transpose()
+                map.shift_remove(&key).and_then(|x| Some([<unwrap_protocol_ $type_name>](x))).transpose()
             }
 
             /// Extracts the type from `map` at key `param_code`, returning an error when not found.
             /// Also returns an error if a value could not be unwrapped.
             #[allow(dead_code)]
-            pub fn [<get_protocol_ $type_name>]<'a>(map: &mut HashMap<ProtocolValue<'a>, ProtocolValue<'a>>, key: ProtocolValue<'static>) -> PacketReadResult<$type> {
+            pub fn [<get_protocol_ $type_name>]<'a>(map: &mut IndexMap<ProtocolValue<'a>, ProtocolValue<'a>>, key: ProtocolValue<'static>) -> PacketReadResult<$type> {
                 // duplicated code because of lifetime issue. honestly can't be bothered to fix it.
-                map.remove(&key).and_then(|x| Some([<unwrap_protocol_ $type_name>](x))).unwrap_or(Err(PacketReadError::CouldNotFindKeyProtocolValue(key)))
+                map.shift_remove(&key).and_then(|x| Some([<unwrap_protocol_ $type_name>](x))).unwrap_or(Err(PacketReadError::CouldNotFindKeyProtocolValue(key)))
             }
 
             /// Extracts the type from `map` at key `param_code`, returning an None when not found.
             /// Returns an error if a value could not be unwrapped.
             #[allow(dead_code)]
-            pub fn [<get_u8_ $type_name _opt>]<'a>(map: &mut HashMap<u8, ProtocolValue<'a>>, param_code: u8) -> PacketReadResult<Option<$type>> {
-                map.remove(&param_code).and_then(|x| Some([<unwrap_protocol_ $type_name>](x))).transpose()
+            pub fn [<get_u8_ $type_name _opt>]<'a>(map: &mut IndexMap<u8, ProtocolValue<'a>>, param_code: u8) -> PacketReadResult<Option<$type>> {
+                map.shift_remove(&param_code).and_then(|x| Some([<unwrap_protocol_ $type_name>](x))).transpose()
             }
 
             /// Extracts the type from `map` at key `param_code`, returning an error when not found.
             /// Also returns an error if a value could not be unwrapped.
             #[allow(dead_code)]
-            pub fn [<get_u8_ $type_name>]<'a>(map: &mut HashMap<u8, ProtocolValue<'a>>, param_code: u8) -> PacketReadResult<$type> {
+            pub fn [<get_u8_ $type_name>]<'a>(map: &mut IndexMap<u8, ProtocolValue<'a>>, param_code: u8) -> PacketReadResult<$type> {
                 [<get_u8_ $type_name _opt>](map, param_code).transpose().unwrap_or(Err(PacketReadError::CouldNotFindKey(param_code)))
             }
         }
     };
 }
 
-gen_protocol_type_functions!(string, &'a str, ProtocolValue::String);
+gen_protocol_type_functions!(string, Cow<'a, str>, ProtocolValue::String);
 gen_protocol_type_functions!(bool, bool, ProtocolValue::Bool);
 gen_protocol_type_functions!(byte, u8, ProtocolValue::Byte);
 gen_protocol_type_functions!(short, i16, ProtocolValue::Short);
 gen_protocol_type_functions!(int, i32, ProtocolValue::Integer);
 gen_protocol_type_functions!(float, f32, ProtocolValue::Float);
-gen_protocol_type_functions!(hashtable, HashMap<ProtocolValue<'a>, ProtocolValue<'a>>, ProtocolValue::Hashtable);
-gen_protocol_type_functions!(array, Vec<ProtocolValue<'a>>, ProtocolValue::Array);
+gen_protocol_type_functions!(hashtable, IndexMap<ProtocolValue<'a>, ProtocolValue<'a>>, ProtocolValue::Hashtable);
+gen_protocol_type_functions!(array, Vec<ProtocolValue<'a>>, values, ProtocolValue::Array(_, values));
+gen_protocol_type_functions!(object_array, Vec<ProtocolValue<'a>>, ProtocolValue::ObjectArray);
+gen_protocol_type_functions!(string_array, Vec<Cow<'a, str>>, ProtocolValue::StringArray);
+gen_protocol_type_functions!(byte_array, Vec<u8>, ProtocolValue::ByteArray);
+gen_protocol_type_functions!(int_array, Vec<i32>, ProtocolValue::IntegerArray);
 
 /// Extracts a `ProtocolValue::Array` from `map` at key `param_code`, unwraps its items using `unwrap_fn`, and returns it (or None when not present).
 /// Returns an error if a value could not be unwrapped.
-pub fn get_u8_array_or_none<T, F>(map: &mut HashMap<u8, ProtocolValue>, param_code: u8, unwrap_fn: F) -> PacketReadResult<Option<Vec<T>>>
+pub fn get_u8_array_or_none<T, F>(map: &mut IndexMap<u8, ProtocolValue>, param_code: u8, unwrap_fn: F) -> PacketReadResult<Option<Vec<T>>>
 where
     F: Fn(ProtocolValue) -> PacketReadResult<T>,
 {
@@ -71,9 +78,19 @@ where
 /// Extracts a `ProtocolValue::Array` from `map` at key `param_code`, unwraps its items using `unwrap_fn`, and returns it (or an empty `Vec<T>` when not present).
 /// Returns an error if a value could not be unwrapped.
 #[allow(dead_code)]
-pub fn get_u8_array_or_empty<T, F>(map: &mut HashMap<u8, ProtocolValue>, param_code: u8, unwrap_fn: F) -> PacketReadResult<Vec<T>>
+pub fn get_u8_array_or_empty<T, F>(map: &mut IndexMap<u8, ProtocolValue>, param_code: u8, unwrap_fn: F) -> PacketReadResult<Vec<T>>
 where
     F: Fn(ProtocolValue) -> PacketReadResult<T>,
 {
     get_u8_array_opt(map, param_code)?.map_or(Ok(Vec::new()), |arr| arr.into_iter().map(unwrap_fn).collect())
 }
+
+/// Copies a string if needed, so it no longer borrows from the deserialized data.
+pub fn owned_string(string: Cow<str>) -> Cow<'static, str> {
+    Cow::Owned(string.into_owned())
+}
+
+/// Copies the keys and values of a string-indexed map, so it no longer borrows from the deserialized data.
+pub fn owned_string_map(map: IndexMap<Cow<str>, ProtocolValue>) -> IndexMap<Cow<'static, str>, ProtocolValue<'static>> {
+    map.into_iter().map(|(key, value)| (owned_string(key), value.into_owned())).collect()
+}
@@ -1,87 +1,265 @@
 use super::macros::*;
 use super::*;
+use indexmap::indexmap;
+use indexmap::IndexMap;
 use log::{debug, warn};
-use maplit::hashmap;
 use num_traits::cast::FromPrimitive;
-use std::collections::HashMap;
-use std::convert::{TryFrom, TryInto};
+use std::convert::TryFrom;
 
-type ParameterTable<'a> = HashMap<u8, ProtocolValue<'a>>;
+type ParameterTable<'a> = IndexMap<u8, ProtocolValue<'a>>;
+
+/// Length of the init data, excluding the packet header
+const INIT_LENGTH: usize = 7 + INIT_APP_ID_LENGTH;
+const INIT_APP_ID_LENGTH: usize = 32;
 
 impl Packet<'_> {
     pub fn read<'a>(data: &'a [u8], direction: Direction) -> PacketReadResult<Packet<'a>> {
-        let photon_packet = PhotonPacket::try_from(data)?;
+        Packet::read_with_protocol(data, direction, Protocol::GpBinaryV16)
+    }
+
+    pub fn read_with_protocol<'a>(data: &'a [u8], direction: Direction, protocol: Protocol) -> PacketReadResult<Packet<'a>> {
+        Packet::from_photon_packet(PhotonPacket::read(data, protocol)?, direction)
+    }
 
+    /// Reads a packet from its raw parameters, such as after changing them. This is the opposite of `into_photon_packet`.
+    pub fn from_photon_packet(photon_packet: PhotonPacket<'_>, direction: Direction) -> PacketReadResult<Packet<'_>> {
         match photon_packet {
-            PhotonPacket::OperationRequest(packet_type, params) => Ok(Packet::OperationRequest(Operation::read(packet_type, params, direction)?)),
+            PhotonPacket::Init(Cow::Borrowed(data)) => Packet::read_init(data),
+            PhotonPacket::Init(Cow::Owned(data)) => Packet::read_init(&data).map(Packet::into_owned),
+            PhotonPacket::InitResponse(data) => Ok(Packet::InitResponse(data)),
+            PhotonPacket::OperationRequest(packet_type, params) => {
+                let order = ParameterOrder::of(&params);
+                Ok(Packet::OperationRequest(Operation::read(packet_type, params, direction)?, order))
+            }
             PhotonPacket::OperationResponse(packet_type, mut params, return_code, debug_string) => Ok(Packet::OperationResponse {
+                order: ParameterOrder::of(&params),
                 return_code,
                 debug_string,
-                secret: params.remove(&ParameterCode::Secret).map(unwrap_protocol_string).transpose()?,
+                secret: params.shift_remove(&ParameterCode::Secret).map(unwrap_protocol_string).transpose()?,
                 parameters: Operation::read(packet_type, params, direction)?,
             }),
             PhotonPacket::Event(packet_type, mut params) => Ok(Packet::Event {
-                sender: params.remove(&ParameterCode::ActorNr).map(unwrap_protocol_int).transpose()?,
-                custom_data: params.remove(&ParameterCode::Data),
+                order: ParameterOrder::of(&params),
+                // in the Leave event, the actor number is the actor that left and is part of the event itself
+                sender: match packet_type {
+                    254 => None,
+                    _ => params.shift_remove(&ParameterCode::ActorNr).map(unwrap_protocol_int).transpose()?,
+                },
+                custom_data: params.shift_remove(&ParameterCode::Data),
                 parameters: Event::read(packet_type, params)?,
             }),
             PhotonPacket::InternalOperationRequest(packet_type, params) => {
-                Ok(Packet::InternalOperationRequest(InternalOperation::read(packet_type, params, direction)?))
+                let order = ParameterOrder::of(&params);
+                Ok(Packet::InternalOperationRequest(
+                    InternalOperation::read(packet_type, params, direction)?,
+                    order,
+                ))
+            }
+            PhotonPacket::InternalOperationResponse(packet_type, params, return_code, debug_string) => {
+                let order = ParameterOrder::of(&params);
+                Ok(Packet::InternalOperationResponse(
+                    InternalOperation::read(packet_type, params, direction)?,
+                    return_code,
+                    debug_string,
+                    order,
+                ))
             }
-            PhotonPacket::InternalOperationResponse(packet_type, params, return_code, debug_string) => Ok(Packet::InternalOperationResponse(
-                InternalOperation::read(packet_type, params, direction)?,
+            PhotonPacket::Message(value) => Ok(Packet::Message(value)),
+            PhotonPacket::RawMessage(data) => Ok(Packet::RawMessage(data)),
+        }
+    }
+
+    fn read_init(data: &[u8]) -> PacketReadResult<Packet<'_>> {
+        if data.len() != INIT_LENGTH {
+            return Err(PacketReadError::InvalidInitLength(data.len()));
+        }
+
+        // the app id is padded with null bytes
+        let app_id = &data[7..];
+        let app_id_len = app_id.iter().rposition(|b| *b != 0).map_or(0, |i| i + 1);
+
+        Ok(Packet::Init {
+            protocol_version: (data[0], data[1]),
+            client_sdk_id: data[2],
+            client_version: (data[3] >> 4, data[3] & 0x0F, data[4], data[5]),
+            flags: data[6],
+            app_id: Cow::Borrowed(std::str::from_utf8(&app_id[..app_id_len]).map_err(PhotonReadError::from)?),
+        })
+    }
+
+    pub fn into_vec(self) -> PacketWriteResult<Vec<u8>> {
+        self.into_vec_with_protocol(Protocol::GpBinaryV16)
+    }
+
+    pub fn into_vec_with_protocol(self, protocol: Protocol) -> PacketWriteResult<Vec<u8>> {
+        Ok(self.into_photon_packet()?.write(protocol)?)
+    }
+
+    /// Converts this packet into one that does not borrow from the deserialized data, copying strings where needed.
+    pub fn into_owned(self) -> Packet<'static> {
+        match self {
+            Packet::Init {
+                protocol_version,
+                client_sdk_id,
+                client_version,
+                flags,
+                app_id,
+            } => Packet::Init {
+                protocol_version,
+                client_sdk_id,
+                client_version,
+                flags,
+                app_id: owned_string(app_id),
+            },
+            Packet::InitResponse(data) => Packet::InitResponse(Cow::Owned(data.into_owned())),
+            Packet::OperationRequest(operation, order) => Packet::OperationRequest(operation.into_owned(), order),
+            Packet::OperationResponse {
+                parameters,
                 return_code,
                 debug_string,
-            )),
+                secret,
+                order,
+            } => Packet::OperationResponse {
+                parameters: parameters.into_owned(),
+                return_code,
+                debug_string: debug_string.map(owned_string),
+                secret: secret.map(owned_string),
+                order,
+            },
+            Packet::Event {
+                parameters,
+                custom_data,
+                sender,
+                order,
+            } => Packet::Event {
+                parameters: parameters.into_owned(),
+                custom_data: custom_data.map(ProtocolValue::into_owned),
+                sender,
+                order,
+            },
+            Packet::InternalOperationRequest(operation, order) => Packet::InternalOperationRequest(operation, order),
+            Packet::InternalOperationResponse(operation, return_code, debug_string, order) => {
+                Packet::InternalOperationResponse(operation, return_code, debug_string.map(owned_string), order)
+            }
+            Packet::Message(value) => Packet::Message(value.into_owned()),
+            Packet::RawMessage(data) => Packet::RawMessage(Cow::Owned(data.into_owned())),
         }
     }
+}
 
-    pub fn into_vec(self) -> PacketWriteResult<Vec<u8>> {
-        let photon_packet: PhotonPacket = match self {
-            Packet::OperationRequest(operation) => PhotonPacket::OperationRequest(operation.get_type(), operation.get_param_map()?),
+impl<'s> Packet<'s> {
+    /// Converts this packet into the untyped packet it is serialized as. The parameters are in the order they were read in, if
+    /// this packet was read.
+    pub fn into_photon_packet(self) -> PacketWriteResult<PhotonPacket<'s>> {
+        Ok(match self {
+            Packet::Init {
+                protocol_version,
+                client_sdk_id,
+                client_version,
+                flags,
+                app_id,
+            } => {
+                let mut data = vec![
+                    protocol_version.0,
+                    protocol_version.1,
+                    client_sdk_id,
+                    client_version.0 << 4 | (client_version.1 & 0x0F),
+                    client_version.2,
+                    client_version.3,
+                    flags,
+                ];
+                let app_id = app_id.as_bytes();
+                data.extend_from_slice(&app_id[..app_id.len().min(INIT_APP_ID_LENGTH)]);
+                data.resize(INIT_LENGTH, 0);
+
+                PhotonPacket::Init(Cow::Owned(data))
+            }
+            Packet::InitResponse(data) => PhotonPacket::InitResponse(data),
+            Packet::OperationRequest(operation, order) => {
+                PhotonPacket::OperationRequest(operation.get_type(), order.apply(operation.get_param_map()?))
+            }
             Packet::OperationResponse {
                 parameters,
                 return_code,
                 debug_string,
                 secret,
+                order,
             } => {
                 let packet_type = parameters.get_type();
                 let mut map = parameters.get_param_map()?;
-                secret.and_then(|s| map.insert(ParameterCode::Secret, ProtocolValue::String(s)));
-                PhotonPacket::OperationResponse(packet_type, map, return_code, debug_string)
+                if let Some(secret) = secret {
+                    // the server sends the secret right after the address of the next server, if there is one
+                    match map.get_index_of(&ParameterCode::Address) {
+                        Some(index) => map.shift_insert(index + 1, ParameterCode::Secret, ProtocolValue::String(secret)),
+                        None => map.insert(ParameterCode::Secret, ProtocolValue::String(secret)),
+                    };
+                }
+                PhotonPacket::OperationResponse(packet_type, order.apply(map), return_code, debug_string)
             }
             Packet::Event {
                 sender,
                 custom_data,
                 parameters,
+                order,
             } => {
                 let packet_type = parameters.get_type();
                 let mut param_map = parameters.get_param_map()?;
                 sender.and_then(|s| param_map.insert(ParameterCode::ActorNr, ProtocolValue::Integer(s)));
                 custom_data.and_then(|d| param_map.insert(ParameterCode::Data, d));
-                PhotonPacket::Event(packet_type, param_map)
+                PhotonPacket::Event(packet_type, order.apply(param_map))
             }
-            Packet::InternalOperationRequest(operation) => PhotonPacket::InternalOperationRequest(operation.get_type(), operation.get_param_map()?),
-            Packet::InternalOperationResponse(operation, return_code, debug_string) => {
-                (PhotonPacket::InternalOperationResponse(operation.get_type(), operation.get_param_map()?, return_code, debug_string))
+            Packet::InternalOperationRequest(operation, order) => {
+                PhotonPacket::InternalOperationRequest(operation.get_type(), order.apply(operation.get_param_map()?))
             }
-        };
+            Packet::InternalOperationResponse(operation, return_code, debug_string, order) => {
+                PhotonPacket::InternalOperationResponse(operation.get_type(), order.apply(operation.get_param_map()?), return_code, debug_string)
+            }
+            Packet::Message(value) => PhotonPacket::Message(value),
+            Packet::RawMessage(data) => PhotonPacket::RawMessage(data),
+        })
+    }
+}
 
-        Ok(photon_packet.try_into()?)
+impl ParameterOrder {
+    /// The order of the parameters in `params`
+    pub fn of(params: &ParameterTable) -> Self {
+        ParameterOrder(params.keys().copied().collect())
+    }
+
+    /// Sorts `params` in this order. Parameters that are not in this order keep their order, after the others.
+    pub fn apply<'a>(&self, mut params: ParameterTable<'a>) -> ParameterTable<'a> {
+        if !self.0.is_empty() {
+            let position = |code: &u8| self.0.iter().position(|c| c == code).unwrap_or(self.0.len());
+            params.sort_by(|a, _, b, _| position(a).cmp(&position(b)));
+        }
+        params
+    }
+}
+
+impl PartialEq for ParameterOrder {
+    fn eq(&self, _other: &Self) -> bool {
+        true
     }
 }
 
 impl<'s> Event<'s> {
     pub fn read<'a>(event_type: u8, mut params: ParameterTable<'a>) -> PacketReadResult<Event<'a>> {
-        fn err<'a>(event: Event<'static>, params: &HashMap<u8, ProtocolValue>) -> PacketReadResult<Event<'a>> {
+        fn err<'a>(event: Event<'static>, params: &IndexMap<u8, ProtocolValue>) -> PacketReadResult<Event<'a>> {
             debug!("Unimplemented Event: {:?}, {:#?}", event, params);
             Err(PacketReadError::UnimplementedEventType(event))
         }
 
         let ret = match event_type {
             210 => err(Event::AzureNodeInfo, &params),
-            223 => err(Event::AuthEvent, &params),
-            224 => err(Event::LobbyStats, &params),
+            223 => Ok(Event::AuthEvent {
+                token: get_u8_string(&mut params, ParameterCode::Secret)?,
+            }),
+            224 => Ok(Event::LobbyStats {
+                names: get_u8_string_array(&mut params, ParameterCode::LobbyName)?,
+                types: get_u8_byte_array(&mut params, ParameterCode::LobbyType)?,
+                peer_counts: get_u8_int_array(&mut params, ParameterCode::PeerCount)?,
+                game_counts: get_u8_int_array(&mut params, ParameterCode::GameCount)?,
+            }),
             226 => Ok(Event::AppStats {
                 game_count: get_u8_int(&mut params, ParameterCode::GameCount)?,
                 peer_count: get_u8_int(&mut params, ParameterCode::PeerCount)?,
@@ -93,18 +271,31 @@ impl<'s> Event<'s> {
                 &mut params,
                 ParameterCode::GameList,
             )?)?)),
-            230 => Ok(Event::GameList(
-                RoomInfo::try_from_hashtable_table(get_u8_hashtable(&mut params, ParameterCode::GameList)?).map(|mut table| {
-                    table
-                        .drain()
-                        .map(|(key, value)| (key, value.expect("GameList packet contained removed game")))
-                        .collect()
-                })?,
-            )),
-            250 => err(Event::CacheSliceChanged, &params),
-            251 => err(Event::ErrorInfo, &params),
-            253 => err(Event::PropertiesChanged, &params),
-            254 => err(Event::Leave, &params),
+            230 => Ok(Event::GameList(get_game_list(&mut params)?)),
+            250 => Ok(Event::CacheSliceChanged {
+                index: get_u8_int(&mut params, ParameterCode::CacheSliceIndex)?,
+            }),
+            251 => Ok(Event::ErrorInfo {
+                info: get_u8_string(&mut params, ParameterCode::Info)?,
+            }),
+            253 => {
+                let target_actor = get_u8_int(&mut params, ParameterCode::TargetActorNr)?;
+                let properties = get_u8_hashtable(&mut params, ParameterCode::Properties)?;
+                Ok(Event::PropertiesChanged {
+                    target_actor,
+                    // room properties are sent with actor 0
+                    properties: match target_actor {
+                        0 => PropertiesChange::Room(RoomPropertiesChange::try_from(properties)?),
+                        _ => PropertiesChange::Player(Player::try_from(properties)?),
+                    },
+                })
+            }
+            254 => Ok(Event::Leave {
+                actor_nr: get_u8_int(&mut params, ParameterCode::ActorNr)?,
+                actor_list: get_u8_array_or_none(&mut params, ParameterCode::ActorList, unwrap_protocol_int)?,
+                is_inactive: get_u8_bool_opt(&mut params, ParameterCode::IsInactive)?,
+                new_master_client_id: get_u8_int_opt(&mut params, ParameterCode::MasterClientId)?,
+            }),
             255 => Ok(Event::Join {
                 actor_list: get_u8_array_or_none(&mut params, ParameterCode::ActorList, unwrap_protocol_int)?,
                 player_properties: Player::try_from(get_u8_hashtable(&mut params, ParameterCode::PlayerProperties)?)?,
@@ -122,94 +313,305 @@ impl<'s> Event<'s> {
     pub fn get_type(&self) -> u8 {
         match self {
             Event::AzureNodeInfo => 210,
-            Event::AuthEvent => 223,
-            Event::LobbyStats => 224,
+            Event::AuthEvent { .. } => 223,
+            Event::LobbyStats { .. } => 224,
             Event::AppStats { .. } => 226,
             Event::Match => 227,
             Event::QueueState => 228,
             Event::GameListUpdate(_) => 229,
             Event::GameList(_) => 230,
-            Event::CacheSliceChanged => 250,
-            Event::ErrorInfo => 251,
-            Event::PropertiesChanged => 253,
-            Event::Leave => 254,
+            Event::CacheSliceChanged { .. } => 250,
+            Event::ErrorInfo { .. } => 251,
+            Event::PropertiesChanged { .. } => 253,
+            Event::Leave { .. } => 254,
             Event::Join { .. } => 255,
         }
     }
 
-    pub fn get_param_map(self) -> PacketWriteResult<HashMap<u8, ProtocolValue<'s>>> {
-        fn err(event: Event<'static>) -> PacketWriteResult<HashMap<u8, ProtocolValue>> {
+    pub fn get_param_map(self) -> PacketWriteResult<IndexMap<u8, ProtocolValue<'s>>> {
+        fn err(event: Event<'static>) -> PacketWriteResult<IndexMap<u8, ProtocolValue<'static>>> {
             Err(PacketWriteError::UnimplementedEventType(event))
         }
 
         match self {
             Event::AzureNodeInfo => err(Event::AzureNodeInfo),
-            Event::AuthEvent => err(Event::AuthEvent),
-            Event::LobbyStats => err(Event::LobbyStats),
+            Event::AuthEvent { token } => Ok(indexmap! {
+                ParameterCode::Secret => ProtocolValue::String(token),
+            }),
+            Event::LobbyStats {
+                names,
+                types,
+                peer_counts,
+                game_counts,
+            } => Ok(indexmap! {
+                ParameterCode::LobbyName => ProtocolValue::StringArray(names),
+                ParameterCode::LobbyType => ProtocolValue::ByteArray(types),
+                ParameterCode::PeerCount => ProtocolValue::IntegerArray(peer_counts),
+                ParameterCode::GameCount => ProtocolValue::IntegerArray(game_counts),
+            }),
             Event::AppStats {
                 game_count,
                 peer_count,
                 master_peer_count,
-            } => Ok(hashmap! {
+            } => Ok(indexmap! {
                 ParameterCode::GameCount => ProtocolValue::Integer(game_count),
                 ParameterCode::PeerCount => ProtocolValue::Integer(peer_count),
                 ParameterCode::MasterPeerCount => ProtocolValue::Integer(master_peer_count),
             }),
             Event::Match => err(Event::Match),
             Event::QueueState => err(Event::QueueState),
-            Event::GameListUpdate(info) => Ok(hashmap! {
+            Event::GameListUpdate(info) => Ok(indexmap! {
                 ParameterCode::GameList => ProtocolValue::Hashtable(info
                     .into_iter()
                     .map(|(k, v)| (ProtocolValue::String(k), ProtocolValue::Hashtable(match v {
                         Some(m) => m.into(),
-                        None => hashmap! (ProtocolValue::Byte(GamePropertyKey::Removed) => ProtocolValue::Bool(true)),
+                        None => indexmap! (ProtocolValue::Byte(GamePropertyKey::Removed) => ProtocolValue::Bool(true)),
                     })))
                     .collect())
             }),
-            Event::GameList(info) => Ok(hashmap! {
-                ParameterCode::GameList => ProtocolValue::Hashtable(info
-                    .into_iter()
-                    .map(|(k, v)| (ProtocolValue::String(k), ProtocolValue::Hashtable(v.into())))
-                    .collect())
+            Event::GameList(info) => Ok(indexmap! {
+                ParameterCode::GameList => game_list_value(info),
+            }),
+            Event::CacheSliceChanged { index } => Ok(indexmap! {
+                ParameterCode::CacheSliceIndex => ProtocolValue::Integer(index),
+            }),
+            Event::ErrorInfo { info } => Ok(indexmap! {
+                ParameterCode::Info => ProtocolValue::String(info),
+            }),
+            Event::PropertiesChanged { target_actor, properties } => Ok(indexmap! {
+                ParameterCode::TargetActorNr => ProtocolValue::Integer(target_actor),
+                ParameterCode::Properties => ProtocolValue::Hashtable(properties.into()),
             }),
-            Event::CacheSliceChanged => err(Event::CacheSliceChanged),
-            Event::ErrorInfo => err(Event::ErrorInfo),
-            Event::PropertiesChanged => err(Event::PropertiesChanged),
-            Event::Leave => err(Event::Leave),
+            Event::Leave {
+                actor_nr,
+                actor_list,
+                is_inactive,
+                new_master_client_id,
+            } => {
+                let mut map = indexmap!();
+                actor_list.and_then(|list| {
+                    map.insert(
+                        ParameterCode::ActorList,
+                        ProtocolValue::Array(105, list.into_iter().map(ProtocolValue::Integer).collect()),
+                    )
+                });
+                is_inactive.and_then(|i| map.insert(ParameterCode::IsInactive, ProtocolValue::Bool(i)));
+                new_master_client_id.and_then(|id| map.insert(ParameterCode::MasterClientId, ProtocolValue::Integer(id)));
+                map.insert(ParameterCode::ActorNr, ProtocolValue::Integer(actor_nr));
+                Ok(map)
+            }
             Event::Join {
                 actor_list,
                 player_properties,
             } => {
-                let mut map = hashmap! { ParameterCode::PlayerProperties => ProtocolValue::Hashtable(player_properties.into()) };
+                let mut map = indexmap! { ParameterCode::PlayerProperties => ProtocolValue::Hashtable(player_properties.into()) };
                 actor_list.and_then(|list| {
                     map.insert(
                         ParameterCode::ActorList,
-                        ProtocolValue::Array(list.into_iter().map(ProtocolValue::Integer).collect()),
+                        ProtocolValue::Array(105, list.into_iter().map(ProtocolValue::Integer).collect()),
                     )
                 });
                 Ok(map)
             }
         }
     }
+
+    /// Converts this event into one that does not borrow from the deserialized data, copying strings where needed.
+    pub fn into_owned(self) -> Event<'static> {
+        match self {
+            Event::AzureNodeInfo => Event::AzureNodeInfo,
+            Event::AuthEvent { token } => Event::AuthEvent { token: owned_string(token) },
+            Event::LobbyStats {
+                names,
+                types,
+                peer_counts,
+                game_counts,
+            } => Event::LobbyStats {
+                names: names.into_iter().map(owned_string).collect(),
+                types,
+                peer_counts,
+                game_counts,
+            },
+            Event::AppStats {
+                game_count,
+                peer_count,
+                master_peer_count,
+            } => Event::AppStats {
+                game_count,
+                peer_count,
+                master_peer_count,
+            },
+            Event::Match => Event::Match,
+            Event::QueueState => Event::QueueState,
+            Event::GameList(games) => Event::GameList(games.into_iter().map(|(k, v)| (owned_string(k), v.into_owned())).collect()),
+            Event::GameListUpdate(games) => {
+                Event::GameListUpdate(games.into_iter().map(|(k, v)| (owned_string(k), v.map(RoomInfo::into_owned))).collect())
+            }
+            Event::CacheSliceChanged { index } => Event::CacheSliceChanged { index },
+            Event::ErrorInfo { info } => Event::ErrorInfo { info: owned_string(info) },
+            Event::PropertiesChanged { target_actor, properties } => Event::PropertiesChanged {
+                target_actor,
+                properties: properties.into_owned(),
+            },
+            Event::Leave {
+                actor_nr,
+                actor_list,
+                is_inactive,
+                new_master_client_id,
+            } => Event::Leave {
+                actor_nr,
+                actor_list,
+                is_inactive,
+                new_master_client_id,
+            },
+            Event::Join {
+                player_properties,
+                actor_list,
+            } => Event::Join {
+                player_properties: player_properties.into_owned(),
+                actor_list,
+            },
+        }
+    }
 }
 
 impl<'s> Operation<'s> {
     pub fn read<'a>(operation_type: u8, mut params: ParameterTable<'a>, direction: Direction) -> PacketReadResult<Operation<'a>> {
-        fn err<'a>(operation: Operation<'static>, params: &HashMap<u8, ProtocolValue>) -> PacketReadResult<Operation<'a>> {
+        fn err<'a>(operation: Operation<'static>, params: &IndexMap<u8, ProtocolValue>) -> PacketReadResult<Operation<'a>> {
             debug!("Unimplemented Operation: {:?}, {:#?}", operation, params);
             Err(PacketReadError::UnimplementedOperationType(operation))
         }
 
         let ret = match operation_type {
-            217 => err(Operation::GetGameList, &params),
+            217 => match direction {
+                Direction::Send => Ok(Operation::GetGameListRequest {
+                    lobby_name: get_u8_string(&mut params, ParameterCode::LobbyName)?,
+                    lobby_type: get_u8_byte(&mut params, ParameterCode::LobbyType)?,
+                    sql_filter: get_u8_string(&mut params, ParameterCode::Data)?,
+                }),
+                Direction::Recv => Ok(Operation::GetGameListResponse {
+                    games: get_game_list(&mut params)?,
+                }),
+            },
             218 => err(Operation::ServerSettings, &params),
             219 => err(Operation::WebRpc, &params),
-            220 => err(Operation::GetRegions, &params),
-            221 => err(Operation::GetLobbyStats, &params),
-            222 => err(Operation::FindFriends, &params),
+            220 => match direction {
+                Direction::Send => Ok(Operation::GetRegionsRequest {
+                    app_id: get_u8_string(&mut params, ParameterCode::ApplicationId)?,
+                }),
+                Direction::Recv => Ok(Operation::GetRegionsResponse {
+                    regions: get_u8_string_array(&mut params, ParameterCode::Region)?,
+                    addresses: get_u8_string_array(&mut params, ParameterCode::Address)?,
+                }),
+            },
+            221 => match direction {
+                Direction::Send => Ok(Operation::GetLobbyStatsRequest {
+                    lobby_names: get_u8_string_array_opt(&mut params, ParameterCode::LobbyName)?,
+                    lobby_types: get_u8_byte_array_opt(&mut params, ParameterCode::LobbyType)?,
+                }),
+                Direction::Recv => Ok(Operation::GetLobbyStatsResponse {
+                    names: get_u8_string_array(&mut params, ParameterCode::LobbyName)?,
+                    types: get_u8_byte_array(&mut params, ParameterCode::LobbyType)?,
+                    peer_counts: get_u8_int_array(&mut params, ParameterCode::PeerCount)?,
+                    game_counts: get_u8_int_array(&mut params, ParameterCode::GameCount)?,
+                }),
+            },
+            222 => match direction {
+                Direction::Send => Ok(Operation::FindFriendsRequest {
+                    user_ids: get_u8_string_array(&mut params, ParameterCode::FindFriendsRequestList)?,
+                    options: get_u8_int_opt(&mut params, ParameterCode::FindFriendsOptions)?
+                        .map(|flags| {
+                            FindFriendsOptions::from_bits(flags as u32)
+                                .ok_or(PacketReadError::InvalidParameterValue(ParameterCode::FindFriendsOptions))
+                        })
+                        .transpose()?,
+                }),
+                Direction::Recv => Ok(Operation::FindFriendsResponse {
+                    online: get_u8_array(&mut params, ParameterCode::FindFriendsResponseOnlineList)?
+                        .into_iter()
+                        .map(unwrap_protocol_bool)
+                        .collect::<PacketReadResult<_>>()?,
+                    room_ids: get_u8_string_array(&mut params, ParameterCode::FindFriendsResponseRoomIdList)?,
+                }),
+            },
             224 => err(Operation::CancelJoinRandom, &params),
-            225 => err(Operation::JoinRandomGame, &params),
-            226 => err(Operation::JoinGame, &params),
+            225 => match direction {
+                Direction::Send => Ok(Operation::JoinRandomGameRequest {
+                    expected_properties: get_u8_hashtable_opt(&mut params, ParameterCode::GameProperties)?,
+                    matchmaking_mode: get_u8_byte_opt(&mut params, ParameterCode::MatchMakingType)?
+                        .map(|byte| MatchmakingMode::from_u8(byte).ok_or(PacketReadError::InvalidParameterValue(ParameterCode::MatchMakingType)))
+                        .transpose()?,
+                    lobby_name: get_u8_string_opt(&mut params, ParameterCode::LobbyName)?,
+                    lobby_type: get_u8_byte_opt(&mut params, ParameterCode::LobbyType)?,
+                    sql_filter: get_u8_string_opt(&mut params, ParameterCode::Data)?,
+                    expected_users: get_u8_array_opt(&mut params, ParameterCode::Add)?
+                        .map_or(Ok(Vec::new()), |arr| arr.into_iter().map(unwrap_protocol_string).collect())?,
+                }),
+                Direction::Recv => Ok(Operation::JoinRandomGameResponse {
+                    room_name: get_u8_string_opt(&mut params, ParameterCode::RoomName)?,
+                    address: get_u8_string_opt(&mut params, ParameterCode::Address)?,
+                }),
+            },
+            226 => match direction {
+                Direction::Send if !params.contains_key(&ParameterCode::PlayerProperties) && !params.contains_key(&ParameterCode::GameProperties) => {
+                    Ok(Operation::JoinGameRequestMaster {
+                        room_name: get_u8_string(&mut params, ParameterCode::RoomName)?,
+                        join_mode: get_u8_byte_opt(&mut params, ParameterCode::JoinMode)?
+                            .map(|byte| JoinMode::from_u8(byte).ok_or(PacketReadError::InvalidParameterValue(ParameterCode::JoinMode)))
+                            .transpose()?,
+                        lobby_name: get_u8_string_opt(&mut params, ParameterCode::LobbyName)?,
+                        lobby_type: get_u8_byte_opt(&mut params, ParameterCode::LobbyType)?,
+                        expected_users: get_u8_array_opt(&mut params, ParameterCode::Add)?
+                            .map_or(Ok(Vec::new()), |arr| arr.into_iter().map(unwrap_protocol_string).collect())?,
+                    })
+                }
+                Direction::Send => Ok(Operation::JoinGameRequestGame {
+                    room_name: get_u8_string(&mut params, ParameterCode::RoomName)?,
+                    join_mode: get_u8_byte_opt(&mut params, ParameterCode::JoinMode)?
+                        .map(|byte| JoinMode::from_u8(byte).ok_or(PacketReadError::InvalidParameterValue(ParameterCode::JoinMode)))
+                        .transpose()?,
+                    lobby_name: get_u8_string_opt(&mut params, ParameterCode::LobbyName)?,
+                    lobby_type: get_u8_byte_opt(&mut params, ParameterCode::LobbyType)?,
+                    expected_users: get_u8_array_opt(&mut params, ParameterCode::Add)?
+                        .map_or(Ok(Vec::new()), |arr| arr.into_iter().map(unwrap_protocol_string).collect())?,
+                    player_properties: get_u8_hashtable_opt(&mut params, ParameterCode::PlayerProperties)?
+                        .map(Player::try_from)
+                        .transpose()?,
+                    broadcast: get_u8_bool_opt(&mut params, ParameterCode::Broadcast)?,
+                    game_properties: get_u8_hashtable_opt(&mut params, ParameterCode::GameProperties)?
+                        .map(RoomOptions::try_from)
+                        .transpose()?,
+                    player_ttl: get_u8_int_opt(&mut params, ParameterCode::PlayerTTL)?,
+                    empty_room_ttl: get_u8_int_opt(&mut params, ParameterCode::EmptyRoomTTL)?,
+                    plugins: get_u8_array_opt(&mut params, ParameterCode::Plugins)?
+                        .map(|arr| arr.into_iter().map(unwrap_protocol_string).collect())
+                        .transpose()?,
+                    room_option_flags: get_u8_int_opt(&mut params, ParameterCode::RoomOptionFlags)?
+                        .map(|flags| {
+                            RoomOptionsFlags::from_bits(flags as u32).ok_or(PacketReadError::InvalidParameterValue(ParameterCode::RoomOptionFlags))
+                        })
+                        .transpose()?,
+                    room_option_params: RoomOptionParams {
+                        suppress_room_events: get_u8_bool_opt(&mut params, ParameterCode::SuppressRoomEvents)?,
+                        cleanup_cache_on_leave: get_u8_bool_opt(&mut params, ParameterCode::CleanupCacheOnLeave)?,
+                        check_user_on_join: get_u8_bool_opt(&mut params, ParameterCode::CheckUserOnJoin)?,
+                        publish_user_id: get_u8_bool_opt(&mut params, ParameterCode::PublishUserId)?,
+                    },
+                }),
+                Direction::Recv if !params.contains_key(&ParameterCode::GameProperties) => Ok(Operation::JoinGameResponseMaster {
+                    room_name: get_u8_string_opt(&mut params, ParameterCode::RoomName)?,
+                    address: get_u8_string_opt(&mut params, ParameterCode::Address)?,
+                }),
+                Direction::Recv => Ok(Operation::JoinGameResponseGame {
+                    actor_nr: get_u8_int(&mut params, ParameterCode::ActorNr)?,
+                    actor_list: get_u8_array_or_none(&mut params, ParameterCode::ActorList, unwrap_protocol_int)?,
+                    game_properties: RoomInfo::try_from(get_u8_hashtable(&mut params, ParameterCode::GameProperties)?)?,
+                    player_properties: get_u8_hashtable(&mut params, ParameterCode::PlayerProperties)?
+                        .into_iter()
+                        .map(|(k, v)| Ok((unwrap_protocol_int(k)?, Player::try_from(unwrap_protocol_hashtable(v)?)?)))
+                        .collect::<PacketReadResult<_>>()?,
+                }),
+            },
             227 => match direction {
                 Direction::Send if !params.contains_key(&ParameterCode::GameProperties) => Ok(Operation::CreateGameRequestMaster {
                     room_name: get_u8_string_opt(&mut params, ParameterCode::RoomName)?,
@@ -247,7 +649,7 @@ impl<'s> Operation<'s> {
                         .map(|arr| arr.into_iter().map(unwrap_protocol_string).collect())
                         .transpose()?,
                     room_option_flags: RoomOptionsFlags::from_bits(get_u8_int(&mut params, ParameterCode::RoomOptionFlags)? as u32)
-                        .expect("Received invalid RoomOptionsFlags"), // possibly not present if value is 0
+                        .ok_or(PacketReadError::InvalidParameterValue(ParameterCode::RoomOptionFlags))?, // possibly not present if value is 0
                 }),
                 Direction::Recv if !params.contains_key(&ParameterCode::GameProperties) => Ok(Operation::CreateGameResponseMaster {
                     room_name: get_u8_string_opt(&mut params, ParameterCode::RoomName)?,
@@ -257,17 +659,10 @@ impl<'s> Operation<'s> {
                     actor_nr: get_u8_int(&mut params, ParameterCode::ActorNr)?,
                     actor_list: get_u8_array_or_none(&mut params, ParameterCode::ActorList, unwrap_protocol_int)?,
                     game_properties: RoomInfo::try_from(get_u8_hashtable(&mut params, ParameterCode::GameProperties)?)?,
-                    player_properties: {
-                        get_u8_hashtable(&mut params, ParameterCode::PlayerProperties)?
-                            .into_iter()
-                            .map(|(k, v)| {
-                                let actor_nr = unwrap_protocol_int(k).unwrap();
-                                let props = unwrap_protocol_hashtable(v).unwrap();
-                                let actor = Player::try_from(props).unwrap();
-                                (actor_nr, actor)
-                            })
-                            .collect()
-                    },
+                    player_properties: get_u8_hashtable(&mut params, ParameterCode::PlayerProperties)?
+                        .into_iter()
+                        .map(|(k, v)| Ok((unwrap_protocol_int(k)?, Player::try_from(unwrap_protocol_hashtable(v)?)?)))
+                        .collect::<PacketReadResult<_>>()?,
                 }),
             },
             228 => err(Operation::LeaveLobby, &params),
@@ -290,16 +685,20 @@ impl<'s> Operation<'s> {
                 Direction::Recv if params.contains_key(&ParameterCode::Address) => Ok(Operation::AuthenticateResponseName {
                     user_id: get_u8_string_opt(&mut params, ParameterCode::UserId)?,
                     nickname: get_u8_string_opt(&mut params, ParameterCode::NickName)?,
-                    encryption_data: get_u8_hashtable_opt(&mut params, ParameterCode::EncryptionData)?.map(|map| {
-                        map.into_iter()
-                            .map(|(k, v)| (unwrap_protocol_byte(k).expect("expected u8 keys in encryption_data"), v))
-                            .collect()
-                    }),
-                    custom_data: get_u8_hashtable_opt(&mut params, ParameterCode::CustomInitData)?.map(|map| {
-                        map.into_iter()
-                            .map(|(k, v)| (unwrap_protocol_string(k).expect("expected string keys in custom_data"), v))
-                            .collect()
-                    }),
+                    encryption_data: get_u8_hashtable_opt(&mut params, ParameterCode::EncryptionData)?
+                        .map(|map| {
+                            map.into_iter()
+                                .map(|(k, v)| Ok((unwrap_protocol_byte(k)?, v)))
+                                .collect::<PacketReadResult<_>>()
+                        })
+                        .transpose()?,
+                    custom_data: get_u8_hashtable_opt(&mut params, ParameterCode::CustomInitData)?
+                        .map(|map| {
+                            map.into_iter()
+                                .map(|(k, v)| Ok((unwrap_protocol_string(k)?, v)))
+                                .collect::<PacketReadResult<_>>()
+                        })
+                        .transpose()?,
 
                     cluster: get_u8_string_opt(&mut params, ParameterCode::Cluster)?,
                     address: get_u8_string(&mut params, ParameterCode::Address)?,
@@ -307,22 +706,33 @@ impl<'s> Operation<'s> {
                 Direction::Recv => Ok(Operation::AuthenticateResponseMasterOrGame {
                     user_id: get_u8_string_opt(&mut params, ParameterCode::UserId)?,
                     nickname: get_u8_string_opt(&mut params, ParameterCode::NickName)?,
-                    encryption_data: get_u8_hashtable_opt(&mut params, ParameterCode::EncryptionData)?.map(|map| {
-                        map.into_iter()
-                            .map(|(k, v)| (unwrap_protocol_byte(k).expect("expected u8 keys in encryption_data"), v))
-                            .collect()
-                    }),
-                    custom_data: get_u8_hashtable_opt(&mut params, ParameterCode::CustomInitData)?.map(|map| {
-                        map.into_iter()
-                            .map(|(k, v)| (unwrap_protocol_string(k).expect("expected string keys in custom_data"), v))
-                            .collect()
-                    }),
+                    encryption_data: get_u8_hashtable_opt(&mut params, ParameterCode::EncryptionData)?
+                        .map(|map| {
+                            map.into_iter()
+                                .map(|(k, v)| Ok((unwrap_protocol_byte(k)?, v)))
+                                .collect::<PacketReadResult<_>>()
+                        })
+                        .transpose()?,
+                    custom_data: get_u8_hashtable_opt(&mut params, ParameterCode::CustomInitData)?
+                        .map(|map| {
+                            map.into_iter()
+                                .map(|(k, v)| Ok((unwrap_protocol_string(k)?, v)))
+                                .collect::<PacketReadResult<_>>()
+                        })
+                        .transpose()?,
                     position: get_u8_int_opt(&mut params, ParameterCode::Position)?,
                 }),
             },
             231 => err(Operation::AuthenticateOnce, &params),
             248 => err(Operation::ChangeGroups, &params),
-            250 => err(Operation::ExchangeKeysForEncryption, &params),
+            250 => match direction {
+                Direction::Send => Ok(Operation::ExchangeKeysForEncryptionRequest {
+                    public_key: get_u8_byte_array(&mut params, ParameterCode::ClientKey)?,
+                }),
+                Direction::Recv => Ok(Operation::ExchangeKeysForEncryptionResponse {
+                    public_key: get_u8_byte_array(&mut params, ParameterCode::ServerKey)?,
+                }),
+            },
             251 => err(Operation::GetProperties, &params),
             252 if params.contains_key(&ParameterCode::ActorNr) => Ok(Operation::SetPropertiesActor {
                 broadcast: get_u8_bool(&mut params, ParameterCode::Broadcast)?,
@@ -338,16 +748,23 @@ impl<'s> Operation<'s> {
                 expected_properties: get_u8_hashtable_opt(&mut params, ParameterCode::ExpectedValues)?,
             }),
             253 => Ok(Operation::RaiseEvent {
-                cache: EventCaching::from_u8(get_u8_byte_opt(&mut params, ParameterCode::Cache)?.unwrap_or(0)).expect("Invalid EventCaching"),
+                cache: EventCaching::from_u8(get_u8_byte_opt(&mut params, ParameterCode::Cache)?.unwrap_or(0))
+                    .ok_or(PacketReadError::InvalidParameterValue(ParameterCode::Cache))?,
                 actor_list: get_u8_array_or_none(&mut params, ParameterCode::ActorList, unwrap_protocol_int)?,
                 group: get_u8_byte_opt(&mut params, ParameterCode::Group)?,
                 receivers: get_u8_byte_opt(&mut params, ParameterCode::ReceiverGroup)?
-                    .map(|byte| ReceiverGroup::from_u8(byte).expect("Invalid ReceiverGroup")),
+                    .map(|byte| ReceiverGroup::from_u8(byte).ok_or(PacketReadError::InvalidParameterValue(ParameterCode::ReceiverGroup)))
+                    .transpose()?,
                 event_forward: get_u8_bool_opt(&mut params, ParameterCode::EventForward)?,
                 code: get_u8_byte_opt(&mut params, ParameterCode::Code)?,
-                data: params.remove(&ParameterCode::Data),
+                data: params.shift_remove(&ParameterCode::Data),
             }),
-            254 => err(Operation::Leave, &params),
+            254 => match direction {
+                Direction::Send => Ok(Operation::LeaveRequest {
+                    is_inactive: get_u8_bool_opt(&mut params, ParameterCode::IsInactive)?,
+                }),
+                Direction::Recv => Ok(Operation::LeaveResponse),
+            },
             255 => err(Operation::Join, &params),
             _ => Err(PacketReadError::UnknownOperationType(operation_type)),
         };
@@ -361,15 +778,23 @@ impl<'s> Operation<'s> {
 
     pub fn get_type(&self) -> u8 {
         match self {
-            Operation::GetGameList => 217,
+            Operation::GetGameListRequest { .. } => 217,
+            Operation::GetGameListResponse { .. } => 217,
             Operation::ServerSettings => 218,
             Operation::WebRpc => 219,
-            Operation::GetRegions => 220,
-            Operation::GetLobbyStats => 221,
-            Operation::FindFriends => 222,
+            Operation::GetRegionsRequest { .. } => 220,
+            Operation::GetRegionsResponse { .. } => 220,
+            Operation::GetLobbyStatsRequest { .. } => 221,
+            Operation::GetLobbyStatsResponse { .. } => 221,
+            Operation::FindFriendsRequest { .. } => 222,
+            Operation::FindFriendsResponse { .. } => 222,
             Operation::CancelJoinRandom => 224,
-            Operation::JoinRandomGame => 225,
-            Operation::JoinGame => 226,
+            Operation::JoinRandomGameRequest { .. } => 225,
+            Operation::JoinRandomGameResponse { .. } => 225,
+            Operation::JoinGameRequestMaster { .. } => 226,
+            Operation::JoinGameRequestGame { .. } => 226,
+            Operation::JoinGameResponseMaster { .. } => 226,
+            Operation::JoinGameResponseGame { .. } => 226,
             Operation::CreateGameRequestMaster { .. } => 227,
             Operation::CreateGameRequestGame { .. } => 227,
             Operation::CreateGameResponseMaster { .. } => 227,
@@ -382,38 +807,218 @@ impl<'s> Operation<'s> {
             Operation::AuthenticateResponseMasterOrGame { .. } => 230,
             Operation::AuthenticateOnce => 231,
             Operation::ChangeGroups => 248,
-            Operation::ExchangeKeysForEncryption => 250,
+            Operation::ExchangeKeysForEncryptionRequest { .. } => 250,
+            Operation::ExchangeKeysForEncryptionResponse { .. } => 250,
             Operation::GetProperties => 251,
             Operation::SetPropertiesGame { .. } => 252,
             Operation::SetPropertiesActor { .. } => 252,
             Operation::RaiseEvent { .. } => 253,
-            Operation::Leave => 254,
+            Operation::LeaveRequest { .. } => 254,
+            Operation::LeaveResponse => 254,
             Operation::Join => 255,
         }
     }
 
-    pub fn get_param_map(self) -> PacketWriteResult<HashMap<u8, ProtocolValue<'s>>> {
-        fn err(operation: Operation<'static>) -> PacketWriteResult<HashMap<u8, ProtocolValue>> {
+    pub fn get_param_map(self) -> PacketWriteResult<IndexMap<u8, ProtocolValue<'s>>> {
+        fn err(operation: Operation<'static>) -> PacketWriteResult<IndexMap<u8, ProtocolValue<'static>>> {
             Err(PacketWriteError::UnimplementedOperationType(operation))
         }
 
         match self {
-            Operation::GetGameList => err(Operation::GetGameList),
+            Operation::GetGameListRequest {
+                lobby_name,
+                lobby_type,
+                sql_filter,
+            } => Ok(indexmap! {
+                ParameterCode::LobbyName => ProtocolValue::String(lobby_name),
+                ParameterCode::LobbyType => ProtocolValue::Byte(lobby_type),
+                ParameterCode::Data => ProtocolValue::String(sql_filter),
+            }),
+            Operation::GetGameListResponse { games } => Ok(indexmap! {
+                ParameterCode::GameList => game_list_value(games),
+            }),
             Operation::ServerSettings => err(Operation::ServerSettings),
             Operation::WebRpc => err(Operation::WebRpc),
-            Operation::GetRegions => err(Operation::GetRegions),
-            Operation::GetLobbyStats => err(Operation::GetLobbyStats),
-            Operation::FindFriends => err(Operation::FindFriends),
+            Operation::GetRegionsRequest { app_id } => Ok(indexmap! {
+                ParameterCode::ApplicationId => ProtocolValue::String(app_id),
+            }),
+            Operation::GetRegionsResponse { regions, addresses } => Ok(indexmap! {
+                ParameterCode::Region => ProtocolValue::StringArray(regions),
+                ParameterCode::Address => ProtocolValue::StringArray(addresses),
+            }),
+            Operation::GetLobbyStatsRequest { lobby_names, lobby_types } => Ok({
+                let mut map = indexmap!();
+
+                lobby_names.and_then(|n| map.insert(ParameterCode::LobbyName, ProtocolValue::StringArray(n)));
+                lobby_types.and_then(|t| map.insert(ParameterCode::LobbyType, ProtocolValue::ByteArray(t)));
+
+                map
+            }),
+            Operation::GetLobbyStatsResponse {
+                names,
+                types,
+                peer_counts,
+                game_counts,
+            } => Ok(indexmap! {
+                ParameterCode::LobbyName => ProtocolValue::StringArray(names),
+                ParameterCode::LobbyType => ProtocolValue::ByteArray(types),
+                ParameterCode::PeerCount => ProtocolValue::IntegerArray(peer_counts),
+                ParameterCode::GameCount => ProtocolValue::IntegerArray(game_counts),
+            }),
+            Operation::FindFriendsRequest { user_ids, options } => Ok({
+                let mut map = indexmap! { ParameterCode::FindFriendsRequestList => ProtocolValue::StringArray(user_ids) };
+
+                options.and_then(|o| map.insert(ParameterCode::FindFriendsOptions, ProtocolValue::Integer(o.bits() as i32)));
+
+                map
+            }),
+            Operation::FindFriendsResponse { online, room_ids } => Ok(indexmap! {
+                ParameterCode::FindFriendsResponseOnlineList => ProtocolValue::Array(111, online.into_iter().map(ProtocolValue::Bool).collect()),
+                ParameterCode::FindFriendsResponseRoomIdList => ProtocolValue::StringArray(room_ids),
+            }),
             Operation::CancelJoinRandom => err(Operation::CancelJoinRandom),
-            Operation::JoinRandomGame => err(Operation::JoinRandomGame),
-            Operation::JoinGame => err(Operation::JoinGame),
+            Operation::JoinRandomGameRequest {
+                expected_properties,
+                matchmaking_mode,
+                lobby_name,
+                lobby_type,
+                sql_filter,
+                expected_users,
+            } => Ok({
+                let mut map = indexmap!();
+
+                expected_properties.and_then(|p| map.insert(ParameterCode::GameProperties, ProtocolValue::Hashtable(p)));
+                matchmaking_mode.and_then(|m| map.insert(ParameterCode::MatchMakingType, ProtocolValue::Byte(m as u8)));
+                lobby_name.and_then(|n| map.insert(ParameterCode::LobbyName, ProtocolValue::String(n)));
+                lobby_type.and_then(|t| map.insert(ParameterCode::LobbyType, ProtocolValue::Byte(t)));
+                sql_filter.and_then(|f| map.insert(ParameterCode::Data, ProtocolValue::String(f)));
+                if !expected_users.is_empty() {
+                    map.insert(
+                        ParameterCode::Add,
+                        ProtocolValue::Array(115, expected_users.into_iter().map(ProtocolValue::String).collect()),
+                    );
+                }
+
+                map
+            }),
+            Operation::JoinRandomGameResponse { room_name, address } | Operation::JoinGameResponseMaster { room_name, address } => Ok({
+                let mut map = indexmap!();
+
+                address.and_then(|a| map.insert(ParameterCode::Address, ProtocolValue::String(a)));
+                room_name
+                    .filter(|r| !r.is_empty())
+                    .and_then(|r| map.insert(ParameterCode::RoomName, ProtocolValue::String(r)));
+
+                map
+            }),
+            Operation::JoinGameRequestMaster {
+                room_name,
+                join_mode,
+                lobby_name,
+                lobby_type,
+                expected_users,
+            } => Ok({
+                let mut map = indexmap! {
+                    ParameterCode::RoomName => ProtocolValue::String(room_name),
+                };
+
+                join_mode.and_then(|m| map.insert(ParameterCode::JoinMode, ProtocolValue::Byte(m as u8)));
+                lobby_name.and_then(|n| map.insert(ParameterCode::LobbyName, ProtocolValue::String(n)));
+                lobby_type.and_then(|t| map.insert(ParameterCode::LobbyType, ProtocolValue::Byte(t)));
+                if !expected_users.is_empty() {
+                    map.insert(
+                        ParameterCode::Add,
+                        ProtocolValue::Array(115, expected_users.into_iter().map(ProtocolValue::String).collect()),
+                    );
+                }
+
+                map
+            }),
+            Operation::JoinGameRequestGame {
+                room_name,
+                join_mode,
+                lobby_name,
+                lobby_type,
+                expected_users,
+
+                player_properties,
+                broadcast,
+                game_properties,
+                player_ttl,
+                empty_room_ttl,
+                plugins,
+                room_option_flags,
+                room_option_params,
+            } => Ok({
+                let mut map = indexmap! {
+                    ParameterCode::RoomName => ProtocolValue::String(room_name),
+                };
+
+                join_mode.and_then(|m| map.insert(ParameterCode::JoinMode, ProtocolValue::Byte(m as u8)));
+                lobby_name.and_then(|n| map.insert(ParameterCode::LobbyName, ProtocolValue::String(n)));
+                lobby_type.and_then(|t| map.insert(ParameterCode::LobbyType, ProtocolValue::Byte(t)));
+                if !expected_users.is_empty() {
+                    map.insert(
+                        ParameterCode::Add,
+                        ProtocolValue::Array(115, expected_users.into_iter().map(ProtocolValue::String).collect()),
+                    );
+                }
+
+                player_properties.and_then(|pp| map.insert(ParameterCode::PlayerProperties, ProtocolValue::Hashtable(pp.into())));
+                broadcast.and_then(|b| map.insert(ParameterCode::Broadcast, ProtocolValue::Bool(b)));
+
+                game_properties.and_then(|gp| map.insert(ParameterCode::GameProperties, ProtocolValue::Hashtable(gp.into())));
+                player_ttl.and_then(|ttl| map.insert(ParameterCode::PlayerTTL, ProtocolValue::Integer(ttl)));
+                empty_room_ttl.and_then(|ttl| map.insert(ParameterCode::EmptyRoomTTL, ProtocolValue::Integer(ttl)));
+                plugins.and_then(|p| {
+                    map.insert(
+                        ParameterCode::Plugins,
+                        ProtocolValue::Array(115, p.into_iter().map(ProtocolValue::String).collect()),
+                    )
+                });
+
+                let RoomOptionParams {
+                    suppress_room_events,
+                    cleanup_cache_on_leave,
+                    check_user_on_join,
+                    publish_user_id,
+                } = room_option_params;
+                suppress_room_events.and_then(|b| map.insert(ParameterCode::SuppressRoomEvents, ProtocolValue::Bool(b)));
+                cleanup_cache_on_leave.and_then(|b| map.insert(ParameterCode::CleanupCacheOnLeave, ProtocolValue::Bool(b)));
+                check_user_on_join.and_then(|b| map.insert(ParameterCode::CheckUserOnJoin, ProtocolValue::Bool(b)));
+                publish_user_id.and_then(|b| map.insert(ParameterCode::PublishUserId, ProtocolValue::Bool(b)));
+                room_option_flags.and_then(|f| map.insert(ParameterCode::RoomOptionFlags, ProtocolValue::Integer(f.bits() as i32)));
+
+                map
+            }),
+            Operation::JoinGameResponseGame {
+                actor_nr,
+                actor_list,
+                game_properties,
+                player_properties,
+            } => Ok({
+                let mut map = indexmap! {
+                    ParameterCode::ActorNr => ProtocolValue::Integer(actor_nr),
+                    ParameterCode::GameProperties => ProtocolValue::Hashtable(game_properties.into()),
+                    ParameterCode::PlayerProperties => ProtocolValue::Hashtable(player_properties.into_iter().map(|(k, v)| (ProtocolValue::Integer(k), ProtocolValue::Hashtable(v.into()))).collect()),
+                };
+
+                actor_list.and_then(|l| {
+                    map.insert(
+                        ParameterCode::ActorList,
+                        ProtocolValue::Array(105, l.into_iter().map(ProtocolValue::Integer).collect()),
+                    )
+                });
+
+                map
+            }),
             Operation::CreateGameRequestMaster {
                 room_name,
                 lobby_name,
                 lobby_type,
                 expected_users,
             } => Ok({
-                let mut map = hashmap!();
+                let mut map = indexmap!();
 
                 room_name
                     .filter(|n| !n.is_empty())
@@ -423,7 +1028,7 @@ impl<'s> Operation<'s> {
                 if !expected_users.is_empty() {
                     map.insert(
                         ParameterCode::Add,
-                        ProtocolValue::Array(expected_users.into_iter().map(ProtocolValue::String).collect()),
+                        ProtocolValue::Array(115, expected_users.into_iter().map(ProtocolValue::String).collect()),
                     );
                 }
 
@@ -443,7 +1048,7 @@ impl<'s> Operation<'s> {
                 plugins,
                 room_option_flags,
             } => Ok({
-                let mut map = hashmap!();
+                let mut map = indexmap!();
 
                 room_name
                     .filter(|n| !n.is_empty())
@@ -453,12 +1058,12 @@ impl<'s> Operation<'s> {
                 if !expected_users.is_empty() {
                     map.insert(
                         ParameterCode::Add,
-                        ProtocolValue::Array(expected_users.into_iter().map(ProtocolValue::String).collect()),
+                        ProtocolValue::Array(115, expected_users.into_iter().map(ProtocolValue::String).collect()),
                     );
                 }
 
                 if let Some(pp) = player_properties {
-                    let pp_map: HashMap<ProtocolValue, ProtocolValue> = pp.into();
+                    let pp_map: IndexMap<ProtocolValue, ProtocolValue> = pp.into();
                     if !pp_map.is_empty() {
                         map.insert(ParameterCode::PlayerProperties, ProtocolValue::Hashtable(pp_map));
                     }
@@ -480,7 +1085,7 @@ impl<'s> Operation<'s> {
                 plugins.and_then(|p| {
                     map.insert(
                         ParameterCode::Plugins,
-                        ProtocolValue::Array(p.into_iter().map(ProtocolValue::String).collect()),
+                        ProtocolValue::Array(115, p.into_iter().map(ProtocolValue::String).collect()),
                     )
                 });
 
@@ -503,7 +1108,7 @@ impl<'s> Operation<'s> {
                 map
             }),
             Operation::CreateGameResponseMaster { room_name, address } => Ok({
-                let mut map = hashmap! {
+                let mut map = indexmap! {
                     ParameterCode::Address => ProtocolValue::String(address),
                 };
 
@@ -519,7 +1124,7 @@ impl<'s> Operation<'s> {
                 game_properties,
                 player_properties,
             } => Ok({
-                let mut map = hashmap! {
+                let mut map = indexmap! {
                     ParameterCode::ActorNr => ProtocolValue::Integer(actor_nr),
                     ParameterCode::GameProperties => ProtocolValue::Hashtable(game_properties.into()),
                     ParameterCode::PlayerProperties => ProtocolValue::Hashtable(player_properties.into_iter().map(|(k, v)| (ProtocolValue::Integer(k), ProtocolValue::Hashtable(v.into()))).collect()),
@@ -528,14 +1133,14 @@ impl<'s> Operation<'s> {
                 actor_list.and_then(|l| {
                     map.insert(
                         ParameterCode::ActorList,
-                        ProtocolValue::Array(l.into_iter().map(ProtocolValue::Integer).collect()),
+                        ProtocolValue::Array(105, l.into_iter().map(ProtocolValue::Integer).collect()),
                     )
                 });
 
                 map
             }),
             Operation::LeaveLobby => err(Operation::LeaveLobby),
-            Operation::JoinLobby() => Ok(hashmap!()),
+            Operation::JoinLobby() => Ok(indexmap!()),
             Operation::AuthenticateRequestNoToken {
                 lobby_stats,
                 app_version,
@@ -546,7 +1151,7 @@ impl<'s> Operation<'s> {
                 client_auth_params,
                 client_auth_data,
             } => Ok({
-                let mut map = hashmap! {
+                let mut map = indexmap! {
                     ParameterCode::AppVersion => ProtocolValue::String(app_version),
                     ParameterCode::ApplicationId => ProtocolValue::String(app_id),
                 };
@@ -562,7 +1167,7 @@ impl<'s> Operation<'s> {
                     .filter(|u| !u.is_empty())
                     .and_then(|u| map.insert(ParameterCode::UserId, ProtocolValue::String(u)));
 
-                client_auth_type.filter(|t| t != &255).and_then(|t| {
+                if let Some(t) = client_auth_type.filter(|t| t != &255) {
                     map.insert(ParameterCode::ClientAuthenticationType, ProtocolValue::Byte(t));
                     client_auth_params
                         .filter(|s| !s.is_empty())
@@ -570,8 +1175,7 @@ impl<'s> Operation<'s> {
                     client_auth_data
                         .filter(|s| !s.is_empty())
                         .and_then(|s| map.insert(ParameterCode::ClientAuthenticationData, ProtocolValue::String(s)));
-                    Some(())
-                });
+                }
 
                 map
             }),
@@ -579,7 +1183,7 @@ impl<'s> Operation<'s> {
                 lobby_stats, // not present if false
                 secret,
             } => Ok({
-                let mut map = hashmap! {
+                let mut map = indexmap! {
                     ParameterCode::Secret => ProtocolValue::String(secret),
                 };
 
@@ -597,7 +1201,9 @@ impl<'s> Operation<'s> {
                 cluster,
                 address,
             } => Ok({
-                let mut map = hashmap!(ParameterCode::Address => ProtocolValue::String(address));
+                let mut map = indexmap!();
+                cluster.and_then(|d| map.insert(ParameterCode::Cluster, ProtocolValue::String(d)));
+                map.insert(ParameterCode::Address, ProtocolValue::String(address));
                 user_id.and_then(|d| map.insert(ParameterCode::UserId, ProtocolValue::String(d)));
                 nickname.and_then(|d| map.insert(ParameterCode::NickName, ProtocolValue::String(d)));
                 encryption_data.and_then(|d| {
@@ -612,7 +1218,6 @@ impl<'s> Operation<'s> {
                         ProtocolValue::Hashtable(d.into_iter().map(|(k, v)| (ProtocolValue::String(k), v)).collect()),
                     )
                 });
-                cluster.and_then(|d| map.insert(ParameterCode::Cluster, ProtocolValue::String(d)));
                 map
             }),
             Operation::AuthenticateResponseMasterOrGame {
@@ -622,7 +1227,7 @@ impl<'s> Operation<'s> {
                 custom_data,
                 position,
             } => Ok({
-                let mut map = hashmap!();
+                let mut map = indexmap!();
                 user_id.and_then(|d| map.insert(ParameterCode::UserId, ProtocolValue::String(d)));
                 nickname.and_then(|d| map.insert(ParameterCode::NickName, ProtocolValue::String(d)));
                 encryption_data.and_then(|d| {
@@ -642,7 +1247,12 @@ impl<'s> Operation<'s> {
             }),
             Operation::AuthenticateOnce => err(Operation::AuthenticateOnce),
             Operation::ChangeGroups => err(Operation::ChangeGroups),
-            Operation::ExchangeKeysForEncryption => err(Operation::ExchangeKeysForEncryption),
+            Operation::ExchangeKeysForEncryptionRequest { public_key } => Ok(indexmap! {
+                ParameterCode::ClientKey => ProtocolValue::ByteArray(public_key),
+            }),
+            Operation::ExchangeKeysForEncryptionResponse { public_key } => Ok(indexmap! {
+                ParameterCode::ServerKey => ProtocolValue::ByteArray(public_key),
+            }),
             Operation::GetProperties => err(Operation::GetProperties),
             Operation::SetPropertiesGame {
                 broadcast,
@@ -650,7 +1260,7 @@ impl<'s> Operation<'s> {
                 expected_properties,
                 event_forward,
             } => Ok({
-                let mut map = hashmap! {
+                let mut map = indexmap! {
                     ParameterCode::Broadcast => ProtocolValue::Bool(broadcast),
                     ParameterCode::Properties => ProtocolValue::Hashtable(properties),
                 };
@@ -669,7 +1279,7 @@ impl<'s> Operation<'s> {
                 expected_properties,
                 event_forward,
             } => Ok({
-                let mut map = hashmap! {
+                let mut map = indexmap! {
                     ParameterCode::Broadcast => ProtocolValue::Bool(broadcast),
                     ParameterCode::ActorNr => ProtocolValue::Integer(actor_nr),
                     ParameterCode::Properties => ProtocolValue::Hashtable(properties),
@@ -691,7 +1301,7 @@ impl<'s> Operation<'s> {
                 code,
                 data,
             } => Ok({
-                let mut map = hashmap!();
+                let mut map = indexmap!();
 
                 if cache != EventCaching::DoNotCache {
                     map.insert(ParameterCode::Cache, ProtocolValue::Byte(cache as u8));
@@ -709,7 +1319,7 @@ impl<'s> Operation<'s> {
                         actor_list.and_then(|l| {
                             map.insert(
                                 ParameterCode::ActorList,
-                                ProtocolValue::Array(l.into_iter().map(ProtocolValue::Integer).collect()),
+                                ProtocolValue::Array(105, l.into_iter().map(ProtocolValue::Integer).collect()),
                             )
                         });
                     }
@@ -717,7 +1327,7 @@ impl<'s> Operation<'s> {
                         actor_list.and_then(|l| {
                             map.insert(
                                 ParameterCode::ActorList,
-                                ProtocolValue::Array(l.into_iter().map(ProtocolValue::Integer).collect()),
+                                ProtocolValue::Array(105, l.into_iter().map(ProtocolValue::Integer).collect()),
                             )
                         });
                         group.and_then(|b| map.insert(ParameterCode::Group, ProtocolValue::Byte(b)));
@@ -734,21 +1344,308 @@ impl<'s> Operation<'s> {
 
                 map
             }),
-            Operation::Leave => err(Operation::Leave),
+            Operation::LeaveRequest { is_inactive } => Ok({
+                let mut map = IndexMap::new();
+                is_inactive.and_then(|i| map.insert(ParameterCode::IsInactive, ProtocolValue::Bool(i)));
+                map
+            }),
+            Operation::LeaveResponse => Ok(indexmap!()),
             Operation::Join => err(Operation::Join),
         }
     }
+
+    /// Converts this operation into one that does not borrow from the deserialized data, copying strings where needed.
+    pub fn into_owned(self) -> Operation<'static> {
+        match self {
+            Operation::GetGameListRequest {
+                lobby_name,
+                lobby_type,
+                sql_filter,
+            } => Operation::GetGameListRequest {
+                lobby_name: owned_string(lobby_name),
+                lobby_type,
+                sql_filter: owned_string(sql_filter),
+            },
+            Operation::GetGameListResponse { games } => Operation::GetGameListResponse {
+                games: games.into_iter().map(|(k, v)| (owned_string(k), v.into_owned())).collect(),
+            },
+            Operation::ServerSettings => Operation::ServerSettings,
+            Operation::WebRpc => Operation::WebRpc,
+            Operation::GetRegionsRequest { app_id } => Operation::GetRegionsRequest {
+                app_id: owned_string(app_id),
+            },
+            Operation::GetRegionsResponse { regions, addresses } => Operation::GetRegionsResponse {
+                regions: regions.into_iter().map(owned_string).collect(),
+                addresses: addresses.into_iter().map(owned_string).collect(),
+            },
+            Operation::GetLobbyStatsRequest { lobby_names, lobby_types } => Operation::GetLobbyStatsRequest {
+                lobby_names: lobby_names.map(|names| names.into_iter().map(owned_string).collect()),
+                lobby_types,
+            },
+            Operation::GetLobbyStatsResponse {
+                names,
+                types,
+                peer_counts,
+                game_counts,
+            } => Operation::GetLobbyStatsResponse {
+                names: names.into_iter().map(owned_string).collect(),
+                types,
+                peer_counts,
+                game_counts,
+            },
+            Operation::FindFriendsRequest { user_ids, options } => Operation::FindFriendsRequest {
+                user_ids: user_ids.into_iter().map(owned_string).collect(),
+                options,
+            },
+            Operation::FindFriendsResponse { online, room_ids } => Operation::FindFriendsResponse {
+                online,
+                room_ids: room_ids.into_iter().map(owned_string).collect(),
+            },
+            Operation::CancelJoinRandom => Operation::CancelJoinRandom,
+            Operation::JoinRandomGameRequest {
+                expected_properties,
+                matchmaking_mode,
+                lobby_name,
+                lobby_type,
+                sql_filter,
+                expected_users,
+            } => Operation::JoinRandomGameRequest {
+                expected_properties: expected_properties.map(map_into_owned),
+                matchmaking_mode,
+                lobby_name: lobby_name.map(owned_string),
+                lobby_type,
+                sql_filter: sql_filter.map(owned_string),
+                expected_users: expected_users.into_iter().map(owned_string).collect(),
+            },
+            Operation::JoinRandomGameResponse { room_name, address } => Operation::JoinRandomGameResponse {
+                room_name: room_name.map(owned_string),
+                address: address.map(owned_string),
+            },
+            Operation::JoinGameRequestMaster {
+                room_name,
+                join_mode,
+                lobby_name,
+                lobby_type,
+                expected_users,
+            } => Operation::JoinGameRequestMaster {
+                room_name: owned_string(room_name),
+                join_mode,
+                lobby_name: lobby_name.map(owned_string),
+                lobby_type,
+                expected_users: expected_users.into_iter().map(owned_string).collect(),
+            },
+            Operation::JoinGameRequestGame {
+                room_name,
+                join_mode,
+                lobby_name,
+                lobby_type,
+                expected_users,
+                player_properties,
+                broadcast,
+                game_properties,
+                player_ttl,
+                empty_room_ttl,
+                plugins,
+                room_option_flags,
+                room_option_params,
+            } => Operation::JoinGameRequestGame {
+                room_name: owned_string(room_name),
+                join_mode,
+                lobby_name: lobby_name.map(owned_string),
+                lobby_type,
+                expected_users: expected_users.into_iter().map(owned_string).collect(),
+                player_properties: player_properties.map(Player::into_owned),
+                broadcast,
+                game_properties: game_properties.map(RoomOptions::into_owned),
+                player_ttl,
+                empty_room_ttl,
+                plugins: plugins.map(|p| p.into_iter().map(owned_string).collect()),
+                room_option_flags,
+                room_option_params,
+            },
+            Operation::JoinGameResponseMaster { room_name, address } => Operation::JoinGameResponseMaster {
+                room_name: room_name.map(owned_string),
+                address: address.map(owned_string),
+            },
+            Operation::JoinGameResponseGame {
+                actor_nr,
+                actor_list,
+                game_properties,
+                player_properties,
+            } => Operation::JoinGameResponseGame {
+                actor_nr,
+                actor_list,
+                game_properties: game_properties.into_owned(),
+                player_properties: player_properties.into_iter().map(|(k, v)| (k, v.into_owned())).collect(),
+            },
+            Operation::CreateGameRequestMaster {
+                room_name,
+                lobby_name,
+                lobby_type,
+                expected_users,
+            } => Operation::CreateGameRequestMaster {
+                room_name: room_name.map(owned_string),
+                lobby_name: lobby_name.map(owned_string),
+                lobby_type,
+                expected_users: expected_users.into_iter().map(owned_string).collect(),
+            },
+            Operation::CreateGameRequestGame {
+                room_name,
+                lobby_name,
+                lobby_type,
+                expected_users,
+                player_properties,
+                broadcast,
+                game_properties,
+                player_ttl,
+                empty_room_ttl,
+                plugins,
+                room_option_flags,
+            } => Operation::CreateGameRequestGame {
+                room_name: room_name.map(owned_string),
+                lobby_name: lobby_name.map(owned_string),
+                lobby_type,
+                expected_users: expected_users.into_iter().map(owned_string).collect(),
+                player_properties: player_properties.map(Player::into_owned),
+                broadcast,
+                game_properties: game_properties.into_owned(),
+                player_ttl,
+                empty_room_ttl,
+                plugins: plugins.map(|p| p.into_iter().map(owned_string).collect()),
+                room_option_flags,
+            },
+            Operation::CreateGameResponseMaster { room_name, address } => Operation::CreateGameResponseMaster {
+                room_name: room_name.map(owned_string),
+                address: owned_string(address),
+            },
+            Operation::CreateGameResponseGame {
+                actor_nr,
+                actor_list,
+                game_properties,
+                player_properties,
+            } => Operation::CreateGameResponseGame {
+                actor_nr,
+                actor_list,
+                game_properties: game_properties.into_owned(),
+                player_properties: player_properties.into_iter().map(|(k, v)| (k, v.into_owned())).collect(),
+            },
+            Operation::LeaveLobby => Operation::LeaveLobby,
+            Operation::JoinLobby() => Operation::JoinLobby(),
+            Operation::AuthenticateRequestNoToken {
+                lobby_stats,
+                app_version,
+                app_id,
+                region,
+                user_id,
+                client_auth_type,
+                client_auth_params,
+                client_auth_data,
+            } => Operation::AuthenticateRequestNoToken {
+                lobby_stats,
+                app_version: owned_string(app_version),
+                app_id: owned_string(app_id),
+                region: region.map(owned_string),
+                user_id: user_id.map(owned_string),
+                client_auth_type,
+                client_auth_params: client_auth_params.map(owned_string),
+                client_auth_data: client_auth_data.map(owned_string),
+            },
+            Operation::AuthenticateRequestToken { lobby_stats, secret } => Operation::AuthenticateRequestToken {
+                lobby_stats,
+                secret: owned_string(secret),
+            },
+            Operation::AuthenticateResponseName {
+                user_id,
+                nickname,
+                encryption_data,
+                custom_data,
+                cluster,
+                address,
+            } => Operation::AuthenticateResponseName {
+                user_id: user_id.map(owned_string),
+                nickname: nickname.map(owned_string),
+                encryption_data: encryption_data.map(table_into_owned),
+                custom_data: custom_data.map(owned_string_map),
+                cluster: cluster.map(owned_string),
+                address: owned_string(address),
+            },
+            Operation::AuthenticateResponseMasterOrGame {
+                user_id,
+                nickname,
+                encryption_data,
+                custom_data,
+                position,
+            } => Operation::AuthenticateResponseMasterOrGame {
+                user_id: user_id.map(owned_string),
+                nickname: nickname.map(owned_string),
+                encryption_data: encryption_data.map(table_into_owned),
+                custom_data: custom_data.map(owned_string_map),
+                position,
+            },
+            Operation::AuthenticateOnce => Operation::AuthenticateOnce,
+            Operation::ChangeGroups => Operation::ChangeGroups,
+            Operation::ExchangeKeysForEncryptionRequest { public_key } => Operation::ExchangeKeysForEncryptionRequest { public_key },
+            Operation::ExchangeKeysForEncryptionResponse { public_key } => Operation::ExchangeKeysForEncryptionResponse { public_key },
+            Operation::GetProperties => Operation::GetProperties,
+            Operation::SetPropertiesGame {
+                properties,
+                expected_properties,
+                broadcast,
+                event_forward,
+            } => Operation::SetPropertiesGame {
+                properties: map_into_owned(properties),
+                expected_properties: expected_properties.map(map_into_owned),
+                broadcast,
+                event_forward,
+            },
+            Operation::SetPropertiesActor {
+                actor_nr,
+                properties,
+                expected_properties,
+                broadcast,
+                event_forward,
+            } => Operation::SetPropertiesActor {
+                actor_nr,
+                properties: map_into_owned(properties),
+                expected_properties: expected_properties.map(map_into_owned),
+                broadcast,
+                event_forward,
+            },
+            Operation::RaiseEvent {
+                cache,
+                actor_list,
+                group,
+                receivers,
+                event_forward,
+                code,
+                data,
+            } => Operation::RaiseEvent {
+                cache,
+                actor_list,
+                group,
+                receivers,
+                event_forward,
+                code,
+                data: data.map(ProtocolValue::into_owned),
+            },
+            Operation::LeaveRequest { is_inactive } => Operation::LeaveRequest { is_inactive },
+            Operation::LeaveResponse => Operation::LeaveResponse,
+            Operation::Join => Operation::Join,
+        }
+    }
 }
 
 impl<'s> InternalOperation {
     pub fn read(operation_type: u8, mut params: ParameterTable<'_>, direction: Direction) -> PacketReadResult<InternalOperation> {
-        fn err(operation: InternalOperation, params: &HashMap<u8, ProtocolValue>) -> PacketReadResult<InternalOperation> {
-            debug!("Unimplemented InternalOperation: {:?}, {:#?}", operation, params);
-            Err(PacketReadError::UnimplementedInternalOperationType(operation))
-        }
-
         let ret = match operation_type {
-            0 => err(InternalOperation::InitEncryption, &params),
+            0 => match direction {
+                Direction::Send => Ok(InternalOperation::InitEncryptionRequest {
+                    public_key: get_u8_byte_array(&mut params, ParameterCode::ClientKey)?,
+                }),
+                Direction::Recv => Ok(InternalOperation::InitEncryptionResponse {
+                    public_key: get_u8_byte_array(&mut params, ParameterCode::ServerKey)?,
+                }),
+            },
             1 => match direction {
                 Direction::Send => Ok(InternalOperation::PingRequest {
                     local_time: get_u8_int(&mut params, 1)?,
@@ -770,24 +1667,44 @@ impl<'s> InternalOperation {
 
     pub fn get_type(&self) -> u8 {
         match self {
-            InternalOperation::InitEncryption => 0,
+            InternalOperation::InitEncryptionRequest { .. } => 0,
+            InternalOperation::InitEncryptionResponse { .. } => 0,
             InternalOperation::PingRequest { .. } => 1,
             InternalOperation::PingResponse { .. } => 1,
         }
     }
 
-    pub fn get_param_map(self) -> PacketWriteResult<HashMap<u8, ProtocolValue<'s>>> {
-        fn err<'a>(operation: InternalOperation) -> PacketWriteResult<HashMap<u8, ProtocolValue<'a>>> {
-            Err(PacketWriteError::UnimplementedInternalOperationType(operation))
-        }
-
+    pub fn get_param_map(self) -> PacketWriteResult<IndexMap<u8, ProtocolValue<'s>>> {
         match self {
-            InternalOperation::InitEncryption => err(InternalOperation::InitEncryption),
-            InternalOperation::PingRequest { local_time } => Ok(hashmap!(1 => ProtocolValue::Integer(local_time))),
-            InternalOperation::PingResponse { local_time, server_time } => Ok(hashmap! {
+            InternalOperation::InitEncryptionRequest { public_key } => {
+                Ok(indexmap!(ParameterCode::ClientKey => ProtocolValue::ByteArray(public_key)))
+            }
+            InternalOperation::InitEncryptionResponse { public_key } => {
+                Ok(indexmap!(ParameterCode::ServerKey => ProtocolValue::ByteArray(public_key)))
+            }
+            InternalOperation::PingRequest { local_time } => Ok(indexmap!(1 => ProtocolValue::Integer(local_time))),
+            InternalOperation::PingResponse { local_time, server_time } => Ok(indexmap! {
                 1 => ProtocolValue::Integer(local_time),
                 2 => ProtocolValue::Integer(server_time),
             }),
         }
     }
 }
+
+/// Reads a full game list, as sent in `Event::GameList` and `Operation::GetGameListResponse`.
+fn get_game_list<'a>(params: &mut ParameterTable<'a>) -> PacketReadResult<IndexMap<Cow<'a, str>, RoomInfo<'a>>> {
+    RoomInfo::try_from_hashtable_table(get_u8_hashtable(params, ParameterCode::GameList)?)?
+        .into_iter()
+        // removed games are only expected in GameListUpdate
+        .map(|(key, value)| Ok((key, value.ok_or(PacketReadError::InvalidParameterValue(ParameterCode::GameList))?)))
+        .collect()
+}
+
+fn game_list_value<'a>(games: IndexMap<Cow<'a, str>, RoomInfo<'a>>) -> ProtocolValue<'a> {
+    ProtocolValue::Hashtable(
+        games
+            .into_iter()
+            .map(|(k, v)| (ProtocolValue::String(k), ProtocolValue::Hashtable(v.into())))
+            .collect(),
+    )
+}
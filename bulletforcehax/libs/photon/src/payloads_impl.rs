@@ -1,13 +1,14 @@
 use super::macros::*;
 use super::*;
-use std::collections::HashMap;
+use indexmap::IndexMap;
+use std::borrow::Cow;
 use std::convert::TryFrom;
 
 impl<'s> RoomInfo<'s> {
     pub fn try_from_hashtable_table<'a>(
-        big_table: HashMap<ProtocolValue<'a>, ProtocolValue<'a>>,
-    ) -> PacketReadResult<HashMap<&'a str, Option<RoomInfo<'a>>>> {
-        let mut map: HashMap<&'a str, Option<RoomInfo<'a>>> = HashMap::new();
+        big_table: IndexMap<ProtocolValue<'a>, ProtocolValue<'a>>,
+    ) -> PacketReadResult<IndexMap<Cow<'a, str>, Option<RoomInfo<'a>>>> {
+        let mut map: IndexMap<Cow<'a, str>, Option<RoomInfo<'a>>> = IndexMap::new();
         for (key, value) in big_table {
             // could look into getting map past the borrow checker
             let ht = unwrap_protocol_hashtable(value)?;
@@ -18,7 +19,7 @@ impl<'s> RoomInfo<'s> {
         Ok(map)
     }
 
-    pub fn try_from_hashtable(table: HashMap<ProtocolValue<'s>, ProtocolValue<'s>>) -> PacketReadResult<Option<RoomInfo<'s>>> {
+    pub fn try_from_hashtable(table: IndexMap<ProtocolValue<'s>, ProtocolValue<'s>>) -> PacketReadResult<Option<RoomInfo<'s>>> {
         if table.contains_key(&ProtocolValue::Byte(GamePropertyKey::Removed)) {
             // got removed
             return Ok(None);
@@ -26,74 +27,218 @@ impl<'s> RoomInfo<'s> {
 
         Some(RoomInfo::try_from(table)).transpose()
     }
+
+    /// Converts this room into one that does not borrow from the deserialized data, copying strings where needed.
+    pub fn into_owned(self) -> RoomInfo<'static> {
+        RoomInfo {
+            max_players: self.max_players,
+            is_open: self.is_open,
+            is_visible: self.is_visible,
+            player_count: self.player_count,
+            cleanup_cache_on_leave: self.cleanup_cache_on_leave,
+            master_client_id: self.master_client_id,
+            custom_properties_lobby: self.custom_properties_lobby.into_iter().map(owned_string).collect(),
+            expected_users: self.expected_users.into_iter().map(owned_string).collect(),
+            empty_room_ttl: self.empty_room_ttl,
+            player_ttl: self.player_ttl,
+            custom_properties: owned_string_map(self.custom_properties),
+        }
+    }
 }
 
-impl<'s> TryFrom<HashMap<ProtocolValue<'s>, ProtocolValue<'s>>> for RoomInfo<'s> {
+impl<'s> RoomInfo<'s> {
+    /// Applies changed properties, as sent in `Operation::SetPropertiesGame` or `Event::PropertiesChanged`.
+    ///
+    /// Returns `false` without changing anything if the expected properties do not match, and an error if a well-known property has
+    /// the wrong type.
+    pub fn apply_properties(&mut self, delta: PropertiesDelta<'s>) -> PacketReadResult<bool> {
+        if !expected_properties_match(|| self.clone().into(), delta.expected_properties.as_ref()) {
+            return Ok(false);
+        }
+
+        let change = RoomPropertiesChange::try_from(delta.properties)?;
+        self.max_players = change.max_players.unwrap_or(self.max_players);
+        self.is_open = change.is_open.unwrap_or(self.is_open);
+        self.is_visible = change.is_visible.unwrap_or(self.is_visible);
+        self.player_count = change.player_count.unwrap_or(self.player_count);
+        self.cleanup_cache_on_leave = change.cleanup_cache_on_leave.unwrap_or(self.cleanup_cache_on_leave);
+        self.master_client_id = change.master_client_id.or(self.master_client_id);
+        if let Some(l) = change.custom_properties_lobby {
+            self.custom_properties_lobby = l;
+        }
+        if let Some(u) = change.expected_users {
+            self.expected_users = u;
+        }
+        self.empty_room_ttl = change.empty_room_ttl.unwrap_or(self.empty_room_ttl);
+        self.player_ttl = change.player_ttl.unwrap_or(self.player_ttl);
+        merge_custom_properties(&mut self.custom_properties, change.custom_properties, delta.delete_null_props);
+
+        Ok(true)
+    }
+}
+
+impl<'s> RoomOptions<'s> {
+    /// Applies changed properties, like `RoomInfo::apply_properties`. Properties that are only known in a `RoomInfo`, such as the
+    /// player count, are ignored.
+    pub fn apply_properties(&mut self, delta: PropertiesDelta<'s>) -> PacketReadResult<bool> {
+        if !expected_properties_match(|| self.clone().into(), delta.expected_properties.as_ref()) {
+            return Ok(false);
+        }
+
+        let change = RoomPropertiesChange::try_from(delta.properties)?;
+        self.max_players = change.max_players.unwrap_or(self.max_players);
+        self.is_open = change.is_open.unwrap_or(self.is_open);
+        self.is_visible = change.is_visible.unwrap_or(self.is_visible);
+        self.cleanup_cache_on_leave = change.cleanup_cache_on_leave.unwrap_or(self.cleanup_cache_on_leave);
+        if let Some(l) = change.custom_properties_lobby {
+            self.custom_properties_lobby = l;
+        }
+        merge_custom_properties(&mut self.custom_properties, change.custom_properties, delta.delete_null_props);
+
+        Ok(true)
+    }
+}
+
+impl RoomOptions<'_> {
+    /// Converts these options into ones that do not borrow from the deserialized data, copying strings where needed.
+    pub fn into_owned(self) -> RoomOptions<'static> {
+        RoomOptions {
+            max_players: self.max_players,
+            is_open: self.is_open,
+            is_visible: self.is_visible,
+            cleanup_cache_on_leave: self.cleanup_cache_on_leave,
+            custom_properties_lobby: self.custom_properties_lobby.into_iter().map(owned_string).collect(),
+            custom_properties: owned_string_map(self.custom_properties),
+        }
+    }
+}
+
+impl RoomPropertiesChange<'_> {
+    /// Converts this change into one that does not borrow from the deserialized data, copying strings where needed.
+    pub fn into_owned(self) -> RoomPropertiesChange<'static> {
+        RoomPropertiesChange {
+            max_players: self.max_players,
+            is_open: self.is_open,
+            is_visible: self.is_visible,
+            player_count: self.player_count,
+            cleanup_cache_on_leave: self.cleanup_cache_on_leave,
+            master_client_id: self.master_client_id,
+            custom_properties_lobby: self.custom_properties_lobby.map(|l| l.into_iter().map(owned_string).collect()),
+            expected_users: self.expected_users.map(|u| u.into_iter().map(owned_string).collect()),
+            empty_room_ttl: self.empty_room_ttl,
+            player_ttl: self.player_ttl,
+            custom_properties: owned_string_map(self.custom_properties),
+        }
+    }
+}
+
+impl PropertiesChange<'_> {
+    /// Converts this change into one that does not borrow from the deserialized data, copying strings where needed.
+    pub fn into_owned(self) -> PropertiesChange<'static> {
+        match self {
+            PropertiesChange::Room(room) => PropertiesChange::Room(room.into_owned()),
+            PropertiesChange::Player(player) => PropertiesChange::Player(player.into_owned()),
+        }
+    }
+}
+
+impl<'s> Player<'s> {
+    /// Applies changed properties, as sent in `Operation::SetPropertiesActor` or `Event::PropertiesChanged`.
+    ///
+    /// Returns `false` without changing anything if the expected properties do not match, and an error if a well-known property has
+    /// the wrong type.
+    pub fn apply_properties(&mut self, delta: PropertiesDelta<'s>) -> PacketReadResult<bool> {
+        if !expected_properties_match(|| self.clone().into(), delta.expected_properties.as_ref()) {
+            return Ok(false);
+        }
+
+        let change = Player::try_from(delta.properties)?;
+        self.name = change.name.or_else(|| self.name.take());
+        self.user_id = change.user_id.or_else(|| self.user_id.take());
+        self.is_inactive = change.is_inactive.or(self.is_inactive);
+        merge_custom_properties(&mut self.custom_properties, change.custom_properties, delta.delete_null_props);
+
+        Ok(true)
+    }
+}
+
+impl Player<'_> {
+    /// Converts this player into one that does not borrow from the deserialized data, copying strings where needed.
+    pub fn into_owned(self) -> Player<'static> {
+        Player {
+            name: self.name.map(owned_string),
+            user_id: self.user_id.map(owned_string),
+            is_inactive: self.is_inactive,
+            custom_properties: owned_string_map(self.custom_properties),
+        }
+    }
+}
+
+impl<'s> TryFrom<IndexMap<ProtocolValue<'s>, ProtocolValue<'s>>> for RoomInfo<'s> {
     type Error = PacketReadError;
 
-    fn try_from(mut table: HashMap<ProtocolValue<'s>, ProtocolValue<'s>>) -> PacketReadResult<Self> {
+    fn try_from(mut table: IndexMap<ProtocolValue<'s>, ProtocolValue<'s>>) -> PacketReadResult<Self> {
         Ok(RoomInfo {
             max_players: get_protocol_byte_opt(&mut table, ProtocolValue::Byte(GamePropertyKey::MaxPlayers))?.unwrap_or(0),
-            is_open: get_protocol_bool_opt(&mut table, ProtocolValue::Byte(GamePropertyKey::IsVisible))?.unwrap_or(true),
-            is_visible: get_protocol_bool_opt(&mut table, ProtocolValue::Byte(GamePropertyKey::IsOpen))?.unwrap_or(true),
+            is_open: get_protocol_bool_opt(&mut table, ProtocolValue::Byte(GamePropertyKey::IsOpen))?.unwrap_or(true),
+            is_visible: get_protocol_bool_opt(&mut table, ProtocolValue::Byte(GamePropertyKey::IsVisible))?.unwrap_or(true),
             player_count: get_protocol_byte_opt(&mut table, ProtocolValue::Byte(GamePropertyKey::PlayerCount))?.unwrap_or(0),
             cleanup_cache_on_leave: get_protocol_bool_opt(&mut table, ProtocolValue::Byte(GamePropertyKey::CleanupCacheOnLeave))?.unwrap_or(true),
             master_client_id: get_protocol_int_opt(&mut table, ProtocolValue::Byte(GamePropertyKey::MasterClientId))?,
             custom_properties_lobby: get_protocol_array_opt(&mut table, ProtocolValue::Byte(GamePropertyKey::PropsListedInLobby))?
                 .map(|arr| arr.into_iter().map(unwrap_protocol_string).collect())
                 .transpose()?
-                .unwrap_or_else(|| vec![]),
+                .unwrap_or_default(),
             expected_users: get_protocol_array_opt(&mut table, ProtocolValue::Byte(GamePropertyKey::ExpectedUsers))?
                 .map(|arr| arr.into_iter().map(unwrap_protocol_string).collect())
                 .transpose()?
-                .unwrap_or_else(|| vec![]),
+                .unwrap_or_default(),
             empty_room_ttl: get_protocol_int_opt(&mut table, ProtocolValue::Byte(GamePropertyKey::EmptyRoomTtl))?.unwrap_or(0),
             player_ttl: get_protocol_int_opt(&mut table, ProtocolValue::Byte(GamePropertyKey::PlayerTtl))?.unwrap_or(0),
 
             // all remaining properties into custom_properties, assume they use string keys
-            // note: maybe I should make this return an error instead of panicking on non-string keys
             custom_properties: table
                 .into_iter()
-                .map(|(key, value)| (unwrap_protocol_string(key).expect("Expected string key for custom_properties"), value))
-                .collect(),
+                .map(|(key, value)| Ok((unwrap_protocol_string(key)?, value)))
+                .collect::<PacketReadResult<_>>()?,
         })
     }
 }
 
-impl<'s> Into<HashMap<ProtocolValue<'s>, ProtocolValue<'s>>> for RoomInfo<'s> {
-    fn into(self) -> HashMap<ProtocolValue<'s>, ProtocolValue<'s>> {
-        let mut map = HashMap::new();
+impl<'s> From<RoomInfo<'s>> for IndexMap<ProtocolValue<'s>, ProtocolValue<'s>> {
+    fn from(info: RoomInfo<'s>) -> Self {
+        let mut map = IndexMap::new();
         // TODO: don't write if default value
-        map.insert(ProtocolValue::Byte(GamePropertyKey::MaxPlayers), ProtocolValue::Byte(self.max_players));
-        map.insert(ProtocolValue::Byte(GamePropertyKey::IsVisible), ProtocolValue::Bool(self.is_open));
-        map.insert(ProtocolValue::Byte(GamePropertyKey::IsOpen), ProtocolValue::Bool(self.is_visible));
-        map.insert(ProtocolValue::Byte(GamePropertyKey::PlayerCount), ProtocolValue::Byte(self.player_count));
+        map.insert(ProtocolValue::Byte(GamePropertyKey::MaxPlayers), ProtocolValue::Byte(info.max_players));
+        map.insert(ProtocolValue::Byte(GamePropertyKey::IsVisible), ProtocolValue::Bool(info.is_visible));
+        map.insert(ProtocolValue::Byte(GamePropertyKey::IsOpen), ProtocolValue::Bool(info.is_open));
+        map.insert(ProtocolValue::Byte(GamePropertyKey::PlayerCount), ProtocolValue::Byte(info.player_count));
         map.insert(
             ProtocolValue::Byte(GamePropertyKey::CleanupCacheOnLeave),
-            ProtocolValue::Bool(self.cleanup_cache_on_leave),
+            ProtocolValue::Bool(info.cleanup_cache_on_leave),
         );
-        self.master_client_id
+        info.master_client_id
             .and_then(|id| map.insert(ProtocolValue::Byte(GamePropertyKey::MasterClientId), ProtocolValue::Integer(id)));
-        if !self.custom_properties_lobby.is_empty() {
+        if !info.custom_properties_lobby.is_empty() {
             map.insert(
                 ProtocolValue::Byte(GamePropertyKey::PropsListedInLobby),
-                ProtocolValue::Array(self.custom_properties_lobby.into_iter().map(ProtocolValue::String).collect()),
+                ProtocolValue::Array(115, info.custom_properties_lobby.into_iter().map(ProtocolValue::String).collect()),
             );
         }
-        if !self.expected_users.is_empty() {
+        if !info.expected_users.is_empty() {
             map.insert(
                 ProtocolValue::Byte(GamePropertyKey::ExpectedUsers),
-                ProtocolValue::Array(self.expected_users.into_iter().map(ProtocolValue::String).collect()),
+                ProtocolValue::Array(115, info.expected_users.into_iter().map(ProtocolValue::String).collect()),
             );
         }
         map.insert(
             ProtocolValue::Byte(GamePropertyKey::EmptyRoomTtl),
-            ProtocolValue::Integer(self.empty_room_ttl),
+            ProtocolValue::Integer(info.empty_room_ttl),
         );
-        map.insert(ProtocolValue::Byte(GamePropertyKey::PlayerTtl), ProtocolValue::Integer(self.player_ttl));
+        map.insert(ProtocolValue::Byte(GamePropertyKey::PlayerTtl), ProtocolValue::Integer(info.player_ttl));
 
         // Add the remaining properties
-        let remaining: HashMap<ProtocolValue, ProtocolValue> = self
+        let remaining: IndexMap<ProtocolValue, ProtocolValue> = info
             .custom_properties
             .into_iter()
             .map(|(key, value)| (ProtocolValue::String(key), value))
@@ -103,50 +248,51 @@ impl<'s> Into<HashMap<ProtocolValue<'s>, ProtocolValue<'s>>> for RoomInfo<'s> {
     }
 }
 
-impl<'s> TryFrom<HashMap<ProtocolValue<'s>, ProtocolValue<'s>>> for RoomOptions<'s> {
+impl<'s> TryFrom<IndexMap<ProtocolValue<'s>, ProtocolValue<'s>>> for RoomOptions<'s> {
     type Error = PacketReadError;
 
-    fn try_from(mut table: HashMap<ProtocolValue<'s>, ProtocolValue<'s>>) -> PacketReadResult<Self> {
+    fn try_from(mut table: IndexMap<ProtocolValue<'s>, ProtocolValue<'s>>) -> PacketReadResult<Self> {
         Ok(RoomOptions {
             max_players: get_protocol_byte_opt(&mut table, ProtocolValue::Byte(GamePropertyKey::MaxPlayers))?.unwrap_or(0),
-            is_open: get_protocol_bool_opt(&mut table, ProtocolValue::Byte(GamePropertyKey::IsVisible))?.unwrap_or(true),
-            is_visible: get_protocol_bool_opt(&mut table, ProtocolValue::Byte(GamePropertyKey::IsOpen))?.unwrap_or(true),
+            is_open: get_protocol_bool_opt(&mut table, ProtocolValue::Byte(GamePropertyKey::IsOpen))?.unwrap_or(true),
+            is_visible: get_protocol_bool_opt(&mut table, ProtocolValue::Byte(GamePropertyKey::IsVisible))?.unwrap_or(true),
             cleanup_cache_on_leave: get_protocol_bool_opt(&mut table, ProtocolValue::Byte(GamePropertyKey::CleanupCacheOnLeave))?.unwrap_or(true),
             custom_properties_lobby: get_protocol_array_opt(&mut table, ProtocolValue::Byte(GamePropertyKey::PropsListedInLobby))?
                 .map(|arr| arr.into_iter().map(unwrap_protocol_string).collect())
                 .transpose()?
-                .unwrap_or_else(|| vec![]),
+                .unwrap_or_default(),
 
             // all remaining properties into custom_properties, assume they use string keys
-            // note: maybe I should make this return an error instead of panicking on non-string keys
             custom_properties: table
                 .into_iter()
-                .map(|(key, value)| (unwrap_protocol_string(key).expect("Expected string key for custom_properties"), value))
-                .collect(),
+                .map(|(key, value)| Ok((unwrap_protocol_string(key)?, value)))
+                .collect::<PacketReadResult<_>>()?,
         })
     }
 }
 
-impl<'s> Into<HashMap<ProtocolValue<'s>, ProtocolValue<'s>>> for RoomOptions<'s> {
-    fn into(self) -> HashMap<ProtocolValue<'s>, ProtocolValue<'s>> {
-        let mut map = HashMap::new();
+impl<'s> From<RoomOptions<'s>> for IndexMap<ProtocolValue<'s>, ProtocolValue<'s>> {
+    fn from(options: RoomOptions<'s>) -> Self {
+        let mut map = IndexMap::new();
         // TODO: don't write if default value
-        if self.max_players != 0 {
-            map.insert(ProtocolValue::Byte(GamePropertyKey::MaxPlayers), ProtocolValue::Byte(self.max_players));
+        if options.max_players != 0 {
+            map.insert(ProtocolValue::Byte(GamePropertyKey::MaxPlayers), ProtocolValue::Byte(options.max_players));
         }
-        map.insert(ProtocolValue::Byte(GamePropertyKey::IsVisible), ProtocolValue::Bool(self.is_open));
-        map.insert(ProtocolValue::Byte(GamePropertyKey::IsOpen), ProtocolValue::Bool(self.is_visible));
+        map.insert(ProtocolValue::Byte(GamePropertyKey::IsVisible), ProtocolValue::Bool(options.is_visible));
+        map.insert(ProtocolValue::Byte(GamePropertyKey::IsOpen), ProtocolValue::Bool(options.is_open));
         map.insert(
             ProtocolValue::Byte(GamePropertyKey::CleanupCacheOnLeave),
-            ProtocolValue::Bool(self.cleanup_cache_on_leave),
-        );
-        map.insert(
-            ProtocolValue::Byte(GamePropertyKey::PropsListedInLobby),
-            ProtocolValue::Array(self.custom_properties_lobby.into_iter().map(ProtocolValue::String).collect()),
+            ProtocolValue::Bool(options.cleanup_cache_on_leave),
         );
+        if !options.custom_properties_lobby.is_empty() {
+            map.insert(
+                ProtocolValue::Byte(GamePropertyKey::PropsListedInLobby),
+                ProtocolValue::Array(115, options.custom_properties_lobby.into_iter().map(ProtocolValue::String).collect()),
+            );
+        }
 
         // Add the remaining properties
-        let remaining: HashMap<ProtocolValue, ProtocolValue> = self
+        let remaining: IndexMap<ProtocolValue, ProtocolValue> = options
             .custom_properties
             .into_iter()
             .map(|(key, value)| (ProtocolValue::String(key), value))
@@ -156,38 +302,131 @@ impl<'s> Into<HashMap<ProtocolValue<'s>, ProtocolValue<'s>>> for RoomOptions<'s>
     }
 }
 
-impl<'s> TryFrom<HashMap<ProtocolValue<'s>, ProtocolValue<'s>>> for Player<'s> {
+impl<'s> TryFrom<IndexMap<ProtocolValue<'s>, ProtocolValue<'s>>> for RoomPropertiesChange<'s> {
     type Error = PacketReadError;
 
-    fn try_from(mut table: HashMap<ProtocolValue<'s>, ProtocolValue<'s>>) -> PacketReadResult<Self> {
+    fn try_from(mut table: IndexMap<ProtocolValue<'s>, ProtocolValue<'s>>) -> PacketReadResult<Self> {
+        Ok(RoomPropertiesChange {
+            max_players: get_protocol_byte_opt(&mut table, ProtocolValue::Byte(GamePropertyKey::MaxPlayers))?,
+            is_open: get_protocol_bool_opt(&mut table, ProtocolValue::Byte(GamePropertyKey::IsOpen))?,
+            is_visible: get_protocol_bool_opt(&mut table, ProtocolValue::Byte(GamePropertyKey::IsVisible))?,
+            player_count: get_protocol_byte_opt(&mut table, ProtocolValue::Byte(GamePropertyKey::PlayerCount))?,
+            cleanup_cache_on_leave: get_protocol_bool_opt(&mut table, ProtocolValue::Byte(GamePropertyKey::CleanupCacheOnLeave))?,
+            master_client_id: get_protocol_int_opt(&mut table, ProtocolValue::Byte(GamePropertyKey::MasterClientId))?,
+            custom_properties_lobby: get_protocol_array_opt(&mut table, ProtocolValue::Byte(GamePropertyKey::PropsListedInLobby))?
+                .map(|arr| arr.into_iter().map(unwrap_protocol_string).collect())
+                .transpose()?,
+            expected_users: get_protocol_array_opt(&mut table, ProtocolValue::Byte(GamePropertyKey::ExpectedUsers))?
+                .map(|arr| arr.into_iter().map(unwrap_protocol_string).collect())
+                .transpose()?,
+            empty_room_ttl: get_protocol_int_opt(&mut table, ProtocolValue::Byte(GamePropertyKey::EmptyRoomTtl))?,
+            player_ttl: get_protocol_int_opt(&mut table, ProtocolValue::Byte(GamePropertyKey::PlayerTtl))?,
+
+            // all remaining properties into custom_properties, assume they use string keys
+            custom_properties: table
+                .into_iter()
+                .map(|(key, value)| Ok((unwrap_protocol_string(key)?, value)))
+                .collect::<PacketReadResult<_>>()?,
+        })
+    }
+}
+
+impl<'s> From<RoomPropertiesChange<'s>> for IndexMap<ProtocolValue<'s>, ProtocolValue<'s>> {
+    fn from(change: RoomPropertiesChange<'s>) -> Self {
+        let mut map = IndexMap::new();
+
+        change
+            .max_players
+            .and_then(|m| map.insert(ProtocolValue::Byte(GamePropertyKey::MaxPlayers), ProtocolValue::Byte(m)));
+        change
+            .is_visible
+            .and_then(|v| map.insert(ProtocolValue::Byte(GamePropertyKey::IsVisible), ProtocolValue::Bool(v)));
+        change
+            .is_open
+            .and_then(|o| map.insert(ProtocolValue::Byte(GamePropertyKey::IsOpen), ProtocolValue::Bool(o)));
+        change
+            .player_count
+            .and_then(|c| map.insert(ProtocolValue::Byte(GamePropertyKey::PlayerCount), ProtocolValue::Byte(c)));
+        change.custom_properties_lobby.and_then(|l| {
+            map.insert(
+                ProtocolValue::Byte(GamePropertyKey::PropsListedInLobby),
+                ProtocolValue::Array(115, l.into_iter().map(ProtocolValue::String).collect()),
+            )
+        });
+        change
+            .cleanup_cache_on_leave
+            .and_then(|c| map.insert(ProtocolValue::Byte(GamePropertyKey::CleanupCacheOnLeave), ProtocolValue::Bool(c)));
+        change
+            .master_client_id
+            .and_then(|id| map.insert(ProtocolValue::Byte(GamePropertyKey::MasterClientId), ProtocolValue::Integer(id)));
+        change.expected_users.and_then(|u| {
+            map.insert(
+                ProtocolValue::Byte(GamePropertyKey::ExpectedUsers),
+                ProtocolValue::Array(115, u.into_iter().map(ProtocolValue::String).collect()),
+            )
+        });
+        change
+            .player_ttl
+            .and_then(|ttl| map.insert(ProtocolValue::Byte(GamePropertyKey::PlayerTtl), ProtocolValue::Integer(ttl)));
+        change
+            .empty_room_ttl
+            .and_then(|ttl| map.insert(ProtocolValue::Byte(GamePropertyKey::EmptyRoomTtl), ProtocolValue::Integer(ttl)));
+
+        // Add the remaining properties
+        let remaining: IndexMap<ProtocolValue, ProtocolValue> = change
+            .custom_properties
+            .into_iter()
+            .map(|(key, value)| (ProtocolValue::String(key), value))
+            .collect();
+        map.extend(remaining);
+
+        map
+    }
+}
+
+impl<'s> From<PropertiesChange<'s>> for IndexMap<ProtocolValue<'s>, ProtocolValue<'s>> {
+    fn from(change: PropertiesChange<'s>) -> Self {
+        match change {
+            PropertiesChange::Room(room) => room.into(),
+            PropertiesChange::Player(player) => player.into(),
+        }
+    }
+}
+
+impl<'s> TryFrom<IndexMap<ProtocolValue<'s>, ProtocolValue<'s>>> for Player<'s> {
+    type Error = PacketReadError;
+
+    fn try_from(mut table: IndexMap<ProtocolValue<'s>, ProtocolValue<'s>>) -> PacketReadResult<Self> {
         Ok(Player {
             name: get_protocol_string_opt(&mut table, ProtocolValue::Byte(ActorProperties::PlayerName))?,
             user_id: get_protocol_string_opt(&mut table, ProtocolValue::Byte(ActorProperties::UserId))?,
             is_inactive: get_protocol_bool_opt(&mut table, ProtocolValue::Byte(ActorProperties::IsInactive))?,
 
             // all remaining properties into custom_properties, assume they use string keys
-            // note: maybe I should make this return an error instead of panicking on non-string keys
             custom_properties: table
                 .into_iter()
-                .map(|(key, value)| (unwrap_protocol_string(key).expect("Expected string key for custom_properties"), value))
-                .collect(),
+                .map(|(key, value)| Ok((unwrap_protocol_string(key)?, value)))
+                .collect::<PacketReadResult<_>>()?,
         })
     }
 }
 
-impl<'s> Into<HashMap<ProtocolValue<'s>, ProtocolValue<'s>>> for Player<'s> {
-    fn into(self) -> HashMap<ProtocolValue<'s>, ProtocolValue<'s>> {
-        let mut map = HashMap::new();
+impl<'s> From<Player<'s>> for IndexMap<ProtocolValue<'s>, ProtocolValue<'s>> {
+    fn from(player: Player<'s>) -> Self {
+        let mut map = IndexMap::new();
 
-        self.name
+        player
+            .name
             .and_then(|n| map.insert(ProtocolValue::Byte(ActorProperties::PlayerName), ProtocolValue::String(n)));
-        self.user_id
+        player
+            .user_id
             .and_then(|n| map.insert(ProtocolValue::Byte(ActorProperties::UserId), ProtocolValue::String(n)));
-        self.is_inactive
+        player
+            .is_inactive
             .and_then(|i| map.insert(ProtocolValue::Byte(ActorProperties::IsInactive), ProtocolValue::Bool(i)));
 
         // Add the remaining properties
-        let remaining: HashMap<ProtocolValue, ProtocolValue> = self
+        let remaining: IndexMap<ProtocolValue, ProtocolValue> = player
             .custom_properties
             .into_iter()
             .map(|(key, value)| (ProtocolValue::String(key), value))
@@ -197,3 +436,50 @@ impl<'s> Into<HashMap<ProtocolValue<'s>, ProtocolValue<'s>>> for Player<'s> {
         map
     }
 }
+
+impl<'s> From<IndexMap<ProtocolValue<'s>, ProtocolValue<'s>>> for PropertiesDelta<'s> {
+    fn from(properties: IndexMap<ProtocolValue<'s>, ProtocolValue<'s>>) -> Self {
+        PropertiesDelta {
+            properties,
+            expected_properties: None,
+            delete_null_props: false,
+        }
+    }
+}
+
+impl<'s> From<PropertiesChange<'s>> for PropertiesDelta<'s> {
+    fn from(change: PropertiesChange<'s>) -> Self {
+        PropertiesDelta::from(IndexMap::from(change))
+    }
+}
+
+/// Checks the expected properties like the server does, where a missing property is the same as `ProtocolValue::Null`. The current
+/// properties are only built when there are expected properties to check.
+fn expected_properties_match<'a>(
+    current: impl FnOnce() -> IndexMap<ProtocolValue<'a>, ProtocolValue<'a>>,
+    expected: Option<&IndexMap<ProtocolValue, ProtocolValue>>,
+) -> bool {
+    expected.is_none_or(|expected| {
+        let current = current();
+        expected
+            .iter()
+            .all(|(key, value)| current.get(key).unwrap_or(&ProtocolValue::Null()) == value)
+    })
+}
+
+fn merge_custom_properties<'s>(
+    properties: &mut IndexMap<Cow<'s, str>, ProtocolValue<'s>>,
+    changes: IndexMap<Cow<'s, str>, ProtocolValue<'s>>,
+    delete_null_props: bool,
+) {
+    for (key, value) in changes {
+        match value {
+            ProtocolValue::Null() if delete_null_props => {
+                properties.shift_remove(&key);
+            }
+            value => {
+                properties.insert(key, value);
+            }
+        }
+    }
+}
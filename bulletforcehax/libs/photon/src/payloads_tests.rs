@@ -1,7 +1,7 @@
 #![cfg(test)]
 
 use super::*;
-use maplit::hashmap;
+use indexmap::indexmap;
 use std::convert::TryFrom;
 
 #[test]
@@ -14,14 +14,14 @@ fn test_room_info() {
             player_count: 0,
             cleanup_cache_on_leave: true,
             master_client_id: Some(1),
-            custom_properties_lobby: vec!["test", "test2", "test3"],
+            custom_properties_lobby: vec!["test".into(), "test2".into(), "test3".into()],
             expected_users: vec![],
             empty_room_ttl: 0,
             player_ttl: 0,
-            custom_properties: hashmap! {
-                "test" => ProtocolValue::Integer(1),
-                "test2" => ProtocolValue::Null(),
-                "test3" => ProtocolValue::String("abc"),
+            custom_properties: indexmap! {
+                "test".into() => ProtocolValue::Integer(1),
+                "test2".into() => ProtocolValue::Null(),
+                "test3".into() => ProtocolValue::String("abc".into()),
             },
         }
     }
@@ -42,11 +42,11 @@ fn test_room_options() {
             is_open: false,
             is_visible: true,
             cleanup_cache_on_leave: true,
-            custom_properties_lobby: vec!["test", "test2", "test3"],
-            custom_properties: hashmap! {
-                "test" => ProtocolValue::Integer(1),
-                "test2" => ProtocolValue::Null(),
-                "test3" => ProtocolValue::String("abc"),
+            custom_properties_lobby: vec!["test".into(), "test2".into(), "test3".into()],
+            custom_properties: indexmap! {
+                "test".into() => ProtocolValue::Integer(1),
+                "test2".into() => ProtocolValue::Null(),
+                "test3".into() => ProtocolValue::String("abc".into()),
             },
         }
     }
@@ -54,7 +54,7 @@ fn test_room_options() {
     let info = gen();
     let info_clone = gen();
 
-    let ht: HashMap<ProtocolValue, ProtocolValue> = info.into();
+    let ht: IndexMap<ProtocolValue, ProtocolValue> = info.into();
     let info_new = RoomOptions::try_from(ht).unwrap();
     assert_eq!(info_clone, info_new);
 }
@@ -63,11 +63,11 @@ fn test_room_options() {
 fn test_player() {
     fn gen<'a>() -> Player<'a> {
         Player {
-            name: Some("Jeffrey"),
-            user_id: Some("abc-def-ghi"),
+            name: Some("Jeffrey".into()),
+            user_id: Some("abc-def-ghi".into()),
             is_inactive: None,
-            custom_properties: hashmap! {
-                "is_cool" => ProtocolValue::Bool(true),
+            custom_properties: indexmap! {
+                "is_cool".into() => ProtocolValue::Bool(true),
             },
         }
     }
@@ -75,7 +75,207 @@ fn test_player() {
     let info = gen();
     let info_clone = gen();
 
-    let ht: HashMap<ProtocolValue, ProtocolValue> = info.into();
+    let ht: IndexMap<ProtocolValue, ProtocolValue> = info.into();
     let info_new = Player::try_from(ht).unwrap();
     assert_eq!(info_clone, info_new);
 }
+
+fn gen_room<'a>() -> RoomInfo<'a> {
+    RoomInfo {
+        max_players: 12,
+        is_open: true,
+        is_visible: true,
+        player_count: 2,
+        cleanup_cache_on_leave: true,
+        master_client_id: Some(1),
+        custom_properties_lobby: vec!["gameMode".into()],
+        expected_users: vec![],
+        empty_room_ttl: 0,
+        player_ttl: 0,
+        custom_properties: indexmap! {
+            "gameMode".into() => ProtocolValue::Integer(0),
+            "roundStarted".into() => ProtocolValue::Bool(false),
+        },
+    }
+}
+
+#[test]
+fn test_room_info_apply_properties() {
+    let mut room = gen_room();
+    let applied = room
+        .apply_properties(PropertiesDelta {
+            properties: indexmap! {
+                ProtocolValue::Byte(GamePropertyKey::IsOpen) => ProtocolValue::Bool(false),
+                ProtocolValue::Byte(GamePropertyKey::MasterClientId) => ProtocolValue::Integer(2),
+                ProtocolValue::String("roundStarted".into()) => ProtocolValue::Bool(true),
+                ProtocolValue::String("gameMode".into()) => ProtocolValue::Null(),
+            },
+            expected_properties: None,
+            delete_null_props: true,
+        })
+        .unwrap();
+
+    assert!(applied);
+    assert!(!room.is_open);
+    assert!(room.is_visible);
+    assert_eq!(room.master_client_id, Some(2));
+    assert_eq!(room.max_players, 12);
+    assert_eq!(room.custom_properties, indexmap! { "roundStarted".into() => ProtocolValue::Bool(true) });
+}
+
+#[test]
+fn test_room_info_apply_properties_keeps_null() {
+    let mut room = gen_room();
+    room.apply_properties(PropertiesDelta::from(indexmap! {
+        ProtocolValue::String("gameMode".into()) => ProtocolValue::Null(),
+    }))
+    .unwrap();
+
+    assert_eq!(room.custom_properties.get("gameMode"), Some(&ProtocolValue::Null()));
+}
+
+#[test]
+fn test_room_info_apply_properties_expected() {
+    fn delta<'a>(expected_mode: i32) -> PropertiesDelta<'a> {
+        PropertiesDelta {
+            properties: indexmap! {
+                ProtocolValue::String("gameMode".into()) => ProtocolValue::Integer(3),
+            },
+            expected_properties: Some(indexmap! {
+                ProtocolValue::String("gameMode".into()) => ProtocolValue::Integer(expected_mode),
+                ProtocolValue::Byte(GamePropertyKey::IsOpen) => ProtocolValue::Bool(true),
+                ProtocolValue::String("missing".into()) => ProtocolValue::Null(),
+            }),
+            delete_null_props: false,
+        }
+    }
+
+    let mut room = gen_room();
+    assert!(!room.apply_properties(delta(1)).unwrap());
+    assert_eq!(room, gen_room());
+
+    assert!(room.apply_properties(delta(0)).unwrap());
+    assert_eq!(room.custom_properties.get("gameMode"), Some(&ProtocolValue::Integer(3)));
+}
+
+#[test]
+fn test_room_info_apply_properties_wrong_type() {
+    let mut room = gen_room();
+    let result = room.apply_properties(PropertiesDelta::from(indexmap! {
+        ProtocolValue::String("roundStarted".into()) => ProtocolValue::Bool(true),
+        ProtocolValue::Byte(GamePropertyKey::MaxPlayers) => ProtocolValue::Integer(12),
+    }));
+
+    assert!(result.is_err());
+    assert_eq!(room, gen_room());
+}
+
+#[test]
+fn test_room_options_apply_properties() {
+    let mut options = RoomOptions {
+        max_players: 12,
+        is_open: true,
+        is_visible: true,
+        cleanup_cache_on_leave: true,
+        custom_properties_lobby: vec![],
+        custom_properties: indexmap! {},
+    };
+    options
+        .apply_properties(PropertiesDelta::from(indexmap! {
+            ProtocolValue::Byte(GamePropertyKey::IsVisible) => ProtocolValue::Bool(false),
+            ProtocolValue::Byte(GamePropertyKey::PlayerCount) => ProtocolValue::Byte(3),
+            ProtocolValue::String("gameMode".into()) => ProtocolValue::Integer(2),
+        }))
+        .unwrap();
+
+    assert!(!options.is_visible);
+    assert_eq!(options.custom_properties, indexmap! { "gameMode".into() => ProtocolValue::Integer(2) });
+}
+
+#[test]
+fn test_player_apply_properties() {
+    let mut player = Player {
+        name: Some("Jeffrey".into()),
+        user_id: Some("abc-def-ghi".into()),
+        is_inactive: None,
+        custom_properties: indexmap! {
+            "kills".into() => ProtocolValue::Integer(3),
+            "team".into() => ProtocolValue::Byte(1),
+        },
+    };
+    let change = PropertiesChange::Player(Player {
+        name: Some("HoLLy".into()),
+        user_id: None,
+        is_inactive: None,
+        custom_properties: indexmap! {
+            "kills".into() => ProtocolValue::Integer(4),
+            "team".into() => ProtocolValue::Null(),
+        },
+    });
+    let delta = PropertiesDelta {
+        delete_null_props: true,
+        ..change.into()
+    };
+
+    assert!(player.apply_properties(delta).unwrap());
+    assert_eq!(
+        player,
+        Player {
+            name: Some("HoLLy".into()),
+            user_id: Some("abc-def-ghi".into()),
+            is_inactive: None,
+            custom_properties: indexmap! {
+                "kills".into() => ProtocolValue::Integer(4),
+            },
+        }
+    );
+}
+
+#[test]
+fn test_room_info_builder() {
+    let room = RoomInfo::new()
+        .with_max_players(12)
+        .with_master_client_id(Some(1))
+        .with_custom_properties_lobby(vec!["gameMode".into()])
+        .with_custom("gameMode", ProtocolValue::Integer(3));
+
+    assert_eq!(room.max_players(), 12);
+    assert!(room.is_open());
+    assert!(room.is_visible());
+    assert!(room.cleanup_cache_on_leave());
+    assert_eq!(room.master_client_id(), Some(1));
+    assert_eq!(room.custom_properties_lobby(), ["gameMode"]);
+    assert_eq!(room.get_custom::<i32>("gameMode"), Some(3));
+    assert_eq!(room.get_custom::<String>("gameMode"), None);
+    assert_eq!(room.get_custom::<i32>("missing"), None);
+
+    // the defaults survive a round trip
+    let ht: IndexMap<ProtocolValue, ProtocolValue> = room.clone().into();
+    assert_eq!(RoomInfo::try_from(ht).unwrap(), room);
+    assert_eq!(RoomInfo::try_from(IndexMap::new()).unwrap(), RoomInfo::default());
+}
+
+#[test]
+fn test_room_options_builder() {
+    let options = RoomOptions::new().with_max_players(8).with_is_visible(false);
+
+    assert_eq!(options.max_players(), 8);
+    assert!(options.is_open());
+    assert!(!options.is_visible());
+    assert!(options.custom_properties().is_empty());
+    assert_eq!(RoomOptions::try_from(IndexMap::new()).unwrap(), RoomOptions::default());
+}
+
+#[test]
+fn test_player_builder() {
+    let player = Player::new()
+        .with_name("HoLLy")
+        .with_custom("rank", ProtocolValue::Byte(3))
+        .with_custom("clan", ProtocolValue::String("abc".into()));
+
+    assert_eq!(player.name(), Some("HoLLy"));
+    assert_eq!(player.user_id(), None);
+    assert_eq!(player.is_inactive(), None);
+    assert_eq!(player.get_custom::<u8>("rank"), Some(3));
+    assert_eq!(player.get_custom::<String>("clan"), Some("abc".to_string()));
+}
@@ -0,0 +1,379 @@
+#![cfg(test)]
+
+use super::*;
+use proptest::collection::vec;
+use proptest::prelude::*;
+
+fn arb_string() -> impl Strategy<Value = Cow<'static, str>> {
+    "[a-zA-Z0-9_]{1,8}".prop_map(Cow::Owned)
+}
+
+fn arb_value() -> impl Strategy<Value = ProtocolValue<'static>> {
+    let leaf = prop_oneof![
+        Just(ProtocolValue::Null()),
+        any::<bool>().prop_map(ProtocolValue::Bool),
+        any::<u8>().prop_map(ProtocolValue::Byte),
+        any::<i16>().prop_map(ProtocolValue::Short),
+        any::<i32>().prop_map(ProtocolValue::Integer),
+        any::<i64>().prop_map(ProtocolValue::Long),
+        // NaN is not equal to itself, so it can never survive a round trip
+        any::<f32>().prop_filter("NaN", |x| !x.is_nan()).prop_map(ProtocolValue::Float),
+        arb_string().prop_map(ProtocolValue::String),
+        vec(any::<u8>(), 0..8).prop_map(ProtocolValue::ByteArray),
+        vec(any::<i32>(), 0..4).prop_map(ProtocolValue::IntegerArray),
+    ];
+    leaf.prop_recursive(2, 16, 4, |inner| {
+        prop_oneof![
+            vec(inner.clone(), 0..4).prop_map(ProtocolValue::ObjectArray),
+            vec((arb_string().prop_map(ProtocolValue::String), inner), 0..4)
+                .prop_map(|entries| ProtocolValue::Hashtable(entries.into_iter().collect())),
+        ]
+    })
+}
+
+fn arb_hashtable() -> impl Strategy<Value = IndexMap<ProtocolValue<'static>, ProtocolValue<'static>>> {
+    vec((any::<u8>().prop_map(ProtocolValue::Byte), arb_value()), 0..4).prop_map(|entries| entries.into_iter().collect())
+}
+
+fn arb_custom_properties() -> impl Strategy<Value = IndexMap<Cow<'static, str>, ProtocolValue<'static>>> {
+    vec((arb_string(), arb_value()), 0..4).prop_map(|entries| entries.into_iter().collect())
+}
+
+/// Actor lists are typed arrays, which can not be empty.
+fn arb_actor_list() -> impl Strategy<Value = Option<Vec<i32>>> {
+    prop::option::of(vec(any::<i32>(), 1..4))
+}
+
+fn arb_player() -> impl Strategy<Value = Player<'static>> {
+    (
+        prop::option::of(arb_string()),
+        prop::option::of(arb_string()),
+        prop::option::of(any::<bool>()),
+        arb_custom_properties(),
+    )
+        .prop_map(|(name, user_id, is_inactive, custom_properties)| Player {
+            name,
+            user_id,
+            is_inactive,
+            custom_properties,
+        })
+}
+
+fn arb_room_info() -> impl Strategy<Value = RoomInfo<'static>> {
+    (any::<(u8, bool, bool, u8)>(), arb_custom_properties()).prop_map(|((max_players, is_open, is_visible, player_count), custom_properties)| {
+        custom_properties.into_iter().fold(
+            RoomInfo::new()
+                .with_max_players(max_players)
+                .with_is_open(is_open)
+                .with_is_visible(is_visible)
+                .with_player_count(player_count),
+            |info, (key, value)| info.with_custom(key, value),
+        )
+    })
+}
+
+fn arb_send_packet() -> impl Strategy<Value = Packet<'static>> {
+    let authenticate_request = (
+        any::<bool>(),
+        arb_string(),
+        arb_string(),
+        prop::option::of(arb_string()),
+        prop::option::of(arb_string()),
+        // auth params and data are only written if there is a valid auth type
+        prop::option::of((0u8..255, prop::option::of(arb_string()), prop::option::of(arb_string()))),
+    )
+        .prop_map(
+            |(lobby_stats, app_version, app_id, region, user_id, client_auth)| Operation::AuthenticateRequestNoToken {
+                lobby_stats,
+                app_version,
+                app_id,
+                region,
+                user_id,
+                client_auth_type: client_auth.as_ref().map(|(auth_type, _, _)| *auth_type),
+                client_auth_params: client_auth.as_ref().and_then(|(_, params, _)| params.clone()),
+                client_auth_data: client_auth.and_then(|(_, _, data)| data),
+            },
+        );
+    let set_properties = (
+        prop::option::of(any::<i32>()),
+        arb_hashtable(),
+        prop::option::of(arb_hashtable()),
+        any::<bool>(),
+        any::<bool>(),
+    )
+        .prop_map(|(actor_nr, properties, expected_properties, broadcast, event_forward)| match actor_nr {
+            Some(actor_nr) => Operation::SetPropertiesActor {
+                actor_nr,
+                properties,
+                expected_properties,
+                broadcast,
+                event_forward,
+            },
+            None => Operation::SetPropertiesGame {
+                properties,
+                expected_properties,
+                broadcast,
+                event_forward,
+            },
+        });
+    let raise_event = (
+        prop::sample::select(vec![
+            EventCaching::DoNotCache,
+            EventCaching::AddToRoomCache,
+            EventCaching::AddToRoomCacheGlobal,
+        ]),
+        arb_actor_list(),
+        prop::option::of(any::<u8>()),
+        prop::option::of(prop::sample::select(vec![ReceiverGroup::All, ReceiverGroup::MasterClient])),
+        prop::option::of(Just(true)),
+        prop::option::of(any::<u8>()),
+        prop::option::of(arb_value()),
+    )
+        .prop_map(|(cache, actor_list, group, receivers, event_forward, code, data)| Operation::RaiseEvent {
+            cache,
+            actor_list,
+            group,
+            receivers,
+            event_forward,
+            code,
+            data,
+        });
+    let join_random_game = (
+        prop::option::of(arb_hashtable()),
+        prop::option::of(prop::sample::select(vec![
+            MatchmakingMode::FillRoom,
+            MatchmakingMode::SerialMatching,
+            MatchmakingMode::RandomMatching,
+        ])),
+        prop::option::of(arb_string()),
+        prop::option::of(any::<u8>()),
+        prop::option::of(arb_string()),
+        vec(arb_string(), 0..3),
+    )
+        .prop_map(
+            |(expected_properties, matchmaking_mode, lobby_name, lobby_type, sql_filter, expected_users)| Operation::JoinRandomGameRequest {
+                expected_properties,
+                matchmaking_mode,
+                lobby_name,
+                lobby_type,
+                sql_filter,
+                expected_users,
+            },
+        );
+    let join_game = (
+        arb_string(),
+        prop::option::of(prop::sample::select(vec![
+            JoinMode::Default,
+            JoinMode::CreateIfNotExists,
+            JoinMode::JoinOrRejoin,
+            JoinMode::RejoinOnly,
+        ])),
+        prop::option::of(arb_string()),
+        prop::option::of(any::<u8>()),
+        vec(arb_string(), 0..3),
+    )
+        .prop_map(
+            |(room_name, join_mode, lobby_name, lobby_type, expected_users)| Operation::JoinGameRequestMaster {
+                room_name,
+                join_mode,
+                lobby_name,
+                lobby_type,
+                expected_users,
+            },
+        );
+
+    prop_oneof![
+        (
+            any::<(u8, u8, u8)>(),
+            (0u8..16, 0u8..16, any::<u8>(), any::<u8>()),
+            any::<u8>(),
+            "[a-z0-9-]{0,32}"
+        )
+            .prop_map(|((major, minor, client_sdk_id), client_version, flags, app_id)| Packet::Init {
+                protocol_version: (major, minor),
+                client_sdk_id,
+                client_version,
+                flags,
+                app_id: app_id.into(),
+            }),
+        any::<i32>()
+            .prop_map(|local_time| Packet::InternalOperationRequest(InternalOperation::PingRequest { local_time }, ParameterOrder::default())),
+        vec(any::<u8>(), 0..96).prop_map(|public_key| Packet::InternalOperationRequest(
+            InternalOperation::InitEncryptionRequest { public_key },
+            ParameterOrder::default()
+        )),
+        vec(any::<u8>(), 0..96)
+            .prop_map(|public_key| Packet::OperationRequest(Operation::ExchangeKeysForEncryptionRequest { public_key }, ParameterOrder::default())),
+        authenticate_request.prop_map(|operation| Packet::OperationRequest(operation, ParameterOrder::default())),
+        (any::<bool>(), arb_string()).prop_map(|(lobby_stats, secret)| Packet::OperationRequest(
+            Operation::AuthenticateRequestToken { lobby_stats, secret },
+            ParameterOrder::default()
+        )),
+        set_properties.prop_map(|operation| Packet::OperationRequest(operation, ParameterOrder::default())),
+        raise_event.prop_map(|operation| Packet::OperationRequest(operation, ParameterOrder::default())),
+        join_random_game.prop_map(|operation| Packet::OperationRequest(operation, ParameterOrder::default())),
+        join_game.prop_map(|operation| Packet::OperationRequest(operation, ParameterOrder::default())),
+        Just(Packet::OperationRequest(Operation::JoinLobby(), ParameterOrder::default())),
+        prop::option::of(any::<bool>())
+            .prop_map(|is_inactive| Packet::OperationRequest(Operation::LeaveRequest { is_inactive }, ParameterOrder::default())),
+        (arb_string(), any::<u8>(), arb_string()).prop_map(|(lobby_name, lobby_type, sql_filter)| {
+            Packet::OperationRequest(
+                Operation::GetGameListRequest {
+                    lobby_name,
+                    lobby_type,
+                    sql_filter,
+                },
+                ParameterOrder::default(),
+            )
+        }),
+        arb_string().prop_map(|app_id| Packet::OperationRequest(Operation::GetRegionsRequest { app_id }, ParameterOrder::default())),
+        (prop::option::of(vec(arb_string(), 0..3)), prop::option::of(vec(any::<u8>(), 0..3))).prop_map(|(lobby_names, lobby_types)| {
+            Packet::OperationRequest(Operation::GetLobbyStatsRequest { lobby_names, lobby_types }, ParameterOrder::default())
+        }),
+        (
+            vec(arb_string(), 0..3),
+            prop::option::of(any::<u32>().prop_map(FindFriendsOptions::from_bits_truncate))
+        )
+            .prop_map(|(user_ids, options)| Packet::OperationRequest(Operation::FindFriendsRequest { user_ids, options }, ParameterOrder::default())),
+        arb_value().prop_map(Packet::Message),
+        vec(any::<u8>(), 0..16).prop_map(|data| Packet::RawMessage(data.into())),
+    ]
+}
+
+fn arb_recv_packet() -> impl Strategy<Value = Packet<'static>> {
+    let authenticate_response = (
+        prop::option::of(arb_string()),
+        prop::option::of(arb_string()),
+        prop::option::of(vec((any::<u8>(), arb_value()), 0..4).prop_map(|entries| entries.into_iter().collect())),
+        prop::option::of(arb_custom_properties()),
+        prop::option::of(arb_string()),
+        arb_string(),
+    )
+        .prop_map(
+            |(user_id, nickname, encryption_data, custom_data, cluster, address)| Operation::AuthenticateResponseName {
+                user_id,
+                nickname,
+                encryption_data,
+                custom_data,
+                cluster,
+                address,
+            },
+        );
+    let response = prop_oneof![
+        authenticate_response,
+        vec((arb_string(), arb_room_info()), 0..3).prop_map(|games| Operation::GetGameListResponse {
+            games: games.into_iter().collect()
+        }),
+        vec((arb_string(), arb_string()), 0..3).prop_map(|regions| Operation::GetRegionsResponse {
+            regions: regions.iter().map(|(region, _)| region.clone()).collect(),
+            addresses: regions.iter().map(|(_, address)| address.clone()).collect(),
+        }),
+        vec((arb_string(), any::<(u8, i32, i32)>()), 0..4).prop_map(|lobbies| Operation::GetLobbyStatsResponse {
+            names: lobbies.iter().map(|(name, _)| name.clone()).collect(),
+            types: lobbies.iter().map(|(_, (lobby_type, _, _))| *lobby_type).collect(),
+            peer_counts: lobbies.iter().map(|(_, (_, peer_count, _))| *peer_count).collect(),
+            game_counts: lobbies.iter().map(|(_, (_, _, game_count))| *game_count).collect(),
+        }),
+        vec(any::<u8>(), 0..96).prop_map(|public_key| Operation::ExchangeKeysForEncryptionResponse { public_key }),
+        // at least 1 friend is always requested
+        vec((any::<bool>(), arb_string()), 1..3).prop_map(|friends| Operation::FindFriendsResponse {
+            online: friends.iter().map(|(online, _)| *online).collect(),
+            room_ids: friends.iter().map(|(_, room_id)| room_id.clone()).collect(),
+        }),
+        Just(Operation::LeaveResponse),
+    ];
+    let event = prop_oneof![
+        any::<(i32, i32, i32)>().prop_map(|(game_count, peer_count, master_peer_count)| Event::AppStats {
+            game_count,
+            peer_count,
+            master_peer_count,
+        }),
+        (arb_player(), arb_actor_list()).prop_map(|(player_properties, actor_list)| Event::Join {
+            player_properties,
+            actor_list
+        }),
+        (1..i32::MAX, arb_player()).prop_map(|(target_actor, player)| Event::PropertiesChanged {
+            target_actor,
+            properties: PropertiesChange::Player(player),
+        }),
+        (
+            any::<i32>(),
+            arb_actor_list(),
+            prop::option::of(any::<bool>()),
+            prop::option::of(any::<i32>())
+        )
+            .prop_map(|(actor_nr, actor_list, is_inactive, new_master_client_id)| Event::Leave {
+                actor_nr,
+                actor_list,
+                is_inactive,
+                new_master_client_id,
+            }),
+        arb_string().prop_map(|token| Event::AuthEvent { token }),
+        vec((arb_string(), any::<(u8, i32, i32)>()), 0..4).prop_map(|lobbies| Event::LobbyStats {
+            names: lobbies.iter().map(|(name, _)| name.clone()).collect(),
+            types: lobbies.iter().map(|(_, (lobby_type, _, _))| *lobby_type).collect(),
+            peer_counts: lobbies.iter().map(|(_, (_, peer_count, _))| *peer_count).collect(),
+            game_counts: lobbies.iter().map(|(_, (_, _, game_count))| *game_count).collect(),
+        }),
+        any::<i32>().prop_map(|index| Event::CacheSliceChanged { index }),
+        arb_string().prop_map(|info| Event::ErrorInfo { info }),
+    ];
+
+    prop_oneof![
+        vec(any::<u8>(), 0..16).prop_map(|data| Packet::InitResponse(data.into())),
+        (any::<(i32, i32, i16)>(), prop::option::of(arb_string())).prop_map(|((local_time, server_time, return_code), debug_string)| {
+            Packet::InternalOperationResponse(
+                InternalOperation::PingResponse { local_time, server_time },
+                return_code,
+                debug_string,
+                ParameterOrder::default(),
+            )
+        }),
+        (vec(any::<u8>(), 0..96), any::<i16>(), prop::option::of(arb_string())).prop_map(|(public_key, return_code, debug_string)| {
+            Packet::InternalOperationResponse(
+                InternalOperation::InitEncryptionResponse { public_key },
+                return_code,
+                debug_string,
+                ParameterOrder::default(),
+            )
+        }),
+        (response, any::<i16>(), prop::option::of(arb_string()), prop::option::of(arb_string())).prop_map(
+            |(parameters, return_code, debug_string, secret)| Packet::OperationResponse {
+                parameters,
+                return_code,
+                debug_string,
+                secret,
+                order: ParameterOrder::default(),
+            }
+        ),
+        (event, prop::option::of(arb_value()), prop::option::of(any::<i32>())).prop_map(|(parameters, custom_data, sender)| Packet::Event {
+            // the actor number of Leave is part of the event, so it has no sender
+            sender: match parameters {
+                Event::Leave { .. } => None,
+                _ => sender,
+            },
+            parameters,
+            custom_data,
+            order: ParameterOrder::default(),
+        }),
+        arb_value().prop_map(Packet::Message),
+    ]
+}
+
+fn arb_protocol() -> impl Strategy<Value = Protocol> {
+    prop_oneof![Just(Protocol::GpBinaryV16), Just(Protocol::GpBinaryV18)]
+}
+
+proptest! {
+    #[test]
+    fn write_read_send(packet in arb_send_packet(), protocol in arb_protocol()) {
+        let data = packet.clone().into_vec_with_protocol(protocol).unwrap();
+        prop_assert_eq!(Packet::read_with_protocol(&data, Direction::Send, protocol).unwrap(), packet);
+    }
+
+    #[test]
+    fn write_read_recv(packet in arb_recv_packet(), protocol in arb_protocol()) {
+        let data = packet.clone().into_vec_with_protocol(protocol).unwrap();
+        prop_assert_eq!(Packet::read_with_protocol(&data, Direction::Recv, protocol).unwrap(), packet);
+    }
+}
@@ -0,0 +1,418 @@
+//! The events used by Photon Unity Networking (PUN), which are sent with `Operation::RaiseEvent` and received as events with the
+//! same code. Their content is stored in the `data` of the request and the `custom_data` of the received event.
+
+use super::macros::*;
+use super::*;
+use indexmap::indexmap;
+use indexmap::IndexMap;
+
+/// A PUN event, decoded from its event code and data.
+///
+/// ```rust
+/// # use photon::*;
+/// let data = PunEvent::Destroy { view_id: 1001 }.into_data();
+/// let event = PunEvent::read(204, data).unwrap();
+/// assert_eq!(event, PunEvent::Destroy { view_id: 1001 });
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PunEvent<'a> {
+    Rpc(PunRpc<'a>),
+    /// Updates of the observed components of photon views
+    SendSerialize(PunSerialize<'a>),
+    /// Same as `SendSerialize`, but sent reliably
+    SendSerializeReliable(PunSerialize<'a>),
+    Instantiation(PunInstantiation<'a>),
+    /// Asks the receiving client to disconnect, only sent by the master client
+    CloseConnection,
+    Destroy {
+        view_id: i32,
+    },
+    /// Removes cached RPCs. The data of this event is kept as-is.
+    RemoveCachedRpcs(Option<ProtocolValue<'a>>),
+    /// Destroys all objects of a player, or of all players if this is -1
+    DestroyPlayer {
+        actor_nr: i32,
+    },
+    /// Asks the current owner to transfer a photon view
+    OwnershipRequest {
+        view_id: i32,
+        /// The owner the view was requested from
+        from_actor: i32,
+    },
+    OwnershipTransfer {
+        view_id: i32,
+        /// The new owner of the view
+        to_actor: i32,
+    },
+}
+
+/// A remote procedure call on a photon view.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PunRpc<'a> {
+    pub view_id: i32,
+    /// The level prefix of the view, not present if 0
+    pub prefix: Option<i16>,
+    pub server_timestamp: i32,
+    pub method: PunRpcMethod<'a>,
+    /// Not present if there are no arguments
+    pub arguments: Option<Vec<ProtocolValue<'a>>>,
+}
+
+/// How an RPC refers to the called method.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PunRpcMethod<'a> {
+    Name(Cow<'a, str>),
+    /// The index of the method in the RPC list of the game, which is sent instead of the name when possible
+    Shortcut(u8),
+}
+
+/// Serialized state of one or more photon views.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PunSerialize<'a> {
+    pub server_timestamp: i32,
+    /// The current level prefix, not present if 0
+    pub level_prefix: Option<i16>,
+    pub views: Vec<PunSerializedView<'a>>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PunSerializedView<'a> {
+    pub view_id: i32,
+    /// Set if values that did not change since the last update were left out
+    pub compressed: bool,
+    /// The indices of values that are null, if compressed
+    pub null_values: Option<Vec<i32>>,
+    /// The values written by the observed components
+    pub data: Vec<ProtocolValue<'a>>,
+}
+
+/// Creates a prefab with one or more photon views.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PunInstantiation<'a> {
+    pub prefab_name: Cow<'a, str>,
+    /// Not present if this is the origin
+    pub position: Option<(f32, f32, f32)>,
+    /// Not present if this is the identity rotation
+    pub rotation: Option<(f32, f32, f32, f32)>,
+    /// Not present if 0
+    pub group: Option<u8>,
+    /// The ids of all views of the prefab, not present if there is only one
+    pub view_ids: Option<Vec<i32>>,
+    /// Custom instantiation data
+    pub data: Option<Vec<ProtocolValue<'a>>>,
+    /// Not present if 0
+    pub level_prefix: Option<i16>,
+    pub server_timestamp: i32,
+    /// The id of the first view, which identifies this instantiation
+    pub instantiation_id: i32,
+}
+
+#[allow(dead_code, non_upper_case_globals, non_snake_case)]
+pub mod PunEventCode {
+    pub const Rpc: u8 = 200;
+    pub const SendSerialize: u8 = 201;
+    pub const Instantiation: u8 = 202;
+    pub const CloseConnection: u8 = 203;
+    pub const Destroy: u8 = 204;
+    pub const RemoveCachedRpcs: u8 = 205;
+    pub const SendSerializeReliable: u8 = 206;
+    pub const DestroyPlayer: u8 = 207;
+    pub const OwnershipRequest: u8 = 209;
+    pub const OwnershipTransfer: u8 = 210;
+}
+
+impl<'s> PunEvent<'s> {
+    /// Decodes the data of a PUN event. Returns `PacketReadError::UnknownEventType` if `event_type` is not a PUN event, and
+    /// `PacketReadError::InvalidPunData` if the data contains anything that would not be written back.
+    pub fn read<'a>(event_type: u8, data: Option<ProtocolValue<'a>>) -> PacketReadResult<PunEvent<'a>> {
+        let data = match (event_type, data) {
+            (PunEventCode::CloseConnection, None) => return Ok(PunEvent::CloseConnection),
+            (PunEventCode::CloseConnection, Some(_)) => return Err(PacketReadError::InvalidPunData(event_type)),
+            (PunEventCode::RemoveCachedRpcs, data) => return Ok(PunEvent::RemoveCachedRpcs(data)),
+            (PunEventCode::Rpc..=PunEventCode::OwnershipTransfer, Some(data)) => data,
+            (PunEventCode::Rpc..=PunEventCode::OwnershipTransfer, None) => return Err(PacketReadError::InvalidPunData(event_type)),
+            _ => return Err(PacketReadError::UnknownEventType(event_type)),
+        };
+
+        let ret = match event_type {
+            PunEventCode::Rpc => {
+                let mut table = unwrap_protocol_hashtable(data)?;
+                let rpc = PunRpc {
+                    view_id: get_protocol_int(&mut table, ProtocolValue::Byte(0))?,
+                    prefix: get_protocol_short_opt(&mut table, ProtocolValue::Byte(1))?,
+                    server_timestamp: get_protocol_int(&mut table, ProtocolValue::Byte(2))?,
+                    method: match get_protocol_string_opt(&mut table, ProtocolValue::Byte(3))? {
+                        Some(name) => PunRpcMethod::Name(name),
+                        None => PunRpcMethod::Shortcut(get_protocol_byte(&mut table, ProtocolValue::Byte(5))?),
+                    },
+                    arguments: table
+                        .shift_remove(&ProtocolValue::Byte(4))
+                        .map(unwrap_protocol_object_array)
+                        .transpose()?,
+                };
+                ensure_empty(table, event_type)?;
+                PunEvent::Rpc(rpc)
+            }
+            PunEventCode::SendSerialize => PunEvent::SendSerialize(PunSerialize::read(data, event_type)?),
+            PunEventCode::SendSerializeReliable => PunEvent::SendSerializeReliable(PunSerialize::read(data, event_type)?),
+            PunEventCode::Instantiation => {
+                let mut table = unwrap_protocol_hashtable(data)?;
+                let instantiation = PunInstantiation {
+                    prefab_name: get_protocol_string(&mut table, ProtocolValue::Byte(0))?,
+                    position: match table.shift_remove(&ProtocolValue::Byte(1)) {
+                        Some(ProtocolValue::Custom(CustomType::Vector3(x, y, z))) => Some((x, y, z)),
+                        Some(_) => return Err(PacketReadError::UnexpectedProtocolValue),
+                        None => None,
+                    },
+                    rotation: match table.shift_remove(&ProtocolValue::Byte(2)) {
+                        Some(ProtocolValue::Custom(CustomType::Quaternion(x, y, z, w))) => Some((x, y, z, w)),
+                        Some(_) => return Err(PacketReadError::UnexpectedProtocolValue),
+                        None => None,
+                    },
+                    group: get_protocol_byte_opt(&mut table, ProtocolValue::Byte(3))?,
+                    view_ids: table.shift_remove(&ProtocolValue::Byte(4)).map(unwrap_protocol_int_array).transpose()?,
+                    data: table
+                        .shift_remove(&ProtocolValue::Byte(5))
+                        .map(unwrap_protocol_object_array)
+                        .transpose()?,
+                    level_prefix: get_protocol_short_opt(&mut table, ProtocolValue::Byte(8))?,
+                    server_timestamp: get_protocol_int(&mut table, ProtocolValue::Byte(6))?,
+                    instantiation_id: get_protocol_int(&mut table, ProtocolValue::Byte(7))?,
+                };
+                ensure_empty(table, event_type)?;
+                PunEvent::Instantiation(instantiation)
+            }
+            PunEventCode::Destroy => PunEvent::Destroy {
+                view_id: read_single_int(data, event_type)?,
+            },
+            PunEventCode::DestroyPlayer => PunEvent::DestroyPlayer {
+                actor_nr: read_single_int(data, event_type)?,
+            },
+            PunEventCode::OwnershipRequest | PunEventCode::OwnershipTransfer => match unwrap_protocol_int_array(data)?.as_slice() {
+                [view_id, from_actor] if event_type == PunEventCode::OwnershipRequest => PunEvent::OwnershipRequest {
+                    view_id: *view_id,
+                    from_actor: *from_actor,
+                },
+                [view_id, to_actor] => PunEvent::OwnershipTransfer {
+                    view_id: *view_id,
+                    to_actor: *to_actor,
+                },
+                _ => return Err(PacketReadError::InvalidPunData(event_type)),
+            },
+            _ => return Err(PacketReadError::UnknownEventType(event_type)),
+        };
+
+        Ok(ret)
+    }
+
+    pub fn get_type(&self) -> u8 {
+        match self {
+            PunEvent::Rpc(_) => PunEventCode::Rpc,
+            PunEvent::SendSerialize(_) => PunEventCode::SendSerialize,
+            PunEvent::SendSerializeReliable(_) => PunEventCode::SendSerializeReliable,
+            PunEvent::Instantiation(_) => PunEventCode::Instantiation,
+            PunEvent::CloseConnection => PunEventCode::CloseConnection,
+            PunEvent::Destroy { .. } => PunEventCode::Destroy,
+            PunEvent::RemoveCachedRpcs(_) => PunEventCode::RemoveCachedRpcs,
+            PunEvent::DestroyPlayer { .. } => PunEventCode::DestroyPlayer,
+            PunEvent::OwnershipRequest { .. } => PunEventCode::OwnershipRequest,
+            PunEvent::OwnershipTransfer { .. } => PunEventCode::OwnershipTransfer,
+        }
+    }
+
+    /// Encodes this event into the data that is sent along with its event code.
+    pub fn into_data(self) -> Option<ProtocolValue<'s>> {
+        match self {
+            PunEvent::Rpc(rpc) => {
+                let mut table = IndexMap::new();
+                table.insert(ProtocolValue::Byte(0), ProtocolValue::Integer(rpc.view_id));
+                if let Some(prefix) = rpc.prefix {
+                    table.insert(ProtocolValue::Byte(1), ProtocolValue::Short(prefix));
+                }
+                table.insert(ProtocolValue::Byte(2), ProtocolValue::Integer(rpc.server_timestamp));
+                match rpc.method {
+                    PunRpcMethod::Name(name) => table.insert(ProtocolValue::Byte(3), ProtocolValue::String(name)),
+                    PunRpcMethod::Shortcut(index) => table.insert(ProtocolValue::Byte(5), ProtocolValue::Byte(index)),
+                };
+                if let Some(arguments) = rpc.arguments {
+                    table.insert(ProtocolValue::Byte(4), ProtocolValue::ObjectArray(arguments));
+                }
+                Some(ProtocolValue::Hashtable(table))
+            }
+            PunEvent::SendSerialize(serialize) | PunEvent::SendSerializeReliable(serialize) => Some(serialize.into()),
+            PunEvent::Instantiation(instantiation) => {
+                let mut table = IndexMap::new();
+                table.insert(ProtocolValue::Byte(0), ProtocolValue::String(instantiation.prefab_name));
+                if let Some((x, y, z)) = instantiation.position {
+                    table.insert(ProtocolValue::Byte(1), ProtocolValue::Custom(CustomType::Vector3(x, y, z)));
+                }
+                if let Some((x, y, z, w)) = instantiation.rotation {
+                    table.insert(ProtocolValue::Byte(2), ProtocolValue::Custom(CustomType::Quaternion(x, y, z, w)));
+                }
+                if let Some(group) = instantiation.group {
+                    table.insert(ProtocolValue::Byte(3), ProtocolValue::Byte(group));
+                }
+                if let Some(view_ids) = instantiation.view_ids {
+                    table.insert(ProtocolValue::Byte(4), ProtocolValue::IntegerArray(view_ids));
+                }
+                if let Some(data) = instantiation.data {
+                    table.insert(ProtocolValue::Byte(5), ProtocolValue::ObjectArray(data));
+                }
+                // PUN writes the level prefix before the timestamp and id
+                if let Some(level_prefix) = instantiation.level_prefix {
+                    table.insert(ProtocolValue::Byte(8), ProtocolValue::Short(level_prefix));
+                }
+                table.insert(ProtocolValue::Byte(6), ProtocolValue::Integer(instantiation.server_timestamp));
+                table.insert(ProtocolValue::Byte(7), ProtocolValue::Integer(instantiation.instantiation_id));
+                Some(ProtocolValue::Hashtable(table))
+            }
+            PunEvent::CloseConnection => None,
+            PunEvent::Destroy { view_id } => Some(single_int(view_id)),
+            PunEvent::RemoveCachedRpcs(data) => data,
+            PunEvent::DestroyPlayer { actor_nr } => Some(single_int(actor_nr)),
+            PunEvent::OwnershipRequest { view_id, from_actor } => Some(ProtocolValue::IntegerArray(vec![view_id, from_actor])),
+            PunEvent::OwnershipTransfer { view_id, to_actor } => Some(ProtocolValue::IntegerArray(vec![view_id, to_actor])),
+        }
+    }
+
+    /// Converts this event into one that does not borrow from the deserialized data, copying strings where needed.
+    pub fn into_owned(self) -> PunEvent<'static> {
+        match self {
+            PunEvent::Rpc(rpc) => PunEvent::Rpc(PunRpc {
+                view_id: rpc.view_id,
+                prefix: rpc.prefix,
+                server_timestamp: rpc.server_timestamp,
+                method: match rpc.method {
+                    PunRpcMethod::Name(name) => PunRpcMethod::Name(owned_string(name)),
+                    PunRpcMethod::Shortcut(index) => PunRpcMethod::Shortcut(index),
+                },
+                arguments: rpc.arguments.map(owned_values),
+            }),
+            PunEvent::SendSerialize(serialize) => PunEvent::SendSerialize(serialize.into_owned()),
+            PunEvent::SendSerializeReliable(serialize) => PunEvent::SendSerializeReliable(serialize.into_owned()),
+            PunEvent::Instantiation(instantiation) => PunEvent::Instantiation(PunInstantiation {
+                prefab_name: owned_string(instantiation.prefab_name),
+                position: instantiation.position,
+                rotation: instantiation.rotation,
+                group: instantiation.group,
+                view_ids: instantiation.view_ids,
+                data: instantiation.data.map(owned_values),
+                level_prefix: instantiation.level_prefix,
+                server_timestamp: instantiation.server_timestamp,
+                instantiation_id: instantiation.instantiation_id,
+            }),
+            PunEvent::CloseConnection => PunEvent::CloseConnection,
+            PunEvent::Destroy { view_id } => PunEvent::Destroy { view_id },
+            PunEvent::RemoveCachedRpcs(data) => PunEvent::RemoveCachedRpcs(data.map(ProtocolValue::into_owned)),
+            PunEvent::DestroyPlayer { actor_nr } => PunEvent::DestroyPlayer { actor_nr },
+            PunEvent::OwnershipRequest { view_id, from_actor } => PunEvent::OwnershipRequest { view_id, from_actor },
+            PunEvent::OwnershipTransfer { view_id, to_actor } => PunEvent::OwnershipTransfer { view_id, to_actor },
+        }
+    }
+}
+
+impl<'s> PunSerialize<'s> {
+    fn read(data: ProtocolValue<'s>, event_type: u8) -> PacketReadResult<PunSerialize<'s>> {
+        let mut values = unwrap_protocol_object_array(data)?.into_iter();
+        let (server_timestamp, level_prefix) = match (values.next(), values.next()) {
+            (Some(server_timestamp), Some(level_prefix)) => (server_timestamp, level_prefix),
+            _ => return Err(PacketReadError::InvalidPunData(event_type)),
+        };
+        let server_timestamp = unwrap_protocol_int(server_timestamp)?;
+        let level_prefix = match level_prefix {
+            ProtocolValue::Null() => None,
+            level_prefix => Some(unwrap_protocol_short(level_prefix)?),
+        };
+
+        let views = values
+            .map(|view| {
+                let mut values = unwrap_protocol_object_array(view)?.into_iter();
+                let (view_id, compressed, null_values) = match (values.next(), values.next(), values.next()) {
+                    (Some(view_id), Some(compressed), Some(null_values)) => (view_id, compressed, null_values),
+                    _ => return Err(PacketReadError::InvalidPunData(event_type)),
+                };
+                Ok(PunSerializedView {
+                    view_id: unwrap_protocol_int(view_id)?,
+                    compressed: unwrap_protocol_bool(compressed)?,
+                    null_values: match null_values {
+                        ProtocolValue::Null() => None,
+                        null_values => Some(unwrap_protocol_int_array(null_values)?),
+                    },
+                    data: values.collect(),
+                })
+            })
+            .collect::<PacketReadResult<_>>()?;
+
+        Ok(PunSerialize {
+            server_timestamp,
+            level_prefix,
+            views,
+        })
+    }
+
+    pub fn into_owned(self) -> PunSerialize<'static> {
+        PunSerialize {
+            server_timestamp: self.server_timestamp,
+            level_prefix: self.level_prefix,
+            views: self
+                .views
+                .into_iter()
+                .map(|view| PunSerializedView {
+                    view_id: view.view_id,
+                    compressed: view.compressed,
+                    null_values: view.null_values,
+                    data: owned_values(view.data),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl<'a> From<PunSerialize<'a>> for ProtocolValue<'a> {
+    fn from(serialize: PunSerialize<'a>) -> Self {
+        let mut values = vec![
+            ProtocolValue::Integer(serialize.server_timestamp),
+            serialize.level_prefix.map_or(ProtocolValue::Null(), ProtocolValue::Short),
+        ];
+        values.extend(serialize.views.into_iter().map(|view| {
+            let mut values = vec![
+                ProtocolValue::Integer(view.view_id),
+                ProtocolValue::Bool(view.compressed),
+                view.null_values.map_or(ProtocolValue::Null(), ProtocolValue::IntegerArray),
+            ];
+            values.extend(view.data);
+            ProtocolValue::ObjectArray(values)
+        }));
+        ProtocolValue::ObjectArray(values)
+    }
+}
+
+/// Reads a hashtable that only contains an int at key 0
+fn read_single_int(data: ProtocolValue, event_type: u8) -> PacketReadResult<i32> {
+    let mut table = unwrap_protocol_hashtable(data)?;
+    let value = get_protocol_int(&mut table, ProtocolValue::Byte(0))?;
+    ensure_empty(table, event_type)?;
+    Ok(value)
+}
+
+fn single_int(value: i32) -> ProtocolValue<'static> {
+    ProtocolValue::Hashtable(indexmap! { ProtocolValue::Byte(0) => ProtocolValue::Integer(value) })
+}
+
+/// Returns an error if the table contains keys that were not read
+fn ensure_empty(table: IndexMap<ProtocolValue, ProtocolValue>, event_type: u8) -> PacketReadResult<()> {
+    match table.is_empty() {
+        true => Ok(()),
+        false => Err(PacketReadError::InvalidPunData(event_type)),
+    }
+}
+
+fn owned_values(values: Vec<ProtocolValue>) -> Vec<ProtocolValue<'static>> {
+    values.into_iter().map(ProtocolValue::into_owned).collect()
+}
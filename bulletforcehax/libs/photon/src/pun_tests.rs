@@ -0,0 +1,254 @@
+#![cfg(test)]
+
+use super::*;
+use indexmap::indexmap;
+use photon_core::CustomType;
+
+fn raise_event(code: u8, data: Option<ProtocolValue>) -> Packet {
+    Packet::OperationRequest(
+        Operation::RaiseEvent {
+            cache: EventCaching::DoNotCache,
+            actor_list: None,
+            group: None,
+            receivers: None,
+            event_forward: None,
+            code: Some(code),
+            data,
+        },
+        ParameterOrder::default(),
+    )
+}
+
+/// Reads a `RaiseEvent` request and decodes its data, then checks that encoding it again gives the original bytes
+macro_rules! gen_pun_test {
+    ($name: ident, $data: expr, $unpacked: expr) => {
+        #[test]
+        fn $name() {
+            let data: Vec<u8> = $data;
+            let expected: PunEvent = $unpacked;
+
+            let event = match Packet::read(data.as_slice(), Direction::Send).expect("Failure while reading packet from bytes") {
+                Packet::OperationRequest(
+                    Operation::RaiseEvent {
+                        code: Some(code), data, ..
+                    },
+                    _,
+                ) => PunEvent::read(code, data).expect("Failure while reading PUN event"),
+                other => panic!("Expected RaiseEvent, got {:?}", other),
+            };
+            assert_eq!(event, expected);
+            assert_eq!(event.into_owned(), expected, "Owned event differs from borrowed event");
+
+            let serialized_expected = raise_event(expected.get_type(), expected.into_data())
+                .into_vec()
+                .expect("Failure while turning packet back into bytes");
+            assert_eq!(
+                data, serialized_expected,
+                "Expected original bytes to be the same as serialized event. You probably missed some data or changed the order."
+            );
+        }
+    };
+}
+
+gen_pun_test!(
+    rpc_name,
+    vec![
+        0xf3, 0x2, 0xfd, 0x0, 0x2, 0xf4, 0x62, 0xc8, 0xf5, 0x68, 0x0, 0x4, 0x62, 0x0, 0x69, 0x0, 0x0, 0x3, 0xe9, 0x62, 0x2, 0x69, 0x0, 0x12, 0xd6,
+        0x87, 0x62, 0x3, 0x73, 0x0, 0x8, 0x52, 0x70, 0x63, 0x53, 0x68, 0x6f, 0x6f, 0x74, 0x62, 0x4, 0x7a, 0x0, 0x2, 0x69, 0x0, 0x0, 0x0, 0x3, 0x63,
+        0x56, 0x0, 0xc, 0x3f, 0x80, 0x0, 0x0, 0x40, 0x0, 0x0, 0x0, 0x40, 0x40, 0x0, 0x0
+    ],
+    PunEvent::Rpc(PunRpc {
+        view_id: 1001,
+        prefix: None,
+        server_timestamp: 1234567,
+        method: PunRpcMethod::Name("RpcShoot".into()),
+        arguments: Some(vec![ProtocolValue::Integer(3), ProtocolValue::Custom(CustomType::Vector3(1., 2., 3.))]),
+    })
+);
+
+gen_pun_test!(
+    rpc_shortcut,
+    vec![
+        0xf3, 0x2, 0xfd, 0x0, 0x2, 0xf4, 0x62, 0xc8, 0xf5, 0x68, 0x0, 0x4, 0x62, 0x0, 0x69, 0x0, 0x0, 0x7, 0xd1, 0x62, 0x1, 0x6b, 0x0, 0x1, 0x62,
+        0x2, 0x69, 0xff, 0xff, 0xff, 0xff, 0x62, 0x5, 0x62, 0xc
+    ],
+    PunEvent::Rpc(PunRpc {
+        view_id: 2001,
+        prefix: Some(1),
+        server_timestamp: -1,
+        method: PunRpcMethod::Shortcut(12),
+        arguments: None,
+    })
+);
+
+gen_pun_test!(
+    send_serialize,
+    vec![
+        0xf3, 0x2, 0xfd, 0x0, 0x2, 0xf4, 0x62, 0xc9, 0xf5, 0x7a, 0x0, 0x4, 0x69, 0x0, 0x12, 0xd6, 0x87, 0x2a, 0x7a, 0x0, 0x5, 0x69, 0x0, 0x0, 0x3,
+        0xe9, 0x6f, 0x0, 0x2a, 0x63, 0x56, 0x0, 0xc, 0x3f, 0x80, 0x0, 0x0, 0x40, 0x0, 0x0, 0x0, 0x40, 0x40, 0x0, 0x0, 0x63, 0x51, 0x0, 0x10, 0x0,
+        0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x3f, 0x80, 0x0, 0x0, 0x7a, 0x0, 0x5, 0x69, 0x0, 0x0, 0x7, 0xd1, 0x6f, 0x1, 0x6e, 0x0,
+        0x0, 0x0, 0x1, 0x0, 0x0, 0x0, 0x1, 0x62, 0x5, 0x2a
+    ],
+    PunEvent::SendSerialize(PunSerialize {
+        server_timestamp: 1234567,
+        level_prefix: None,
+        views: vec![
+            PunSerializedView {
+                view_id: 1001,
+                compressed: false,
+                null_values: None,
+                data: vec![
+                    ProtocolValue::Custom(CustomType::Vector3(1., 2., 3.)),
+                    ProtocolValue::Custom(CustomType::Quaternion(0., 0., 0., 1.)),
+                ],
+            },
+            PunSerializedView {
+                view_id: 2001,
+                compressed: true,
+                null_values: Some(vec![1]),
+                data: vec![ProtocolValue::Byte(5), ProtocolValue::Null()],
+            },
+        ],
+    })
+);
+
+gen_pun_test!(
+    send_serialize_reliable,
+    vec![
+        0xf3, 0x2, 0xfd, 0x0, 0x2, 0xf4, 0x62, 0xce, 0xf5, 0x7a, 0x0, 0x3, 0x69, 0x0, 0x12, 0xd6, 0x87, 0x6b, 0x0, 0x2, 0x7a, 0x0, 0x4, 0x69, 0x0,
+        0x0, 0x3, 0xe9, 0x6f, 0x0, 0x2a, 0x6f, 0x1
+    ],
+    PunEvent::SendSerializeReliable(PunSerialize {
+        server_timestamp: 1234567,
+        level_prefix: Some(2),
+        views: vec![PunSerializedView {
+            view_id: 1001,
+            compressed: false,
+            null_values: None,
+            data: vec![ProtocolValue::Bool(true)],
+        }],
+    })
+);
+
+gen_pun_test!(
+    instantiation,
+    vec![
+        0xf3, 0x2, 0xfd, 0x0, 0x2, 0xf4, 0x62, 0xca, 0xf5, 0x68, 0x0, 0x8, 0x62, 0x0, 0x73, 0x0, 0xa, 0x50, 0x6c, 0x61, 0x79, 0x65, 0x72, 0x42, 0x6f,
+        0x64, 0x79, 0x62, 0x1, 0x63, 0x56, 0x0, 0xc, 0x3f, 0x80, 0x0, 0x0, 0x40, 0x0, 0x0, 0x0, 0x40, 0x40, 0x0, 0x0, 0x62, 0x2, 0x63, 0x51, 0x0,
+        0x10, 0x0, 0x0, 0x0, 0x0, 0x3f, 0x19, 0x99, 0x9a, 0x0, 0x0, 0x0, 0x0, 0x3f, 0x4c, 0xcc, 0xcd, 0x62, 0x4, 0x6e, 0x0, 0x0, 0x0, 0x2, 0x0, 0x0,
+        0x3, 0xe9, 0x0, 0x0, 0x3, 0xea, 0x62, 0x5, 0x7a, 0x0, 0x1, 0x73, 0x0, 0x5, 0x48, 0x6f, 0x4c, 0x4c, 0x79, 0x62, 0x8, 0x6b, 0x0, 0x1, 0x62,
+        0x6, 0x69, 0x0, 0x12, 0xd6, 0x87, 0x62, 0x7, 0x69, 0x0, 0x0, 0x3, 0xe9
+    ],
+    PunEvent::Instantiation(PunInstantiation {
+        prefab_name: "PlayerBody".into(),
+        position: Some((1., 2., 3.)),
+        rotation: Some((0., 0.6, 0., 0.8)),
+        group: None,
+        view_ids: Some(vec![1001, 1002]),
+        data: Some(vec![ProtocolValue::String("HoLLy".into())]),
+        level_prefix: Some(1),
+        server_timestamp: 1234567,
+        instantiation_id: 1001,
+    })
+);
+
+gen_pun_test!(
+    instantiation_minimal,
+    vec![
+        0xf3, 0x2, 0xfd, 0x0, 0x2, 0xf4, 0x62, 0xca, 0xf5, 0x68, 0x0, 0x4, 0x62, 0x0, 0x73, 0x0, 0x7, 0x47, 0x72, 0x65, 0x6e, 0x61, 0x64, 0x65, 0x62,
+        0x3, 0x62, 0x1, 0x62, 0x6, 0x69, 0x0, 0x12, 0xd6, 0x87, 0x62, 0x7, 0x69, 0x0, 0x0, 0x3, 0xeb
+    ],
+    PunEvent::Instantiation(PunInstantiation {
+        prefab_name: "Grenade".into(),
+        position: None,
+        rotation: None,
+        group: Some(1),
+        view_ids: None,
+        data: None,
+        level_prefix: None,
+        server_timestamp: 1234567,
+        instantiation_id: 1003,
+    })
+);
+
+gen_pun_test!(
+    close_connection,
+    vec![0xf3, 0x2, 0xfd, 0x0, 0x1, 0xf4, 0x62, 0xcb],
+    PunEvent::CloseConnection
+);
+
+gen_pun_test!(
+    destroy,
+    vec![0xf3, 0x2, 0xfd, 0x0, 0x2, 0xf4, 0x62, 0xcc, 0xf5, 0x68, 0x0, 0x1, 0x62, 0x0, 0x69, 0x0, 0x0, 0x3, 0xe9],
+    PunEvent::Destroy { view_id: 1001 }
+);
+
+gen_pun_test!(
+    remove_cached_rpcs,
+    vec![0xf3, 0x2, 0xfd, 0x0, 0x2, 0xf4, 0x62, 0xcd, 0xf5, 0x68, 0x0, 0x1, 0x62, 0x0, 0x69, 0x0, 0x0, 0x0, 0x1],
+    PunEvent::RemoveCachedRpcs(Some(ProtocolValue::Hashtable(indexmap! {
+        ProtocolValue::Byte(0) => ProtocolValue::Integer(1),
+    })))
+);
+
+gen_pun_test!(
+    destroy_player,
+    vec![0xf3, 0x2, 0xfd, 0x0, 0x2, 0xf4, 0x62, 0xcf, 0xf5, 0x68, 0x0, 0x1, 0x62, 0x0, 0x69, 0xff, 0xff, 0xff, 0xff],
+    PunEvent::DestroyPlayer { actor_nr: -1 }
+);
+
+gen_pun_test!(
+    ownership_request,
+    vec![0xf3, 0x2, 0xfd, 0x0, 0x2, 0xf4, 0x62, 0xd1, 0xf5, 0x6e, 0x0, 0x0, 0x0, 0x2, 0x0, 0x0, 0x3, 0xe9, 0x0, 0x0, 0x0, 0x1],
+    PunEvent::OwnershipRequest {
+        view_id: 1001,
+        from_actor: 1,
+    }
+);
+
+gen_pun_test!(
+    ownership_transfer,
+    vec![0xf3, 0x2, 0xfd, 0x0, 0x2, 0xf4, 0x62, 0xd2, 0xf5, 0x6e, 0x0, 0x0, 0x0, 0x2, 0x0, 0x0, 0x3, 0xe9, 0x0, 0x0, 0x0, 0x2],
+    PunEvent::OwnershipTransfer { view_id: 1001, to_actor: 2 }
+);
+
+#[test]
+fn unknown_event() {
+    match PunEvent::read(100, None) {
+        Err(PacketReadError::UnknownEventType(100)) => (),
+        other => panic!("Expected UnknownEventType, got {:?}", other),
+    }
+}
+
+#[test]
+fn rpc_with_unknown_key() {
+    // the extra key would be lost when writing the event back
+    let data = ProtocolValue::Hashtable(indexmap! {
+        ProtocolValue::Byte(0) => ProtocolValue::Integer(1001),
+        ProtocolValue::Byte(2) => ProtocolValue::Integer(1234567),
+        ProtocolValue::Byte(3) => ProtocolValue::String("RpcShoot".into()),
+        ProtocolValue::Byte(9) => ProtocolValue::Null(),
+    });
+    match PunEvent::read(PunEventCode::Rpc, Some(data)) {
+        Err(PacketReadError::InvalidPunData(PunEventCode::Rpc)) => (),
+        other => panic!("Expected InvalidPunData, got {:?}", other),
+    }
+}
+
+#[test]
+fn send_serialize_without_level_prefix() {
+    let data = ProtocolValue::ObjectArray(vec![ProtocolValue::Integer(1234567)]);
+    match PunEvent::read(PunEventCode::SendSerialize, Some(data)) {
+        Err(PacketReadError::InvalidPunData(PunEventCode::SendSerialize)) => (),
+        other => panic!("Expected InvalidPunData, got {:?}", other),
+    }
+}
+
+#[test]
+fn ownership_request_wrong_length() {
+    let data = ProtocolValue::IntegerArray(vec![1001]);
+    match PunEvent::read(PunEventCode::OwnershipRequest, Some(data)) {
+        Err(PacketReadError::InvalidPunData(PunEventCode::OwnershipRequest)) => (),
+        other => panic!("Expected InvalidPunData, got {:?}", other),
+    }
+}
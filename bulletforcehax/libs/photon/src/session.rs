@@ -0,0 +1,345 @@
+use super::*;
+use indexmap::IndexMap;
+use log::warn;
+
+/// The servers a client connects to, in order. The NameServer sends the client to the MasterServer of its region, which in turn sends
+/// it to the GameServer hosting its room.
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ServerType {
+    NameServer,
+    MasterServer,
+    GameServer,
+}
+
+/// Tracks the state of a client by following the packets it sends and receives.
+///
+/// ```rust
+/// # use photon::*;
+/// # use indexmap::indexmap;
+/// let mut session = Session::new();
+/// session.handle(&Packet::Event {
+///     parameters: Event::GameList(indexmap! { "room".into() => RoomInfo::new().with_max_players(12) }),
+///     custom_data: None,
+///     sender: None,
+///     order: ParameterOrder::default(),
+/// });
+///
+/// assert_eq!(session.server(), ServerType::NameServer);
+/// assert_eq!(session.lobby().get("room").map(RoomInfo::max_players), Some(12));
+/// assert!(session.room().is_none());
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Session {
+    server: ServerType,
+    /// The server we were told to connect to next, with its address
+    next_server: Option<(ServerType, String)>,
+    /// The client returns to the MasterServer after leaving a room
+    master_address: Option<String>,
+    /// The room we are joining or creating on the next server
+    next_room_name: Option<String>,
+    /// The last token we received, which is used to authenticate on the next server
+    secret: Option<String>,
+    /// Rooms listed in the lobby of the MasterServer
+    lobby: Lobby,
+    room: Option<RoomState>,
+}
+
+/// The room we are in on the GameServer.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RoomState {
+    name: Option<String>,
+    /// Our actor number, 0 until the GameServer accepted our join
+    local_actor: i32,
+    info: RoomInfo<'static>,
+    /// All actors in the room, including ourselves and inactive actors that can still rejoin
+    players: IndexMap<i32, Player<'static>>,
+    /// Set if we created the room with `RoomOptionsFlags::DELETE_NULL_PROPS`
+    delete_null_props: bool,
+}
+
+impl Session {
+    /// Creates a session for a client that is about to connect to the NameServer.
+    pub fn new() -> Self {
+        Session {
+            server: ServerType::NameServer,
+            next_server: None,
+            master_address: None,
+            next_room_name: None,
+            secret: None,
+            lobby: Lobby::new(),
+            room: None,
+        }
+    }
+
+    /// The server we are currently connected to
+    pub fn server(&self) -> ServerType {
+        self.server
+    }
+
+    /// The server we were told to connect to next, and its address
+    pub fn next_server(&self) -> Option<(ServerType, &str)> {
+        self.next_server.as_ref().map(|(server, address)| (*server, address.as_str()))
+    }
+
+    /// The last token we received from an `OperationResponse` or `Event::AuthEvent`, which is used to authenticate on the next
+    /// server
+    pub fn secret(&self) -> Option<&str> {
+        self.secret.as_deref()
+    }
+
+    /// Rooms listed in the lobby. Only filled after joining the lobby on the MasterServer.
+    pub fn lobby(&self) -> &Lobby {
+        &self.lobby
+    }
+
+    /// The room we are in or are joining on the GameServer
+    pub fn room(&self) -> Option<&RoomState> {
+        self.room.as_ref()
+    }
+
+    /// Switches to the next server. This happens automatically when the client starts a new connection or authenticates, but can be
+    /// called directly when the new connection is noticed earlier.
+    pub fn connect_next_server(&mut self) {
+        if let Some((server, _)) = self.next_server.take() {
+            self.server = server;
+            self.room = match server {
+                ServerType::GameServer => Some(RoomState::new(self.next_room_name.take())),
+                _ => None,
+            };
+            if server != ServerType::MasterServer {
+                self.lobby.clear();
+            }
+        }
+    }
+
+    /// Updates the session with a packet that was sent or received.
+    pub fn handle(&mut self, packet: &Packet) {
+        match packet {
+            Packet::Init { .. } => self.connect_next_server(),
+            Packet::OperationRequest(operation, _) => self.handle_request(operation),
+            Packet::OperationResponse {
+                parameters,
+                return_code: 0,
+                secret,
+                ..
+            } => {
+                if let Some(secret) = secret {
+                    self.secret = Some(secret.to_string());
+                }
+                self.handle_response(parameters)
+            }
+            Packet::Event { parameters, sender, .. } => self.handle_event(parameters, *sender),
+            _ => (),
+        }
+    }
+
+    fn handle_request(&mut self, operation: &Operation) {
+        match operation {
+            // authentication is the first thing done on a new connection
+            Operation::AuthenticateRequestNoToken { .. } | Operation::AuthenticateRequestToken { .. } => self.connect_next_server(),
+            Operation::JoinGameRequestMaster { room_name, .. } => self.next_room_name = Some(room_name.to_string()),
+            Operation::CreateGameRequestMaster { room_name, .. } => self.next_room_name = room_name.as_ref().map(|n| n.to_string()),
+            Operation::CreateGameRequestGame { room_option_flags, .. } => self.set_delete_null_props(*room_option_flags),
+            Operation::JoinGameRequestGame {
+                room_option_flags: Some(room_option_flags),
+                ..
+            } => self.set_delete_null_props(*room_option_flags),
+            // like the official client, changes without expected properties are applied without waiting for the server
+            Operation::SetPropertiesGame {
+                properties,
+                expected_properties: None,
+                ..
+            } => {
+                if let Some(room) = &mut self.room {
+                    let delta = room.delta(properties.clone());
+                    room.apply_logged(|room| room.info.apply_properties(delta));
+                }
+            }
+            Operation::SetPropertiesActor {
+                actor_nr,
+                properties,
+                expected_properties: None,
+                ..
+            } => {
+                if let Some(room) = &mut self.room {
+                    let delta = room.delta(properties.clone());
+                    room.apply_logged(|room| room.players.entry(*actor_nr).or_default().apply_properties(delta));
+                }
+            }
+            Operation::LeaveRequest { .. } => {
+                self.room = None;
+                self.next_server = self.master_address.clone().map(|address| (ServerType::MasterServer, address));
+            }
+            _ => (),
+        }
+    }
+
+    fn handle_response(&mut self, operation: &Operation) {
+        match operation {
+            Operation::AuthenticateResponseName { address, .. } => {
+                self.next_server = Some((ServerType::MasterServer, address.to_string()));
+                self.master_address = Some(address.to_string());
+            }
+            Operation::CreateGameResponseMaster { room_name, address } => {
+                self.next_server = Some((ServerType::GameServer, address.to_string()));
+                self.set_next_room_name(room_name.as_deref());
+            }
+            Operation::JoinGameResponseMaster {
+                room_name,
+                address: Some(address),
+            }
+            | Operation::JoinRandomGameResponse {
+                room_name,
+                address: Some(address),
+            } => {
+                self.next_server = Some((ServerType::GameServer, address.to_string()));
+                self.set_next_room_name(room_name.as_deref());
+            }
+            Operation::CreateGameResponseGame {
+                actor_nr,
+                game_properties,
+                player_properties,
+                ..
+            }
+            | Operation::JoinGameResponseGame {
+                actor_nr,
+                game_properties,
+                player_properties,
+                ..
+            } => {
+                let room = self.room.get_or_insert_with(|| RoomState::new(None));
+                room.local_actor = *actor_nr;
+                room.info = game_properties.clone().into_owned();
+                room.players = player_properties.iter().map(|(k, v)| (*k, v.clone().into_owned())).collect();
+            }
+            _ => (),
+        }
+    }
+
+    fn handle_event(&mut self, event: &Event, sender: Option<i32>) {
+        match event {
+            Event::AuthEvent { token } => self.secret = Some(token.to_string()),
+            Event::GameList(_) | Event::GameListUpdate(_) => {
+                self.lobby.apply_event(event);
+            }
+            Event::Join { player_properties, .. } => {
+                if let (Some(room), Some(actor)) = (&mut self.room, sender) {
+                    room.players.insert(actor, player_properties.clone().into_owned());
+                }
+            }
+            Event::Leave {
+                actor_nr,
+                is_inactive,
+                new_master_client_id,
+                ..
+            } => {
+                if let Some(room) = &mut self.room {
+                    match (is_inactive, room.players.get_mut(actor_nr)) {
+                        (Some(true), Some(player)) => player.is_inactive = Some(true),
+                        _ => {
+                            room.players.shift_remove(actor_nr);
+                        }
+                    }
+                    if new_master_client_id.is_some() {
+                        room.info.master_client_id = *new_master_client_id;
+                    }
+                }
+            }
+            Event::PropertiesChanged { target_actor, properties } => {
+                if let Some(room) = &mut self.room {
+                    let delta = room.delta(properties.clone().into_owned().into());
+                    match target_actor {
+                        0 => room.apply_logged(|room| room.info.apply_properties(delta)),
+                        actor => room.apply_logged(|room| room.players.entry(*actor).or_default().apply_properties(delta)),
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn set_next_room_name(&mut self, room_name: Option<&str>) {
+        if let Some(room_name) = room_name {
+            self.next_room_name = Some(room_name.to_string());
+        }
+    }
+
+    fn set_delete_null_props(&mut self, room_option_flags: RoomOptionsFlags) {
+        if let Some(room) = &mut self.room {
+            room.delete_null_props = room_option_flags.contains(RoomOptionsFlags::DELETE_NULL_PROPS);
+        }
+    }
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Session::new()
+    }
+}
+
+impl RoomState {
+    fn new(name: Option<String>) -> Self {
+        RoomState {
+            name,
+            local_actor: 0,
+            info: RoomInfo::new(),
+            players: IndexMap::new(),
+            delete_null_props: false,
+        }
+    }
+
+    /// The name of the room, if it is known
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Our actor number, or 0 if we did not join yet
+    pub fn local_actor(&self) -> i32 {
+        self.local_actor
+    }
+
+    /// The properties of the room
+    pub fn info(&self) -> &RoomInfo<'static> {
+        &self.info
+    }
+
+    /// All actors in the room, including ourselves and inactive actors that can still rejoin
+    pub fn players(&self) -> &IndexMap<i32, Player<'static>> {
+        &self.players
+    }
+
+    pub fn player(&self, actor_nr: i32) -> Option<&Player<'static>> {
+        self.players.get(&actor_nr)
+    }
+
+    /// Our own player, if we joined already
+    pub fn local_player(&self) -> Option<&Player<'static>> {
+        self.player(self.local_actor)
+    }
+
+    pub fn master_client_id(&self) -> Option<i32> {
+        self.info.master_client_id
+    }
+
+    /// Returns true if we are the master client of this room
+    pub fn is_master_client(&self) -> bool {
+        self.local_actor != 0 && self.info.master_client_id == Some(self.local_actor)
+    }
+
+    fn delta(&self, properties: IndexMap<ProtocolValue, ProtocolValue>) -> PropertiesDelta<'static> {
+        PropertiesDelta {
+            properties: properties.into_iter().map(|(k, v)| (k.into_owned(), v.into_owned())).collect(),
+            expected_properties: None,
+            delete_null_props: self.delete_null_props,
+        }
+    }
+
+    fn apply_logged(&mut self, apply: impl FnOnce(&mut Self) -> PacketReadResult<bool>) {
+        if let Err(error) = apply(self) {
+            warn!("Could not apply changed properties: {:?}", error);
+        }
+    }
+}
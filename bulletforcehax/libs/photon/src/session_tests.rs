@@ -0,0 +1,264 @@
+#![cfg(test)]
+
+use super::*;
+use indexmap::indexmap;
+
+/// A Leave request that does not stay in the room as an inactive actor
+const LEAVE: [u8; 8] = [0xf3, 0x2, 0xfe, 0x0, 0x1, 0xe9, 0x6f, 0x0];
+
+fn response(parameters: Operation<'static>) -> Packet<'static> {
+    Packet::OperationResponse {
+        parameters,
+        return_code: 0,
+        debug_string: None,
+        secret: Some("secret".into()),
+        order: ParameterOrder::default(),
+    }
+}
+
+fn event(parameters: Event<'static>, sender: Option<i32>) -> Packet<'static> {
+    Packet::Event {
+        parameters,
+        custom_data: None,
+        sender,
+        order: ParameterOrder::default(),
+    }
+}
+
+/// A token authentication request, which is sent to the master and game servers. Also used by the `Connection` tests.
+pub(crate) fn authenticate() -> Packet<'static> {
+    Packet::OperationRequest(
+        Operation::AuthenticateRequestToken {
+            lobby_stats: false,
+            secret: "secret".into(),
+        },
+        ParameterOrder::default(),
+    )
+}
+
+/// Follows the client from the NameServer to the MasterServer
+fn session_on_master() -> Session {
+    let mut session = Session::new();
+    session.handle(&response(Operation::AuthenticateResponseName {
+        user_id: None,
+        nickname: None,
+        encryption_data: None,
+        custom_data: None,
+        cluster: None,
+        address: "wss://master".into(),
+    }));
+    assert_eq!(session.server(), ServerType::NameServer);
+    assert_eq!(session.next_server(), Some((ServerType::MasterServer, "wss://master")));
+
+    session.handle(&authenticate());
+    assert_eq!(session.server(), ServerType::MasterServer);
+    assert_eq!(session.next_server(), None);
+    assert_eq!(session.secret(), Some("secret"));
+    session
+}
+
+/// Follows the client from the MasterServer into room "abc" on the GameServer, where actor 1 is the master client
+fn session_in_room() -> Session {
+    let mut session = session_on_master();
+    session.handle(&Packet::OperationRequest(
+        Operation::JoinGameRequestMaster {
+            room_name: "abc".into(),
+            join_mode: None,
+            lobby_name: None,
+            lobby_type: None,
+            expected_users: vec![],
+        },
+        ParameterOrder::default(),
+    ));
+    session.handle(&response(Operation::JoinGameResponseMaster {
+        room_name: None,
+        address: Some("wss://game".into()),
+    }));
+    assert_eq!(session.next_server(), Some((ServerType::GameServer, "wss://game")));
+
+    session.handle(&authenticate());
+    assert_eq!(session.server(), ServerType::GameServer);
+    assert_eq!(session.room().and_then(RoomState::name), Some("abc"));
+
+    session.handle(&response(Operation::JoinGameResponseGame {
+        actor_nr: 2,
+        actor_list: Some(vec![1, 2]),
+        game_properties: RoomInfo::new().with_max_players(12).with_master_client_id(Some(1)),
+        player_properties: indexmap! {
+            1 => Player::new().with_name("a"),
+            2 => Player::new().with_name("b"),
+        },
+    }));
+    session
+}
+
+#[test]
+fn lobby() {
+    let mut session = session_on_master();
+    session.handle(&event(
+        Event::GameList(indexmap! {
+            "abc".into() => RoomInfo::new().with_player_count(1),
+            "def".into() => RoomInfo::new(),
+        }),
+        None,
+    ));
+    session.handle(&event(
+        Event::GameListUpdate(indexmap! {
+            "abc".into() => Some(RoomInfo::new().with_player_count(2)),
+            "def".into() => None,
+            "ghi".into() => Some(RoomInfo::new()),
+        }),
+        None,
+    ));
+
+    let lobby = session.lobby();
+    assert_eq!(lobby.rooms().keys().collect::<Vec<_>>(), ["abc", "ghi"]);
+    assert_eq!(lobby.rooms()["abc"].player_count(), 2);
+}
+
+#[test]
+fn join_room() {
+    let session = session_in_room();
+    let room = session.room().unwrap();
+
+    assert_eq!(room.local_actor(), 2);
+    assert_eq!(room.local_player().and_then(Player::name), Some("b"));
+    assert_eq!(room.master_client_id(), Some(1));
+    assert!(!room.is_master_client());
+    assert_eq!(room.info().max_players(), 12);
+    assert!(session.lobby().is_empty());
+}
+
+#[test]
+fn room_events() {
+    let mut session = session_in_room();
+    session.handle(&event(
+        Event::Join {
+            player_properties: Player::new().with_name("c"),
+            actor_list: Some(vec![1, 2, 3]),
+        },
+        Some(3),
+    ));
+    session.handle(&event(
+        Event::PropertiesChanged {
+            target_actor: 0,
+            properties: PropertiesChange::Room(RoomPropertiesChange {
+                is_open: Some(false),
+                ..RoomPropertiesChange::default()
+            }),
+        },
+        Some(1),
+    ));
+    session.handle(&event(
+        Event::PropertiesChanged {
+            target_actor: 3,
+            properties: PropertiesChange::Player(Player::new().with_custom("kills", ProtocolValue::Integer(1))),
+        },
+        Some(3),
+    ));
+    session.handle(&event(
+        Event::Leave {
+            actor_nr: 1,
+            actor_list: Some(vec![2, 3]),
+            is_inactive: Some(false),
+            new_master_client_id: Some(2),
+        },
+        None,
+    ));
+
+    let room = session.room().unwrap();
+    assert_eq!(room.players().keys().collect::<Vec<_>>(), [&2, &3]);
+    assert_eq!(room.player(3).and_then(|p| p.get_custom::<i32>("kills")), Some(1));
+    assert!(room.is_master_client());
+    assert!(!room.info().is_open());
+}
+
+#[test]
+fn inactive_players_stay() {
+    let mut session = session_in_room();
+    session.handle(&event(
+        Event::Leave {
+            actor_nr: 1,
+            actor_list: Some(vec![1, 2]),
+            is_inactive: Some(true),
+            new_master_client_id: None,
+        },
+        None,
+    ));
+
+    let room = session.room().unwrap();
+    assert_eq!(room.player(1).and_then(Player::is_inactive), Some(true));
+    assert_eq!(room.master_client_id(), Some(1));
+}
+
+#[test]
+fn set_properties_is_applied() {
+    let mut session = session_in_room();
+    session.handle(&Packet::OperationRequest(
+        Operation::SetPropertiesGame {
+            properties: indexmap! {
+                ProtocolValue::Byte(GamePropertyKey::IsOpen) => ProtocolValue::Bool(false),
+            },
+            expected_properties: None,
+            broadcast: true,
+            event_forward: false,
+        },
+        ParameterOrder::default(),
+    ));
+    // the server may reject changes with expected properties, so these wait for the event
+    session.handle(&Packet::OperationRequest(
+        Operation::SetPropertiesActor {
+            actor_nr: 2,
+            properties: indexmap! {
+                ProtocolValue::Byte(ActorProperties::PlayerName) => ProtocolValue::String("HoLLy".into()),
+            },
+            expected_properties: Some(indexmap! {
+                ProtocolValue::Byte(ActorProperties::PlayerName) => ProtocolValue::String("b".into()),
+            }),
+            broadcast: true,
+            event_forward: false,
+        },
+        ParameterOrder::default(),
+    ));
+
+    let room = session.room().unwrap();
+    assert!(!room.info().is_open());
+    assert_eq!(room.local_player().and_then(Player::name), Some("b"));
+}
+
+#[test]
+fn leave_room() {
+    let mut session = session_in_room();
+    session.handle(&Packet::read(&LEAVE, Direction::Send).unwrap());
+    assert!(session.room().is_none());
+    assert_eq!(session.next_server(), Some((ServerType::MasterServer, "wss://master")));
+
+    session.handle(&authenticate());
+    assert_eq!(session.server(), ServerType::MasterServer);
+}
+
+#[test]
+fn failed_responses_are_ignored() {
+    let mut session = session_on_master();
+    session.handle(&Packet::OperationResponse {
+        parameters: Operation::JoinRandomGameResponse {
+            room_name: None,
+            address: None,
+        },
+        return_code: 32760,
+        debug_string: Some("No match found".into()),
+        secret: None,
+        order: ParameterOrder::default(),
+    });
+
+    assert_eq!(session.next_server(), None);
+    session.handle(&authenticate());
+    assert_eq!(session.server(), ServerType::MasterServer);
+}
+
+#[test]
+fn auth_event_updates_secret() {
+    let mut session = session_on_master();
+    session.handle(&event(Event::AuthEvent { token: "new secret".into() }, None));
+    assert_eq!(session.secret(), Some("new secret"));
+}
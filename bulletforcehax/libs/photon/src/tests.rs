@@ -1,6 +1,7 @@
 #![cfg(test)]
 
 use super::*;
+use indexmap::indexmap;
 
 const TEST_TOKEN_NAME: &str = "AulCdG5VnAHZvN+szlhEdQ05tkCjp41x0e/KgxUezpfEvhgGsuR5AOEbYuurSm1omxSDuwfPUNlgHoOBdPXj2Aj6CcB7A13C/o0/jSnIwxfQQi+rJR8P+dH8EHtbLEzZhLTHe/lWLnXmEKPlL3xBlrWM42/iSqu1/L2LN/+2StPNYnXJXQTGcacKp6piFm/HbVs8EbwQBSk3lOxV/D1xuZPkTreBPQMtOa2i+Z/8EB1EyNNRBocu4RPzMy+jhY7GIM3ZShoyib+miMUcoywV4uOkqN4gNz0wweVgtk/tT3gZ+4TUQuQZtNu3Z+sAbaplQkrQQaQJ0ul2WcXw/Wd7Voe3MfIw+EnUv4jbSiYW2l+D";
 const TEST_TOKEN_MASTER: &str = "eHWX1lVV2VC8wfiwZP11NkMW8Vnni4zZqJfeIwhdqYFNAiqY9J6Shz/UvBD46oypAy+U0ZthuWJSjlwRvJ2YI2uOSaBjsOPXXaHCIVzYLWtkLn0ow0GCDUaJ4tTyN0lBK5s4gTQRaICJDey6YOU1vEPRvy0DDt5z4RkLcM+GklZdEVzvXMERwoSqBBeAiHYyBcxlDqDdeLLYmcRPFq4QinH1vy1h8YtjplpTXYzYe6wDM2qVA3F+KMXlBUSM9pZXzFlI6OrIhUPNl60UkTlzCcWAsFJTuSvCgzLnuFZevq8=";
@@ -8,33 +9,154 @@ const TEST_TOKEN_MASTER2: &str = "eHWX1lVV2VC8wfiwZP11NroXhq5d0Yiy5G10tlSd90zotI
 
 macro_rules! gen_test {
     ($name: ident, $direction: path, $data: expr, $unpacked: expr) => {
+        gen_test!($name, $direction, Protocol::GpBinaryV16, $data, $unpacked);
+    };
+    // the parameters are not in the order packets created from scratch are written in
+    ($name: ident, $direction: path, $data: expr, $unpacked: expr, reordered) => {
+        gen_test!(@test $name, $direction, Protocol::GpBinaryV16, $data, $unpacked, false);
+    };
+    ($name: ident, $direction: path, $protocol: expr, $data: expr, $unpacked: expr) => {
+        gen_test!(@test $name, $direction, $protocol, $data, $unpacked, true);
+    };
+    (@test $name: ident, $direction: path, $protocol: expr, $data: expr, $unpacked: expr, $in_order: expr) => {
         #[test]
         fn $name() {
             let data = $data;
             let expected = $unpacked;
 
-            let packet = Packet::read(data.as_slice(), $direction).expect("Failure while reading packet from bytes");
+            let packet = Packet::read_with_protocol(data.as_slice(), $direction, $protocol).expect("Failure while reading packet from bytes");
 
             assert_eq!(packet, expected);
+            assert_eq!(packet.clone().into_owned(), expected, "Owned packet differs from borrowed packet");
 
-            // now test if serializing and deserializing it gives the original value again
-            let serialized_expected = expected.into_vec().expect("Failure while turning packet back into bytes");
+            // an unmodified packet should be written back byte for byte
+            let serialized = packet
+                .clone()
+                .into_vec_with_protocol($protocol)
+                .expect("Failure while turning packet back into bytes");
             assert_eq!(
-                $data.len(),
-                serialized_expected.len(),
-                "Expected original bytes to have same length as serialized packet. You probably missed some data."
+                $data, serialized,
+                "Expected original bytes to be the same as serialized packet. You probably missed some data."
             );
-            let new_expected = Packet::read(serialized_expected.as_slice(), $direction).expect("Failure while reading serialized packet back");
+
+            // now test if serializing and deserializing it gives the original value again
+            let serialized_expected = expected
+                .into_vec_with_protocol($protocol)
+                .expect("Failure while turning packet back into bytes");
+            if $in_order {
+                assert_eq!(
+                    $data, serialized_expected,
+                    "Expected original bytes to be the same as serialized packet. You probably changed the order."
+                );
+            }
+            let new_expected = Packet::read_with_protocol(serialized_expected.as_slice(), $direction, $protocol)
+                .expect("Failure while reading serialized packet back");
             assert_eq!(new_expected, $unpacked, "Deserialized packet differs from expected packet");
+
+            // packets edited as JSON should still serialize to the same bytes
+            #[cfg(feature = "serde")]
+            {
+                let json = serde_json::to_string(&packet).expect("Failure while turning packet into JSON");
+                let from_json: Packet = serde_json::from_str(&json).expect("Failure while reading packet from JSON");
+                assert_eq!(from_json, $unpacked, "Packet read from JSON differs from expected packet");
+                let serialized_json = from_json
+                    .into_vec_with_protocol($protocol)
+                    .expect("Failure while turning packet from JSON into bytes");
+                assert_eq!(
+                    $data, serialized_json,
+                    "Expected original bytes to be the same as packet read from JSON"
+                );
+            }
         }
     };
 }
 
+gen_test!(
+    init,
+    Direction::Send,
+    vec![
+        0xf3, 0x0, 0x1, 0x6, 0x22, 0x41, 0x2, 0x4, 0x0, 0x38, 0x63, 0x32, 0x63, 0x61, 0x64, 0x33, 0x65, 0x2d, 0x32, 0x65, 0x33, 0x66, 0x2d, 0x34,
+        0x39, 0x34, 0x31, 0x2d, 0x39, 0x30, 0x34, 0x34, 0x2d, 0x62, 0x33, 0x39, 0x30, 0x66, 0x66, 0x32, 0x63,
+    ],
+    Packet::Init {
+        protocol_version: (1, 6),
+        client_sdk_id: 0x22,
+        client_version: (4, 1, 2, 4),
+        flags: 0,
+        app_id: "8c2cad3e-2e3f-4941-9044-b390ff2c".into(),
+    }
+);
+
+gen_test!(
+    init_short_app_id,
+    Direction::Send,
+    vec![
+        0xf3, 0x0, 0x1, 0x8, 0x22, 0x41, 0x2, 0x4, 0x0, 0x61, 0x62, 0x63, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+        0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0,
+    ],
+    Packet::Init {
+        protocol_version: (1, 8),
+        client_sdk_id: 0x22,
+        client_version: (4, 1, 2, 4),
+        flags: 0,
+        app_id: "abc".into(),
+    }
+);
+
+gen_test!(
+    init_response,
+    Direction::Recv,
+    vec![0xf3, 0x1, 0x0],
+    Packet::InitResponse(Cow::Borrowed(&[0x0]))
+);
+
+gen_test!(
+    message,
+    Direction::Recv,
+    vec![0xf3, 0x8, 0x73, 0x0, 0x3, 0x61, 0x62, 0x63],
+    Packet::Message(ProtocolValue::String("abc".into()))
+);
+
+gen_test!(
+    raw_message,
+    Direction::Send,
+    vec![0xf3, 0x9, 0xde, 0xad, 0xbe, 0xef],
+    Packet::RawMessage(Cow::Borrowed(&[0xde, 0xad, 0xbe, 0xef]))
+);
+
+#[test]
+fn init_invalid_length() {
+    let data = vec![0xf3, 0x0, 0x1, 0x6];
+    match Packet::read(data.as_slice(), Direction::Send) {
+        Err(PacketReadError::InvalidInitLength(2)) => (),
+        other => panic!("Expected InvalidInitLength, got {:?}", other),
+    }
+}
+
+#[test]
+fn raise_event_invalid_cache() {
+    // found by fuzzing, used to panic
+    let data = vec![0xf3, 0x2, 0xfd, 0x0, 0x1, 0xf7, 0x62, 0x63];
+    match Packet::read(data.as_slice(), Direction::Send) {
+        Err(PacketReadError::InvalidParameterValue(ParameterCode::Cache)) => (),
+        other => panic!("Expected InvalidParameterValue, got {:?}", other),
+    }
+}
+
+#[test]
+fn raise_event_invalid_receiver_group() {
+    let data = vec![0xf3, 0x2, 0xfd, 0x0, 0x1, 0xf6, 0x62, 0x63];
+    match Packet::read(data.as_slice(), Direction::Send) {
+        Err(PacketReadError::InvalidParameterValue(ParameterCode::ReceiverGroup)) => (),
+        other => panic!("Expected InvalidParameterValue, got {:?}", other),
+    }
+}
+
 gen_test!(
     ping_request,
     Direction::Send,
     vec![0xf3, 0x6, 0x1, 0x0, 0x1, 0x1, 0x69, 0x0, 0x0, 0x2d, 0x51],
-    Packet::InternalOperationRequest(InternalOperation::PingRequest { local_time: 11601 })
+    Packet::InternalOperationRequest(InternalOperation::PingRequest { local_time: 11601 }, ParameterOrder::default())
 );
 
 gen_test!(
@@ -47,7 +169,32 @@ gen_test!(
             server_time: -1836592187,
         },
         0,
-        None
+        None,
+        ParameterOrder::default()
+    )
+);
+
+gen_test!(
+    ping_request_v18,
+    Direction::Send,
+    Protocol::GpBinaryV18,
+    vec![0xf3, 0x6, 0x1, 0x1, 0x1, 0xd, 0x51, 0x2d],
+    Packet::InternalOperationRequest(InternalOperation::PingRequest { local_time: 11601 }, ParameterOrder::default())
+);
+
+gen_test!(
+    ping_response_v18,
+    Direction::Recv,
+    Protocol::GpBinaryV18,
+    vec![0xf3, 0x7, 0x1, 0x0, 0x0, 0x8, 0x2, 0x1, 0xd, 0x51, 0x2d, 0x2, 0x9, 0xf5, 0xb0, 0xc1, 0xd7, 0xd],
+    Packet::InternalOperationResponse(
+        InternalOperation::PingResponse {
+            local_time: 11601,
+            server_time: -1836592187,
+        },
+        0,
+        None,
+        ParameterOrder::default()
     )
 );
 
@@ -60,16 +207,19 @@ gen_test!(
         0x38, 0x63, 0x32, 0x63, 0x61, 0x64, 0x33, 0x65, 0x2d, 0x32, 0x65, 0x33, 0x66, 0x2d, 0x34, 0x39, 0x34, 0x31, 0x2d, 0x39, 0x30, 0x34, 0x34,
         0x2d, 0x62, 0x33, 0x39, 0x30, 0x66, 0x66, 0x32, 0x63, 0x34, 0x39, 0x35, 0x36, 0xd2, 0x73, 0x0, 0x2, 0x75, 0x73,
     ],
-    Packet::OperationRequest(Operation::AuthenticateRequestNoToken {
-        lobby_stats: false,
-        app_version: "1.67.0_1.99",
-        app_id: "8c2cad3e-2e3f-4941-9044-b390ff2c4956",
-        region: Some("us"),
-        user_id: None,
-        client_auth_type: None,
-        client_auth_params: None,
-        client_auth_data: None,
-    })
+    Packet::OperationRequest(
+        Operation::AuthenticateRequestNoToken {
+            lobby_stats: false,
+            app_version: "1.67.0_1.99".into(),
+            app_id: "8c2cad3e-2e3f-4941-9044-b390ff2c4956".into(),
+            region: Some("us".into()),
+            user_id: None,
+            client_auth_type: None,
+            client_auth_params: None,
+            client_auth_data: None,
+        },
+        ParameterOrder::default()
+    )
 );
 
 // Simulates auth response from name server
@@ -101,16 +251,17 @@ gen_test!(
     ],
     Packet::OperationResponse {
         parameters: Operation::AuthenticateResponseName {
-            user_id: Some("8dbb619a-a41b-4e22-8bd6-eb142ff0ee7e"),
+            user_id: Some("8dbb619a-a41b-4e22-8bd6-eb142ff0ee7e".into()),
             nickname: None,
             encryption_data: None,
             custom_data: None,
-            cluster: Some("default"),
-            address: "wss://GCASH013.exitgames.com:19090"
+            cluster: Some("default".into()),
+            address: "wss://GCASH013.exitgames.com:19090".into()
         },
         return_code: 0,
         debug_string: None,
-        secret: Some(TEST_TOKEN_NAME),
+        secret: Some(TEST_TOKEN_NAME.into()),
+        order: ParameterOrder::default(),
     }
 );
 
@@ -137,10 +288,13 @@ gen_test!(
         0x57, 0x64, 0x37, 0x56, 0x6f, 0x65, 0x33, 0x4d, 0x66, 0x49, 0x77, 0x2b, 0x45, 0x6e, 0x55, 0x76, 0x34, 0x6a, 0x62, 0x53, 0x69, 0x59, 0x57,
         0x32, 0x6c, 0x2b, 0x44,
     ],
-    Packet::OperationRequest(Operation::AuthenticateRequestToken {
-        lobby_stats: false,
-        secret: TEST_TOKEN_NAME,
-    })
+    Packet::OperationRequest(
+        Operation::AuthenticateRequestToken {
+            lobby_stats: false,
+            secret: TEST_TOKEN_NAME.into(),
+        },
+        ParameterOrder::default()
+    )
 );
 
 // Simulates auth response from master server
@@ -173,7 +327,8 @@ gen_test!(
         },
         return_code: 0,
         debug_string: None,
-        secret: Some(TEST_TOKEN_MASTER),
+        secret: Some(TEST_TOKEN_MASTER.into()),
+        order: ParameterOrder::default(),
     }
 );
 
@@ -196,10 +351,13 @@ gen_test!(
         0x37, 0x37, 0x75, 0x58, 0x63, 0x76, 0x39, 0x6d, 0x66, 0x75, 0x53, 0x41, 0x5a, 0x43, 0x74, 0x56, 0x41, 0x39, 0x43, 0x31, 0x6c, 0x41, 0x56,
         0x39, 0x59, 0x46, 0x51, 0x68, 0x34, 0x39, 0x6b, 0x3d
     ],
-    Packet::OperationRequest(Operation::AuthenticateRequestToken {
-        lobby_stats: false,
-        secret: TEST_TOKEN_MASTER2, // I don't know either
-    })
+    Packet::OperationRequest(
+        Operation::AuthenticateRequestToken {
+            lobby_stats: false,
+            secret: TEST_TOKEN_MASTER2.into(), // I don't know either
+        },
+        ParameterOrder::default()
+    )
 );
 
 gen_test!(
@@ -216,6 +374,763 @@ gen_test!(
         },
         return_code: 0,
         debug_string: None,
-        secret: None
+        secret: None,
+        order: ParameterOrder::default()
+    }
+);
+
+gen_test!(
+    join_random_game_request,
+    Direction::Send,
+    vec![0xf3, 0x2, 0xe1, 0x0, 0x2, 0xf8, 0x68, 0x0, 0x1, 0x62, 0xff, 0x62, 0xc, 0xdf, 0x62, 0x2],
+    Packet::OperationRequest(
+        Operation::JoinRandomGameRequest {
+            expected_properties: Some(indexmap! {
+                ProtocolValue::Byte(GamePropertyKey::MaxPlayers) => ProtocolValue::Byte(12),
+            }),
+            matchmaking_mode: Some(MatchmakingMode::RandomMatching),
+            lobby_name: None,
+            lobby_type: None,
+            sql_filter: None,
+            expected_users: vec![],
+        },
+        ParameterOrder::default()
+    )
+);
+
+// No game could be found, so no address is given
+gen_test!(
+    join_random_game_response_not_found,
+    Direction::Recv,
+    vec![0xf3, 0x3, 0xe1, 0x7f, 0xf8, 0x73, 0x0, 0xe, 0x4e, 0x6f, 0x20, 0x6d, 0x61, 0x74, 0x63, 0x68, 0x20, 0x66, 0x6f, 0x75, 0x6e, 0x64, 0x0, 0x0],
+    Packet::OperationResponse {
+        parameters: Operation::JoinRandomGameResponse {
+            room_name: None,
+            address: None
+        },
+        return_code: 32760,
+        debug_string: Some("No match found".into()),
+        secret: None,
+        order: ParameterOrder::default(),
+    }
+);
+
+gen_test!(
+    join_game_request_master,
+    Direction::Send,
+    vec![0xf3, 0x2, 0xe2, 0x0, 0x1, 0xff, 0x73, 0x0, 0x3, 0x61, 0x62, 0x63],
+    Packet::OperationRequest(
+        Operation::JoinGameRequestMaster {
+            room_name: "abc".into(),
+            join_mode: None,
+            lobby_name: None,
+            lobby_type: None,
+            expected_users: vec![],
+        },
+        ParameterOrder::default()
+    )
+);
+
+gen_test!(
+    join_game_response_master,
+    Direction::Recv,
+    vec![
+        0xf3, 0x3, 0xe2, 0x0, 0x0, 0x2a, 0x0, 0x2, 0xe6, 0x73, 0x0, 0x13, 0x77, 0x73, 0x3a, 0x2f, 0x2f, 0x31, 0x32, 0x37, 0x2e, 0x30, 0x2e, 0x30,
+        0x2e, 0x31, 0x3a, 0x39, 0x30, 0x39, 0x30, 0xdd, 0x73, 0x0, 0x3, 0x74, 0x6f, 0x6b
+    ],
+    Packet::OperationResponse {
+        parameters: Operation::JoinGameResponseMaster {
+            room_name: None,
+            address: Some("ws://127.0.0.1:9090".into())
+        },
+        return_code: 0,
+        debug_string: None,
+        secret: Some("tok".into()),
+        order: ParameterOrder::default(),
+    }
+);
+
+// Joins the room, creating it on the game server if it does not exist yet
+gen_test!(
+    join_game_request_game,
+    Direction::Send,
+    vec![
+        0xf3, 0x2, 0xe2, 0x0, 0x8, 0xff, 0x73, 0x0, 0x3, 0x61, 0x62, 0x63, 0xd7, 0x62, 0x1, 0xf9, 0x68, 0x0, 0x1, 0x62, 0xff, 0x73, 0x0, 0x5, 0x48,
+        0x6f, 0x4c, 0x4c, 0x79, 0xfa, 0x6f, 0x1, 0xf8, 0x68, 0x0, 0x6, 0x62, 0xff, 0x62, 0xc, 0x62, 0xfe, 0x6f, 0x1, 0x62, 0xfd, 0x6f, 0x1, 0x62,
+        0xf9, 0x6f, 0x1, 0x62, 0xfa, 0x79, 0x0, 0x1, 0x73, 0x0, 0x8, 0x67, 0x61, 0x6d, 0x65, 0x4d, 0x6f, 0x64, 0x65, 0x73, 0x0, 0x8, 0x67, 0x61,
+        0x6d, 0x65, 0x4d, 0x6f, 0x64, 0x65, 0x69, 0x0, 0x0, 0x0, 0x0, 0xf1, 0x6f, 0x1, 0xe8, 0x6f, 0x1, 0xbf, 0x69, 0x0, 0x0, 0x0, 0x3
+    ],
+    Packet::OperationRequest(
+        Operation::JoinGameRequestGame {
+            room_name: "abc".into(),
+            join_mode: Some(JoinMode::CreateIfNotExists),
+            lobby_name: None,
+            lobby_type: None,
+            expected_users: vec![],
+            player_properties: Some(Player {
+                name: Some("HoLLy".into()),
+                user_id: None,
+                is_inactive: None,
+                custom_properties: indexmap! {},
+            }),
+            broadcast: Some(true),
+            game_properties: Some(RoomOptions {
+                max_players: 12,
+                is_open: true,
+                is_visible: true,
+                cleanup_cache_on_leave: true,
+                custom_properties_lobby: vec!["gameMode".into()],
+                custom_properties: indexmap! { "gameMode".into() => ProtocolValue::Integer(0) },
+            }),
+            player_ttl: None,
+            empty_room_ttl: None,
+            plugins: None,
+            room_option_flags: Some(RoomOptionsFlags::CHECK_USER_ON_JOIN | RoomOptionsFlags::DELETE_CACHE_ON_LEAVE),
+            room_option_params: RoomOptionParams {
+                cleanup_cache_on_leave: Some(true),
+                check_user_on_join: Some(true),
+                ..RoomOptionParams::default()
+            },
+        },
+        ParameterOrder::default()
+    )
+);
+
+// Joins the room without sending the room option flags, like older clients do
+gen_test!(
+    join_game_request_game_without_flags,
+    Direction::Send,
+    vec![
+        0xf3, 0x2, 0xe2, 0x0, 0x4, 0xff, 0x73, 0x0, 0x3, 0x61, 0x62, 0x63, 0xf9, 0x68, 0x0, 0x1, 0x62, 0xff, 0x73, 0x0, 0x5, 0x48, 0x6f, 0x4c, 0x4c,
+        0x79, 0xf1, 0x6f, 0x1, 0xe8, 0x6f, 0x0
+    ],
+    Packet::OperationRequest(
+        Operation::JoinGameRequestGame {
+            room_name: "abc".into(),
+            join_mode: None,
+            lobby_name: None,
+            lobby_type: None,
+            expected_users: vec![],
+            player_properties: Some(Player {
+                name: Some("HoLLy".into()),
+                user_id: None,
+                is_inactive: None,
+                custom_properties: indexmap! {},
+            }),
+            broadcast: None,
+            game_properties: None,
+            player_ttl: None,
+            empty_room_ttl: None,
+            plugins: None,
+            room_option_flags: None,
+            room_option_params: RoomOptionParams {
+                cleanup_cache_on_leave: Some(true),
+                check_user_on_join: Some(false),
+                ..RoomOptionParams::default()
+            },
+        },
+        ParameterOrder::default()
+    )
+);
+
+// Joins the room with only the room option flags, without the separate parameters
+gen_test!(
+    join_game_request_game_flags_only,
+    Direction::Send,
+    vec![
+        0xf3, 0x2, 0xe2, 0x0, 0x3, 0xff, 0x73, 0x0, 0x3, 0x61, 0x62, 0x63, 0xf9, 0x68, 0x0, 0x1, 0x62, 0xff, 0x73, 0x0, 0x5, 0x48, 0x6f, 0x4c, 0x4c,
+        0x79, 0xbf, 0x69, 0x0, 0x0, 0x0, 0x3
+    ],
+    Packet::OperationRequest(
+        Operation::JoinGameRequestGame {
+            room_name: "abc".into(),
+            join_mode: None,
+            lobby_name: None,
+            lobby_type: None,
+            expected_users: vec![],
+            player_properties: Some(Player {
+                name: Some("HoLLy".into()),
+                user_id: None,
+                is_inactive: None,
+                custom_properties: indexmap! {},
+            }),
+            broadcast: None,
+            game_properties: None,
+            player_ttl: None,
+            empty_room_ttl: None,
+            plugins: None,
+            room_option_flags: Some(RoomOptionsFlags::CHECK_USER_ON_JOIN | RoomOptionsFlags::DELETE_CACHE_ON_LEAVE),
+            room_option_params: RoomOptionParams::default(),
+        },
+        ParameterOrder::default()
+    )
+);
+
+// Joins the room with a single room option parameter, which is the only one written back
+gen_test!(
+    join_game_request_game_single_option,
+    Direction::Send,
+    vec![
+        0xf3, 0x2, 0xe2, 0x0, 0x3, 0xff, 0x73, 0x0, 0x3, 0x61, 0x62, 0x63, 0xf9, 0x68, 0x0, 0x1, 0x62, 0xff, 0x73, 0x0, 0x5, 0x48, 0x6f, 0x4c, 0x4c,
+        0x79, 0xef, 0x6f, 0x1
+    ],
+    Packet::OperationRequest(
+        Operation::JoinGameRequestGame {
+            room_name: "abc".into(),
+            join_mode: None,
+            lobby_name: None,
+            lobby_type: None,
+            expected_users: vec![],
+            player_properties: Some(Player {
+                name: Some("HoLLy".into()),
+                user_id: None,
+                is_inactive: None,
+                custom_properties: indexmap! {},
+            }),
+            broadcast: None,
+            game_properties: None,
+            player_ttl: None,
+            empty_room_ttl: None,
+            plugins: None,
+            room_option_flags: None,
+            room_option_params: RoomOptionParams {
+                publish_user_id: Some(true),
+                ..RoomOptionParams::default()
+            },
+        },
+        ParameterOrder::default()
+    )
+);
+
+gen_test!(
+    join_game_response_game,
+    Direction::Recv,
+    vec![
+        0xf3, 0x3, 0xe2, 0x0, 0x0, 0x2a, 0x0, 0x4, 0xfe, 0x69, 0x0, 0x0, 0x0, 0x2, 0xf8, 0x68, 0x0, 0x8, 0x62, 0xff, 0x62, 0xc, 0x62, 0xfe, 0x6f,
+        0x1, 0x62, 0xfd, 0x6f, 0x1, 0x62, 0xfc, 0x62, 0x2, 0x62, 0xf9, 0x6f, 0x1, 0x62, 0xf8, 0x69, 0x0, 0x0, 0x0, 0x1, 0x62, 0xf5, 0x69, 0x0, 0x0,
+        0x0, 0x0, 0x62, 0xf6, 0x69, 0x0, 0x0, 0x0, 0x0, 0xf9, 0x68, 0x0, 0x2, 0x69, 0x0, 0x0, 0x0, 0x1, 0x68, 0x0, 0x1, 0x62, 0xff, 0x73, 0x0, 0x1,
+        0x61, 0x69, 0x0, 0x0, 0x0, 0x2, 0x68, 0x0, 0x1, 0x62, 0xff, 0x73, 0x0, 0x1, 0x62, 0xfc, 0x79, 0x0, 0x2, 0x69, 0x0, 0x0, 0x0, 0x1, 0x0, 0x0,
+        0x0, 0x2
+    ],
+    Packet::OperationResponse {
+        parameters: Operation::JoinGameResponseGame {
+            actor_nr: 2,
+            actor_list: Some(vec![1, 2]),
+            game_properties: RoomInfo {
+                max_players: 12,
+                is_open: true,
+                is_visible: true,
+                player_count: 2,
+                cleanup_cache_on_leave: true,
+                master_client_id: Some(1),
+                custom_properties_lobby: vec![],
+                expected_users: vec![],
+                empty_room_ttl: 0,
+                player_ttl: 0,
+                custom_properties: indexmap! {},
+            },
+            player_properties: indexmap! {
+                1 => Player { name: Some("a".into()), user_id: None, is_inactive: None, custom_properties: indexmap! {} },
+                2 => Player { name: Some("b".into()), user_id: None, is_inactive: None, custom_properties: indexmap! {} },
+            },
+        },
+        return_code: 0,
+        debug_string: None,
+        secret: None,
+        order: ParameterOrder::default(),
+    }
+);
+
+gen_test!(
+    properties_changed_room,
+    Direction::Recv,
+    vec![
+        0xf3, 0x4, 0xfd, 0x0, 0x3, 0xfd, 0x69, 0x0, 0x0, 0x0, 0x0, 0xfb, 0x68, 0x0, 0x2, 0x62, 0xfd, 0x6f, 0x0, 0x73, 0x0, 0xc, 0x72, 0x6f, 0x75,
+        0x6e, 0x64, 0x53, 0x74, 0x61, 0x72, 0x74, 0x65, 0x64, 0x6f, 0x1, 0xfe, 0x69, 0x0, 0x0, 0x0, 0x1
+    ],
+    Packet::Event {
+        sender: Some(1),
+        custom_data: None,
+        parameters: Event::PropertiesChanged {
+            target_actor: 0,
+            properties: PropertiesChange::Room(RoomPropertiesChange {
+                is_open: Some(false),
+                custom_properties: indexmap! {
+                    "roundStarted".into() => ProtocolValue::Bool(true),
+                },
+                ..RoomPropertiesChange::default()
+            }),
+        },
+        order: ParameterOrder::default()
+    }
+);
+
+// Custom properties set to null are removed
+gen_test!(
+    properties_changed_player,
+    Direction::Recv,
+    vec![
+        0xf3, 0x4, 0xfd, 0x0, 0x3, 0xfd, 0x69, 0x0, 0x0, 0x0, 0x2, 0xfb, 0x68, 0x0, 0x2, 0x73, 0x0, 0x5, 0x6b, 0x69, 0x6c, 0x6c, 0x73, 0x69, 0x0,
+        0x0, 0x0, 0x3, 0x73, 0x0, 0x4, 0x74, 0x65, 0x61, 0x6d, 0x2a, 0xfe, 0x69, 0x0, 0x0, 0x0, 0x2
+    ],
+    Packet::Event {
+        sender: Some(2),
+        custom_data: None,
+        parameters: Event::PropertiesChanged {
+            target_actor: 2,
+            properties: PropertiesChange::Player(Player {
+                name: None,
+                user_id: None,
+                is_inactive: None,
+                custom_properties: indexmap! {
+                    "kills".into() => ProtocolValue::Integer(3),
+                    "team".into() => ProtocolValue::Null(),
+                },
+            }),
+        },
+        order: ParameterOrder::default()
+    }
+);
+
+// Actor 1 was the master client, so actor 2 takes over
+gen_test!(
+    leave,
+    Direction::Recv,
+    vec![
+        0xf3, 0x4, 0xfe, 0x0, 0x4, 0xfc, 0x79, 0x0, 0x2, 0x69, 0x0, 0x0, 0x0, 0x2, 0x0, 0x0, 0x0, 0x3, 0xe9, 0x6f, 0x0, 0xcb, 0x69, 0x0, 0x0, 0x0,
+        0x2, 0xfe, 0x69, 0x0, 0x0, 0x0, 0x1
+    ],
+    Packet::Event {
+        sender: None,
+        custom_data: None,
+        parameters: Event::Leave {
+            actor_nr: 1,
+            actor_list: Some(vec![2, 3]),
+            is_inactive: Some(false),
+            new_master_client_id: Some(2),
+        },
+        order: ParameterOrder::default()
+    }
+);
+
+// Like `leave`, but with the sender before the other parameters
+gen_test!(
+    leave_reordered,
+    Direction::Recv,
+    vec![
+        0xf3, 0x4, 0xfe, 0x0, 0x4, 0xfe, 0x69, 0x0, 0x0, 0x0, 0x1, 0xfc, 0x79, 0x0, 0x2, 0x69, 0x0, 0x0, 0x0, 0x2, 0x0, 0x0, 0x0, 0x3, 0xe9, 0x6f,
+        0x0, 0xcb, 0x69, 0x0, 0x0, 0x0, 0x2
+    ],
+    Packet::Event {
+        sender: None,
+        custom_data: None,
+        parameters: Event::Leave {
+            actor_nr: 1,
+            actor_list: Some(vec![2, 3]),
+            is_inactive: Some(false),
+            new_master_client_id: Some(2),
+        },
+        order: ParameterOrder::default(),
+    },
+    reordered
+);
+
+gen_test!(
+    auth_event,
+    Direction::Recv,
+    vec![0xf3, 0x4, 0xdf, 0x0, 0x1, 0xdd, 0x73, 0x0, 0x3, 0x61, 0x62, 0x63],
+    Packet::Event {
+        parameters: Event::AuthEvent { token: "abc".into() },
+        custom_data: None,
+        sender: None,
+        order: ParameterOrder::default(),
+    }
+);
+
+gen_test!(
+    lobby_stats,
+    Direction::Recv,
+    vec![
+        0xf3, 0x4, 0xe0, 0x0, 0x4, 0xd5, 0x61, 0x0, 0x2, 0x0, 0x0, 0x0, 0x6, 0x72, 0x61, 0x6e, 0x6b, 0x65, 0x64, 0xd4, 0x78, 0x0, 0x0, 0x0, 0x2, 0x0,
+        0x2, 0xe5, 0x6e, 0x0, 0x0, 0x0, 0x2, 0x0, 0x0, 0x0, 0x78, 0x0, 0x0, 0x0, 0x8, 0xe4, 0x6e, 0x0, 0x0, 0x0, 0x2, 0x0, 0x0, 0x0, 0xe, 0x0, 0x0,
+        0x0, 0x1
+    ],
+    Packet::Event {
+        parameters: Event::LobbyStats {
+            names: vec!["".into(), "ranked".into()],
+            types: vec![0, 2],
+            peer_counts: vec![120, 8],
+            game_counts: vec![14, 1],
+        },
+        custom_data: None,
+        sender: None,
+        order: ParameterOrder::default(),
+    }
+);
+
+gen_test!(
+    cache_slice_changed,
+    Direction::Recv,
+    vec![0xf3, 0x4, 0xfa, 0x0, 0x1, 0xcd, 0x69, 0x0, 0x0, 0x0, 0x3],
+    Packet::Event {
+        parameters: Event::CacheSliceChanged { index: 3 },
+        custom_data: None,
+        sender: None,
+        order: ParameterOrder::default(),
+    }
+);
+
+gen_test!(
+    error_info,
+    Direction::Recv,
+    vec![0xf3, 0x4, 0xfb, 0x0, 0x1, 0xda, 0x73, 0x0, 0xc, 0x50, 0x6c, 0x75, 0x67, 0x69, 0x6e, 0x20, 0x65, 0x72, 0x72, 0x6f, 0x72],
+    Packet::Event {
+        parameters: Event::ErrorInfo { info: "Plugin error".into() },
+        custom_data: None,
+        sender: None,
+        order: ParameterOrder::default(),
+    }
+);
+
+gen_test!(
+    get_game_list_request,
+    Direction::Send,
+    vec![
+        0xf3, 0x2, 0xd9, 0x0, 0x3, 0xd5, 0x73, 0x0, 0x3, 0x73, 0x71, 0x6c, 0xd4, 0x62, 0x2, 0xf5, 0x73, 0x0, 0x6, 0x43, 0x30, 0x20, 0x3d, 0x20, 0x31
+    ],
+    Packet::OperationRequest(
+        Operation::GetGameListRequest {
+            lobby_name: "sql".into(),
+            lobby_type: 2,
+            sql_filter: "C0 = 1".into()
+        },
+        ParameterOrder::default()
+    )
+);
+
+gen_test!(
+    get_game_list_response,
+    Direction::Recv,
+    vec![
+        0xf3, 0x3, 0xd9, 0x0, 0x0, 0x2a, 0x0, 0x1, 0xde, 0x68, 0x0, 0x1, 0x73, 0x0, 0x4, 0x72, 0x6f, 0x6f, 0x6d, 0x68, 0x0, 0x7, 0x62, 0xff, 0x62,
+        0xc, 0x62, 0xfe, 0x6f, 0x1, 0x62, 0xfd, 0x6f, 0x1, 0x62, 0xfc, 0x62, 0x3, 0x62, 0xf9, 0x6f, 0x1, 0x62, 0xf5, 0x69, 0x0, 0x0, 0x0, 0x0, 0x62,
+        0xf6, 0x69, 0x0, 0x0, 0x0, 0x0
+    ],
+    Packet::OperationResponse {
+        parameters: Operation::GetGameListResponse {
+            games: indexmap! { "room".into() => RoomInfo::new().with_max_players(12).with_player_count(3) }
+        },
+        return_code: 0,
+        debug_string: None,
+        secret: None,
+        order: ParameterOrder::default()
+    }
+);
+
+gen_test!(
+    get_regions_request,
+    Direction::Send,
+    vec![
+        0xf3, 0x2, 0xdc, 0x0, 0x1, 0xe0, 0x73, 0x0, 0x24, 0x38, 0x63, 0x32, 0x63, 0x61, 0x64, 0x33, 0x65, 0x2d, 0x32, 0x65, 0x33, 0x66, 0x2d, 0x34,
+        0x39, 0x34, 0x31, 0x2d, 0x39, 0x30, 0x34, 0x34, 0x2d, 0x62, 0x33, 0x39, 0x30, 0x66, 0x66, 0x32, 0x63, 0x34, 0x39, 0x35, 0x36
+    ],
+    Packet::OperationRequest(
+        Operation::GetRegionsRequest {
+            app_id: "8c2cad3e-2e3f-4941-9044-b390ff2c4956".into()
+        },
+        ParameterOrder::default()
+    )
+);
+
+gen_test!(
+    get_regions_response,
+    Direction::Recv,
+    vec![
+        0xf3, 0x3, 0xdc, 0x0, 0x0, 0x2a, 0x0, 0x2, 0xd2, 0x61, 0x0, 0x2, 0x0, 0x2, 0x65, 0x75, 0x0, 0x2, 0x75, 0x73, 0xe6, 0x61, 0x0, 0x2, 0x0, 0x16,
+        0x77, 0x73, 0x73, 0x3a, 0x2f, 0x2f, 0x65, 0x75, 0x2e, 0x65, 0x78, 0x61, 0x6d, 0x70, 0x6c, 0x65, 0x3a, 0x31, 0x39, 0x30, 0x39, 0x30, 0x0,
+        0x16, 0x77, 0x73, 0x73, 0x3a, 0x2f, 0x2f, 0x75, 0x73, 0x2e, 0x65, 0x78, 0x61, 0x6d, 0x70, 0x6c, 0x65, 0x3a, 0x31, 0x39, 0x30, 0x39, 0x30
+    ],
+    Packet::OperationResponse {
+        parameters: Operation::GetRegionsResponse {
+            regions: vec!["eu".into(), "us".into()],
+            addresses: vec!["wss://eu.example:19090".into(), "wss://us.example:19090".into()]
+        },
+        return_code: 0,
+        debug_string: None,
+        secret: None,
+        order: ParameterOrder::default()
+    }
+);
+
+gen_test!(
+    get_lobby_stats_request,
+    Direction::Send,
+    vec![0xf3, 0x2, 0xdd, 0x0, 0x2, 0xd5, 0x61, 0x0, 0x1, 0x0, 0x6, 0x72, 0x61, 0x6e, 0x6b, 0x65, 0x64, 0xd4, 0x78, 0x0, 0x0, 0x0, 0x1, 0x0],
+    Packet::OperationRequest(
+        Operation::GetLobbyStatsRequest {
+            lobby_names: Some(vec!["ranked".into()]),
+            lobby_types: Some(vec![0])
+        },
+        ParameterOrder::default()
+    )
+);
+
+gen_test!(
+    get_lobby_stats_response,
+    Direction::Recv,
+    vec![
+        0xf3, 0x3, 0xdd, 0x0, 0x0, 0x2a, 0x0, 0x4, 0xd5, 0x61, 0x0, 0x2, 0x0, 0x0, 0x0, 0x6, 0x72, 0x61, 0x6e, 0x6b, 0x65, 0x64, 0xd4, 0x78, 0x0,
+        0x0, 0x0, 0x2, 0x0, 0x2, 0xe5, 0x6e, 0x0, 0x0, 0x0, 0x2, 0x0, 0x0, 0x0, 0x78, 0x0, 0x0, 0x0, 0x8, 0xe4, 0x6e, 0x0, 0x0, 0x0, 0x2, 0x0, 0x0,
+        0x0, 0xe, 0x0, 0x0, 0x0, 0x1
+    ],
+    Packet::OperationResponse {
+        parameters: Operation::GetLobbyStatsResponse {
+            names: vec!["".into(), "ranked".into()],
+            types: vec![0, 2],
+            peer_counts: vec![120, 8],
+            game_counts: vec![14, 1]
+        },
+        return_code: 0,
+        debug_string: None,
+        secret: None,
+        order: ParameterOrder::default()
     }
 );
+
+gen_test!(
+    find_friends_request,
+    Direction::Send,
+    vec![
+        0xf3, 0x2, 0xde, 0x0, 0x2, 0x1, 0x61, 0x0, 0x2, 0x0, 0x5, 0x48, 0x6f, 0x4c, 0x4c, 0x79, 0x0, 0x6, 0x66, 0x72, 0x69, 0x65, 0x6e, 0x64, 0x2,
+        0x69, 0x0, 0x0, 0x0, 0x6
+    ],
+    Packet::OperationRequest(
+        Operation::FindFriendsRequest {
+            user_ids: vec!["HoLLy".into(), "friend".into()],
+            options: Some(FindFriendsOptions::VISIBLE | FindFriendsOptions::OPEN)
+        },
+        ParameterOrder::default()
+    )
+);
+
+gen_test!(
+    find_friends_response,
+    Direction::Recv,
+    vec![
+        0xf3, 0x3, 0xde, 0x0, 0x0, 0x2a, 0x0, 0x2, 0x1, 0x79, 0x0, 0x2, 0x6f, 0x1, 0x0, 0x2, 0x61, 0x0, 0x2, 0x0, 0x4, 0x72, 0x6f, 0x6f, 0x6d, 0x0,
+        0x0
+    ],
+    Packet::OperationResponse {
+        parameters: Operation::FindFriendsResponse {
+            online: vec![true, false],
+            room_ids: vec!["room".into(), "".into()]
+        },
+        return_code: 0,
+        debug_string: None,
+        secret: None,
+        order: ParameterOrder::default()
+    }
+);
+
+gen_test!(
+    leave_request,
+    Direction::Send,
+    vec![0xf3, 0x2, 0xfe, 0x0, 0x1, 0xe9, 0x6f, 0x1],
+    Packet::OperationRequest(Operation::LeaveRequest { is_inactive: Some(true) }, ParameterOrder::default())
+);
+
+gen_test!(
+    leave_response,
+    Direction::Recv,
+    vec![0xf3, 0x3, 0xfe, 0x0, 0x0, 0x2a, 0x0, 0x0],
+    Packet::OperationResponse {
+        parameters: Operation::LeaveResponse,
+        return_code: 0,
+        debug_string: None,
+        secret: None,
+        order: ParameterOrder::default()
+    }
+);
+
+gen_test!(
+    init_encryption_request,
+    Direction::Send,
+    vec![0xf3, 0x6, 0x0, 0x0, 0x1, 0x1, 0x78, 0x0, 0x0, 0x0, 0x3, 0x1, 0x2, 0x3],
+    Packet::InternalOperationRequest(
+        InternalOperation::InitEncryptionRequest {
+            public_key: vec![0x1, 0x2, 0x3]
+        },
+        ParameterOrder::default()
+    )
+);
+
+gen_test!(
+    init_encryption_response,
+    Direction::Recv,
+    vec![0xf3, 0x7, 0x0, 0x0, 0x0, 0x2a, 0x0, 0x1, 0x1, 0x78, 0x0, 0x0, 0x0, 0x3, 0x4, 0x5, 0x6],
+    Packet::InternalOperationResponse(
+        InternalOperation::InitEncryptionResponse {
+            public_key: vec![0x4, 0x5, 0x6]
+        },
+        0,
+        None,
+        ParameterOrder::default()
+    )
+);
+
+gen_test!(
+    exchange_keys_for_encryption_request,
+    Direction::Send,
+    vec![0xf3, 0x2, 0xfa, 0x0, 0x1, 0x1, 0x78, 0x0, 0x0, 0x0, 0x3, 0x1, 0x2, 0x3],
+    Packet::OperationRequest(
+        Operation::ExchangeKeysForEncryptionRequest {
+            public_key: vec![0x1, 0x2, 0x3]
+        },
+        ParameterOrder::default()
+    )
+);
+
+gen_test!(
+    exchange_keys_for_encryption_response,
+    Direction::Recv,
+    vec![0xf3, 0x3, 0xfa, 0x0, 0x0, 0x2a, 0x0, 0x1, 0x1, 0x78, 0x0, 0x0, 0x0, 0x3, 0x4, 0x5, 0x6],
+    Packet::OperationResponse {
+        parameters: Operation::ExchangeKeysForEncryptionResponse {
+            public_key: vec![0x4, 0x5, 0x6]
+        },
+        return_code: 0,
+        debug_string: None,
+        secret: None,
+        order: ParameterOrder::default(),
+    }
+);
+
+#[test]
+fn into_owned_outlives_data() {
+    let mut history: Vec<Packet<'static>> = Vec::new();
+    {
+        let data = vec![
+            0xf3, 0x3, 0xe6, 0x0, 0x0, 0x73, 0x0, 0x3, 0x61, 0x62, 0x63, 0x0, 0x1, 0xe6, 0x73, 0x0, 0x1, 0x78,
+        ];
+        let packet = Packet::read(data.as_slice(), Direction::Recv).expect("Failure while reading packet from bytes");
+        history.push(packet.into_owned());
+    }
+
+    match &history[0] {
+        Packet::OperationResponse {
+            parameters: Operation::AuthenticateResponseName { address, .. },
+            debug_string: Some(debug_string),
+            ..
+        } => {
+            assert_eq!(address, "x");
+            assert_eq!(debug_string, "abc");
+        }
+        other => panic!("Unexpected packet {:?}", other),
+    }
+}
+
+#[test]
+fn create_game_request_game_from_builder() {
+    let packet = Packet::OperationRequest(
+        Operation::CreateGameRequestGame {
+            room_name: Some("abc".into()),
+            lobby_name: None,
+            lobby_type: None,
+            expected_users: vec![],
+            player_properties: None,
+            broadcast: None,
+            game_properties: RoomOptions::new().with_max_players(12),
+            player_ttl: 0,
+            empty_room_ttl: 0,
+            plugins: None,
+            room_option_flags: RoomOptionsFlags::empty(),
+        },
+        ParameterOrder::default(),
+    );
+
+    let data = packet.clone().into_vec().expect("Failure while turning packet into bytes");
+    assert_eq!(
+        Packet::read(data.as_slice(), Direction::Send).expect("Failure while reading packet back"),
+        packet
+    );
+}
+
+#[test]
+fn display_compact() {
+    let packet = Packet::InternalOperationResponse(
+        InternalOperation::PingResponse {
+            local_time: 11601,
+            server_time: -1836592187,
+        },
+        0,
+        None,
+        ParameterOrder::default(),
+    );
+
+    assert_eq!(
+        packet.to_string(),
+        "InternalOperationResponse 1 Ping, return code 0 { 1 LocalTime: Integer(11601), 2 ServerTime: Integer(-1836592187) }"
+    );
+}
+
+#[test]
+fn display_multiline() {
+    let packet = Packet::OperationRequest(
+        Operation::SetPropertiesActor {
+            actor_nr: 1,
+            properties: indexmap! {
+                ProtocolValue::Byte(ActorProperties::PlayerName) => ProtocolValue::String("HoLLy".into()),
+                ProtocolValue::String("rank".into()) => ProtocolValue::Integer(3),
+            },
+            expected_properties: None,
+            broadcast: true,
+            event_forward: false,
+        },
+        ParameterOrder::default(),
+    );
+
+    assert_eq!(
+        format!("{:#}", packet),
+        r#"OperationRequest 252 SetProperties
+  250 Broadcast: Bool(true)
+  254 ActorNr: Integer(1)
+  251 Properties: Hashtable(2)
+    Byte(255) PlayerName: String("HoLLy")
+    String("rank"): Integer(3)"#
+    );
+}
+
+#[test]
+fn display_game_properties() {
+    let packet = PhotonPacket::Event(
+        230,
+        indexmap! {
+            ParameterCode::GameList => ProtocolValue::Hashtable(indexmap! {
+                ProtocolValue::String("room".into()) => ProtocolValue::Hashtable(indexmap! {
+                    ProtocolValue::Byte(GamePropertyKey::MaxPlayers) => ProtocolValue::Byte(12),
+                }),
+            }),
+        },
+    );
+
+    assert_eq!(
+        packet.display_with(&Names).to_string(),
+        r#"Event 230 GameList { 222 GameList: Hashtable(1) { String("room"): Hashtable(1) { Byte(255) MaxPlayers: Byte(12) } } }"#
+    );
+}
+
+#[test]
+fn hex_dump() {
+    let data = vec![0xf3, 0x6, 0x1, 0x0, 0x1, 0x1, 0x69, 0x0, 0x0, 0x2d, 0x51];
+    assert_eq!(
+        Packet::hex_dump(&data, Protocol::GpBinaryV16).unwrap(),
+        "\
+0000  f3                        magic
+0001  06                        InternalOperationRequest
+0002  01                        code 1 Ping
+0003  00 01                     1 parameters
+0005  01                          1 LocalTime
+0006  69 00 00 2d 51                Integer(11601)
+"
+    );
+}
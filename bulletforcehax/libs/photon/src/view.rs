@@ -0,0 +1,105 @@
+use super::*;
+
+/// A frame read as far as possible, so it can still be inspected and changed when it is not fully supported.
+///
+/// ```rust
+/// # use photon::*;
+/// # use photon_core::{PhotonPacket, ProtocolValue};
+/// // a ChangeGroups request that removes group 1, which is not implemented as an `Operation`
+/// let data = [0xf3, 0x2, 0xf8, 0x0, 0x1, 0xef, 0x78, 0x0, 0x0, 0x0, 0x1, 0x1];
+/// let mut view = PacketView::read(&data, Direction::Send, Protocol::GpBinaryV16);
+/// assert!(view.packet().is_none());
+///
+/// let params = view.parameters_mut().unwrap();
+/// assert_eq!(params.get(&239), Some(&ProtocolValue::ByteArray(vec![1])));
+/// params.insert(239, ProtocolValue::ByteArray(vec![2]));
+///
+/// assert_eq!(
+///     view.into_vec_with_protocol(Protocol::GpBinaryV16).unwrap(),
+///     [0xf3, 0x2, 0xf8, 0x0, 0x1, 0xef, 0x78, 0x0, 0x0, 0x0, 0x1, 0x2]
+/// );
+/// ```
+#[derive(Debug)]
+pub enum PacketView<'a> {
+    /// The frame was fully read
+    Packet(Packet<'a>),
+    /// The frame was read into raw parameters, but not into a `Packet`, such as for operations that are not implemented. Contains
+    /// the reason it could not be read as a `Packet`.
+    Raw(PhotonPacket<'a>, PacketReadError),
+    /// The frame could not be read at all. Contains the original bytes, which are written back as-is.
+    Invalid(Cow<'a, [u8]>, PacketReadError),
+}
+
+impl<'a> PacketView<'a> {
+    pub fn read(data: &'a [u8], direction: Direction, protocol: Protocol) -> PacketView<'a> {
+        let photon_packet = match PhotonPacket::read(data, protocol) {
+            Ok(photon_packet) => photon_packet,
+            Err(error) => return PacketView::Invalid(Cow::Borrowed(data), error.into()),
+        };
+
+        match Packet::from_photon_packet(photon_packet, direction) {
+            Ok(packet) => PacketView::Packet(packet),
+            // reading it again is cheaper than cloning every packet in case this fails
+            Err(error) => match PhotonPacket::read(data, protocol) {
+                Ok(photon_packet) => PacketView::Raw(photon_packet, error),
+                Err(_) => PacketView::Invalid(Cow::Borrowed(data), error),
+            },
+        }
+    }
+
+    pub fn into_owned(self) -> PacketView<'static> {
+        match self {
+            PacketView::Packet(packet) => PacketView::Packet(packet.into_owned()),
+            PacketView::Raw(photon_packet, error) => PacketView::Raw(photon_packet.into_owned(), error),
+            PacketView::Invalid(data, error) => PacketView::Invalid(Cow::Owned(data.into_owned()), error),
+        }
+    }
+
+    pub fn packet(&self) -> Option<&Packet<'a>> {
+        match self {
+            PacketView::Packet(packet) => Some(packet),
+            _ => None,
+        }
+    }
+
+    pub fn packet_mut(&mut self) -> Option<&mut Packet<'a>> {
+        match self {
+            PacketView::Packet(packet) => Some(packet),
+            _ => None,
+        }
+    }
+
+    /// The parameters of a raw packet, if it has any. These are not available for a `Packet`, use `packet_mut` instead.
+    pub fn parameters_mut(&mut self) -> Option<&mut ParameterTable<'a>> {
+        match self {
+            PacketView::Raw(PhotonPacket::OperationRequest(_, params), _)
+            | PacketView::Raw(PhotonPacket::OperationResponse(_, params, _, _), _)
+            | PacketView::Raw(PhotonPacket::Event(_, params), _)
+            | PacketView::Raw(PhotonPacket::InternalOperationRequest(_, params), _)
+            | PacketView::Raw(PhotonPacket::InternalOperationResponse(_, params, _, _), _) => Some(params),
+            _ => None,
+        }
+    }
+
+    /// Why the frame could not be read as a `Packet`, if it could not
+    pub fn error(&self) -> Option<&PacketReadError> {
+        match self {
+            PacketView::Packet(_) => None,
+            PacketView::Raw(_, error) | PacketView::Invalid(_, error) => Some(error),
+        }
+    }
+
+    pub fn into_vec_with_protocol(self, protocol: Protocol) -> PacketWriteResult<Vec<u8>> {
+        match self {
+            PacketView::Packet(packet) => packet.into_vec_with_protocol(protocol),
+            PacketView::Raw(photon_packet, _) => Ok(photon_packet.write(protocol)?),
+            PacketView::Invalid(data, _) => Ok(data.into_owned()),
+        }
+    }
+}
+
+impl<'a> From<Packet<'a>> for PacketView<'a> {
+    fn from(packet: Packet<'a>) -> Self {
+        PacketView::Packet(packet)
+    }
+}
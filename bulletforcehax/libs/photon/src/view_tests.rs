@@ -0,0 +1,58 @@
+#![cfg(test)]
+
+use super::*;
+
+const PING: [u8; 11] = [0xf3, 0x6, 0x1, 0x0, 0x1, 0x1, 0x69, 0x0, 0x0, 0x2d, 0x51];
+/// A ChangeGroups request that removes group 1, which is not implemented as an `Operation`
+const CHANGE_GROUPS: [u8; 12] = [0xf3, 0x2, 0xf8, 0x0, 0x1, 0xef, 0x78, 0x0, 0x0, 0x0, 0x1, 0x1];
+
+#[test]
+fn packet() {
+    let view = PacketView::read(&PING, Direction::Send, Protocol::GpBinaryV16);
+    assert_eq!(
+        view.packet(),
+        Some(&Packet::InternalOperationRequest(
+            InternalOperation::PingRequest { local_time: 11601 },
+            ParameterOrder::default()
+        ))
+    );
+    assert!(view.error().is_none());
+    assert_eq!(view.into_vec_with_protocol(Protocol::GpBinaryV16).unwrap(), PING);
+}
+
+#[test]
+fn raw() {
+    let mut view = PacketView::read(&CHANGE_GROUPS, Direction::Send, Protocol::GpBinaryV16).into_owned();
+    assert!(view.packet_mut().is_none());
+    assert!(matches!(
+        view.error(),
+        Some(PacketReadError::UnimplementedOperationType(Operation::ChangeGroups))
+    ));
+    assert_eq!(
+        view.to_string(),
+        "OperationRequest 248 ChangeGroups { 239 Remove/PublishUserId: ByteArray(1) [01] } (UnimplementedOperationType(ChangeGroups))"
+    );
+
+    view.parameters_mut().unwrap().shift_remove(&239);
+    assert_eq!(view.into_vec_with_protocol(Protocol::GpBinaryV16).unwrap(), [0xf3, 0x2, 0xf8, 0x0, 0x0]);
+}
+
+#[test]
+fn invalid() {
+    let mut view = PacketView::read(&[0x12, 0x34], Direction::Recv, Protocol::GpBinaryV16);
+    assert!(view.parameters_mut().is_none());
+    assert!(matches!(
+        view.error(),
+        Some(PacketReadError::PhotonError(PhotonReadError::InvalidMagic(0x12)))
+    ));
+    assert_eq!(view.into_vec_with_protocol(Protocol::GpBinaryV16).unwrap(), [0x12, 0x34]);
+}
+
+#[test]
+fn raw_empty_array() {
+    // an unknown operation with an empty `int[]`, which keeps its type
+    let data = [0xf3, 0x2, 0xc8, 0x0, 0x1, 0xf5, 0x79, 0x0, 0x0, 0x69];
+    let view = PacketView::read(&data, Direction::Send, Protocol::GpBinaryV16);
+    assert!(matches!(view, PacketView::Raw(PhotonPacket::OperationRequest(200, _), _)));
+    assert_eq!(view.into_vec_with_protocol(Protocol::GpBinaryV16).unwrap(), data);
+}
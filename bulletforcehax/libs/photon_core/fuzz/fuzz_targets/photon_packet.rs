@@ -0,0 +1,10 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use photon_core::{PhotonPacket, Protocol};
+use std::convert::TryFrom;
+
+// Reading may fail, but must never panic
+fuzz_target!(|data: &[u8]| {
+    let _ = PhotonPacket::try_from(data);
+    let _ = PhotonPacket::read(data, Protocol::GpBinaryV18);
+});
@@ -11,8 +11,31 @@ pub enum PhotonReadError {
     UnknownProtocolValueType(u8),
     UnimplementedPacketType(u8),
     UnimplementedProtocolValueType(ProtocolValue<'static>),
+    /// The protocol value type exists, but cannot be represented by this protocol.
+    UnsupportedProtocolValueType(Protocol, u8),
     InvalidDebugStringType,
+    /// A variable-length integer was longer than the integer type it encodes.
+    InvalidCompressedInteger,
     CustomTypeInvalidLength,
+    /// A length prefix points past the end of the data.
+    UnexpectedEof {
+        needed: usize,
+        available: usize,
+    },
+    /// A hashtable or dictionary key is a collection, which can not be used as a key.
+    UnhashableKey,
+    /// Values are nested deeper than [`ReadLimits::max_depth`]. Contains the depth that was reached.
+    ///
+    /// [`ReadLimits::max_depth`]: struct.ReadLimits.html#structfield.max_depth
+    DepthLimitExceeded(usize),
+    /// A collection has more items than [`ReadLimits::max_count`]. Contains the item count.
+    ///
+    /// [`ReadLimits::max_count`]: struct.ReadLimits.html#structfield.max_count
+    CountLimitExceeded(usize),
+    /// Deserializing would allocate more than [`ReadLimits::max_allocation`]. Contains the amount of bytes.
+    ///
+    /// [`ReadLimits::max_allocation`]: struct.ReadLimits.html#structfield.max_allocation
+    AllocationLimitExceeded(usize),
     IOError(std::io::Error),
     EncodingError(std::str::Utf8Error),
 }
@@ -33,6 +56,17 @@ impl From<std::str::Utf8Error> for PhotonReadError {
 #[derive(Debug)]
 pub enum PhotonWriteError {
     UnimplementedProtocolValueType(ProtocolValue<'static>),
+    /// The value cannot be represented by this protocol. Contains the protocol 1.6 type of the value.
+    UnsupportedProtocolValueType(Protocol, u8),
+    /// A key or value of a typed `Dictionary` does not have the type of the dictionary. Contains the protocol 1.6 type of the
+    /// dictionary and of the value.
+    DictionaryTypeMismatch(u8, u8),
+    /// The elements of an `Array` do not all have the same type. Contains the protocol 1.6 type of the first element and of the
+    /// element that differs.
+    ArrayTypeMismatch(u8, u8),
+    /// A parameter table, or a value such as a string or array, has more entries or bytes than its length can hold. Contains the
+    /// number of entries or bytes.
+    TooManyParameters(usize),
     IOError(std::io::Error),
 }
 
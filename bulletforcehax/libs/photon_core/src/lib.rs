@@ -1,6 +1,9 @@
 //! This crate handles the core serializing and deserializing of bytes to a [`PhotonPacket`], which in turn indirectly
 //! contains [`ProtocolValue`]s . The core type is [`PhotonPacket`], which always contains at least a `u8` type and
-//! `HashMap<u8, ProtocolValue>` parameters.
+//! `IndexMap<u8, ProtocolValue>` parameters.
+//!
+//! Parameter tables, hashtables and dictionaries keep their entries in the order they were read in, so serializing an
+//! unmodified packet gives back the exact bytes it was read from.
 //!
 //! [`PhotonPacket`]: enum.PhotonPacket.html
 //! [`ProtocolValue`]: enum.ProtocolValue.html
@@ -9,10 +12,10 @@
 //!
 //! ## Serializing a packet
 //! ```rust
-//! # use std::collections::HashMap;
+//! # use indexmap::IndexMap;
 //! # use photon_core::*;
 //! # use std::convert::TryInto;
-//! let mut params = HashMap::new();
+//! let mut params = IndexMap::new();
 //! params.insert(0x42, ProtocolValue::Float(13.37));
 //! let packet = PhotonPacket::OperationRequest(0x20, params);
 //!
@@ -22,7 +25,7 @@
 //!
 //! ## Deserializing a packet
 //! ```rust
-//! # use std::collections::HashMap;
+//! # use indexmap::IndexMap;
 //! # use photon_core::*;
 //! # use std::convert::TryFrom;
 //! let bytes = vec![0xF3, 0x02, 0x20, 0, 0x01, 0x42, 0x66, 0x41, 0x55, 0xeb, 0x85];
@@ -37,32 +40,103 @@
 //! }
 //! ```
 
-use std::collections::HashMap;
+use indexmap::IndexMap;
+use std::borrow::Cow;
 use std::hash::{Hash, Hasher};
 
 mod errors;
 pub use errors::*;
 mod photon_impl;
+mod pretty;
+pub use pretty::*;
+mod pretty_tests;
 mod read_write;
+#[cfg(feature = "serde")]
+pub mod serde_pairs;
+
+/// Parameters of a packet, in the order they are serialized in.
+pub type ParameterTable<'a> = IndexMap<u8, ProtocolValue<'a>>;
 
-type ParameterTable<'a> = HashMap<u8, ProtocolValue<'a>>;
+/// The serialization protocol used for packets. Both protocols map to the same [`ProtocolValue`]s.
+///
+/// [`ProtocolValue`]: enum.ProtocolValue.html
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Protocol {
+    /// Protocol 1.6, big-endian with fixed-size lengths. This is the default.
+    #[default]
+    GpBinaryV16,
+    /// Protocol 1.8, little-endian with variable-length integers.
+    GpBinaryV18,
+}
+
+impl Protocol {
+    /// Detects the protocol from the version in an `Init` packet, which is sent at the start of each connection.
+    ///
+    /// Returns `None` if the data is not an `Init` packet or the version is not known.
+    pub fn detect(data: &[u8]) -> Option<Protocol> {
+        match data {
+            [0xF3, 0, 1, 6, ..] => Some(Protocol::GpBinaryV16),
+            [0xF3, 0, 1, 8, ..] => Some(Protocol::GpBinaryV18),
+            _ => None,
+        }
+    }
+}
+
+/// Limits that protect against malicious or corrupted packets while deserializing.
+///
+/// Lengths are always checked against the remaining data, so these only matter for values that take little or no space
+/// in the serialized data but a lot of memory after deserializing, such as deeply nested or large arrays of `null`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ReadLimits {
+    /// How deep values may be nested in each other.
+    pub max_depth: usize,
+    /// The maximum amount of items in a single array, hashtable, dictionary or parameter table. Does not apply to the
+    /// length of strings and byte arrays.
+    pub max_count: usize,
+    /// The maximum amount of bytes that may be allocated for the values in a single packet. This is an estimate.
+    pub max_allocation: usize,
+}
+
+impl Default for ReadLimits {
+    fn default() -> Self {
+        ReadLimits {
+            max_depth: 32,
+            max_count: 0x10000,
+            max_allocation: 16 * 1024 * 1024,
+        }
+    }
+}
 
 /// A parsed packet. Can be read/written using the `TryInto` and `TryFrom` methods.
-#[derive(Debug)]
+///
+/// Strings and raw data borrow from the deserialized bytes. Use [`into_owned`] to keep the packet around longer.
+///
+/// [`into_owned`]: #method.into_owned
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PhotonPacket<'a> {
-    // Init,
-    // InitResponse,
+    /// Connection handshake sent by the client. Contains the raw init data, such as protocol version and app id.
+    Init(Cow<'a, [u8]>),
+    /// Response to `Init`, sent by the server. Contains the raw response data.
+    InitResponse(Cow<'a, [u8]>),
     OperationRequest(u8, ParameterTable<'a>),
-    OperationResponse(u8, ParameterTable<'a>, i16, Option<&'a str>),
+    OperationResponse(u8, ParameterTable<'a>, i16, Option<Cow<'a, str>>),
     Event(u8, ParameterTable<'a>),
     InternalOperationRequest(u8, ParameterTable<'a>),
-    InternalOperationResponse(u8, ParameterTable<'a>, i16, Option<&'a str>),
-    // Message,
-    // RawMessage,
+    InternalOperationResponse(u8, ParameterTable<'a>, i16, Option<Cow<'a, str>>),
+    /// A single serialized value, sent outside of operations and events.
+    Message(ProtocolValue<'a>),
+    /// Unserialized bytes, sent outside of operations and events.
+    RawMessage(Cow<'a, [u8]>),
 }
 
 /// A deserialized Photon value, converted to its Rust equivalent.
-#[derive(Debug, PartialEq)]
+///
+/// With the `serde` feature, values are serialized with their variant as tag (eg. `{"Short": 5}`) so the exact protocol
+/// type survives a round trip. Hashtables and dictionaries are written as a list of `[key, value]` pairs.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ProtocolValue<'a> {
     Null(),
     Bool(bool),
@@ -72,29 +146,46 @@ pub enum ProtocolValue<'a> {
     Long(i64),
     Float(f32),
     Double(f64),
-    String(&'a str),
-    OperationRequest,
-    OperationResponse,
-    EventData,
+    String(Cow<'a, str>),
+    /// A nested operation request, with its operation code and parameters.
+    OperationRequest(u8, ParameterTable<'a>),
+    /// A nested operation response, with its operation code, parameters, return code and debug message.
+    OperationResponse(u8, ParameterTable<'a>, i16, Option<Cow<'a, str>>),
+    /// A nested event, with its event code and parameters.
+    EventData(u8, ParameterTable<'a>),
     /// array of predetermined type, `Array` in C#.
-    Array(Vec<ProtocolValue<'a>>),
+    ///
+    /// Contains the protocol 1.6 type of the elements, so an empty array keeps its type. A type of `42` is used for arrays
+    /// that do not have a single type, such as empty arrays of nulls.
+    Array(u8, Vec<ProtocolValue<'a>>),
     /// array of arbitrary types, `List<object>` in C#
     ObjectArray(Vec<ProtocolValue<'a>>),
     ByteArray(Vec<u8>),
-    StringArray(Vec<&'a str>),
+    StringArray(Vec<Cow<'a, str>>),
     IntegerArray(Vec<i32>),
     /// hashmap of predefined types, `IDictionary` or `Dictionary<T1, T2>` in C#
-    Dictionary,
+    ///
+    /// `key_type` and `value_type` are the protocol types of the keys and values. A type of `0` or `42` means `object`,
+    /// in which case each key or value carries its own type.
+    Dictionary {
+        key_type: u8,
+        value_type: u8,
+        #[cfg_attr(feature = "serde", serde(with = "serde_pairs"))]
+        entries: IndexMap<ProtocolValue<'a>, ProtocolValue<'a>>,
+    },
     /// hashmap of arbitrary types, `Hashtable` or `Dictionary<object, object>` in C#
     ///
-    /// # Panics
-    /// This will panic if a key is not hashable, i.e. collections or custom data.
-    Hashtable(HashMap<ProtocolValue<'a>, ProtocolValue<'a>>),
+    /// Keys can not be collections. Deserializing a hashtable or dictionary with such a key returns
+    /// [`PhotonReadError::UnhashableKey`].
+    ///
+    /// [`PhotonReadError::UnhashableKey`]: enum.PhotonReadError.html#variant.UnhashableKey
+    Hashtable(#[cfg_attr(feature = "serde", serde(with = "serde_pairs"))] IndexMap<ProtocolValue<'a>, ProtocolValue<'a>>),
     Custom(CustomType),
 }
 
 /// A non-primitive serializable value.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CustomType {
     Vector2(f32, f32),
     Vector3(f32, f32, f32),
@@ -110,20 +201,127 @@ pub enum CustomType {
 // this may not work, I'm not sure yet
 impl Eq for ProtocolValue<'_> {}
 
-// really annoying and ugly hack, but I wouldn't know how to fix it
+// Floats are hashed by their bits with -0.0 as 0.0, and collections only by their length, so that equal values always have the same
+// hash. NaN is never equal to itself, so its hash does not matter.
+// Collections are rejected as hashtable keys while reading, but can still end up in a `HashSet` or similar.
 impl Hash for ProtocolValue<'_> {
     fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
         match self {
-            ProtocolValue::Null() => 0.hash(state),
+            ProtocolValue::Null() => (),
             ProtocolValue::Bool(x) => x.hash(state),
             ProtocolValue::Byte(x) => x.hash(state),
             ProtocolValue::Short(x) => x.hash(state),
             ProtocolValue::Integer(x) => x.hash(state),
             ProtocolValue::Long(x) => x.hash(state),
-            ProtocolValue::Float(x) => unsafe { std::mem::transmute::<f32, u32>(*x) }.hash(state),
-            ProtocolValue::Double(x) => unsafe { std::mem::transmute::<f64, u64>(*x) }.hash(state),
+            ProtocolValue::Float(x) => float_bits(*x).hash(state),
+            ProtocolValue::Double(x) => double_bits(*x).hash(state),
             ProtocolValue::String(x) => x.hash(state),
-            _ => panic!("Tried to hash {:?}", self),
+            ProtocolValue::OperationRequest(code, params) | ProtocolValue::EventData(code, params) => {
+                code.hash(state);
+                params.len().hash(state);
+            }
+            ProtocolValue::OperationResponse(code, params, return_code, _) => {
+                code.hash(state);
+                params.len().hash(state);
+                return_code.hash(state);
+            }
+            ProtocolValue::Array(_, x) | ProtocolValue::ObjectArray(x) => x.hash(state),
+            ProtocolValue::ByteArray(x) => x.hash(state),
+            ProtocolValue::StringArray(x) => x.hash(state),
+            ProtocolValue::IntegerArray(x) => x.hash(state),
+            ProtocolValue::Dictionary { entries, .. } => entries.len().hash(state),
+            ProtocolValue::Hashtable(x) => x.len().hash(state),
+            ProtocolValue::Custom(x) => x.hash(state),
+        }
+    }
+}
+
+impl Hash for CustomType {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            CustomType::Vector2(x, y) => [x, y].iter().for_each(|f| float_bits(**f).hash(state)),
+            CustomType::Vector3(x, y, z) => [x, y, z].iter().for_each(|f| float_bits(**f).hash(state)),
+            CustomType::Quaternion(x, y, z, w) => [x, y, z, w].iter().for_each(|f| float_bits(**f).hash(state)),
+            CustomType::Player(x) => x.hash(state),
+            CustomType::Custom { id, data } => {
+                id.hash(state);
+                data.hash(state);
+            }
         }
     }
 }
+
+/// The bits of a float, with -0.0 as 0.0 since they are equal
+fn float_bits(x: f32) -> u32 {
+    if x == 0.0 {
+        0
+    } else {
+        x.to_bits()
+    }
+}
+
+fn double_bits(x: f64) -> u64 {
+    if x == 0.0 {
+        0
+    } else {
+        x.to_bits()
+    }
+}
+
+impl ProtocolValue<'_> {
+    /// The protocol 1.6 type code of this value, as used for the key and value types of `Dictionary`.
+    pub fn type_code(&self) -> u8 {
+        read_write::get_value_type(self)
+    }
+
+    /// Converts this value into one that does not borrow from the deserialized data, copying strings where needed.
+    pub fn into_owned(self) -> ProtocolValue<'static> {
+        match self {
+            ProtocolValue::Null() => ProtocolValue::Null(),
+            ProtocolValue::Bool(x) => ProtocolValue::Bool(x),
+            ProtocolValue::Byte(x) => ProtocolValue::Byte(x),
+            ProtocolValue::Short(x) => ProtocolValue::Short(x),
+            ProtocolValue::Integer(x) => ProtocolValue::Integer(x),
+            ProtocolValue::Long(x) => ProtocolValue::Long(x),
+            ProtocolValue::Float(x) => ProtocolValue::Float(x),
+            ProtocolValue::Double(x) => ProtocolValue::Double(x),
+            ProtocolValue::String(x) => ProtocolValue::String(Cow::Owned(x.into_owned())),
+            ProtocolValue::OperationRequest(code, params) => ProtocolValue::OperationRequest(code, table_into_owned(params)),
+            ProtocolValue::OperationResponse(code, params, return_code, debug_string) => ProtocolValue::OperationResponse(
+                code,
+                table_into_owned(params),
+                return_code,
+                debug_string.map(|x| Cow::Owned(x.into_owned())),
+            ),
+            ProtocolValue::EventData(code, params) => ProtocolValue::EventData(code, table_into_owned(params)),
+            ProtocolValue::Array(t, x) => ProtocolValue::Array(t, x.into_iter().map(ProtocolValue::into_owned).collect()),
+            ProtocolValue::ObjectArray(x) => ProtocolValue::ObjectArray(x.into_iter().map(ProtocolValue::into_owned).collect()),
+            ProtocolValue::ByteArray(x) => ProtocolValue::ByteArray(x),
+            ProtocolValue::StringArray(x) => ProtocolValue::StringArray(x.into_iter().map(|x| Cow::Owned(x.into_owned())).collect()),
+            ProtocolValue::IntegerArray(x) => ProtocolValue::IntegerArray(x),
+            ProtocolValue::Dictionary {
+                key_type,
+                value_type,
+                entries,
+            } => ProtocolValue::Dictionary {
+                key_type,
+                value_type,
+                entries: map_into_owned(entries),
+            },
+            ProtocolValue::Hashtable(x) => ProtocolValue::Hashtable(map_into_owned(x)),
+            ProtocolValue::Custom(x) => ProtocolValue::Custom(x),
+        }
+    }
+}
+
+/// Converts a parameter table into one that does not borrow from the deserialized data.
+pub fn table_into_owned(table: ParameterTable) -> ParameterTable<'static> {
+    table.into_iter().map(|(key, value)| (key, value.into_owned())).collect()
+}
+
+/// Converts a hashtable or dictionary into one that does not borrow from the deserialized data.
+pub fn map_into_owned(map: IndexMap<ProtocolValue, ProtocolValue>) -> IndexMap<ProtocolValue<'static>, ProtocolValue<'static>> {
+    map.into_iter().map(|(key, value)| (key.into_owned(), value.into_owned())).collect()
+}
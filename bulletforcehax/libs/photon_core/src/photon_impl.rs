@@ -1,30 +1,134 @@
 use super::*;
-use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
-use read_write::{read_debug_string, read_parameter_table, write_debug_string, write_parameter_table};
+use byteorder::{BigEndian, LittleEndian, ReadBytesExt, WriteBytesExt};
+use read_write::v18;
+use read_write::{CollectionHeader, Reader};
 use std::convert::{TryFrom, TryInto};
-use std::io::Cursor;
+use std::io::Write;
+
+impl Protocol {
+    pub(crate) fn read_value<'a>(self, c: &mut Reader<'a>) -> PhotonReadResult<ProtocolValue<'a>> {
+        match self {
+            Protocol::GpBinaryV16 => read_write::read_value(c),
+            Protocol::GpBinaryV18 => v18::read_value(c),
+        }
+    }
+
+    fn read_parameter_table<'a>(self, c: &mut Reader<'a>) -> PhotonReadResult<ParameterTable<'a>> {
+        match self {
+            Protocol::GpBinaryV16 => read_write::read_parameter_table(c),
+            Protocol::GpBinaryV18 => v18::read_parameter_table(c),
+        }
+    }
+
+    pub(crate) fn read_return_code(self, c: &mut Reader) -> PhotonReadResult<i16> {
+        match self {
+            Protocol::GpBinaryV16 => Ok(c.read_i16::<BigEndian>()?),
+            Protocol::GpBinaryV18 => Ok(c.read_i16::<LittleEndian>()?),
+        }
+    }
+
+    pub(crate) fn read_debug_string<'a>(self, c: &mut Reader<'a>) -> PhotonReadResult<Option<Cow<'a, str>>> {
+        match self {
+            Protocol::GpBinaryV16 => read_write::read_debug_string(c),
+            Protocol::GpBinaryV18 => v18::read_debug_string(c),
+        }
+    }
+
+    pub(crate) fn read_parameter_count(self, c: &mut Reader) -> PhotonReadResult<usize> {
+        match self {
+            Protocol::GpBinaryV16 => read_write::read_parameter_count(c),
+            Protocol::GpBinaryV18 => v18::read_parameter_count(c),
+        }
+    }
+
+    pub(crate) fn read_collection_header(self, c: &mut Reader) -> PhotonReadResult<Option<CollectionHeader>> {
+        match self {
+            Protocol::GpBinaryV16 => read_write::read_collection_header(c),
+            Protocol::GpBinaryV18 => v18::read_collection_header(c),
+        }
+    }
+
+    fn write_value(self, c: &mut dyn Write, value: ProtocolValue) -> PhotonWriteResult<()> {
+        match self {
+            Protocol::GpBinaryV16 => read_write::write_value_of_type(c, value),
+            Protocol::GpBinaryV18 => v18::write_value_of_type(c, value),
+        }
+    }
+
+    fn write_parameter_table(self, c: &mut dyn Write, params: ParameterTable) -> PhotonWriteResult<()> {
+        match self {
+            Protocol::GpBinaryV16 => read_write::write_parameter_table(c, params),
+            Protocol::GpBinaryV18 => v18::write_parameter_table(c, params),
+        }
+    }
+
+    fn write_return_code(self, c: &mut dyn Write, return_code: i16) -> PhotonWriteResult<()> {
+        match self {
+            Protocol::GpBinaryV16 => Ok(c.write_i16::<BigEndian>(return_code)?),
+            Protocol::GpBinaryV18 => Ok(c.write_i16::<LittleEndian>(return_code)?),
+        }
+    }
+
+    fn write_debug_string(self, c: &mut dyn Write, debug_string: Option<&str>) -> PhotonWriteResult<()> {
+        match self {
+            Protocol::GpBinaryV16 => read_write::write_debug_string(c, debug_string),
+            Protocol::GpBinaryV18 => v18::write_debug_string(c, debug_string),
+        }
+    }
+}
 
 impl PhotonPacket<'_> {
     fn get_type(&self) -> u8 {
         match self {
-            // PhotonPacket::Init => 0,
-            // PhotonPacket::InitResponse => 1,
+            PhotonPacket::Init(_) => 0,
+            PhotonPacket::InitResponse(_) => 1,
             PhotonPacket::OperationRequest(_, _) => 2,
             PhotonPacket::OperationResponse(_, _, _, _) => 3,
             PhotonPacket::Event(_, _) => 4,
             PhotonPacket::InternalOperationRequest(_, _) => 6,
             PhotonPacket::InternalOperationResponse(_, _, _, _) => 7,
-            // PhotonPacket::Message => 8,
-            // PhotonPacket::RawMessage => 9,
+            PhotonPacket::Message(_) => 8,
+            PhotonPacket::RawMessage(_) => 9,
+        }
+    }
+
+    /// Converts this packet into one that does not borrow from the deserialized data, copying strings where needed.
+    pub fn into_owned(self) -> PhotonPacket<'static> {
+        match self {
+            PhotonPacket::Init(data) => PhotonPacket::Init(Cow::Owned(data.into_owned())),
+            PhotonPacket::InitResponse(data) => PhotonPacket::InitResponse(Cow::Owned(data.into_owned())),
+            PhotonPacket::OperationRequest(code, params) => PhotonPacket::OperationRequest(code, table_into_owned(params)),
+            PhotonPacket::OperationResponse(code, params, return_code, debug_string) => PhotonPacket::OperationResponse(
+                code,
+                table_into_owned(params),
+                return_code,
+                debug_string.map(|x| Cow::Owned(x.into_owned())),
+            ),
+            PhotonPacket::Event(code, params) => PhotonPacket::Event(code, table_into_owned(params)),
+            PhotonPacket::InternalOperationRequest(code, params) => PhotonPacket::InternalOperationRequest(code, table_into_owned(params)),
+            PhotonPacket::InternalOperationResponse(code, params, return_code, debug_string) => PhotonPacket::InternalOperationResponse(
+                code,
+                table_into_owned(params),
+                return_code,
+                debug_string.map(|x| Cow::Owned(x.into_owned())),
+            ),
+            PhotonPacket::Message(value) => PhotonPacket::Message(value.into_owned()),
+            PhotonPacket::RawMessage(data) => PhotonPacket::RawMessage(Cow::Owned(data.into_owned())),
         }
     }
 }
 
-impl<'s> TryFrom<&'s [u8]> for PhotonPacket<'s> {
-    type Error = PhotonReadError;
+impl<'s> PhotonPacket<'s> {
+    /// Deserializes a packet using the given protocol and the default [`ReadLimits`]. `TryFrom` always uses protocol 1.6.
+    ///
+    /// [`ReadLimits`]: struct.ReadLimits.html
+    pub fn read(data: &'s [u8], protocol: Protocol) -> PhotonReadResult<PhotonPacket<'s>> {
+        PhotonPacket::read_with_limits(data, protocol, ReadLimits::default())
+    }
 
-    fn try_from<'a>(data: &'a [u8]) -> PhotonReadResult<PhotonPacket<'a>> {
-        let c = &mut Cursor::new(data);
+    /// Deserializes a packet using the given protocol, returning an error instead of going over `limits`.
+    pub fn read_with_limits(data: &'s [u8], protocol: Protocol, limits: ReadLimits) -> PhotonReadResult<PhotonPacket<'s>> {
+        let c = &mut Reader::with_limits(data, limits);
         let magic = c.read_u8()?;
         if magic != 0xF3 {
             return Err(PhotonReadError::InvalidMagic(magic));
@@ -32,12 +136,14 @@ impl<'s> TryFrom<&'s [u8]> for PhotonPacket<'s> {
 
         let packet_type: u8 = c.read_u8()?;
         match packet_type {
-            2 => Ok(PhotonPacket::OperationRequest(c.read_u8()?, read_parameter_table(c)?)),
+            0 => Ok(PhotonPacket::Init(Cow::Borrowed(&data[c.position() as usize..]))),
+            1 => Ok(PhotonPacket::InitResponse(Cow::Borrowed(&data[c.position() as usize..]))),
+            2 => Ok(PhotonPacket::OperationRequest(c.read_u8()?, protocol.read_parameter_table(c)?)),
             3 => {
                 let operation_type = c.read_u8()?;
-                let return_code = c.read_i16::<BigEndian>()?;
-                let debug_string = read_debug_string(c)?;
-                let parameter_table = read_parameter_table(c)?;
+                let return_code = protocol.read_return_code(c)?;
+                let debug_string = protocol.read_debug_string(c)?;
+                let parameter_table = protocol.read_parameter_table(c)?;
                 Ok(PhotonPacket::OperationResponse(
                     operation_type,
                     parameter_table,
@@ -45,13 +151,13 @@ impl<'s> TryFrom<&'s [u8]> for PhotonPacket<'s> {
                     debug_string,
                 ))
             }
-            4 => Ok(PhotonPacket::Event(c.read_u8()?, read_parameter_table(c)?)),
-            6 => Ok(PhotonPacket::InternalOperationRequest(c.read_u8()?, read_parameter_table(c)?)),
+            4 => Ok(PhotonPacket::Event(c.read_u8()?, protocol.read_parameter_table(c)?)),
+            6 => Ok(PhotonPacket::InternalOperationRequest(c.read_u8()?, protocol.read_parameter_table(c)?)),
             7 => {
                 let operation_type = c.read_u8()?;
-                let return_code = c.read_i16::<BigEndian>()?;
-                let debug_string = read_debug_string(c)?;
-                let parameter_table = read_parameter_table(c)?;
+                let return_code = protocol.read_return_code(c)?;
+                let debug_string = protocol.read_debug_string(c)?;
+                let parameter_table = protocol.read_parameter_table(c)?;
                 Ok(PhotonPacket::InternalOperationResponse(
                     operation_type,
                     parameter_table,
@@ -59,16 +165,24 @@ impl<'s> TryFrom<&'s [u8]> for PhotonPacket<'s> {
                     debug_string,
                 ))
             }
-            0 | 1 | 8 | 9 => Err(PhotonReadError::UnimplementedPacketType(packet_type)),
+            8 => Ok(PhotonPacket::Message(protocol.read_value(c)?)),
+            9 => Ok(PhotonPacket::RawMessage(Cow::Borrowed(&data[c.position() as usize..]))),
             _ => Err(PhotonReadError::UnknownPacketType(packet_type)),
         }
     }
 }
 
-impl<'s> TryInto<Vec<u8>> for PhotonPacket<'s> {
-    type Error = PhotonWriteError;
+impl<'s> TryFrom<&'s [u8]> for PhotonPacket<'s> {
+    type Error = PhotonReadError;
 
-    fn try_into(self) -> PhotonWriteResult<Vec<u8>> {
+    fn try_from<'a>(data: &'a [u8]) -> PhotonReadResult<PhotonPacket<'a>> {
+        PhotonPacket::read(data, Protocol::GpBinaryV16)
+    }
+}
+
+impl PhotonPacket<'_> {
+    /// Serializes a packet using the given protocol. `TryInto` always uses protocol 1.6.
+    pub fn write(self, protocol: Protocol) -> PhotonWriteResult<Vec<u8>> {
         let mut vec = Vec::new();
         let writer = &mut vec;
 
@@ -76,32 +190,44 @@ impl<'s> TryInto<Vec<u8>> for PhotonPacket<'s> {
         writer.write_u8(self.get_type())?;
 
         match self {
+            PhotonPacket::Init(data) => writer.write_all(&data)?,
+            PhotonPacket::InitResponse(data) => writer.write_all(&data)?,
             PhotonPacket::OperationRequest(packet_type, params) => {
                 writer.write_u8(packet_type)?;
-                write_parameter_table(writer, params)?;
+                protocol.write_parameter_table(writer, params)?;
             }
             PhotonPacket::OperationResponse(packet_type, params, return_value, debug_string) => {
                 writer.write_u8(packet_type)?;
-                writer.write_i16::<BigEndian>(return_value)?;
-                write_debug_string(writer, debug_string)?;
-                write_parameter_table(writer, params)?;
+                protocol.write_return_code(writer, return_value)?;
+                protocol.write_debug_string(writer, debug_string.as_deref())?;
+                protocol.write_parameter_table(writer, params)?;
             }
             PhotonPacket::Event(packet_type, params) => {
                 writer.write_u8(packet_type)?;
-                write_parameter_table(writer, params)?;
+                protocol.write_parameter_table(writer, params)?;
             }
             PhotonPacket::InternalOperationRequest(packet_type, params) => {
                 writer.write_u8(packet_type)?;
-                write_parameter_table(writer, params)?;
+                protocol.write_parameter_table(writer, params)?;
             }
             PhotonPacket::InternalOperationResponse(packet_type, params, return_value, debug_string) => {
                 writer.write_u8(packet_type)?;
-                writer.write_i16::<BigEndian>(return_value)?;
-                write_debug_string(writer, debug_string)?;
-                write_parameter_table(writer, params)?;
+                protocol.write_return_code(writer, return_value)?;
+                protocol.write_debug_string(writer, debug_string.as_deref())?;
+                protocol.write_parameter_table(writer, params)?;
             }
+            PhotonPacket::Message(value) => protocol.write_value(writer, value)?,
+            PhotonPacket::RawMessage(data) => writer.write_all(&data)?,
         }
 
         Ok(vec)
     }
 }
+
+impl<'s> TryInto<Vec<u8>> for PhotonPacket<'s> {
+    type Error = PhotonWriteError;
+
+    fn try_into(self) -> PhotonWriteResult<Vec<u8>> {
+        self.write(Protocol::GpBinaryV16)
+    }
+}
@@ -0,0 +1,484 @@
+use super::*;
+use byteorder::ReadBytesExt;
+use read_write::{CollectionHeader, Reader};
+use std::fmt::{self, Display, Formatter, Write};
+
+/// Gives names to the codes in a packet, so they can be shown next to the numbers when printing it.
+///
+/// Every method returns `None` by default, in which case only the number is shown. The codes themselves are defined by
+/// the application, which is why this crate does not know them.
+pub trait CodeNames {
+    /// The name of the operation, event or internal operation code of `packet`.
+    fn packet_code(&self, _packet: &PhotonPacket) -> Option<&'static str> {
+        None
+    }
+
+    /// The name of parameter `code` in `packet`.
+    fn parameter(&self, _packet: &PhotonPacket, _code: u8) -> Option<&'static str> {
+        None
+    }
+
+    /// The name of a byte key in a hashtable that is (nested in) parameter `parameter`, such as a room or player property.
+    fn property(&self, _packet: &PhotonPacket, _parameter: u8, _key: u8) -> Option<&'static str> {
+        None
+    }
+}
+
+/// Does not name any code.
+pub struct NoNames;
+
+impl CodeNames for NoNames {}
+
+/// Shows a packet with the names of its codes. Created by [`PhotonPacket::display_with`].
+///
+/// Everything is printed on a single line, unless the alternate flag (`{:#}`) is used. In that case each parameter and
+/// each item in a hashtable or array is printed on its own, indented line.
+///
+/// [`PhotonPacket::display_with`]: enum.PhotonPacket.html#method.display_with
+pub struct PacketDisplay<'p, 'a> {
+    packet: &'p PhotonPacket<'a>,
+    names: &'p dyn CodeNames,
+}
+
+impl<'a> PhotonPacket<'a> {
+    /// Shows this packet using the names from `names`. Without names, the `Display` impl of the packet can be used.
+    pub fn display_with<'p>(&'p self, names: &'p dyn CodeNames) -> PacketDisplay<'p, 'a> {
+        PacketDisplay { packet: self, names }
+    }
+
+    /// The name of the packet type, such as `OperationRequest`.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            PhotonPacket::Init(_) => "Init",
+            PhotonPacket::InitResponse(_) => "InitResponse",
+            PhotonPacket::OperationRequest(_, _) => "OperationRequest",
+            PhotonPacket::OperationResponse(_, _, _, _) => "OperationResponse",
+            PhotonPacket::Event(_, _) => "Event",
+            PhotonPacket::InternalOperationRequest(_, _) => "InternalOperationRequest",
+            PhotonPacket::InternalOperationResponse(_, _, _, _) => "InternalOperationResponse",
+            PhotonPacket::Message(_) => "Message",
+            PhotonPacket::RawMessage(_) => "RawMessage",
+        }
+    }
+
+    /// The parameters of this packet, if it has any.
+    pub fn parameters(&self) -> Option<&ParameterTable<'a>> {
+        match self {
+            PhotonPacket::OperationRequest(_, params)
+            | PhotonPacket::OperationResponse(_, params, _, _)
+            | PhotonPacket::Event(_, params)
+            | PhotonPacket::InternalOperationRequest(_, params)
+            | PhotonPacket::InternalOperationResponse(_, params, _, _) => Some(params),
+            _ => None,
+        }
+    }
+
+    /// Deserializes `data` and lists the bytes of every header field, parameter, hashtable entry and array item next to
+    /// the value they decode into, using the names from `names`.
+    ///
+    /// ```text
+    /// 0000  f3                        magic
+    /// 0001  02                        OperationRequest
+    /// 0002  20                        code 32
+    /// 0003  00 01                     1 parameters
+    /// 0005  42                          66
+    /// 0006  66 41 55 eb 85                Float(13.37)
+    /// ```
+    pub fn hex_dump(data: &[u8], protocol: Protocol, names: &dyn CodeNames) -> PhotonReadResult<String> {
+        let packet = PhotonPacket::read(data, protocol)?;
+        let mut dump = HexDump {
+            out: String::new(),
+            data,
+            packet: &packet,
+            names,
+            protocol,
+        };
+        dump.packet(&mut Reader::new(data))?;
+        Ok(dump.out)
+    }
+}
+
+impl Display for PacketDisplay<'_, '_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let multiline = f.alternate();
+        Printer {
+            out: f,
+            names: self.names,
+            packet: Some(self.packet),
+            multiline,
+            depth: 0,
+        }
+        .packet(self.packet)
+    }
+}
+
+impl Display for PhotonPacket<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        self.display_with(&NoNames).fmt(f)
+    }
+}
+
+impl Display for ProtocolValue<'_> {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let multiline = f.alternate();
+        Printer {
+            out: f,
+            names: &NoNames,
+            packet: None,
+            multiline,
+            depth: 0,
+        }
+        .value(self, None)
+    }
+}
+
+impl Display for CustomType {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            CustomType::Vector2(x, y) => write!(f, "Vector2({:?}, {:?})", x, y),
+            CustomType::Vector3(x, y, z) => write!(f, "Vector3({:?}, {:?}, {:?})", x, y, z),
+            CustomType::Quaternion(x, y, z, w) => write!(f, "Quaternion({:?}, {:?}, {:?}, {:?})", x, y, z, w),
+            CustomType::Player(actor_nr) => write!(f, "Player({})", actor_nr),
+            CustomType::Custom { id, data } => {
+                write!(f, "Custom({}, {})", id, data.len())?;
+                write_hex_list(f, data)
+            }
+        }
+    }
+}
+
+/// Writes bytes as ` [01 02 03]`.
+fn write_hex_list(out: &mut dyn Write, bytes: &[u8]) -> fmt::Result {
+    out.write_str(" [")?;
+    write_hex(out, bytes)?;
+    out.write_char(']')
+}
+
+fn write_hex(out: &mut dyn Write, bytes: &[u8]) -> fmt::Result {
+    for (i, byte) in bytes.iter().enumerate() {
+        if i != 0 {
+            out.write_char(' ')?;
+        }
+        write!(out, "{:02x}", byte)?;
+    }
+    Ok(())
+}
+
+/// Writes packets and values with type tags, such as `Integer(1)`, and the names of known codes.
+struct Printer<'o, 'p, 'a> {
+    out: &'o mut dyn Write,
+    names: &'p dyn CodeNames,
+    /// The packet that is being printed, passed to `names`
+    packet: Option<&'p PhotonPacket<'a>>,
+    multiline: bool,
+    depth: usize,
+}
+
+impl Printer<'_, '_, '_> {
+    fn packet(&mut self, packet: &PhotonPacket) -> fmt::Result {
+        self.out.write_str(packet.type_name())?;
+        match packet {
+            PhotonPacket::Init(data) | PhotonPacket::InitResponse(data) | PhotonPacket::RawMessage(data) => {
+                write!(self.out, "({})", data.len())?;
+                write_hex_list(self.out, data)
+            }
+            PhotonPacket::OperationRequest(code, params)
+            | PhotonPacket::Event(code, params)
+            | PhotonPacket::InternalOperationRequest(code, params) => {
+                self.packet_code(packet, *code)?;
+                self.parameters(params, true)
+            }
+            PhotonPacket::OperationResponse(code, params, return_code, debug_string)
+            | PhotonPacket::InternalOperationResponse(code, params, return_code, debug_string) => {
+                self.packet_code(packet, *code)?;
+                self.response(*return_code, debug_string.as_deref())?;
+                self.parameters(params, true)
+            }
+            PhotonPacket::Message(value) => {
+                self.out.write_char(' ')?;
+                self.value(value, None)
+            }
+        }
+    }
+
+    fn packet_code(&mut self, packet: &PhotonPacket, code: u8) -> fmt::Result {
+        write!(self.out, " {}", code)?;
+        match self.names.packet_code(packet) {
+            Some(name) => write!(self.out, " {}", name),
+            None => Ok(()),
+        }
+    }
+
+    fn response(&mut self, return_code: i16, debug_string: Option<&str>) -> fmt::Result {
+        write!(self.out, ", return code {}", return_code)?;
+        match debug_string {
+            Some(debug_string) => write!(self.out, ": {:?}", debug_string),
+            None => Ok(()),
+        }
+    }
+
+    /// Writes a parameter table. Only the parameters of the packet itself are named, not those of nested values.
+    fn parameters(&mut self, params: &ParameterTable, named: bool) -> fmt::Result {
+        self.entries('{', '}', params.len(), params.iter(), |printer, (code, value)| {
+            write!(printer.out, "{}", code)?;
+            let name = printer.packet.filter(|_| named).and_then(|packet| printer.names.parameter(packet, *code));
+            if let Some(name) = name {
+                write!(printer.out, " {}", name)?;
+            }
+            printer.out.write_str(": ")?;
+            printer.value(value, Some(*code).filter(|_| named))
+        })
+    }
+
+    /// Writes a value. Byte keys in hashtables are named as properties of `parameter`, if it is set.
+    fn value(&mut self, value: &ProtocolValue, parameter: Option<u8>) -> fmt::Result {
+        match value {
+            ProtocolValue::Null() => self.out.write_str("Null"),
+            ProtocolValue::Bool(x) => write!(self.out, "Bool({})", x),
+            ProtocolValue::Byte(x) => write!(self.out, "Byte({})", x),
+            ProtocolValue::Short(x) => write!(self.out, "Short({})", x),
+            ProtocolValue::Integer(x) => write!(self.out, "Integer({})", x),
+            ProtocolValue::Long(x) => write!(self.out, "Long({})", x),
+            ProtocolValue::Float(x) => write!(self.out, "Float({:?})", x),
+            ProtocolValue::Double(x) => write!(self.out, "Double({:?})", x),
+            ProtocolValue::String(x) => write!(self.out, "String({:?})", x),
+            ProtocolValue::OperationRequest(code, params) => {
+                write!(self.out, "OperationRequest {}", code)?;
+                self.parameters(params, false)
+            }
+            ProtocolValue::OperationResponse(code, params, return_code, debug_string) => {
+                write!(self.out, "OperationResponse {}", code)?;
+                self.response(*return_code, debug_string.as_deref())?;
+                self.parameters(params, false)
+            }
+            ProtocolValue::EventData(code, params) => {
+                write!(self.out, "EventData {}", code)?;
+                self.parameters(params, false)
+            }
+            ProtocolValue::Array(_, items) | ProtocolValue::ObjectArray(items) => {
+                let name = if matches!(value, ProtocolValue::Array(..)) {
+                    "Array"
+                } else {
+                    "ObjectArray"
+                };
+                write!(self.out, "{}({})", name, items.len())?;
+                self.entries('[', ']', items.len(), items.iter(), |printer, item| printer.value(item, parameter))
+            }
+            ProtocolValue::ByteArray(x) => {
+                write!(self.out, "ByteArray({})", x.len())?;
+                write_hex_list(self.out, x)
+            }
+            ProtocolValue::StringArray(x) => write!(self.out, "StringArray({}) {:?}", x.len(), x),
+            ProtocolValue::IntegerArray(x) => write!(self.out, "IntegerArray({}) {:?}", x.len(), x),
+            ProtocolValue::Dictionary {
+                key_type,
+                value_type,
+                entries,
+            } => {
+                write!(self.out, "Dictionary<{}, {}>({})", key_type, value_type, entries.len())?;
+                self.map(entries, parameter)
+            }
+            ProtocolValue::Hashtable(entries) => {
+                write!(self.out, "Hashtable({})", entries.len())?;
+                self.map(entries, parameter)
+            }
+            ProtocolValue::Custom(x) => write!(self.out, "{}", x),
+        }
+    }
+
+    fn map(&mut self, entries: &IndexMap<ProtocolValue, ProtocolValue>, parameter: Option<u8>) -> fmt::Result {
+        self.entries('{', '}', entries.len(), entries.iter(), |printer, (key, value)| {
+            printer.key(key, parameter)?;
+            printer.out.write_str(": ")?;
+            printer.value(value, parameter)
+        })
+    }
+
+    /// Writes a hashtable key, followed by its name if it is a known property.
+    fn key(&mut self, key: &ProtocolValue, parameter: Option<u8>) -> fmt::Result {
+        self.value(key, None)?;
+        match (self.packet, parameter, key) {
+            (Some(packet), Some(parameter), ProtocolValue::Byte(key)) => match self.names.property(packet, parameter, *key) {
+                Some(name) => write!(self.out, " {}", name),
+                None => Ok(()),
+            },
+            _ => Ok(()),
+        }
+    }
+
+    /// Writes the entries of a collection between `open` and `close` on a single line, or each on their own line without
+    /// the brackets if printing on multiple lines.
+    fn entries<T>(
+        &mut self,
+        open: char,
+        close: char,
+        len: usize,
+        entries: impl Iterator<Item = T>,
+        mut write_entry: impl FnMut(&mut Self, T) -> fmt::Result,
+    ) -> fmt::Result {
+        if len == 0 {
+            return write!(self.out, " {}{}", open, close);
+        }
+
+        if !self.multiline {
+            write!(self.out, " {}", open)?;
+        }
+        self.depth += 1;
+        for (i, entry) in entries.enumerate() {
+            if self.multiline {
+                self.out.write_char('\n')?;
+                for _ in 0..self.depth {
+                    self.out.write_str("  ")?;
+                }
+            } else {
+                self.out.write_str(if i == 0 { " " } else { ", " })?;
+            }
+            write_entry(self, entry)?;
+        }
+        self.depth -= 1;
+        if !self.multiline {
+            write!(self.out, " {}", close)?;
+        }
+        Ok(())
+    }
+}
+
+/// How many bytes are shown on a single line of a hex dump.
+const HEX_DUMP_WIDTH: usize = 8;
+
+/// Walks over serialized data a second time to find out where each part of the already deserialized `packet` came from.
+struct HexDump<'p, 'a> {
+    out: String,
+    data: &'a [u8],
+    packet: &'p PhotonPacket<'a>,
+    names: &'p dyn CodeNames,
+    protocol: Protocol,
+}
+
+impl HexDump<'_, '_> {
+    /// Adds a line for the bytes from `start` up to the current position, spreading them over multiple lines if needed.
+    fn line(&mut self, c: &Reader, start: u64, depth: usize, annotation: &str) {
+        let bytes = &self.data[start as usize..c.position() as usize];
+        let mut chunks = bytes.chunks(HEX_DUMP_WIDTH);
+        let mut hex = String::new();
+        write_hex(&mut hex, chunks.next().unwrap_or(&[])).unwrap();
+        self.out += &format!("{:04x}  {:<24}  {}{}\n", start, hex, "  ".repeat(depth), annotation);
+        for (i, chunk) in chunks.enumerate() {
+            hex.clear();
+            write_hex(&mut hex, chunk).unwrap();
+            self.out += &format!("{:04x}  {}\n", start as usize + (i + 1) * HEX_DUMP_WIDTH, hex);
+        }
+    }
+
+    /// Writes something to a string using a single line `Printer`.
+    fn describe(&self, write: impl FnOnce(&mut Printer) -> fmt::Result) -> String {
+        let mut out = String::new();
+        write(&mut Printer {
+            out: &mut out,
+            names: self.names,
+            packet: Some(self.packet),
+            multiline: false,
+            depth: 0,
+        })
+        .unwrap();
+        out
+    }
+
+    fn packet(&mut self, c: &mut Reader) -> PhotonReadResult<()> {
+        let packet = self.packet;
+        c.read_u8()?;
+        self.line(c, 0, 0, "magic");
+        c.read_u8()?;
+        self.line(c, 1, 0, packet.type_name());
+
+        match packet {
+            PhotonPacket::Init(data) | PhotonPacket::InitResponse(data) | PhotonPacket::RawMessage(data) => {
+                if !data.is_empty() {
+                    c.set_position(self.data.len() as u64);
+                    self.line(c, 2, 0, &format!("data ({} bytes)", data.len()));
+                }
+            }
+            PhotonPacket::OperationRequest(code, _) | PhotonPacket::Event(code, _) | PhotonPacket::InternalOperationRequest(code, _) => {
+                self.packet_code(c, *code)?;
+                self.parameters(c)?;
+            }
+            PhotonPacket::OperationResponse(code, _, return_code, _) | PhotonPacket::InternalOperationResponse(code, _, return_code, _) => {
+                self.packet_code(c, *code)?;
+                let start = c.position();
+                self.protocol.read_return_code(c)?;
+                self.line(c, start, 0, &format!("return code {}", return_code));
+                let start = c.position();
+                let annotation = match self.protocol.read_debug_string(c)? {
+                    Some(debug_string) => format!("debug message {:?}", debug_string),
+                    None => "no debug message".to_string(),
+                };
+                self.line(c, start, 0, &annotation);
+                self.parameters(c)?;
+            }
+            PhotonPacket::Message(_) => self.value(c, 0, None, false)?,
+        }
+
+        let start = c.position();
+        if c.remaining() > 0 {
+            c.set_position(self.data.len() as u64);
+            self.line(c, start, 0, "trailing data");
+        }
+        Ok(())
+    }
+
+    fn packet_code(&mut self, c: &mut Reader, code: u8) -> PhotonReadResult<()> {
+        let start = c.position();
+        c.read_u8()?;
+        let name = self.names.packet_code(self.packet).map(|name| format!(" {}", name)).unwrap_or_default();
+        self.line(c, start, 0, &format!("code {}{}", code, name));
+        Ok(())
+    }
+
+    fn parameters(&mut self, c: &mut Reader) -> PhotonReadResult<()> {
+        let start = c.position();
+        let len = self.protocol.read_parameter_count(c)?;
+        self.line(c, start, 0, &format!("{} parameters", len));
+        for _ in 0..len {
+            let start = c.position();
+            let code = c.read_u8()?;
+            let name = self
+                .names
+                .parameter(self.packet, code)
+                .map(|name| format!(" {}", name))
+                .unwrap_or_default();
+            self.line(c, start, 1, &format!("{}{}", code, name));
+            self.value(c, 2, Some(code), false)?;
+        }
+        Ok(())
+    }
+
+    /// Adds the lines of a single value. Hashtables and object arrays get a line for each item.
+    fn value(&mut self, c: &mut Reader, depth: usize, parameter: Option<u8>, key: bool) -> PhotonReadResult<()> {
+        let start = c.position();
+        match self.protocol.read_collection_header(c)? {
+            Some(CollectionHeader::Hashtable(len)) => {
+                self.line(c, start, depth, &format!("Hashtable({})", len));
+                for _ in 0..len {
+                    self.value(c, depth + 1, parameter, true)?;
+                    self.value(c, depth + 2, parameter, false)?;
+                }
+            }
+            Some(CollectionHeader::ObjectArray(len)) => {
+                self.line(c, start, depth, &format!("ObjectArray({})", len));
+                for _ in 0..len {
+                    self.value(c, depth + 1, parameter, false)?;
+                }
+            }
+            None => {
+                let value = self.protocol.read_value(c)?;
+                let annotation = if key {
+                    self.describe(|printer| printer.key(&value, parameter))
+                } else {
+                    self.describe(|printer| printer.value(&value, parameter))
+                };
+                self.line(c, start, depth, &annotation);
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,196 @@
+#![cfg(test)]
+
+use super::*;
+use indexmap::indexmap;
+
+fn test_packet() -> PhotonPacket<'static> {
+    PhotonPacket::OperationRequest(
+        252,
+        indexmap! {
+            254 => ProtocolValue::Integer(1),
+            251 => ProtocolValue::Hashtable(indexmap! {
+                ProtocolValue::Byte(255) => ProtocolValue::String("hi".into()),
+                ProtocolValue::String("team".into()) => ProtocolValue::ObjectArray(vec![ProtocolValue::Byte(1), ProtocolValue::Null()]),
+            }),
+            1 => ProtocolValue::Hashtable(indexmap! {}),
+        },
+    )
+}
+
+struct TestNames;
+
+impl CodeNames for TestNames {
+    fn packet_code(&self, _packet: &PhotonPacket) -> Option<&'static str> {
+        Some("SetProperties")
+    }
+
+    fn parameter(&self, _packet: &PhotonPacket, code: u8) -> Option<&'static str> {
+        match code {
+            254 => Some("ActorNr"),
+            251 => Some("Properties"),
+            _ => None,
+        }
+    }
+
+    fn property(&self, _packet: &PhotonPacket, parameter: u8, key: u8) -> Option<&'static str> {
+        match (parameter, key) {
+            (251, 255) => Some("PlayerName"),
+            _ => None,
+        }
+    }
+}
+
+#[test]
+fn display_value() {
+    assert_eq!(ProtocolValue::Float(1.0).to_string(), "Float(1.0)");
+    assert_eq!(ProtocolValue::String("a\"b".into()).to_string(), r#"String("a\"b")"#);
+    assert_eq!(ProtocolValue::ByteArray(vec![0xde, 0xad]).to_string(), "ByteArray(2) [de ad]");
+    assert_eq!(ProtocolValue::IntegerArray(vec![1, 2]).to_string(), "IntegerArray(2) [1, 2]");
+    assert_eq!(ProtocolValue::Custom(CustomType::Vector2(1.0, 2.5)).to_string(), "Vector2(1.0, 2.5)");
+    assert_eq!(
+        ProtocolValue::Custom(CustomType::Custom { id: 65, data: vec![1] }).to_string(),
+        "Custom(65, 1) [01]"
+    );
+    assert_eq!(
+        ProtocolValue::Dictionary {
+            key_type: 115,
+            value_type: 0,
+            entries: indexmap! { ProtocolValue::String("a".into()) => ProtocolValue::Null() },
+        }
+        .to_string(),
+        r#"Dictionary<115, 0>(1) { String("a"): Null }"#
+    );
+    assert_eq!(ProtocolValue::Array(105, vec![]).to_string(), "Array(0) []");
+}
+
+#[test]
+fn display_packet() {
+    assert_eq!(
+        test_packet().to_string(),
+        r#"OperationRequest 252 { 254: Integer(1), 251: Hashtable(2) { Byte(255): String("hi"), String("team"): ObjectArray(2) [ Byte(1), Null ] }, 1: Hashtable(0) {} }"#
+    );
+}
+
+#[test]
+fn display_packet_multiline() {
+    assert_eq!(
+        format!("{:#}", test_packet().display_with(&TestNames)),
+        r#"OperationRequest 252 SetProperties
+  254 ActorNr: Integer(1)
+  251 Properties: Hashtable(2)
+    Byte(255) PlayerName: String("hi")
+    String("team"): ObjectArray(2)
+      Byte(1)
+      Null
+  1: Hashtable(0) {}"#
+    );
+}
+
+#[test]
+fn display_response() {
+    let packet = PhotonPacket::OperationResponse(230, indexmap! {}, -2, Some("oops".into()));
+    assert_eq!(packet.to_string(), r#"OperationResponse 230, return code -2: "oops" {}"#);
+}
+
+#[test]
+fn hex_dump_v16() {
+    let data = test_packet().write(Protocol::GpBinaryV16).unwrap();
+    assert_eq!(
+        PhotonPacket::hex_dump(&data, Protocol::GpBinaryV16, &TestNames).unwrap(),
+        r#"0000  f3                        magic
+0001  02                        OperationRequest
+0002  fc                        code 252 SetProperties
+0003  00 03                     3 parameters
+0005  fe                          254 ActorNr
+0006  69 00 00 00 01                Integer(1)
+000b  fb                          251 Properties
+000c  68 00 02                      Hashtable(2)
+000f  62 ff                           Byte(255) PlayerName
+0011  73 00 02 68 69                    String("hi")
+0016  73 00 04 74 65 61 6d            String("team")
+001d  7a 00 02                          ObjectArray(2)
+0020  62 01                               Byte(1)
+0022  2a                                  Null
+0023  01                          1
+0024  68 00 00                      Hashtable(0)
+"#
+    );
+}
+
+#[test]
+fn hex_dump_v18() {
+    let data = test_packet().write(Protocol::GpBinaryV18).unwrap();
+    assert_eq!(
+        PhotonPacket::hex_dump(&data, Protocol::GpBinaryV18, &NoNames).unwrap(),
+        r#"0000  f3                        magic
+0001  02                        OperationRequest
+0002  fc                        code 252
+0003  03                        3 parameters
+0004  fe                          254
+0005  0b 01                         Integer(1)
+0007  fb                          251
+0008  15 02                         Hashtable(2)
+000a  03 ff                           Byte(255)
+000c  07 02 68 69                       String("hi")
+0010  07 04 74 65 61 6d               String("team")
+0016  17 02                             ObjectArray(2)
+0018  03 01                               Byte(1)
+001a  08                                  Null
+001b  01                          1
+001c  15 00                         Hashtable(0)
+"#
+    );
+}
+
+#[test]
+fn hex_dump_response() {
+    let data = PhotonPacket::OperationResponse(230, indexmap! {}, -2, Some("oops".into()))
+        .write(Protocol::GpBinaryV16)
+        .unwrap();
+    assert_eq!(
+        PhotonPacket::hex_dump(&data, Protocol::GpBinaryV16, &NoNames).unwrap(),
+        r#"0000  f3                        magic
+0001  03                        OperationResponse
+0002  e6                        code 230
+0003  ff fe                     return code -2
+0005  73 00 04 6f 6f 70 73      debug message "oops"
+000c  00 00                     0 parameters
+"#
+    );
+}
+
+#[test]
+fn hex_dump_long_data() {
+    let data = [0xf3, 0x09, 0, 1, 2, 3, 4, 5, 6, 7, 8, 9];
+    assert_eq!(
+        PhotonPacket::hex_dump(&data, Protocol::GpBinaryV16, &NoNames).unwrap(),
+        "\
+0000  f3                        magic
+0001  09                        RawMessage
+0002  00 01 02 03 04 05 06 07   data (10 bytes)
+000a  08 09
+"
+    );
+}
+
+#[test]
+fn hex_dump_trailing_data() {
+    let data = [0xf3, 0x08, 0x2a, 0xff];
+    assert_eq!(
+        PhotonPacket::hex_dump(&data, Protocol::GpBinaryV16, &NoNames).unwrap(),
+        "\
+0000  f3                        magic
+0001  08                        Message
+0002  2a                        Null
+0003  ff                        trailing data
+"
+    );
+}
+
+#[test]
+fn hex_dump_invalid() {
+    match PhotonPacket::hex_dump(&[0xf3, 0x02], Protocol::GpBinaryV16, &NoNames) {
+        Err(PhotonReadError::IOError(_)) | Err(PhotonReadError::UnexpectedEof { .. }) => (),
+        other => panic!("Expected error, got {:?}", other),
+    }
+}
@@ -1,6 +1,10 @@
+mod proptests;
 mod read;
+mod reader;
 mod tests;
+pub mod v18;
 mod write;
 
 pub use read::*;
+pub use reader::*;
 pub use write::*;
@@ -0,0 +1,144 @@
+#![cfg(test)]
+
+use super::super::*;
+use super::*;
+use proptest::collection::vec;
+use proptest::prelude::*;
+
+// NaN is not equal to itself, so it can never survive a round trip
+fn arb_f32() -> impl Strategy<Value = f32> {
+    any::<f32>().prop_filter("NaN", |x| !x.is_nan())
+}
+
+fn arb_f64() -> impl Strategy<Value = f64> {
+    any::<f64>().prop_filter("NaN", |x| !x.is_nan())
+}
+
+fn arb_string() -> impl Strategy<Value = Cow<'static, str>> {
+    ".{0,8}".prop_map(Cow::Owned)
+}
+
+fn arb_custom() -> impl Strategy<Value = CustomType> {
+    prop_oneof![
+        (arb_f32(), arb_f32()).prop_map(|(x, y)| CustomType::Vector2(x, y)),
+        (arb_f32(), arb_f32(), arb_f32()).prop_map(|(x, y, z)| CustomType::Vector3(x, y, z)),
+        (arb_f32(), arb_f32(), arb_f32(), arb_f32()).prop_map(|(x, y, z, w)| CustomType::Quaternion(x, y, z, w)),
+        any::<i32>().prop_map(CustomType::Player),
+        // the known ids would be read back as their own type
+        (
+            any::<u8>().prop_filter("known custom type", |id| !b"WVQP".contains(id)),
+            vec(any::<u8>(), 0..8)
+        )
+            .prop_map(|(id, data)| CustomType::Custom { id, data }),
+    ]
+}
+
+/// Values that can be used as hashtable key.
+fn arb_key() -> impl Strategy<Value = ProtocolValue<'static>> {
+    prop_oneof![
+        any::<bool>().prop_map(ProtocolValue::Bool),
+        any::<u8>().prop_map(ProtocolValue::Byte),
+        any::<i16>().prop_map(ProtocolValue::Short),
+        any::<i32>().prop_map(ProtocolValue::Integer),
+        any::<i64>().prop_map(ProtocolValue::Long),
+        arb_f32().prop_map(ProtocolValue::Float),
+        arb_f64().prop_map(ProtocolValue::Double),
+        arb_string().prop_map(ProtocolValue::String),
+    ]
+}
+
+fn arb_leaf() -> impl Strategy<Value = ProtocolValue<'static>> {
+    prop_oneof![
+        Just(ProtocolValue::Null()),
+        arb_key(),
+        vec(any::<u8>(), 0..8).prop_map(ProtocolValue::ByteArray),
+        vec(arb_string(), 0..4).prop_map(ProtocolValue::StringArray),
+        vec(any::<i32>(), 0..4).prop_map(ProtocolValue::IntegerArray),
+        arb_custom().prop_map(ProtocolValue::Custom),
+    ]
+}
+
+fn arb_table(value: BoxedStrategy<ProtocolValue<'static>>) -> impl Strategy<Value = ParameterTable<'static>> {
+    vec((any::<u8>(), value), 0..4).prop_map(|entries| entries.into_iter().collect())
+}
+
+/// Dictionary keys or values of a single type. A type of `0` means `object`, so any value can be used.
+fn arb_of_dictionary_type(protocol_type: u8, object: BoxedStrategy<ProtocolValue<'static>>) -> BoxedStrategy<ProtocolValue<'static>> {
+    match protocol_type {
+        98 => any::<u8>().prop_map(ProtocolValue::Byte).boxed(),
+        105 => any::<i32>().prop_map(ProtocolValue::Integer).boxed(),
+        115 => arb_string().prop_map(ProtocolValue::String).boxed(),
+        _ => object,
+    }
+}
+
+fn arb_value() -> impl Strategy<Value = ProtocolValue<'static>> {
+    arb_leaf().prop_recursive(3, 32, 4, |inner| {
+        let inner = inner.boxed();
+        let dictionary_inner = inner.clone();
+        prop_oneof![
+            vec(inner.clone(), 0..4).prop_map(ProtocolValue::ObjectArray),
+            // typed arrays only contain items of the same type
+            vec(inner.clone(), 0..4).prop_map(|items| {
+                let protocol_type = items.first().map_or(42, get_value_type);
+                ProtocolValue::Array(protocol_type, items.into_iter().filter(|x| get_value_type(x) == protocol_type).collect())
+            }),
+            vec((arb_key(), inner.clone()), 0..4).prop_map(|entries| ProtocolValue::Hashtable(entries.into_iter().collect())),
+            (prop::sample::select(vec![0u8, 98, 105, 115]), prop::sample::select(vec![0u8, 105, 115])).prop_flat_map(
+                move |(key_type, value_type)| {
+                    let keys = arb_of_dictionary_type(key_type, arb_key().boxed());
+                    let values = arb_of_dictionary_type(value_type, dictionary_inner.clone());
+                    vec((keys, values), 0..4).prop_map(move |entries| ProtocolValue::Dictionary {
+                        key_type,
+                        value_type,
+                        entries: entries.into_iter().collect(),
+                    })
+                }
+            ),
+            (any::<u8>(), arb_table(inner.clone())).prop_map(|(code, params)| ProtocolValue::EventData(code, params)),
+            (any::<u8>(), arb_table(inner.clone())).prop_map(|(code, params)| ProtocolValue::OperationRequest(code, params)),
+            (any::<u8>(), arb_table(inner), any::<i16>(), prop::option::of(arb_string()))
+                .prop_map(|(code, params, return_code, debug_string)| ProtocolValue::OperationResponse(code, params, return_code, debug_string)),
+        ]
+    })
+}
+
+fn arb_packet() -> impl Strategy<Value = PhotonPacket<'static>> {
+    let table = || arb_table(arb_value().boxed());
+    prop_oneof![
+        vec(any::<u8>(), 0..16).prop_map(|data| PhotonPacket::Init(Cow::Owned(data))),
+        vec(any::<u8>(), 0..16).prop_map(|data| PhotonPacket::InitResponse(Cow::Owned(data))),
+        (any::<u8>(), table()).prop_map(|(code, params)| PhotonPacket::OperationRequest(code, params)),
+        (any::<u8>(), table(), any::<i16>(), prop::option::of(arb_string()))
+            .prop_map(|(code, params, return_code, debug_string)| { PhotonPacket::OperationResponse(code, params, return_code, debug_string) }),
+        (any::<u8>(), table()).prop_map(|(code, params)| PhotonPacket::Event(code, params)),
+        (any::<u8>(), table()).prop_map(|(code, params)| PhotonPacket::InternalOperationRequest(code, params)),
+        (any::<u8>(), table(), any::<i16>(), prop::option::of(arb_string())).prop_map(|(code, params, return_code, debug_string)| {
+            PhotonPacket::InternalOperationResponse(code, params, return_code, debug_string)
+        }),
+        arb_value().prop_map(PhotonPacket::Message),
+        vec(any::<u8>(), 0..16).prop_map(|data| PhotonPacket::RawMessage(Cow::Owned(data))),
+    ]
+}
+
+proptest! {
+    #[test]
+    fn write_read_v16(value in arb_value()) {
+        let data = &mut Vec::new();
+        write_value_of_type(data, value.clone()).unwrap();
+        prop_assert_eq!(read_value(&mut Reader::new(data)).unwrap(), value);
+    }
+
+    #[test]
+    fn write_read_v18(value in arb_value()) {
+        let data = &mut Vec::new();
+        v18::write_value_of_type(data, value.clone()).unwrap();
+        prop_assert_eq!(v18::read_value(&mut Reader::new(data)).unwrap(), value);
+    }
+
+    #[test]
+    fn write_read_packet(packet in arb_packet(), protocol in prop_oneof![Just(Protocol::GpBinaryV16), Just(Protocol::GpBinaryV18)]) {
+        let data = packet.clone().write(protocol).unwrap();
+        prop_assert_eq!(PhotonPacket::read(&data, protocol).unwrap(), packet);
+    }
+}
@@ -1,10 +1,11 @@
 use byteorder::{BigEndian, ReadBytesExt};
-use std::collections::HashMap;
-use std::io::{Cursor, Read};
+use indexmap::IndexMap;
+use std::borrow::Cow;
 
 use super::super::*;
+use super::reader::{hashable_key, CollectionHeader, MapEntry, ParameterEntry, Reader};
 
-pub fn read_debug_string<'a>(c: &mut Cursor<&'a [u8]>) -> PhotonReadResult<Option<&'a str>> {
+pub fn read_debug_string<'a>(c: &mut Reader<'a>) -> PhotonReadResult<Option<Cow<'a, str>>> {
     match read_value(c)? {
         ProtocolValue::Null() => Ok(None),
         ProtocolValue::String(string) => Ok(Some(string)),
@@ -12,25 +13,34 @@ pub fn read_debug_string<'a>(c: &mut Cursor<&'a [u8]>) -> PhotonReadResult<Optio
     }
 }
 
-pub fn read_value<'a>(c: &mut Cursor<&'a [u8]>) -> PhotonReadResult<ProtocolValue<'a>> {
+pub fn read_value<'a>(c: &mut Reader<'a>) -> PhotonReadResult<ProtocolValue<'a>> {
     let protocol_type = c.read_u8()?;
     read_value_of_type(c, protocol_type)
 }
 
-pub fn read_value_of_type<'a>(c: &mut Cursor<&'a [u8]>, protocol_type: u8) -> PhotonReadResult<ProtocolValue<'a>> {
+pub fn read_value_of_type<'a>(c: &mut Reader<'a>, protocol_type: u8) -> PhotonReadResult<ProtocolValue<'a>> {
+    c.nested(|c| read_value_contents(c, protocol_type))
+}
+
+fn read_value_contents<'a>(c: &mut Reader<'a>, protocol_type: u8) -> PhotonReadResult<ProtocolValue<'a>> {
     match protocol_type {
         42 => Ok(ProtocolValue::Null()),
-        68 => Err(PhotonReadError::UnimplementedProtocolValueType(ProtocolValue::Dictionary)),
+        68 => {
+            let key_type = c.read_u8()?;
+            let value_type = c.read_u8()?;
+            Ok(ProtocolValue::Dictionary {
+                key_type,
+                value_type,
+                entries: read_dictionary(c, key_type, value_type)?,
+            })
+        }
         97 => {
             let len = c.read_u16::<BigEndian>()? as usize;
-            let mut vec = Vec::new();
+            c.reserve::<Cow<str>>(len, 2)?;
+            let mut vec = Vec::with_capacity(len);
             for _ in 0..len {
                 let len = c.read_u16::<BigEndian>()? as usize;
-                let pos = c.position() as usize;
-                let return_slice = &(*c.get_ref())[pos..pos + len];
-                c.set_position((pos + len) as u64);
-                let str_slice = std::str::from_utf8(return_slice)?;
-                vec.push(str_slice);
+                vec.push(Cow::Borrowed(c.read_str(len)?));
             }
             Ok(ProtocolValue::StringArray(vec))
         }
@@ -38,103 +48,155 @@ pub fn read_value_of_type<'a>(c: &mut Cursor<&'a [u8]>, protocol_type: u8) -> Ph
         99 => {
             let id = c.read_u8()?;
             let len = c.read_u16::<BigEndian>()?;
-            Ok(ProtocolValue::Custom(match id {
-                // note: should change these len checks with asserts
-                b'W' if len == 8 => Ok(CustomType::Vector2(c.read_f32::<BigEndian>()?, c.read_f32::<BigEndian>()?)),
-                b'V' if len == 12 => Ok(CustomType::Vector3(
-                    c.read_f32::<BigEndian>()?,
-                    c.read_f32::<BigEndian>()?,
-                    c.read_f32::<BigEndian>()?,
-                )),
-                b'Q' if len == 16 => Ok(CustomType::Quaternion(
-                    c.read_f32::<BigEndian>()?,
-                    c.read_f32::<BigEndian>()?,
-                    c.read_f32::<BigEndian>()?,
-                    c.read_f32::<BigEndian>()?,
-                )),
-                b'P' if len == 4 => Ok(CustomType::Player(c.read_i32::<BigEndian>()?)),
-                b'W' | b'V' | b'Q' | b'P' => Err(PhotonReadError::CustomTypeInvalidLength),
-                _ => {
-                    let mut data = vec![0; len as usize];
-                    c.read_exact(data.as_mut_slice())?;
-                    Ok(CustomType::Custom { id, data })
-                }
-            }?))
+            Ok(ProtocolValue::Custom(read_custom_type(c, id, len as usize)?))
         }
         100 => Ok(ProtocolValue::Double(c.read_f64::<BigEndian>()?)),
-        101 => Err(PhotonReadError::UnimplementedProtocolValueType(ProtocolValue::EventData)),
+        101 => Ok(ProtocolValue::EventData(c.read_u8()?, read_parameter_table(c)?)),
         102 => Ok(ProtocolValue::Float(c.read_f32::<BigEndian>()?)),
         104 => Ok(ProtocolValue::Hashtable(read_hash_table(c)?)),
         105 => Ok(ProtocolValue::Integer(c.read_i32::<BigEndian>()?)),
         107 => Ok(ProtocolValue::Short(c.read_i16::<BigEndian>()?)),
         108 => Ok(ProtocolValue::Long(c.read_i64::<BigEndian>()?)),
         110 => {
+            // a negative length becomes too large, and is rejected by the limits
             let len = c.read_i32::<BigEndian>()? as usize;
-            let mut vec = Vec::new();
+            c.reserve::<i32>(len, 4)?;
+            let mut vec = Vec::with_capacity(len);
             for _ in 0..len {
                 vec.push(c.read_i32::<BigEndian>()?);
             }
             Ok(ProtocolValue::IntegerArray(vec))
         }
         111 => Ok(ProtocolValue::Bool(c.read_u8()? != 0)),
-        112 => Err(PhotonReadError::UnimplementedProtocolValueType(ProtocolValue::OperationResponse)),
-        113 => Err(PhotonReadError::UnimplementedProtocolValueType(ProtocolValue::OperationRequest)),
+        112 => {
+            let operation_type = c.read_u8()?;
+            let return_code = c.read_i16::<BigEndian>()?;
+            let debug_string = read_debug_string(c)?;
+            let parameter_table = read_parameter_table(c)?;
+            Ok(ProtocolValue::OperationResponse(
+                operation_type,
+                parameter_table,
+                return_code,
+                debug_string,
+            ))
+        }
+        113 => Ok(ProtocolValue::OperationRequest(c.read_u8()?, read_parameter_table(c)?)),
         115 => {
             let len = c.read_u16::<BigEndian>()? as usize;
-            let pos = c.position() as usize;
-
-            let return_slice = &(*c.get_ref())[pos..pos + len];
-            c.set_position((pos + len) as u64);
-            let str_slice = std::str::from_utf8(return_slice)?;
-            Ok(ProtocolValue::String(str_slice))
+            Ok(ProtocolValue::String(Cow::Borrowed(c.read_str(len)?)))
         }
         120 => {
             let len = c.read_u32::<BigEndian>()? as usize;
-            let mut vec = Vec::new();
-            for _ in 0..len {
-                vec.push(c.read_u8()?);
-            }
-            Ok(ProtocolValue::ByteArray(vec))
+            Ok(ProtocolValue::ByteArray(c.read_bytes(len)?))
+        }
+        121 => {
+            let len = c.read_u16::<BigEndian>()? as usize;
+            let protocol_type = c.read_u8()?;
+            Ok(ProtocolValue::Array(protocol_type, read_array_of_type(c, len, protocol_type)?))
         }
-        121 => Ok(ProtocolValue::Array(read_value_array_of_same_type(c)?)),
         122 => Ok(ProtocolValue::ObjectArray(read_value_array(c)?)),
         _ => Err(PhotonReadError::UnknownProtocolValueType(protocol_type)),
     }
 }
 
-pub fn read_value_array_of_same_type<'a>(c: &mut Cursor<&'a [u8]>) -> PhotonReadResult<Vec<ProtocolValue<'a>>> {
-    let len = c.read_u16::<BigEndian>()?;
-    let protocol_type = c.read_u8()?;
-    let mut ret = Vec::new();
+/// Reads the data of a custom type. This data is independent of the protocol version.
+pub fn read_custom_type(c: &mut Reader, id: u8, len: usize) -> PhotonReadResult<CustomType> {
+    match id {
+        // note: should change these len checks with asserts
+        b'W' if len == 8 => Ok(CustomType::Vector2(c.read_f32::<BigEndian>()?, c.read_f32::<BigEndian>()?)),
+        b'V' if len == 12 => Ok(CustomType::Vector3(
+            c.read_f32::<BigEndian>()?,
+            c.read_f32::<BigEndian>()?,
+            c.read_f32::<BigEndian>()?,
+        )),
+        b'Q' if len == 16 => Ok(CustomType::Quaternion(
+            c.read_f32::<BigEndian>()?,
+            c.read_f32::<BigEndian>()?,
+            c.read_f32::<BigEndian>()?,
+            c.read_f32::<BigEndian>()?,
+        )),
+        b'P' if len == 4 => Ok(CustomType::Player(c.read_i32::<BigEndian>()?)),
+        b'W' | b'V' | b'Q' | b'P' => Err(PhotonReadError::CustomTypeInvalidLength),
+        _ => Ok(CustomType::Custom {
+            id,
+            data: c.read_bytes(len)?,
+        }),
+    }
+}
+
+fn read_array_of_type<'a>(c: &mut Reader<'a>, len: usize, protocol_type: u8) -> PhotonReadResult<Vec<ProtocolValue<'a>>> {
+    c.reserve::<ProtocolValue>(len, 0)?;
+    let mut ret = Vec::with_capacity(len);
     for _i in 0..len {
         ret.push(read_value_of_type(c, protocol_type)?);
     }
     Ok(ret)
 }
 
-pub fn read_value_array<'a>(c: &mut Cursor<&'a [u8]>) -> PhotonReadResult<Vec<ProtocolValue<'a>>> {
-    let len = c.read_u16::<BigEndian>()?;
-    let mut ret = Vec::new();
+pub fn read_value_array<'a>(c: &mut Reader<'a>) -> PhotonReadResult<Vec<ProtocolValue<'a>>> {
+    let len = c.read_u16::<BigEndian>()? as usize;
+    c.reserve::<ProtocolValue>(len, 1)?;
+    let mut ret = Vec::with_capacity(len);
     for _i in 0..len {
         ret.push(read_value(c)?);
     }
     Ok(ret)
 }
 
-pub fn read_hash_table<'a>(c: &mut Cursor<&'a [u8]>) -> PhotonReadResult<HashMap<ProtocolValue<'a>, ProtocolValue<'a>>> {
-    let mut ret = HashMap::new();
-    let len = c.read_u16::<BigEndian>()?;
+pub fn read_hash_table<'a>(c: &mut Reader<'a>) -> PhotonReadResult<IndexMap<ProtocolValue<'a>, ProtocolValue<'a>>> {
+    let len = c.read_u16::<BigEndian>()? as usize;
+    c.reserve::<MapEntry>(len, 2)?;
+    let mut ret = IndexMap::with_capacity(len);
+    for _i in 0..len {
+        ret.insert(hashable_key(read_value(c)?)?, read_value(c)?);
+    }
+    Ok(ret)
+}
+
+pub fn read_dictionary<'a>(c: &mut Reader<'a>, key_type: u8, value_type: u8) -> PhotonReadResult<IndexMap<ProtocolValue<'a>, ProtocolValue<'a>>> {
+    let len = c.read_u16::<BigEndian>()? as usize;
+    c.reserve::<MapEntry>(len, 0)?;
+    let mut ret = IndexMap::with_capacity(len);
     for _i in 0..len {
-        ret.insert(read_value(c)?, read_value(c)?);
+        ret.insert(
+            hashable_key(read_value_of_dictionary_type(c, key_type)?)?,
+            read_value_of_dictionary_type(c, value_type)?,
+        );
     }
     Ok(ret)
 }
 
-pub fn read_parameter_table<'a>(c: &mut Cursor<&'a [u8]>) -> PhotonReadResult<HashMap<u8, ProtocolValue<'a>>> {
-    let mut ret = HashMap::new();
-    let len = c.read_u16::<BigEndian>()?;
+/// Reads a dictionary key or value, which only has its own type byte if the dictionary type is `object`.
+fn read_value_of_dictionary_type<'a>(c: &mut Reader<'a>, protocol_type: u8) -> PhotonReadResult<ProtocolValue<'a>> {
+    match protocol_type {
+        0 | 42 => read_value(c),
+        _ => read_value_of_type(c, protocol_type),
+    }
+}
+
+pub fn read_parameter_table<'a>(c: &mut Reader<'a>) -> PhotonReadResult<IndexMap<u8, ProtocolValue<'a>>> {
+    let len = read_parameter_count(c)?;
+    c.reserve::<ParameterEntry>(len, 2)?;
+    let mut ret = IndexMap::with_capacity(len);
     for _i in 0..len {
         ret.insert(c.read_u8()?, read_value(c)?);
     }
     Ok(ret)
 }
+
+pub fn read_parameter_count(c: &mut Reader) -> PhotonReadResult<usize> {
+    Ok(c.read_u16::<BigEndian>()? as usize)
+}
+
+/// Reads the type and length of a hashtable or object array, but not its items. Reads nothing for other types.
+pub fn read_collection_header(c: &mut Reader) -> PhotonReadResult<Option<CollectionHeader>> {
+    let position = c.position();
+    match c.read_u8()? {
+        104 => Ok(Some(CollectionHeader::Hashtable(c.read_u16::<BigEndian>()? as usize))),
+        122 => Ok(Some(CollectionHeader::ObjectArray(c.read_u16::<BigEndian>()? as usize))),
+        _ => {
+            c.set_position(position);
+            Ok(None)
+        }
+    }
+}
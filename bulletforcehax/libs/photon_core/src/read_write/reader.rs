@@ -0,0 +1,142 @@
+use std::io::Cursor;
+use std::ops::{Deref, DerefMut};
+
+use super::super::*;
+
+/// An entry in a hashtable or dictionary, including the hash stored next to it. Only used to estimate memory usage.
+pub type MapEntry<'a> = (u64, ProtocolValue<'a>, ProtocolValue<'a>);
+/// An entry in a parameter table, including the hash stored next to it. Only used to estimate memory usage.
+pub type ParameterEntry<'a> = (u64, u8, ProtocolValue<'a>);
+
+/// The start of a hashtable or object array, with the amount of items in it. These items each carry their own type, so
+/// they can be read one by one. Used to map bytes to the values they decode into.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum CollectionHeader {
+    Hashtable(usize),
+    ObjectArray(usize),
+}
+
+/// A cursor over the data being deserialized, which keeps track of the [`ReadLimits`] while reading.
+///
+/// Derefs to the inner `Cursor`, so the `byteorder` read functions can be used directly.
+///
+/// [`ReadLimits`]: ../struct.ReadLimits.html
+pub struct Reader<'a> {
+    cursor: Cursor<&'a [u8]>,
+    limits: ReadLimits,
+    depth: usize,
+    allocated: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self::with_limits(data, ReadLimits::default())
+    }
+
+    pub fn with_limits(data: &'a [u8], limits: ReadLimits) -> Self {
+        Self {
+            cursor: Cursor::new(data),
+            limits,
+            depth: 0,
+            allocated: 0,
+        }
+    }
+
+    /// The amount of bytes that have not been read yet.
+    pub fn remaining(&self) -> usize {
+        self.cursor.get_ref().len().saturating_sub(self.cursor.position() as usize)
+    }
+
+    /// Returns an error if there are less than `needed` bytes left to read.
+    pub fn ensure_remaining(&self, needed: usize) -> PhotonReadResult<()> {
+        let available = self.remaining();
+        if needed > available {
+            return Err(PhotonReadError::UnexpectedEof { needed, available });
+        }
+        Ok(())
+    }
+
+    /// Reads the next `len` bytes without copying them.
+    pub fn read_slice(&mut self, len: usize) -> PhotonReadResult<&'a [u8]> {
+        self.ensure_remaining(len)?;
+        let pos = self.cursor.position() as usize;
+        let data: &'a [u8] = self.cursor.get_ref();
+        self.cursor.set_position((pos + len) as u64);
+        Ok(&data[pos..pos + len])
+    }
+
+    /// Reads the next `len` bytes as an UTF-8 string, without copying them.
+    pub fn read_str(&mut self, len: usize) -> PhotonReadResult<&'a str> {
+        Ok(std::str::from_utf8(self.read_slice(len)?)?)
+    }
+
+    /// Reads the next `len` bytes into a new `Vec`, counting them towards the allocation limit.
+    pub fn read_bytes(&mut self, len: usize) -> PhotonReadResult<Vec<u8>> {
+        self.ensure_remaining(len)?;
+        self.allocate(len)?;
+        Ok(self.read_slice(len)?.to_vec())
+    }
+
+    /// Checks a collection of `len` items of type `T`, where each item takes at least `min_item_len` bytes to
+    /// serialize. Its memory is counted towards the allocation limit, so it is safe to allocate it up front.
+    pub fn reserve<T>(&mut self, len: usize, min_item_len: usize) -> PhotonReadResult<()> {
+        if len > self.limits.max_count {
+            return Err(PhotonReadError::CountLimitExceeded(len));
+        }
+        self.ensure_remaining(len.saturating_mul(min_item_len))?;
+        self.allocate(len.saturating_mul(std::mem::size_of::<T>()))
+    }
+
+    fn allocate(&mut self, bytes: usize) -> PhotonReadResult<()> {
+        self.allocated = self.allocated.saturating_add(bytes);
+        if self.allocated > self.limits.max_allocation {
+            return Err(PhotonReadError::AllocationLimitExceeded(self.allocated));
+        }
+        Ok(())
+    }
+
+    /// Reads a nested value using `read_fn`, returning an error if this goes too deep.
+    pub fn nested<T, F>(&mut self, read_fn: F) -> PhotonReadResult<T>
+    where
+        F: FnOnce(&mut Self) -> PhotonReadResult<T>,
+    {
+        if self.depth >= self.limits.max_depth {
+            return Err(PhotonReadError::DepthLimitExceeded(self.depth));
+        }
+        self.depth += 1;
+        let ret = read_fn(self);
+        self.depth -= 1;
+        ret
+    }
+}
+
+impl<'a> Deref for Reader<'a> {
+    type Target = Cursor<&'a [u8]>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.cursor
+    }
+}
+
+impl DerefMut for Reader<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.cursor
+    }
+}
+
+/// Returns the key back if it can be used as key in a hashtable or dictionary, or an error if it is a collection.
+pub fn hashable_key(key: ProtocolValue) -> PhotonReadResult<ProtocolValue> {
+    match key {
+        ProtocolValue::Null()
+        | ProtocolValue::Bool(_)
+        | ProtocolValue::Byte(_)
+        | ProtocolValue::Short(_)
+        | ProtocolValue::Integer(_)
+        | ProtocolValue::Long(_)
+        | ProtocolValue::Float(_)
+        | ProtocolValue::Double(_)
+        | ProtocolValue::String(_)
+        | ProtocolValue::Custom(_) => Ok(key),
+        _ => Err(PhotonReadError::UnhashableKey),
+    }
+}
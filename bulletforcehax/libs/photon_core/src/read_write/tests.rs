@@ -3,10 +3,12 @@
 
 use super::super::*;
 use super::*;
-use maplit::hashmap;
-use std::io::Cursor;
+use indexmap::indexmap;
+use std::borrow::Cow;
 
 // union error of PhotonReadError and PhotonWriteError
+// fields are only read through the `Debug` impl when a test fails
+#[allow(dead_code)]
 #[derive(Debug)]
 enum TestError {
     Read(PhotonReadError),
@@ -32,7 +34,7 @@ macro_rules! generate_read_write_test {
         paste::item! {
             #[test]
             fn [<can_read_ $test_name>]() -> TestResult<()> {
-                let reader = &mut Cursor::new($data.as_ref());
+                let reader = &mut Reader::new($data.as_ref());
                 let t = read_value(reader)?;
                 assert_eq!(t, $obj);
 
@@ -64,10 +66,10 @@ generate_read_write_test!(
 );
 generate_read_write_test!(f32, ProtocolValue::Float(42.), [0x66, 0x42, 0x28, 0x00, 0x00]);
 generate_read_write_test!(f64, ProtocolValue::Double(13.37), [0x64, 0x40, 0x2a, 0xbd, 0x70, 0xa3, 0xd7, 0x0a, 0x3d]);
-generate_read_write_test!(string, ProtocolValue::String("abc"), [0x73, 0x00, 0x03, 0x61, 0x62, 0x63]);
+generate_read_write_test!(string, ProtocolValue::String("abc".into()), [0x73, 0x00, 0x03, 0x61, 0x62, 0x63]);
 generate_read_write_test!(
     string_unicode,
-    ProtocolValue::String("abc»d"),
+    ProtocolValue::String("abc»d".into()),
     [0x73, 0x00, 0x06, 0x61, 0x62, 0x63, 0xc2, 0xbb, 0x64]
 );
 generate_read_write_test!(
@@ -82,30 +84,139 @@ generate_read_write_test!(
 );
 generate_read_write_test!(
     string_array,
-    ProtocolValue::StringArray(vec!["abc", ""]),
+    ProtocolValue::StringArray(vec!["abc".into(), "".into()]),
     [97, 0, 2, 0, 3, 0x61, 0x62, 0x63, 0, 0]
 );
 generate_read_write_test!(
     array,
-    ProtocolValue::Array(vec![ProtocolValue::Bool(true), ProtocolValue::Bool(false), ProtocolValue::Bool(true)]),
+    ProtocolValue::Array(
+        111,
+        vec![ProtocolValue::Bool(true), ProtocolValue::Bool(false), ProtocolValue::Bool(true)]
+    ),
     [121, 0, 3, 111, 1, 0, 1]
 );
+generate_read_write_test!(array_empty, ProtocolValue::Array(42, vec![]), [121, 0, 0, 42]);
+generate_read_write_test!(array_empty_typed, ProtocolValue::Array(105, vec![]), [121, 0, 0, 105]);
 generate_read_write_test!(
     object_array,
-    ProtocolValue::ObjectArray(vec![ProtocolValue::String("abc"), ProtocolValue::Null(), ProtocolValue::Short(0x123)]),
+    ProtocolValue::ObjectArray(vec![
+        ProtocolValue::String("abc".into()),
+        ProtocolValue::Null(),
+        ProtocolValue::Short(0x123)
+    ]),
     [122, 0, 3, 115, 0, 3, 0x61, 0x62, 0x63, 42, 107, 0x01, 0x23]
 );
 
-// hashtable can only have 1 item because order is not deterministic
 generate_read_write_test!(
     hashtable,
-    ProtocolValue::Hashtable(hashmap! { ProtocolValue::Byte(0xFF) => ProtocolValue::Null(), }),
+    ProtocolValue::Hashtable(indexmap! { ProtocolValue::Byte(0xFF) => ProtocolValue::Null(), }),
     [0x68, 0x00, 0x01, 98, 0xFF, 42]
 );
+generate_read_write_test!(
+    hashtable_ordered,
+    ProtocolValue::Hashtable(indexmap! {
+        ProtocolValue::Byte(3) => ProtocolValue::Null(),
+        ProtocolValue::Byte(1) => ProtocolValue::Null(),
+        ProtocolValue::Byte(2) => ProtocolValue::Null(),
+    }),
+    [0x68, 0x00, 0x03, 98, 3, 42, 98, 1, 42, 98, 2, 42]
+);
+
+#[test]
+fn read_write_keeps_order() -> TestResult<()> {
+    let data = [
+        113u8, 0xDB, 0, 3, 0x10, 42, 0x01, 104, 0, 2, 115, 0, 1, 0x62, 42, 115, 0, 1, 0x61, 42, 0xFF, 42,
+    ];
+    let value = read_value(&mut Reader::new(data.as_ref()))?;
+
+    let writer = &mut Vec::new();
+    write_value_of_type(writer, value)?;
+    assert_eq!(writer, &data);
+
+    Ok(())
+}
 
-// TODO: Dictionary test
-// TODO: EventData test
-// TODO: OperationResponse test
+generate_read_write_test!(
+    dictionary,
+    ProtocolValue::Dictionary {
+        key_type: 115,
+        value_type: 105,
+        entries: indexmap! { ProtocolValue::String("abc".into()) => ProtocolValue::Integer(-559038737), },
+    },
+    [68, 115, 105, 0, 1, 0, 3, 0x61, 0x62, 0x63, 0xDE, 0xAD, 0xBE, 0xEF]
+);
+generate_read_write_test!(
+    dictionary_empty,
+    ProtocolValue::Dictionary {
+        key_type: 98,
+        value_type: 115,
+        entries: indexmap! {},
+    },
+    [68, 98, 115, 0, 0]
+);
+generate_read_write_test!(
+    dictionary_object_key,
+    ProtocolValue::Dictionary {
+        key_type: 0,
+        value_type: 111,
+        entries: indexmap! { ProtocolValue::Short(0x123) => ProtocolValue::Bool(true), },
+    },
+    [68, 0, 111, 0, 1, 107, 0x01, 0x23, 1]
+);
+generate_read_write_test!(
+    dictionary_object_value,
+    ProtocolValue::Dictionary {
+        key_type: 98,
+        value_type: 42,
+        entries: indexmap! { ProtocolValue::Byte(0xFF) => ProtocolValue::Null(), },
+    },
+    [68, 98, 42, 0, 1, 0xFF, 42]
+);
+generate_read_write_test!(
+    dictionary_nested,
+    ProtocolValue::Dictionary {
+        key_type: 0,
+        value_type: 68,
+        entries: indexmap! {
+            ProtocolValue::Byte(1) => ProtocolValue::Dictionary {
+                key_type: 115,
+                value_type: 0,
+                entries: indexmap! { ProtocolValue::String("a".into()) => ProtocolValue::Byte(2), },
+            },
+        },
+    },
+    [68, 0, 68, 0, 1, 98, 1, 115, 0, 0, 1, 0, 1, 0x61, 98, 2]
+);
+
+generate_read_write_test!(
+    event_data,
+    ProtocolValue::EventData(0xE2, indexmap! { 0xE4 => ProtocolValue::Integer(0x123), }),
+    [101, 0xE2, 0, 1, 0xE4, 105, 0, 0, 0x01, 0x23]
+);
+generate_read_write_test!(event_data_empty, ProtocolValue::EventData(0xFF, indexmap! {}), [101, 0xFF, 0, 0]);
+generate_read_write_test!(
+    operation_request,
+    ProtocolValue::OperationRequest(0xDB, indexmap! { 0xD1 => ProtocolValue::String("abc".into()), }),
+    [113, 0xDB, 0, 1, 0xD1, 115, 0, 3, 0x61, 0x62, 0x63]
+);
+generate_read_write_test!(
+    operation_response,
+    ProtocolValue::OperationResponse(0xDB, indexmap! { 0xCF => ProtocolValue::Byte(1), }, -1337, None),
+    [112, 0xDB, 0xFA, 0xC7, 42, 0, 1, 0xCF, 98, 1]
+);
+generate_read_write_test!(
+    operation_response_debug_string,
+    ProtocolValue::OperationResponse(0xDB, indexmap! {}, 0, Some("abc".into())),
+    [112, 0xDB, 0, 0, 115, 0, 3, 0x61, 0x62, 0x63, 0, 0]
+);
+generate_read_write_test!(
+    operation_request_nested,
+    ProtocolValue::OperationRequest(
+        0xDB,
+        indexmap! { 0xD0 => ProtocolValue::EventData(0x01, indexmap! { 0x02 => ProtocolValue::Null(), }), }
+    ),
+    [113, 0xDB, 0, 1, 0xD0, 101, 0x01, 0, 1, 0x02, 42]
+);
 
 generate_read_write_test!(
     vec2,
@@ -135,3 +246,284 @@ generate_read_write_test!(
     }),
     [99, 15, 0, 4, 0xDE, 0xAD, 0xBE, 0xEF]
 );
+
+#[test]
+fn into_owned_outlives_data() -> TestResult<()> {
+    let owned = {
+        let data = vec![104u8, 0, 1, 115, 0, 1, 0x61, 97, 0, 1, 0, 1, 0x62];
+        let value = read_value(&mut Reader::new(data.as_slice()))?;
+        assert!(matches!(&value, ProtocolValue::Hashtable(x) if matches!(x.keys().next(), Some(ProtocolValue::String(Cow::Borrowed(_))))));
+        value.into_owned()
+    };
+
+    assert_eq!(
+        owned,
+        ProtocolValue::Hashtable(indexmap! { ProtocolValue::String("a".into()) => ProtocolValue::StringArray(vec!["b".into()]), })
+    );
+
+    Ok(())
+}
+
+#[test]
+fn string_too_long() {
+    let reader = &mut Reader::new([0x73u8, 0x00, 0x04, 0x61, 0x62].as_ref());
+    match read_value(reader) {
+        Err(PhotonReadError::UnexpectedEof { needed: 4, available: 2 }) => (),
+        x => panic!("Expected UnexpectedEof, got {:?}", x),
+    }
+}
+
+#[test]
+fn string_array_too_long() {
+    let reader = &mut Reader::new([0x61u8, 0x00, 0x01, 0x00, 0x03, 0x61].as_ref());
+    match read_value(reader) {
+        Err(PhotonReadError::UnexpectedEof { needed: 3, available: 1 }) => (),
+        x => panic!("Expected UnexpectedEof, got {:?}", x),
+    }
+}
+
+#[test]
+fn byte_array_too_long() {
+    let reader = &mut Reader::new([0x78u8, 0xFF, 0xFF, 0xFF, 0xFF, 0x01].as_ref());
+    match read_value(reader) {
+        Err(PhotonReadError::UnexpectedEof {
+            needed: 0xFFFF_FFFF,
+            available: 1,
+        }) => (),
+        x => panic!("Expected UnexpectedEof, got {:?}", x),
+    }
+}
+
+#[test]
+fn custom_type_too_long() {
+    let reader = &mut Reader::new([0x63u8, 0x41, 0xFF, 0xFF, 0x01].as_ref());
+    match read_value(reader) {
+        Err(PhotonReadError::UnexpectedEof {
+            needed: 0xFFFF,
+            available: 1,
+        }) => (),
+        x => panic!("Expected UnexpectedEof, got {:?}", x),
+    }
+}
+
+#[test]
+fn integer_array_negative_length() {
+    let reader = &mut Reader::new([0x6Eu8, 0xFF, 0xFF, 0xFF, 0xFF].as_ref());
+    match read_value(reader) {
+        Err(PhotonReadError::CountLimitExceeded(_)) => (),
+        x => panic!("Expected CountLimitExceeded, got {:?}", x),
+    }
+}
+
+#[test]
+fn hashtable_array_key_unhashable() {
+    let reader = &mut Reader::new([0x68u8, 0x00, 0x01, 0x7A, 0x00, 0x00, 0x2A].as_ref());
+    match read_value(reader) {
+        Err(PhotonReadError::UnhashableKey) => (),
+        x => panic!("Expected UnhashableKey, got {:?}", x),
+    }
+}
+
+#[test]
+fn dictionary_type_mismatch() {
+    let value = ProtocolValue::Dictionary {
+        key_type: 115,
+        value_type: 105,
+        entries: indexmap! { ProtocolValue::String("a".into()) => ProtocolValue::Byte(1) },
+    };
+    match write_value_of_type(&mut Vec::new(), value) {
+        Err(PhotonWriteError::DictionaryTypeMismatch(105, 98)) => (),
+        x => panic!("Expected DictionaryTypeMismatch, got {:?}", x),
+    }
+}
+
+#[test]
+fn dictionary_too_many_entries() {
+    let value = ProtocolValue::Dictionary {
+        key_type: 105,
+        value_type: 42,
+        entries: (0..0x10000).map(|i| (ProtocolValue::Integer(i), ProtocolValue::Null())).collect(),
+    };
+    match write_value_of_type(&mut Vec::new(), value) {
+        Err(PhotonWriteError::TooManyParameters(0x10000)) => (),
+        x => panic!("Expected TooManyParameters, got {:?}", x),
+    }
+}
+
+#[test]
+fn array_type_mismatch() {
+    let value = ProtocolValue::Array(105, vec![ProtocolValue::Integer(1), ProtocolValue::Byte(1)]);
+    match write_value_of_type(&mut Vec::new(), value) {
+        Err(PhotonWriteError::ArrayTypeMismatch(105, 98)) => (),
+        x => panic!("Expected ArrayTypeMismatch, got {:?}", x),
+    }
+}
+
+#[test]
+fn length_overflow() {
+    let value = ProtocolValue::Custom(CustomType::Custom {
+        id: 1,
+        data: vec![0; 0x10000],
+    });
+    match write_value_of_type(&mut Vec::new(), value) {
+        Err(PhotonWriteError::TooManyParameters(0x10000)) => (),
+        x => panic!("Expected TooManyParameters, got {:?}", x),
+    }
+
+    let value = ProtocolValue::String("a".repeat(0x10000).into());
+    match write_value_of_type(&mut Vec::new(), value) {
+        Err(PhotonWriteError::TooManyParameters(0x10000)) => (),
+        x => panic!("Expected TooManyParameters, got {:?}", x),
+    }
+
+    let writer = &mut Vec::new();
+    write_value_of_type(writer, ProtocolValue::String("a".repeat(0xFFFF).into())).unwrap();
+    assert_eq!(&writer[..3], [115, 0xFF, 0xFF]);
+}
+
+#[test]
+fn dictionary_hashtable_key_unhashable() {
+    let reader = &mut Reader::new([0x44u8, 0x68, 0x2A, 0x00, 0x01, 0x00, 0x00, 0x2A].as_ref());
+    match read_value(reader) {
+        Err(PhotonReadError::UnhashableKey) => (),
+        x => panic!("Expected UnhashableKey, got {:?}", x),
+    }
+}
+
+#[test]
+fn depth_limit() {
+    // object arrays, each containing the next one
+    let mut data = [0x7Au8, 0x00, 0x01].repeat(10);
+    data.push(0x2A);
+
+    let limits = ReadLimits {
+        max_depth: 10,
+        ..ReadLimits::default()
+    };
+    let reader = &mut Reader::with_limits(&data, limits);
+    match read_value(reader) {
+        Err(PhotonReadError::DepthLimitExceeded(10)) => (),
+        x => panic!("Expected DepthLimitExceeded, got {:?}", x),
+    }
+
+    let limits = ReadLimits {
+        max_depth: 11,
+        ..ReadLimits::default()
+    };
+    assert!(read_value(&mut Reader::with_limits(&data, limits)).is_ok());
+}
+
+#[test]
+fn count_limit() {
+    let data = [0x7Au8, 0x00, 0x03, 0x2A, 0x2A, 0x2A];
+
+    let limits = ReadLimits {
+        max_count: 2,
+        ..ReadLimits::default()
+    };
+    match read_value(&mut Reader::with_limits(&data, limits)) {
+        Err(PhotonReadError::CountLimitExceeded(3)) => (),
+        x => panic!("Expected CountLimitExceeded, got {:?}", x),
+    }
+}
+
+#[test]
+fn allocation_limit() {
+    // arrays of 65535 nulls take 4 bytes each to serialize, but a lot more memory
+    let mut data = vec![0x79u8, 0xFF, 0xFF, 0x79];
+    data.extend([0xFFu8, 0xFF, 0x2A].repeat(0xFFFF));
+
+    match read_value(&mut Reader::new(&data)) {
+        Err(PhotonReadError::AllocationLimitExceeded(_)) => (),
+        x => panic!("Expected AllocationLimitExceeded, got {:?}", x),
+    }
+}
+
+#[test]
+fn packet_read_with_limits() {
+    let data = [0xF3u8, 0x02, 0x20, 0x00, 0x01, 0x42, 0x7A, 0x00, 0x00];
+    assert!(PhotonPacket::read(&data, Protocol::GpBinaryV16).is_ok());
+
+    let limits = ReadLimits {
+        max_depth: 0,
+        ..ReadLimits::default()
+    };
+    match PhotonPacket::read_with_limits(&data, Protocol::GpBinaryV16, limits) {
+        Err(PhotonReadError::DepthLimitExceeded(0)) => (),
+        x => panic!("Expected DepthLimitExceeded, got {:?}", x),
+    }
+}
+
+#[test]
+fn hash_collection_does_not_panic() {
+    use std::collections::hash_map::DefaultHasher;
+    use std::hash::{Hash, Hasher};
+
+    let values = [
+        ProtocolValue::Array(42, vec![ProtocolValue::Null()]),
+        ProtocolValue::Hashtable(indexmap! { ProtocolValue::Byte(1) => ProtocolValue::Null() }),
+        ProtocolValue::EventData(1, indexmap! {}),
+        ProtocolValue::Custom(CustomType::Vector2(1., 2.)),
+    ];
+    for value in values.iter() {
+        value.hash(&mut DefaultHasher::new());
+    }
+
+    let hash = |value: &ProtocolValue| {
+        let hasher = &mut DefaultHasher::new();
+        value.hash(hasher);
+        hasher.finish()
+    };
+    let a = ProtocolValue::Hashtable(indexmap! { ProtocolValue::Byte(1) => ProtocolValue::Null(), ProtocolValue::Byte(2) => ProtocolValue::Null() });
+    let b = ProtocolValue::Hashtable(indexmap! { ProtocolValue::Byte(2) => ProtocolValue::Null(), ProtocolValue::Byte(1) => ProtocolValue::Null() });
+    assert_eq!(a, b);
+    assert_eq!(hash(&a), hash(&b));
+
+    // -0.0 is equal to 0.0
+    for (a, b) in [
+        (ProtocolValue::Float(0.0), ProtocolValue::Float(-0.0)),
+        (ProtocolValue::Double(0.0), ProtocolValue::Double(-0.0)),
+        (
+            ProtocolValue::Custom(CustomType::Vector2(0.0, 1.0)),
+            ProtocolValue::Custom(CustomType::Vector2(-0.0, 1.0)),
+        ),
+    ] {
+        assert_eq!(a, b);
+        assert_eq!(hash(&a), hash(&b));
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_json_keeps_types() {
+    let value = ProtocolValue::Hashtable(indexmap! {
+        ProtocolValue::Byte(1) => ProtocolValue::Short(1),
+        ProtocolValue::Short(1) => ProtocolValue::Integer(1),
+        ProtocolValue::Integer(1) => ProtocolValue::ByteArray(vec![0xDE, 0xAD]),
+        ProtocolValue::String("dict".into()) => ProtocolValue::Dictionary {
+            key_type: 115,
+            value_type: 0,
+            entries: indexmap! { ProtocolValue::String("a".into()) => ProtocolValue::Null() },
+        },
+    });
+
+    let json = serde_json::to_string(&value).unwrap();
+    assert!(json.starts_with(r#"{"Hashtable":[[{"Byte":1},{"Short":1}],"#), "{}", json);
+    assert_eq!(serde_json::from_str::<ProtocolValue>(&json).unwrap(), value);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_json_packet_round_trip() -> TestResult<()> {
+    let data = [
+        0xF3, 0x07, 0x01, 0xFF, 0xFF, 0x2A, 0x00, 0x02, 0x00, 0x68, 0x00, 0x02, 0x62, 0x01, 0x6B, 0x00, 0x01, 0x69, 0x00, 0x00, 0x00, 0x01, 0x78,
+        0x00, 0x00, 0x00, 0x02, 0x01, 0x02, 0x01, 0x78, 0x00, 0x00, 0x00, 0x00,
+    ];
+    let packet = PhotonPacket::read(&data, Protocol::GpBinaryV16)?;
+
+    let json = serde_json::to_string(&packet).unwrap();
+    let packet: PhotonPacket = serde_json::from_str(&json).unwrap();
+    assert_eq!(packet.write(Protocol::GpBinaryV16)?, data);
+
+    Ok(())
+}
@@ -0,0 +1,114 @@
+//! Serialization for protocol 1.8, also called `GpBinaryV18`.
+//!
+//! Compared to protocol 1.6, this uses little-endian numbers, variable-length (and zig-zag encoded) integers and
+//! lengths, separate type codes for common values such as `0` or `true` and a typed array for every primitive type.
+
+mod read;
+mod tests;
+mod write;
+
+pub use read::*;
+pub use write::*;
+
+use super::super::*;
+
+// Type codes used by protocol 1.8
+const TYPE_UNKNOWN: u8 = 0;
+const TYPE_BOOLEAN: u8 = 2;
+const TYPE_BYTE: u8 = 3;
+const TYPE_SHORT: u8 = 4;
+const TYPE_FLOAT: u8 = 5;
+const TYPE_DOUBLE: u8 = 6;
+const TYPE_STRING: u8 = 7;
+const TYPE_NULL: u8 = 8;
+const TYPE_COMPRESSED_INT: u8 = 9;
+const TYPE_COMPRESSED_LONG: u8 = 10;
+const TYPE_INT1: u8 = 11;
+const TYPE_INT1_NEGATIVE: u8 = 12;
+const TYPE_INT2: u8 = 13;
+const TYPE_INT2_NEGATIVE: u8 = 14;
+const TYPE_LONG1: u8 = 15;
+const TYPE_LONG1_NEGATIVE: u8 = 16;
+const TYPE_LONG2: u8 = 17;
+const TYPE_LONG2_NEGATIVE: u8 = 18;
+const TYPE_CUSTOM: u8 = 19;
+const TYPE_DICTIONARY: u8 = 20;
+const TYPE_HASHTABLE: u8 = 21;
+const TYPE_OBJECT_ARRAY: u8 = 23;
+const TYPE_OPERATION_REQUEST: u8 = 24;
+const TYPE_OPERATION_RESPONSE: u8 = 25;
+const TYPE_EVENT_DATA: u8 = 26;
+const TYPE_BOOLEAN_FALSE: u8 = 27;
+const TYPE_BOOLEAN_TRUE: u8 = 28;
+const TYPE_SHORT_ZERO: u8 = 29;
+const TYPE_INT_ZERO: u8 = 30;
+const TYPE_LONG_ZERO: u8 = 31;
+const TYPE_FLOAT_ZERO: u8 = 32;
+const TYPE_DOUBLE_ZERO: u8 = 33;
+const TYPE_BYTE_ZERO: u8 = 34;
+const TYPE_ARRAY: u8 = 64;
+const TYPE_BOOLEAN_ARRAY: u8 = TYPE_ARRAY | TYPE_BOOLEAN;
+const TYPE_BYTE_ARRAY: u8 = TYPE_ARRAY | TYPE_BYTE;
+const TYPE_SHORT_ARRAY: u8 = TYPE_ARRAY | TYPE_SHORT;
+const TYPE_FLOAT_ARRAY: u8 = TYPE_ARRAY | TYPE_FLOAT;
+const TYPE_DOUBLE_ARRAY: u8 = TYPE_ARRAY | TYPE_DOUBLE;
+const TYPE_STRING_ARRAY: u8 = TYPE_ARRAY | TYPE_STRING;
+const TYPE_COMPRESSED_INT_ARRAY: u8 = TYPE_ARRAY | TYPE_COMPRESSED_INT;
+const TYPE_COMPRESSED_LONG_ARRAY: u8 = TYPE_ARRAY | TYPE_COMPRESSED_LONG;
+const TYPE_CUSTOM_TYPE_ARRAY: u8 = TYPE_ARRAY | TYPE_CUSTOM;
+const TYPE_DICTIONARY_ARRAY: u8 = TYPE_ARRAY | TYPE_DICTIONARY;
+const TYPE_HASHTABLE_ARRAY: u8 = TYPE_ARRAY | TYPE_HASHTABLE;
+/// Custom types with an id below 100 are written as `TYPE_CUSTOM_SLIM + id`, without a separate id byte
+const TYPE_CUSTOM_SLIM: u8 = 128;
+const CUSTOM_SLIM_MAX_ID: u8 = 99;
+
+/// Converts the key or value type of a dictionary to the protocol 1.6 type stored in `ProtocolValue::Dictionary`.
+fn dictionary_type_from_v18(protocol_type: u8) -> PhotonReadResult<u8> {
+    match protocol_type {
+        TYPE_UNKNOWN => Ok(0),
+        TYPE_BOOLEAN => Ok(111),
+        TYPE_BYTE => Ok(98),
+        TYPE_SHORT => Ok(107),
+        TYPE_FLOAT => Ok(102),
+        TYPE_DOUBLE => Ok(100),
+        TYPE_STRING => Ok(115),
+        TYPE_COMPRESSED_INT => Ok(105),
+        TYPE_COMPRESSED_LONG => Ok(108),
+        TYPE_CUSTOM => Ok(99),
+        TYPE_HASHTABLE => Ok(104),
+        TYPE_OBJECT_ARRAY => Ok(122),
+        TYPE_OPERATION_REQUEST => Ok(113),
+        TYPE_OPERATION_RESPONSE => Ok(112),
+        TYPE_EVENT_DATA => Ok(101),
+        TYPE_BYTE_ARRAY => Ok(120),
+        TYPE_STRING_ARRAY => Ok(97),
+        TYPE_COMPRESSED_INT_ARRAY => Ok(110),
+        // nested dictionaries and the other arrays store extra type info in the dictionary header
+        _ => Err(PhotonReadError::UnsupportedProtocolValueType(Protocol::GpBinaryV18, protocol_type)),
+    }
+}
+
+/// Converts the key or value type of a `ProtocolValue::Dictionary` to the type used by protocol 1.8.
+fn dictionary_type_to_v18(protocol_type: u8) -> PhotonWriteResult<u8> {
+    match protocol_type {
+        0 | 42 => Ok(TYPE_UNKNOWN),
+        111 => Ok(TYPE_BOOLEAN),
+        98 => Ok(TYPE_BYTE),
+        107 => Ok(TYPE_SHORT),
+        102 => Ok(TYPE_FLOAT),
+        100 => Ok(TYPE_DOUBLE),
+        115 => Ok(TYPE_STRING),
+        105 => Ok(TYPE_COMPRESSED_INT),
+        108 => Ok(TYPE_COMPRESSED_LONG),
+        99 => Ok(TYPE_CUSTOM),
+        104 => Ok(TYPE_HASHTABLE),
+        122 => Ok(TYPE_OBJECT_ARRAY),
+        113 => Ok(TYPE_OPERATION_REQUEST),
+        112 => Ok(TYPE_OPERATION_RESPONSE),
+        101 => Ok(TYPE_EVENT_DATA),
+        120 => Ok(TYPE_BYTE_ARRAY),
+        97 => Ok(TYPE_STRING_ARRAY),
+        110 => Ok(TYPE_COMPRESSED_INT_ARRAY),
+        _ => Err(PhotonWriteError::UnsupportedProtocolValueType(Protocol::GpBinaryV18, protocol_type)),
+    }
+}
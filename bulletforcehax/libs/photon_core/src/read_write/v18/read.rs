@@ -0,0 +1,280 @@
+use byteorder::{LittleEndian, ReadBytesExt};
+use indexmap::IndexMap;
+use std::borrow::Cow;
+
+use super::super::{hashable_key, read_custom_type, CollectionHeader, MapEntry, ParameterEntry, Reader};
+use super::*;
+
+pub fn read_debug_string<'a>(c: &mut Reader<'a>) -> PhotonReadResult<Option<Cow<'a, str>>> {
+    match read_value(c)? {
+        ProtocolValue::Null() => Ok(None),
+        ProtocolValue::String(string) => Ok(Some(string)),
+        _ => Err(PhotonReadError::InvalidDebugStringType),
+    }
+}
+
+pub fn read_value<'a>(c: &mut Reader<'a>) -> PhotonReadResult<ProtocolValue<'a>> {
+    let protocol_type = c.read_u8()?;
+    read_value_of_type(c, protocol_type)
+}
+
+pub fn read_value_of_type<'a>(c: &mut Reader<'a>, protocol_type: u8) -> PhotonReadResult<ProtocolValue<'a>> {
+    c.nested(|c| read_value_contents(c, protocol_type))
+}
+
+fn read_value_contents<'a>(c: &mut Reader<'a>, protocol_type: u8) -> PhotonReadResult<ProtocolValue<'a>> {
+    match protocol_type {
+        TYPE_UNKNOWN | TYPE_NULL => Ok(ProtocolValue::Null()),
+        TYPE_BOOLEAN => Ok(ProtocolValue::Bool(c.read_u8()? != 0)),
+        TYPE_BYTE => Ok(ProtocolValue::Byte(c.read_u8()?)),
+        TYPE_SHORT => Ok(ProtocolValue::Short(c.read_i16::<LittleEndian>()?)),
+        TYPE_FLOAT => Ok(ProtocolValue::Float(c.read_f32::<LittleEndian>()?)),
+        TYPE_DOUBLE => Ok(ProtocolValue::Double(c.read_f64::<LittleEndian>()?)),
+        TYPE_STRING => Ok(ProtocolValue::String(Cow::Borrowed(read_string(c)?))),
+        TYPE_COMPRESSED_INT => Ok(ProtocolValue::Integer(read_compressed_i32(c)?)),
+        TYPE_COMPRESSED_LONG => Ok(ProtocolValue::Long(read_compressed_i64(c)?)),
+        TYPE_INT1 => Ok(ProtocolValue::Integer(i32::from(c.read_u8()?))),
+        TYPE_INT1_NEGATIVE => Ok(ProtocolValue::Integer(-i32::from(c.read_u8()?))),
+        TYPE_INT2 => Ok(ProtocolValue::Integer(i32::from(c.read_u16::<LittleEndian>()?))),
+        TYPE_INT2_NEGATIVE => Ok(ProtocolValue::Integer(-i32::from(c.read_u16::<LittleEndian>()?))),
+        TYPE_LONG1 => Ok(ProtocolValue::Long(i64::from(c.read_u8()?))),
+        TYPE_LONG1_NEGATIVE => Ok(ProtocolValue::Long(-i64::from(c.read_u8()?))),
+        TYPE_LONG2 => Ok(ProtocolValue::Long(i64::from(c.read_u16::<LittleEndian>()?))),
+        TYPE_LONG2_NEGATIVE => Ok(ProtocolValue::Long(-i64::from(c.read_u16::<LittleEndian>()?))),
+        TYPE_CUSTOM => {
+            let id = c.read_u8()?;
+            Ok(ProtocolValue::Custom(read_custom_type_with_length(c, id)?))
+        }
+        TYPE_DICTIONARY => {
+            let key_type = c.read_u8()?;
+            let value_type = c.read_u8()?;
+            Ok(ProtocolValue::Dictionary {
+                key_type: dictionary_type_from_v18(key_type)?,
+                value_type: dictionary_type_from_v18(value_type)?,
+                entries: read_dictionary(c, key_type, value_type)?,
+            })
+        }
+        TYPE_HASHTABLE => Ok(ProtocolValue::Hashtable(read_hash_table(c)?)),
+        TYPE_OBJECT_ARRAY => Ok(ProtocolValue::ObjectArray(read_value_array(c)?)),
+        TYPE_OPERATION_REQUEST => Ok(ProtocolValue::OperationRequest(c.read_u8()?, read_parameter_table(c)?)),
+        TYPE_OPERATION_RESPONSE => {
+            let operation_type = c.read_u8()?;
+            let return_code = c.read_i16::<LittleEndian>()?;
+            let debug_string = read_debug_string(c)?;
+            let parameter_table = read_parameter_table(c)?;
+            Ok(ProtocolValue::OperationResponse(
+                operation_type,
+                parameter_table,
+                return_code,
+                debug_string,
+            ))
+        }
+        TYPE_EVENT_DATA => Ok(ProtocolValue::EventData(c.read_u8()?, read_parameter_table(c)?)),
+        TYPE_BOOLEAN_FALSE => Ok(ProtocolValue::Bool(false)),
+        TYPE_BOOLEAN_TRUE => Ok(ProtocolValue::Bool(true)),
+        TYPE_SHORT_ZERO => Ok(ProtocolValue::Short(0)),
+        TYPE_INT_ZERO => Ok(ProtocolValue::Integer(0)),
+        TYPE_LONG_ZERO => Ok(ProtocolValue::Long(0)),
+        TYPE_FLOAT_ZERO => Ok(ProtocolValue::Float(0.)),
+        TYPE_DOUBLE_ZERO => Ok(ProtocolValue::Double(0.)),
+        TYPE_BYTE_ZERO => Ok(ProtocolValue::Byte(0)),
+        TYPE_ARRAY => {
+            let values = read_value_array(c)?;
+            Ok(ProtocolValue::Array(get_element_type(&values), values))
+        }
+        TYPE_BOOLEAN_ARRAY => {
+            let len = read_compressed_u32(c)? as usize;
+            c.reserve::<ProtocolValue>(len, 0)?;
+            c.ensure_remaining(len.div_ceil(8))?;
+            let mut vec = Vec::with_capacity(len);
+            let mut byte = 0;
+            for i in 0..len {
+                // packed as bits, least significant bit first
+                if i % 8 == 0 {
+                    byte = c.read_u8()?;
+                }
+                vec.push(ProtocolValue::Bool(byte & (1 << (i % 8)) != 0));
+            }
+            Ok(ProtocolValue::Array(111, vec))
+        }
+        TYPE_BYTE_ARRAY => {
+            let len = read_compressed_u32(c)? as usize;
+            Ok(ProtocolValue::ByteArray(c.read_bytes(len)?))
+        }
+        TYPE_SHORT_ARRAY => Ok(ProtocolValue::Array(107, read_array_of_type(c, TYPE_SHORT)?)),
+        TYPE_FLOAT_ARRAY => Ok(ProtocolValue::Array(102, read_array_of_type(c, TYPE_FLOAT)?)),
+        TYPE_DOUBLE_ARRAY => Ok(ProtocolValue::Array(100, read_array_of_type(c, TYPE_DOUBLE)?)),
+        TYPE_STRING_ARRAY => {
+            let len = read_compressed_u32(c)? as usize;
+            c.reserve::<Cow<str>>(len, 1)?;
+            let mut vec = Vec::with_capacity(len);
+            for _ in 0..len {
+                vec.push(Cow::Borrowed(read_string(c)?));
+            }
+            Ok(ProtocolValue::StringArray(vec))
+        }
+        TYPE_COMPRESSED_INT_ARRAY => {
+            let len = read_compressed_u32(c)? as usize;
+            c.reserve::<i32>(len, 1)?;
+            let mut vec = Vec::with_capacity(len);
+            for _ in 0..len {
+                vec.push(read_compressed_i32(c)?);
+            }
+            Ok(ProtocolValue::IntegerArray(vec))
+        }
+        TYPE_COMPRESSED_LONG_ARRAY => Ok(ProtocolValue::Array(108, read_array_of_type(c, TYPE_COMPRESSED_LONG)?)),
+        TYPE_CUSTOM_TYPE_ARRAY => {
+            let len = read_compressed_u32(c)? as usize;
+            let id = c.read_u8()?;
+            c.reserve::<ProtocolValue>(len, 1)?;
+            let mut vec = Vec::with_capacity(len);
+            for _ in 0..len {
+                vec.push(ProtocolValue::Custom(read_custom_type_with_length(c, id)?));
+            }
+            Ok(ProtocolValue::Array(99, vec))
+        }
+        TYPE_HASHTABLE_ARRAY => Ok(ProtocolValue::Array(104, read_array_of_type(c, TYPE_HASHTABLE)?)),
+        TYPE_DICTIONARY_ARRAY => Err(PhotonReadError::UnsupportedProtocolValueType(Protocol::GpBinaryV18, protocol_type)),
+        TYPE_CUSTOM_SLIM..=255 if protocol_type - TYPE_CUSTOM_SLIM <= CUSTOM_SLIM_MAX_ID => {
+            Ok(ProtocolValue::Custom(read_custom_type_with_length(c, protocol_type - TYPE_CUSTOM_SLIM)?))
+        }
+        _ => Err(PhotonReadError::UnknownProtocolValueType(protocol_type)),
+    }
+}
+
+/// Reads an unsigned integer that is stored in groups of 7 bits, least significant group first.
+pub fn read_compressed_u32(c: &mut Reader) -> PhotonReadResult<u32> {
+    let mut value = 0u32;
+    for shift in (0..35).step_by(7) {
+        let byte = c.read_u8()?;
+        value |= u32::from(byte & 0x7F) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(PhotonReadError::InvalidCompressedInteger)
+}
+
+/// Reads an unsigned long that is stored in groups of 7 bits, least significant group first.
+pub fn read_compressed_u64(c: &mut Reader) -> PhotonReadResult<u64> {
+    let mut value = 0u64;
+    for shift in (0..70).step_by(7) {
+        let byte = c.read_u8()?;
+        value |= u64::from(byte & 0x7F) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(PhotonReadError::InvalidCompressedInteger)
+}
+
+/// Reads a zig-zag encoded compressed integer.
+pub fn read_compressed_i32(c: &mut Reader) -> PhotonReadResult<i32> {
+    let value = read_compressed_u32(c)?;
+    Ok((value >> 1) as i32 ^ -((value & 1) as i32))
+}
+
+/// Reads a zig-zag encoded compressed long.
+pub fn read_compressed_i64(c: &mut Reader) -> PhotonReadResult<i64> {
+    let value = read_compressed_u64(c)?;
+    Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+}
+
+pub fn read_string<'a>(c: &mut Reader<'a>) -> PhotonReadResult<&'a str> {
+    let len = read_compressed_u32(c)? as usize;
+    c.read_str(len)
+}
+
+fn read_custom_type_with_length(c: &mut Reader, id: u8) -> PhotonReadResult<CustomType> {
+    let len = read_compressed_u32(c)? as usize;
+    read_custom_type(c, id, len)
+}
+
+/// Reads an array where each item has the same, predetermined, type.
+fn read_array_of_type<'a>(c: &mut Reader<'a>, protocol_type: u8) -> PhotonReadResult<Vec<ProtocolValue<'a>>> {
+    let len = read_compressed_u32(c)? as usize;
+    c.reserve::<ProtocolValue>(len, 0)?;
+    let mut ret = Vec::with_capacity(len);
+    for _i in 0..len {
+        ret.push(read_value_of_type(c, protocol_type)?);
+    }
+    Ok(ret)
+}
+
+/// Gets the protocol 1.6 type of the elements of an array of typed values, or `42` if they do not have a single type.
+fn get_element_type(values: &[ProtocolValue]) -> u8 {
+    match values.first().map(ProtocolValue::type_code) {
+        Some(protocol_type) if values.iter().all(|value| value.type_code() == protocol_type) => protocol_type,
+        _ => 42,
+    }
+}
+
+pub fn read_value_array<'a>(c: &mut Reader<'a>) -> PhotonReadResult<Vec<ProtocolValue<'a>>> {
+    let len = read_compressed_u32(c)? as usize;
+    c.reserve::<ProtocolValue>(len, 1)?;
+    let mut ret = Vec::with_capacity(len);
+    for _i in 0..len {
+        ret.push(read_value(c)?);
+    }
+    Ok(ret)
+}
+
+pub fn read_hash_table<'a>(c: &mut Reader<'a>) -> PhotonReadResult<IndexMap<ProtocolValue<'a>, ProtocolValue<'a>>> {
+    let len = read_compressed_u32(c)? as usize;
+    c.reserve::<MapEntry>(len, 2)?;
+    let mut ret = IndexMap::with_capacity(len);
+    for _i in 0..len {
+        ret.insert(hashable_key(read_value(c)?)?, read_value(c)?);
+    }
+    Ok(ret)
+}
+
+/// Reads the entries of a dictionary, using the protocol 1.8 key and value types.
+pub fn read_dictionary<'a>(c: &mut Reader<'a>, key_type: u8, value_type: u8) -> PhotonReadResult<IndexMap<ProtocolValue<'a>, ProtocolValue<'a>>> {
+    let len = read_compressed_u32(c)? as usize;
+    c.reserve::<MapEntry>(len, 0)?;
+    let mut ret = IndexMap::with_capacity(len);
+    for _i in 0..len {
+        ret.insert(
+            hashable_key(read_value_of_dictionary_type(c, key_type)?)?,
+            read_value_of_dictionary_type(c, value_type)?,
+        );
+    }
+    Ok(ret)
+}
+
+/// Reads a dictionary key or value, which only has its own type byte if the dictionary type is `object`.
+fn read_value_of_dictionary_type<'a>(c: &mut Reader<'a>, protocol_type: u8) -> PhotonReadResult<ProtocolValue<'a>> {
+    match protocol_type {
+        TYPE_UNKNOWN => read_value(c),
+        _ => read_value_of_type(c, protocol_type),
+    }
+}
+
+/// Reads a parameter table. Unlike in protocol 1.6, its length is a single byte.
+pub fn read_parameter_table<'a>(c: &mut Reader<'a>) -> PhotonReadResult<IndexMap<u8, ProtocolValue<'a>>> {
+    let len = read_parameter_count(c)?;
+    c.reserve::<ParameterEntry>(len, 2)?;
+    let mut ret = IndexMap::with_capacity(len);
+    for _i in 0..len {
+        ret.insert(c.read_u8()?, read_value(c)?);
+    }
+    Ok(ret)
+}
+
+pub fn read_parameter_count(c: &mut Reader) -> PhotonReadResult<usize> {
+    Ok(c.read_u8()? as usize)
+}
+
+/// Reads the type and length of a hashtable or object array, but not its items. Reads nothing for other types.
+pub fn read_collection_header(c: &mut Reader) -> PhotonReadResult<Option<CollectionHeader>> {
+    let position = c.position();
+    match c.read_u8()? {
+        TYPE_HASHTABLE => Ok(Some(CollectionHeader::Hashtable(read_compressed_u32(c)? as usize))),
+        TYPE_OBJECT_ARRAY => Ok(Some(CollectionHeader::ObjectArray(read_compressed_u32(c)? as usize))),
+        _ => {
+            c.set_position(position);
+            Ok(None)
+        }
+    }
+}
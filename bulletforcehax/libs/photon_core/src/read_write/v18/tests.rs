@@ -0,0 +1,344 @@
+#![cfg(test)]
+
+use super::super::super::*;
+use super::super::Reader;
+use super::*;
+use indexmap::indexmap;
+
+// union error of PhotonReadError and PhotonWriteError
+// fields are only read through the `Debug` impl when a test fails
+#[allow(dead_code)]
+#[derive(Debug)]
+enum TestError {
+    Read(PhotonReadError),
+    Write(PhotonWriteError),
+}
+
+impl From<PhotonReadError> for TestError {
+    fn from(err: PhotonReadError) -> Self {
+        TestError::Read(err)
+    }
+}
+
+impl From<PhotonWriteError> for TestError {
+    fn from(err: PhotonWriteError) -> Self {
+        TestError::Write(err)
+    }
+}
+
+type TestResult<T> = Result<T, TestError>;
+
+macro_rules! generate_read_write_test {
+    ($test_name: ident, $obj: expr, $data: expr) => {
+        paste::item! {
+            #[test]
+            fn [<can_read_ $test_name>]() -> TestResult<()> {
+                let reader = &mut Reader::new($data.as_ref());
+                let t = read_value(reader)?;
+                assert_eq!(t, $obj);
+
+                Ok(())
+            }
+
+            #[test]
+            fn [<can_write_ $test_name>]() -> TestResult<()> {
+                let writer = &mut Vec::new();
+                write_value_of_type(writer, $obj)?;
+                assert_eq!(writer, &$data);
+
+                Ok(())
+            }
+        }
+    };
+}
+
+macro_rules! generate_read_test {
+    ($test_name: ident, $obj: expr, $data: expr) => {
+        paste::item! {
+            #[test]
+            fn [<can_read_ $test_name>]() -> TestResult<()> {
+                let reader = &mut Reader::new($data.as_ref());
+                let t = read_value(reader)?;
+                assert_eq!(t, $obj);
+
+                Ok(())
+            }
+        }
+    };
+}
+
+generate_read_write_test!(null, ProtocolValue::Null(), [8u8]);
+generate_read_write_test!(bool_true, ProtocolValue::Bool(true), [28u8]);
+generate_read_write_test!(bool_false, ProtocolValue::Bool(false), [27u8]);
+generate_read_write_test!(u8, ProtocolValue::Byte(0x90), [3, 0x90]);
+generate_read_write_test!(u8_zero, ProtocolValue::Byte(0), [34u8]);
+generate_read_write_test!(s16, ProtocolValue::Short(-1337), [4, 0xC7, 0xFA]);
+generate_read_write_test!(s16_zero, ProtocolValue::Short(0), [29u8]);
+generate_read_write_test!(s32_zero, ProtocolValue::Integer(0), [30u8]);
+generate_read_write_test!(s32_1, ProtocolValue::Integer(42), [11, 42]);
+generate_read_write_test!(s32_1_negative, ProtocolValue::Integer(-42), [12, 42]);
+generate_read_write_test!(s32_2, ProtocolValue::Integer(1337), [13, 0x39, 0x05]);
+generate_read_write_test!(s32_2_negative, ProtocolValue::Integer(-1337), [14, 0x39, 0x05]);
+generate_read_write_test!(s32, ProtocolValue::Integer(-559038737), [9, 0xA1, 0x84, 0x92, 0x95, 0x04]);
+generate_read_write_test!(s32_min, ProtocolValue::Integer(i32::MIN), [9, 0xFF, 0xFF, 0xFF, 0xFF, 0x0F]);
+generate_read_write_test!(s64_zero, ProtocolValue::Long(0), [31u8]);
+generate_read_write_test!(s64_1, ProtocolValue::Long(42), [15, 42]);
+generate_read_write_test!(s64_2_negative, ProtocolValue::Long(-1337), [18, 0x39, 0x05]);
+generate_read_write_test!(
+    s64,
+    ProtocolValue::Long(-3886136854700967234),
+    [10, 0x83, 0x95, 0x8A, 0xD0, 0xA6, 0xB8, 0xAA, 0xEE, 0x6B]
+);
+generate_read_write_test!(f32, ProtocolValue::Float(42.), [5, 0x00, 0x00, 0x28, 0x42]);
+generate_read_write_test!(f32_zero, ProtocolValue::Float(0.), [32u8]);
+generate_read_write_test!(f64, ProtocolValue::Double(13.37), [6, 0x3d, 0x0a, 0xd7, 0xa3, 0x70, 0xbd, 0x2a, 0x40]);
+generate_read_write_test!(f64_zero, ProtocolValue::Double(0.), [33u8]);
+generate_read_write_test!(string, ProtocolValue::String("abc".into()), [7, 3, 0x61, 0x62, 0x63]);
+generate_read_write_test!(
+    string_unicode,
+    ProtocolValue::String("abc»d".into()),
+    [7, 6, 0x61, 0x62, 0x63, 0xc2, 0xbb, 0x64]
+);
+generate_read_write_test!(
+    byte_array,
+    ProtocolValue::ByteArray(vec![0xDE, 0xAD, 0xBE, 0xEF]),
+    [67, 4, 0xDE, 0xAD, 0xBE, 0xEF]
+);
+generate_read_write_test!(int_array, ProtocolValue::IntegerArray(vec![-1, 100000]), [73, 2, 0x01, 0xC0, 0x9A, 0x0C]);
+generate_read_write_test!(
+    string_array,
+    ProtocolValue::StringArray(vec!["abc".into(), "".into()]),
+    [71, 2, 3, 0x61, 0x62, 0x63, 0]
+);
+generate_read_write_test!(
+    bool_array,
+    ProtocolValue::Array(
+        111,
+        vec![
+            ProtocolValue::Bool(true),
+            ProtocolValue::Bool(false),
+            ProtocolValue::Bool(true),
+            ProtocolValue::Bool(true),
+            ProtocolValue::Bool(false),
+            ProtocolValue::Bool(false),
+            ProtocolValue::Bool(false),
+            ProtocolValue::Bool(false),
+            ProtocolValue::Bool(true),
+        ]
+    ),
+    [66, 9, 0b0000_1101, 0b0000_0001]
+);
+generate_read_write_test!(
+    short_array,
+    ProtocolValue::Array(107, vec![ProtocolValue::Short(-1337), ProtocolValue::Short(0)]),
+    [68, 2, 0xC7, 0xFA, 0x00, 0x00]
+);
+generate_read_write_test!(
+    long_array,
+    ProtocolValue::Array(108, vec![ProtocolValue::Long(-1), ProtocolValue::Long(150)]),
+    [74, 2, 0x01, 0xAC, 0x02]
+);
+generate_read_write_test!(
+    custom_array,
+    ProtocolValue::Array(
+        99,
+        vec![ProtocolValue::Custom(CustomType::Player(1)), ProtocolValue::Custom(CustomType::Player(2))]
+    ),
+    [83, 2, 0x50, 4, 0, 0, 0, 1, 4, 0, 0, 0, 2]
+);
+generate_read_write_test!(
+    array_in_array,
+    ProtocolValue::Array(115, vec![ProtocolValue::String("a".into()), ProtocolValue::String("b".into())]),
+    [64, 2, 7, 1, 0x61, 7, 1, 0x62]
+);
+generate_read_write_test!(array_empty, ProtocolValue::Array(42, vec![]), [64, 0]);
+generate_read_write_test!(float_array_empty, ProtocolValue::Array(102, vec![]), [69, 0]);
+generate_read_write_test!(bool_array_empty, ProtocolValue::Array(111, vec![]), [66, 0]);
+generate_read_write_test!(
+    object_array,
+    ProtocolValue::ObjectArray(vec![
+        ProtocolValue::String("abc".into()),
+        ProtocolValue::Null(),
+        ProtocolValue::Short(0x123)
+    ]),
+    [23, 3, 7, 3, 0x61, 0x62, 0x63, 8, 4, 0x23, 0x01]
+);
+
+generate_read_write_test!(
+    hashtable,
+    ProtocolValue::Hashtable(indexmap! { ProtocolValue::Byte(0xFF) => ProtocolValue::Null(), }),
+    [21, 1, 3, 0xFF, 8]
+);
+
+generate_read_write_test!(
+    dictionary,
+    ProtocolValue::Dictionary {
+        key_type: 115,
+        value_type: 105,
+        entries: indexmap! { ProtocolValue::String("abc".into()) => ProtocolValue::Integer(-559038737), },
+    },
+    [20, 7, 9, 1, 3, 0x61, 0x62, 0x63, 0xA1, 0x84, 0x92, 0x95, 0x04]
+);
+generate_read_write_test!(
+    dictionary_object_key,
+    ProtocolValue::Dictionary {
+        key_type: 0,
+        value_type: 111,
+        entries: indexmap! { ProtocolValue::Short(0x123) => ProtocolValue::Bool(true), },
+    },
+    [20, 0, 2, 1, 4, 0x23, 0x01, 1]
+);
+
+generate_read_write_test!(
+    event_data,
+    ProtocolValue::EventData(0xE2, indexmap! { 0xE4 => ProtocolValue::Integer(0x123), }),
+    [26, 0xE2, 1, 0xE4, 13, 0x23, 0x01]
+);
+generate_read_write_test!(
+    operation_request,
+    ProtocolValue::OperationRequest(0xDB, indexmap! { 0xD1 => ProtocolValue::String("abc".into()), }),
+    [24, 0xDB, 1, 0xD1, 7, 3, 0x61, 0x62, 0x63]
+);
+generate_read_write_test!(
+    operation_response,
+    ProtocolValue::OperationResponse(0xDB, indexmap! { 0xCF => ProtocolValue::Byte(1), }, -1337, None),
+    [25, 0xDB, 0xC7, 0xFA, 8, 1, 0xCF, 3, 1]
+);
+generate_read_write_test!(
+    operation_response_debug_string,
+    ProtocolValue::OperationResponse(0xDB, indexmap! {}, 0, Some("abc".into())),
+    [25, 0xDB, 0, 0, 7, 3, 0x61, 0x62, 0x63, 0]
+);
+
+generate_read_write_test!(
+    vec2,
+    ProtocolValue::Custom(CustomType::Vector2(1., 13.37)),
+    [0xD7, 8, 0x3f, 0x80, 0x00, 0x00, 0x41, 0x55, 0xeb, 0x85]
+);
+generate_read_write_test!(
+    player,
+    ProtocolValue::Custom(CustomType::Player(0x00C0FFEE)),
+    [0xD0, 4, 0x00, 0xC0, 0xFF, 0xEE]
+);
+generate_read_write_test!(
+    other_custom,
+    ProtocolValue::Custom(CustomType::Custom {
+        id: 200,
+        data: vec![0xDE, 0xAD, 0xBE, 0xEF]
+    }),
+    [19, 200, 4, 0xDE, 0xAD, 0xBE, 0xEF]
+);
+
+// these are never written, since a shorter representation exists
+generate_read_test!(bool_typed, ProtocolValue::Bool(true), [2, 1]);
+generate_read_test!(s32_typed, ProtocolValue::Integer(1), [9, 2]);
+generate_read_test!(custom_typed, ProtocolValue::Custom(CustomType::Player(1)), [19, 0x50, 4, 0, 0, 0, 1]);
+
+#[test]
+fn compressed_int_too_long() {
+    let reader = &mut Reader::new([9u8, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01].as_ref());
+    match read_value(reader) {
+        Err(PhotonReadError::InvalidCompressedInteger) => (),
+        x => panic!("Expected InvalidCompressedInteger, got {:?}", x),
+    }
+}
+
+#[test]
+fn dictionary_array_unsupported() {
+    let reader = &mut Reader::new([84u8, 0].as_ref());
+    match read_value(reader) {
+        Err(PhotonReadError::UnsupportedProtocolValueType(Protocol::GpBinaryV18, 84)) => (),
+        x => panic!("Expected UnsupportedProtocolValueType, got {:?}", x),
+    }
+}
+
+#[test]
+fn dictionary_type_mismatch() {
+    let value = ProtocolValue::Dictionary {
+        key_type: 98,
+        value_type: 42,
+        entries: indexmap! { ProtocolValue::Integer(1) => ProtocolValue::Null() },
+    };
+    match write_value_of_type(&mut Vec::new(), value) {
+        Err(PhotonWriteError::DictionaryTypeMismatch(98, 105)) => (),
+        x => panic!("Expected DictionaryTypeMismatch, got {:?}", x),
+    }
+}
+
+#[test]
+fn too_many_parameters() {
+    let params = (0..=255).map(|key| (key, ProtocolValue::Null())).collect();
+    match write_parameter_table(&mut Vec::new(), params) {
+        Err(PhotonWriteError::TooManyParameters(256)) => (),
+        x => panic!("Expected TooManyParameters, got {:?}", x),
+    }
+
+    let params: ParameterTable = (0..255).map(|key| (key, ProtocolValue::Null())).collect();
+    let writer = &mut Vec::new();
+    write_parameter_table(writer, params).unwrap();
+    assert_eq!(writer[0], 255);
+}
+
+#[test]
+fn nested_dictionary_unsupported() {
+    let value = ProtocolValue::Dictionary {
+        key_type: 0,
+        value_type: 68,
+        entries: indexmap! {},
+    };
+    match write_value_of_type(&mut Vec::new(), value) {
+        Err(PhotonWriteError::UnsupportedProtocolValueType(Protocol::GpBinaryV18, 68)) => (),
+        x => panic!("Expected UnsupportedProtocolValueType, got {:?}", x),
+    }
+}
+
+#[test]
+fn packet_round_trip() -> TestResult<()> {
+    let data = [0xF3, 3, 0xE2, 0xC7, 0xFA, 7, 1, 0x61, 1, 0xF5, 30];
+    let packet = PhotonPacket::read(&data, Protocol::GpBinaryV18)?;
+    match &packet {
+        PhotonPacket::OperationResponse(0xE2, params, -1337, Some(debug_string)) => {
+            assert_eq!(debug_string, "a");
+            assert_eq!(params[&0xF5], ProtocolValue::Integer(0));
+        }
+        x => panic!("Unexpected packet {:?}", x),
+    }
+    assert_eq!(packet.write(Protocol::GpBinaryV18)?, data);
+
+    Ok(())
+}
+
+#[test]
+fn detect_protocol() {
+    assert_eq!(Protocol::detect(&[0xF3, 0, 1, 6, 1, 0x42]), Some(Protocol::GpBinaryV16));
+    assert_eq!(Protocol::detect(&[0xF3, 0, 1, 8, 1, 0x42]), Some(Protocol::GpBinaryV18));
+    assert_eq!(Protocol::detect(&[0xF3, 2, 1, 8]), None);
+    assert_eq!(Protocol::detect(&[0xF3, 0]), None);
+}
+
+#[test]
+fn string_too_long() {
+    let reader = &mut Reader::new([7u8, 4, 0x61, 0x62].as_ref());
+    match read_value(reader) {
+        Err(PhotonReadError::UnexpectedEof { needed: 4, available: 2 }) => (),
+        x => panic!("Expected UnexpectedEof, got {:?}", x),
+    }
+}
+
+#[test]
+fn boolean_array_too_long() {
+    let reader = &mut Reader::new([66u8, 0x80, 0x80, 0x04].as_ref());
+    match read_value(reader) {
+        Err(PhotonReadError::UnexpectedEof { needed: 8192, available: 0 }) => (),
+        x => panic!("Expected UnexpectedEof, got {:?}", x),
+    }
+}
+
+#[test]
+fn hashtable_array_key_unhashable() {
+    let reader = &mut Reader::new([21u8, 1, 64, 0, 8].as_ref());
+    match read_value(reader) {
+        Err(PhotonReadError::UnhashableKey) => (),
+        x => panic!("Expected UnhashableKey, got {:?}", x),
+    }
+}
@@ -0,0 +1,332 @@
+use byteorder::{LittleEndian, WriteBytesExt};
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::io::Write;
+
+use super::super::get_custom_type_data;
+use super::super::get_value_type as get_value_type_v16;
+use super::*;
+
+pub fn write_debug_string(writer: &mut dyn Write, debug_string: Option<&str>) -> PhotonWriteResult<()> {
+    match debug_string {
+        Some(x) => write_value_of_type(writer, ProtocolValue::String(Cow::Borrowed(x))),
+        None => write_value_of_type(writer, ProtocolValue::Null()),
+    }
+}
+
+/// Gets the protocol 1.8 type of a value, without using the shorter type codes for values such as `0` or `true`.
+pub fn get_value_type(value: &ProtocolValue) -> u8 {
+    match value {
+        ProtocolValue::Null() => TYPE_NULL,
+        ProtocolValue::Bool(_) => TYPE_BOOLEAN,
+        ProtocolValue::Byte(_) => TYPE_BYTE,
+        ProtocolValue::Short(_) => TYPE_SHORT,
+        ProtocolValue::Integer(_) => TYPE_COMPRESSED_INT,
+        ProtocolValue::Long(_) => TYPE_COMPRESSED_LONG,
+        ProtocolValue::Float(_) => TYPE_FLOAT,
+        ProtocolValue::Double(_) => TYPE_DOUBLE,
+        ProtocolValue::String(_) => TYPE_STRING,
+        ProtocolValue::OperationRequest(_, _) => TYPE_OPERATION_REQUEST,
+        ProtocolValue::OperationResponse(_, _, _, _) => TYPE_OPERATION_RESPONSE,
+        ProtocolValue::EventData(_, _) => TYPE_EVENT_DATA,
+        ProtocolValue::Array(element_type, x) => get_array_type(*element_type, x),
+        ProtocolValue::ObjectArray(_) => TYPE_OBJECT_ARRAY,
+        ProtocolValue::ByteArray(_) => TYPE_BYTE_ARRAY,
+        ProtocolValue::StringArray(_) => TYPE_STRING_ARRAY,
+        ProtocolValue::IntegerArray(_) => TYPE_COMPRESSED_INT_ARRAY,
+        ProtocolValue::Dictionary { .. } => TYPE_DICTIONARY,
+        ProtocolValue::Hashtable(_) => TYPE_HASHTABLE,
+        ProtocolValue::Custom(_) => TYPE_CUSTOM,
+    }
+}
+
+/// Gets the type of an array from the protocol 1.6 type of its elements. Types that have no typed array in protocol 1.8 are
+/// written as an array of typed values, as are empty arrays of custom types, since they have no element to take the custom type
+/// from.
+fn get_array_type(element_type: u8, values: &[ProtocolValue]) -> u8 {
+    let array_type = match element_type {
+        111 => TYPE_BOOLEAN_ARRAY,
+        107 => TYPE_SHORT_ARRAY,
+        102 => TYPE_FLOAT_ARRAY,
+        100 => TYPE_DOUBLE_ARRAY,
+        108 => TYPE_COMPRESSED_LONG_ARRAY,
+        104 => TYPE_HASHTABLE_ARRAY,
+        99 if !values.is_empty() => TYPE_CUSTOM_TYPE_ARRAY,
+        _ => return TYPE_ARRAY,
+    };
+
+    let first_custom_id = match values.first() {
+        Some(ProtocolValue::Custom(custom)) => Some(get_custom_type_id(custom)),
+        _ => None,
+    };
+    let all_same_type = values.iter().all(|value| match value {
+        ProtocolValue::Custom(custom) => Some(get_custom_type_id(custom)) == first_custom_id,
+        value => get_value_type_v16(value) == element_type,
+    });
+
+    if all_same_type {
+        array_type
+    } else {
+        TYPE_ARRAY
+    }
+}
+
+fn get_custom_type_id(custom: &CustomType) -> u8 {
+    match custom {
+        CustomType::Vector2(_, _) => b'W',
+        CustomType::Vector3(_, _, _) => b'V',
+        CustomType::Quaternion(_, _, _, _) => b'Q',
+        CustomType::Player(_) => b'P',
+        CustomType::Custom { id, .. } => *id,
+    }
+}
+
+pub fn write_value_of_type(c: &mut dyn Write, value: ProtocolValue) -> PhotonWriteResult<()> {
+    match value {
+        ProtocolValue::Bool(true) => Ok(c.write_u8(TYPE_BOOLEAN_TRUE)?),
+        ProtocolValue::Bool(false) => Ok(c.write_u8(TYPE_BOOLEAN_FALSE)?),
+        ProtocolValue::Byte(0) => Ok(c.write_u8(TYPE_BYTE_ZERO)?),
+        ProtocolValue::Short(0) => Ok(c.write_u8(TYPE_SHORT_ZERO)?),
+        ProtocolValue::Integer(x) => write_integer(c, x),
+        ProtocolValue::Long(x) => write_long(c, x),
+        // compare the bits, so -0.0 keeps its sign
+        ProtocolValue::Float(x) if x.to_bits() == 0 => Ok(c.write_u8(TYPE_FLOAT_ZERO)?),
+        ProtocolValue::Double(x) if x.to_bits() == 0 => Ok(c.write_u8(TYPE_DOUBLE_ZERO)?),
+        ProtocolValue::Custom(custom) => {
+            let (id, data) = get_custom_type_data(custom)?;
+            if id <= CUSTOM_SLIM_MAX_ID {
+                c.write_u8(TYPE_CUSTOM_SLIM + id)?;
+            } else {
+                c.write_u8(TYPE_CUSTOM)?;
+                c.write_u8(id)?;
+            }
+            write_compressed_u32(c, data.len() as u32)?;
+            Ok(c.write_all(data.as_slice())?)
+        }
+        value => {
+            c.write_u8(get_value_type(&value))?;
+            write_value_of_type_without_type(c, value)
+        }
+    }
+}
+
+fn write_integer(c: &mut dyn Write, value: i32) -> PhotonWriteResult<()> {
+    match (value, value.unsigned_abs()) {
+        (0, _) => c.write_u8(TYPE_INT_ZERO)?,
+        (x, abs) if abs <= 0xFF => {
+            c.write_u8(if x < 0 { TYPE_INT1_NEGATIVE } else { TYPE_INT1 })?;
+            c.write_u8(abs as u8)?;
+        }
+        (x, abs) if abs <= 0xFFFF => {
+            c.write_u8(if x < 0 { TYPE_INT2_NEGATIVE } else { TYPE_INT2 })?;
+            c.write_u16::<LittleEndian>(abs as u16)?;
+        }
+        (x, _) => {
+            c.write_u8(TYPE_COMPRESSED_INT)?;
+            write_compressed_i32(c, x)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_long(c: &mut dyn Write, value: i64) -> PhotonWriteResult<()> {
+    match (value, value.unsigned_abs()) {
+        (0, _) => c.write_u8(TYPE_LONG_ZERO)?,
+        (x, abs) if abs <= 0xFF => {
+            c.write_u8(if x < 0 { TYPE_LONG1_NEGATIVE } else { TYPE_LONG1 })?;
+            c.write_u8(abs as u8)?;
+        }
+        (x, abs) if abs <= 0xFFFF => {
+            c.write_u8(if x < 0 { TYPE_LONG2_NEGATIVE } else { TYPE_LONG2 })?;
+            c.write_u16::<LittleEndian>(abs as u16)?;
+        }
+        (x, _) => {
+            c.write_u8(TYPE_COMPRESSED_LONG)?;
+            write_compressed_i64(c, x)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn write_value_of_type_without_type(c: &mut dyn Write, value: ProtocolValue) -> PhotonWriteResult<()> {
+    match value {
+        ProtocolValue::Null() => Ok(()),
+        ProtocolValue::Bool(true) => Ok(c.write_u8(1)?),
+        ProtocolValue::Bool(false) => Ok(c.write_u8(0)?),
+        ProtocolValue::Byte(x) => Ok(c.write_u8(x)?),
+        ProtocolValue::Short(x) => Ok(c.write_i16::<LittleEndian>(x)?),
+        ProtocolValue::Integer(x) => write_compressed_i32(c, x),
+        ProtocolValue::Long(x) => write_compressed_i64(c, x),
+        ProtocolValue::Float(x) => Ok(c.write_f32::<LittleEndian>(x)?),
+        ProtocolValue::Double(x) => Ok(c.write_f64::<LittleEndian>(x)?),
+        ProtocolValue::String(x) => write_string(c, &x),
+        ProtocolValue::ByteArray(bytes) => {
+            write_compressed_u32(c, bytes.len() as u32)?;
+            Ok(c.write_all(bytes.as_slice())?)
+        }
+        ProtocolValue::IntegerArray(ints) => {
+            write_compressed_u32(c, ints.len() as u32)?;
+            for i in ints {
+                write_compressed_i32(c, i)?;
+            }
+            Ok(())
+        }
+        ProtocolValue::StringArray(strings) => {
+            write_compressed_u32(c, strings.len() as u32)?;
+            for s in strings {
+                write_string(c, &s)?;
+            }
+            Ok(())
+        }
+        ProtocolValue::Array(element_type, x) => {
+            let protocol_type = get_array_type(element_type, &x);
+            write_compressed_u32(c, x.len() as u32)?;
+            match protocol_type {
+                TYPE_ARRAY => {
+                    for i in x {
+                        write_value_of_type(c, i)?;
+                    }
+                }
+                TYPE_BOOLEAN_ARRAY => {
+                    // packed as bits, least significant bit first
+                    for chunk in x.chunks(8) {
+                        let mut byte = 0u8;
+                        for (i, value) in chunk.iter().enumerate() {
+                            if let ProtocolValue::Bool(true) = value {
+                                byte |= 1 << i;
+                            }
+                        }
+                        c.write_u8(byte)?;
+                    }
+                }
+                TYPE_CUSTOM_TYPE_ARRAY => {
+                    let mut first = true;
+                    for i in x {
+                        if let ProtocolValue::Custom(custom) = i {
+                            let (id, data) = get_custom_type_data(custom)?;
+                            if first {
+                                c.write_u8(id)?;
+                                first = false;
+                            }
+                            write_compressed_u32(c, data.len() as u32)?;
+                            c.write_all(data.as_slice())?;
+                        }
+                    }
+                }
+                _ => {
+                    for i in x {
+                        write_value_of_type_without_type(c, i)?;
+                    }
+                }
+            }
+            Ok(())
+        }
+        ProtocolValue::ObjectArray(x) => {
+            write_compressed_u32(c, x.len() as u32)?;
+            for i in x {
+                write_value_of_type(c, i)?;
+            }
+            Ok(())
+        }
+        ProtocolValue::Dictionary {
+            key_type,
+            value_type,
+            entries,
+        } => {
+            c.write_u8(dictionary_type_to_v18(key_type)?)?;
+            c.write_u8(dictionary_type_to_v18(value_type)?)?;
+            write_compressed_u32(c, entries.len() as u32)?;
+            for (key, value) in entries {
+                write_value_of_dictionary_type(c, key_type, key)?;
+                write_value_of_dictionary_type(c, value_type, value)?;
+            }
+            Ok(())
+        }
+        ProtocolValue::Hashtable(x) => {
+            write_compressed_u32(c, x.len() as u32)?;
+            for (key, value) in x {
+                write_value_of_type(c, key)?;
+                write_value_of_type(c, value)?;
+            }
+            Ok(())
+        }
+        ProtocolValue::EventData(event_type, params) => {
+            c.write_u8(event_type)?;
+            write_parameter_table(c, params)
+        }
+        ProtocolValue::OperationResponse(operation_type, params, return_code, debug_string) => {
+            c.write_u8(operation_type)?;
+            c.write_i16::<LittleEndian>(return_code)?;
+            write_debug_string(c, debug_string.as_deref())?;
+            write_parameter_table(c, params)
+        }
+        ProtocolValue::OperationRequest(operation_type, params) => {
+            c.write_u8(operation_type)?;
+            write_parameter_table(c, params)
+        }
+        ProtocolValue::Custom(custom) => {
+            let (id, data) = get_custom_type_data(custom)?;
+            c.write_u8(id)?;
+            write_compressed_u32(c, data.len() as u32)?;
+            Ok(c.write_all(data.as_slice())?)
+        }
+    }
+}
+
+/// Writes an unsigned integer in groups of 7 bits, least significant group first.
+pub fn write_compressed_u32(c: &mut dyn Write, mut value: u32) -> PhotonWriteResult<()> {
+    while value >= 0x80 {
+        c.write_u8(value as u8 | 0x80)?;
+        value >>= 7;
+    }
+    Ok(c.write_u8(value as u8)?)
+}
+
+/// Writes an unsigned long in groups of 7 bits, least significant group first.
+pub fn write_compressed_u64(c: &mut dyn Write, mut value: u64) -> PhotonWriteResult<()> {
+    while value >= 0x80 {
+        c.write_u8(value as u8 | 0x80)?;
+        value >>= 7;
+    }
+    Ok(c.write_u8(value as u8)?)
+}
+
+/// Writes a zig-zag encoded compressed integer.
+pub fn write_compressed_i32(c: &mut dyn Write, value: i32) -> PhotonWriteResult<()> {
+    write_compressed_u32(c, ((value << 1) ^ (value >> 31)) as u32)
+}
+
+/// Writes a zig-zag encoded compressed long.
+pub fn write_compressed_i64(c: &mut dyn Write, value: i64) -> PhotonWriteResult<()> {
+    write_compressed_u64(c, ((value << 1) ^ (value >> 63)) as u64)
+}
+
+fn write_string(c: &mut dyn Write, string: &str) -> PhotonWriteResult<()> {
+    let bytes = string.as_bytes(); // as utf8 bytes
+    write_compressed_u32(c, bytes.len() as u32)?;
+    Ok(c.write_all(bytes)?)
+}
+
+/// Writes a dictionary key or value, which only has its own type byte if the dictionary type is `object`.
+///
+/// `protocol_type` is the protocol 1.6 type, as stored in `ProtocolValue::Dictionary`.
+fn write_value_of_dictionary_type(c: &mut dyn Write, protocol_type: u8, value: ProtocolValue) -> PhotonWriteResult<()> {
+    match protocol_type {
+        0 | 42 => write_value_of_type(c, value),
+        _ => match get_value_type_v16(&value) {
+            value_type if value_type == protocol_type => write_value_of_type_without_type(c, value),
+            value_type => Err(PhotonWriteError::DictionaryTypeMismatch(protocol_type, value_type)),
+        },
+    }
+}
+
+/// Writes a parameter table. Unlike in protocol 1.6, its length is a single byte, so it can hold at most 255 parameters.
+pub fn write_parameter_table(c: &mut dyn Write, x: IndexMap<u8, ProtocolValue>) -> PhotonWriteResult<()> {
+    let len = u8::try_from(x.len()).map_err(|_| PhotonWriteError::TooManyParameters(x.len()))?;
+    c.write_u8(len)?;
+    for (key, value) in x {
+        c.write_u8(key)?;
+        write_value_of_type(c, value)?;
+    }
+    Ok(())
+}
@@ -2,26 +2,28 @@
 #![allow(clippy::many_single_char_names)] // required because of quaternion
 
 use byteorder::{BigEndian, WriteBytesExt};
+use std::borrow::Cow;
+use std::convert::TryFrom;
 use std::io::Write;
 
 use super::super::*;
 
 pub fn write_debug_string(writer: &mut dyn Write, debug_string: Option<&str>) -> PhotonWriteResult<()> {
     match debug_string {
-        Some(x) => write_value_of_type(writer, ProtocolValue::String(x)),
+        Some(x) => write_value_of_type(writer, ProtocolValue::String(Cow::Borrowed(x))),
         None => write_value_of_type(writer, ProtocolValue::Null()),
     }
 }
 
-fn get_value_type(value: &ProtocolValue) -> u8 {
+pub fn get_value_type(value: &ProtocolValue) -> u8 {
     match value {
         ProtocolValue::Null() => 42,
-        ProtocolValue::Dictionary => 68,
+        ProtocolValue::Dictionary { .. } => 68,
         ProtocolValue::StringArray(_) => 97,
         ProtocolValue::Byte(_) => 98,
         ProtocolValue::Custom(_) => 99,
         ProtocolValue::Double(_) => 100,
-        ProtocolValue::EventData => 101,
+        ProtocolValue::EventData(_, _) => 101,
         ProtocolValue::Float(_) => 102,
         ProtocolValue::Hashtable(_) => 104,
         ProtocolValue::Integer(_) => 105,
@@ -29,11 +31,11 @@ fn get_value_type(value: &ProtocolValue) -> u8 {
         ProtocolValue::Long(_) => 108,
         ProtocolValue::IntegerArray(_) => 110,
         ProtocolValue::Bool(_) => 111,
-        ProtocolValue::OperationResponse => 112,
-        ProtocolValue::OperationRequest => 113,
+        ProtocolValue::OperationResponse(_, _, _, _) => 112,
+        ProtocolValue::OperationRequest(_, _) => 113,
         ProtocolValue::String(_) => 115,
         ProtocolValue::ByteArray(_) => 120,
-        ProtocolValue::Array(_) => 121,
+        ProtocolValue::Array(_, _) => 121,
         ProtocolValue::ObjectArray(_) => 122,
     }
 }
@@ -57,7 +59,7 @@ pub fn write_value_of_type_without_type(c: &mut dyn Write, value: ProtocolValue)
         ProtocolValue::Double(x) => Ok(c.write_f64::<BigEndian>(x)?),
         ProtocolValue::String(x) => {
             let bytes = x.as_bytes(); // as utf8 bytes
-            c.write_u16::<BigEndian>(bytes.len() as u16)?;
+            write_length(c, bytes.len())?;
             Ok(c.write_all(bytes)?)
         }
         ProtocolValue::ByteArray(bytes) => {
@@ -72,89 +74,127 @@ pub fn write_value_of_type_without_type(c: &mut dyn Write, value: ProtocolValue)
             Ok(())
         }
         ProtocolValue::StringArray(strings) => {
-            c.write_u16::<BigEndian>(strings.len() as u16)?;
+            write_length(c, strings.len())?;
             for s in strings {
                 let bytes = s.as_bytes(); // as utf8 bytes
-                c.write_u16::<BigEndian>(bytes.len() as u16)?;
+                write_length(c, bytes.len())?;
                 c.write_all(bytes)?;
             }
             Ok(())
         }
-        ProtocolValue::Array(x) => {
-            c.write_u16::<BigEndian>(x.len() as u16)?;
-            // if we hit this, we may need to implement returning an empty array of nulls
-            assert!(
-                !x.is_empty(),
-                "Tried to serialize empty Array! Not allowed since we need a protocol type."
-            );
-            let protocol_type = get_value_type(&x[0]);
+        ProtocolValue::Array(protocol_type, x) => {
+            write_length(c, x.len())?;
             c.write_u8(protocol_type)?;
             for i in x {
-                assert_eq!(protocol_type, get_value_type(&i), "Array types did not match!");
-                write_value_of_type_without_type(c, i)?;
+                match get_value_type(&i) {
+                    value_type if value_type == protocol_type => write_value_of_type_without_type(c, i)?,
+                    value_type => return Err(PhotonWriteError::ArrayTypeMismatch(protocol_type, value_type)),
+                }
             }
             Ok(())
         }
         ProtocolValue::ObjectArray(x) => {
-            c.write_u16::<BigEndian>(x.len() as u16)?;
+            write_length(c, x.len())?;
             for i in x {
                 write_value_of_type(c, i)?;
             }
             Ok(())
         }
-        ProtocolValue::Dictionary => Err(PhotonWriteError::UnimplementedProtocolValueType(ProtocolValue::Dictionary)),
+        ProtocolValue::Dictionary {
+            key_type,
+            value_type,
+            entries,
+        } => {
+            c.write_u8(key_type)?;
+            c.write_u8(value_type)?;
+            write_length(c, entries.len())?;
+            for (key, value) in entries {
+                write_value_of_dictionary_type(c, key_type, key)?;
+                write_value_of_dictionary_type(c, value_type, value)?;
+            }
+            Ok(())
+        }
         ProtocolValue::Hashtable(x) => {
-            c.write_u16::<BigEndian>(x.len() as u16)?;
+            write_length(c, x.len())?;
             for (key, value) in x {
                 write_value_of_type(c, key)?;
                 write_value_of_type(c, value)?;
             }
             Ok(())
         }
-        ProtocolValue::EventData => Err(PhotonWriteError::UnimplementedProtocolValueType(ProtocolValue::EventData)),
-        ProtocolValue::OperationResponse => Err(PhotonWriteError::UnimplementedProtocolValueType(ProtocolValue::OperationResponse)),
-        ProtocolValue::OperationRequest => Err(PhotonWriteError::UnimplementedProtocolValueType(ProtocolValue::OperationRequest)),
+        ProtocolValue::EventData(event_type, params) => {
+            c.write_u8(event_type)?;
+            write_parameter_table(c, params)
+        }
+        ProtocolValue::OperationResponse(operation_type, params, return_code, debug_string) => {
+            c.write_u8(operation_type)?;
+            c.write_i16::<BigEndian>(return_code)?;
+            write_debug_string(c, debug_string.as_deref())?;
+            write_parameter_table(c, params)
+        }
+        ProtocolValue::OperationRequest(operation_type, params) => {
+            c.write_u8(operation_type)?;
+            write_parameter_table(c, params)
+        }
         ProtocolValue::Custom(custom) => {
-            match custom {
-                CustomType::Vector2(x, y) => {
-                    c.write_u8(b'W')?;
-                    c.write_u16::<BigEndian>(8)?;
-                    c.write_f32::<BigEndian>(x)?;
-                    c.write_f32::<BigEndian>(y)?;
-                }
-                CustomType::Vector3(x, y, z) => {
-                    c.write_u8(b'V')?;
-                    c.write_u16::<BigEndian>(12)?;
-                    c.write_f32::<BigEndian>(x)?;
-                    c.write_f32::<BigEndian>(y)?;
-                    c.write_f32::<BigEndian>(z)?;
-                }
-                CustomType::Quaternion(x, y, z, w) => {
-                    c.write_u8(b'Q')?;
-                    c.write_u16::<BigEndian>(16)?;
-                    c.write_f32::<BigEndian>(x)?;
-                    c.write_f32::<BigEndian>(y)?;
-                    c.write_f32::<BigEndian>(z)?;
-                    c.write_f32::<BigEndian>(w)?;
-                }
-                CustomType::Player(id) => {
-                    c.write_u8(b'P')?;
-                    c.write_u16::<BigEndian>(4)?;
-                    c.write_i32::<BigEndian>(id)?;
-                }
-                CustomType::Custom { id, data } => {
-                    c.write_u8(id)?;
-                    c.write_u16::<BigEndian>(data.len() as u16)?;
-                    c.write_all(data.as_slice())?;
-                }
-            }
-            Ok(())
+            let (id, data) = get_custom_type_data(custom)?;
+            c.write_u8(id)?;
+            write_length(c, data.len())?;
+            Ok(c.write_all(data.as_slice())?)
+        }
+    }
+}
+
+/// Gets the id and serialized data of a custom type. This data is independent of the protocol version.
+pub fn get_custom_type_data(custom: CustomType) -> PhotonWriteResult<(u8, Vec<u8>)> {
+    let mut data = Vec::new();
+    let id = match custom {
+        CustomType::Vector2(x, y) => {
+            data.write_f32::<BigEndian>(x)?;
+            data.write_f32::<BigEndian>(y)?;
+            b'W'
+        }
+        CustomType::Vector3(x, y, z) => {
+            data.write_f32::<BigEndian>(x)?;
+            data.write_f32::<BigEndian>(y)?;
+            data.write_f32::<BigEndian>(z)?;
+            b'V'
         }
+        CustomType::Quaternion(x, y, z, w) => {
+            data.write_f32::<BigEndian>(x)?;
+            data.write_f32::<BigEndian>(y)?;
+            data.write_f32::<BigEndian>(z)?;
+            data.write_f32::<BigEndian>(w)?;
+            b'Q'
+        }
+        CustomType::Player(id) => {
+            data.write_i32::<BigEndian>(id)?;
+            b'P'
+        }
+        CustomType::Custom { id, data } => return Ok((id, data)),
+    };
+    Ok((id, data))
+}
+
+/// Writes the length of a parameter table or value, which cannot be more than `u16::MAX`.
+fn write_length(c: &mut dyn Write, len: usize) -> PhotonWriteResult<()> {
+    let len = u16::try_from(len).map_err(|_| PhotonWriteError::TooManyParameters(len))?;
+    Ok(c.write_u16::<BigEndian>(len)?)
+}
+
+/// Writes a dictionary key or value, which only has its own type byte if the dictionary type is `object`.
+fn write_value_of_dictionary_type(c: &mut dyn Write, protocol_type: u8, value: ProtocolValue) -> PhotonWriteResult<()> {
+    match protocol_type {
+        0 | 42 => write_value_of_type(c, value),
+        _ => match get_value_type(&value) {
+            value_type if value_type == protocol_type => write_value_of_type_without_type(c, value),
+            value_type => Err(PhotonWriteError::DictionaryTypeMismatch(protocol_type, value_type)),
+        },
     }
 }
 
-pub fn write_parameter_table(c: &mut dyn Write, x: HashMap<u8, ProtocolValue>) -> PhotonWriteResult<()> {
-    c.write_u16::<BigEndian>(x.len() as u16)?;
+pub fn write_parameter_table(c: &mut dyn Write, x: IndexMap<u8, ProtocolValue>) -> PhotonWriteResult<()> {
+    write_length(c, x.len())?;
     for (key, value) in x {
         c.write_u8(key)?;
         write_value_of_type(c, value)?;
@@ -0,0 +1,56 @@
+//! Serializes an `IndexMap` as a sequence of `[key, value]` pairs, for use with `#[serde(with = "...")]`.
+//!
+//! Formats like JSON only allow string keys in maps, but hashtables and dictionaries can be keyed by any
+//! [`ProtocolValue`]. Writing them as pairs keeps both the key types and the entry order intact.
+//!
+//! [`ProtocolValue`]: ../enum.ProtocolValue.html
+
+use indexmap::IndexMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::hash::Hash;
+
+pub fn serialize<K, V, S>(map: &IndexMap<K, V>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    K: Serialize,
+    V: Serialize,
+    S: Serializer,
+{
+    serializer.collect_seq(map.iter())
+}
+
+pub fn deserialize<'de, K, V, D>(deserializer: D) -> Result<IndexMap<K, V>, D::Error>
+where
+    K: Deserialize<'de> + Hash + Eq,
+    V: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Ok(Vec::<(K, V)>::deserialize(deserializer)?.into_iter().collect())
+}
+
+/// Same as the parent module, but for an `Option<IndexMap>`.
+pub mod option {
+    use indexmap::IndexMap;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::hash::Hash;
+
+    pub fn serialize<K, V, S>(map: &Option<IndexMap<K, V>>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        K: Serialize,
+        V: Serialize,
+        S: Serializer,
+    {
+        match map {
+            Some(map) => serializer.serialize_some(&map.iter().collect::<Vec<_>>()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, K, V, D>(deserializer: D) -> Result<Option<IndexMap<K, V>>, D::Error>
+    where
+        K: Deserialize<'de> + Hash + Eq,
+        V: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        Ok(Option::<Vec<(K, V)>>::deserialize(deserializer)?.map(|pairs| pairs.into_iter().collect()))
+    }
+}
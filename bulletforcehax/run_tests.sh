@@ -1,4 +1,6 @@
 #/bin/bash
 cargo test --manifest-path=libs/photon_core/Cargo.toml && \
 cargo test --manifest-path=libs/photon/Cargo.toml && \
+cargo test --manifest-path=libs/photon_core/Cargo.toml --features serde && \
+cargo test --manifest-path=libs/photon/Cargo.toml --features serde && \
 cargo test
@@ -4,10 +4,42 @@
 
 extern crate wasm_bindgen;
 
-use log::{debug, error, info, Level};
+use log::{error, info, warn, Level};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
 use wasm_bindgen::prelude::*;
 
-use photon::{Direction, Packet};
+use photon::{is_encrypted, Connection, Connections, Direction, PacketView, PacketWriteResult, Session};
+
+mod pipeline;
+pub use pipeline::*;
+mod pipeline_tests;
+mod rules;
+pub use rules::*;
+mod rules_tests;
+
+thread_local! {
+    /// The state of the game's connection, as seen through the packets passing through `sock_send` and `sock_recv`
+    static SESSION: RefCell<Session> = RefCell::new(Session::new());
+    /// The rewrite rules loaded with `load_rules`, which are applied by the `rules` handler
+    static RULES: Rc<RefCell<RuleSet>> = Rc::new(RefCell::new(RuleSet::default()));
+    /// The handlers that see every packet, in order
+    static PIPELINE: RefCell<Pipeline> = RefCell::new({
+        let mut pipeline = Pipeline::new();
+        pipeline.push(RulesHandler::new(RULES.with(Rc::clone)));
+        pipeline.push(Logger);
+        pipeline
+    });
+    /// The websockets opened by the game, by the id returned from `sock_open`
+    static CONNECTIONS: RefCell<Connections> = RefCell::new(Connections::new());
+    /// The secrets for our own key exchange with both sides, if enabled using `enable_encryption_mitm`
+    static ENCRYPTION_SECRETS: RefCell<Option<(Vec<u8>, Vec<u8>)>> = const { RefCell::new(None) };
+    /// Frames pushed by the page to send to the server, with the id of their socket
+    static SEND_QUEUE: RefCell<VecDeque<(u32, Vec<u8>)>> = const { RefCell::new(VecDeque::new()) };
+    /// Frames pushed by the page to deliver to the game, with the id of their socket
+    static RECV_QUEUE: RefCell<VecDeque<(u32, Vec<u8>)>> = const { RefCell::new(VecDeque::new()) };
+}
 
 #[wasm_bindgen]
 extern "C" {
@@ -29,44 +61,289 @@ pub fn main() -> Result<(), JsValue> {
     Ok(())
 }
 
-// TODO: support returning multiple sent packets
-// TODO: perhaps pass boolean or enum that tells which socket we're on
+/// Starts intercepting the key exchange, so encrypted packets can be read and changed. This has to be called before the game connects,
+/// with 2 random secrets of 20 bytes: one for the key exchanges with the game and one for the key exchanges with the servers.
 #[wasm_bindgen]
-pub fn sock_send(data: &[u8]) -> Vec<u8> {
-    match Packet::read(&data, Direction::Send) {
-        Ok(packet) => {
-            debug!("SEND: {:?}", packet);
-            match packet.into_vec() {
-                Ok(vec) => vec,
-                Err(error) => {
-                    error!("SEND ERR: {:?}, data: {:?}", error, data);
-                    Vec::from(data)
-                }
+pub fn enable_encryption_mitm(client_secret: &[u8], server_secret: &[u8]) {
+    ENCRYPTION_SECRETS.with(|secrets| *secrets.borrow_mut() = Some((Vec::from(client_secret), Vec::from(server_secret))));
+}
+
+/// Registers a websocket opened by the game, and returns the id to pass to the other functions.
+#[wasm_bindgen]
+pub fn sock_open(url: &str) -> u32 {
+    CONNECTIONS.with(|connections| {
+        let mut connections = connections.borrow_mut();
+        let socket_id = SESSION.with(|session| connections.open(url, &session.borrow()));
+        let connection = connections.get_mut(socket_id).unwrap();
+        ENCRYPTION_SECRETS.with(|secrets| {
+            if let Some((client_secret, server_secret)) = &*secrets.borrow() {
+                connection.enable_encryption(client_secret, server_secret);
             }
+        });
+        info!("OPEN {}: {}", label(socket_id, connection), url);
+        socket_id
+    })
+}
+
+/// Forgets a websocket that was closed, including the frames that were queued for it.
+#[wasm_bindgen]
+pub fn sock_close(socket_id: u32) {
+    if let Some(connection) = CONNECTIONS.with(|connections| connections.borrow_mut().close(socket_id)) {
+        info!("CLOSE {}: {:?}", label(socket_id, &connection), connection);
+    }
+    SEND_QUEUE.with(|queue| queue.borrow_mut().retain(|(id, _)| *id != socket_id));
+    RECV_QUEUE.with(|queue| queue.borrow_mut().retain(|(id, _)| *id != socket_id));
+}
+
+/// The frames to deliver after `sock_send` or `sock_recv` handled one. By default, this is only the original frame.
+#[wasm_bindgen]
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Frames {
+    /// Frames to deliver in the same direction as the original frame
+    forward: Vec<Vec<u8>>,
+    /// Frames to deliver in the opposite direction, such as a response made up by us
+    reply: Vec<Vec<u8>>,
+}
+
+#[wasm_bindgen]
+impl Frames {
+    pub fn forward_count(&self) -> usize {
+        self.forward.len()
+    }
+
+    pub fn forward(&self, index: usize) -> Option<Vec<u8>> {
+        self.forward.get(index).cloned()
+    }
+
+    pub fn reply_count(&self) -> usize {
+        self.reply.len()
+    }
+
+    pub fn reply(&self, index: usize) -> Option<Vec<u8>> {
+        self.reply.get(index).cloned()
+    }
+}
+
+impl Frames {
+    /// Delivers only the original frame, possibly changed
+    pub fn passthrough(data: Vec<u8>) -> Self {
+        Frames {
+            forward: vec![data],
+            reply: vec![],
         }
-        Err(error) => {
-            error!("SEND ERR: {:?}, data: {:?}", error, data);
-            Vec::from(data)
-        }
     }
+
+    /// Delivers nothing, dropping the original frame
+    pub fn dropped() -> Self {
+        Frames::default()
+    }
+
+    pub fn push_forward(&mut self, data: Vec<u8>) {
+        self.forward.push(data);
+    }
+
+    pub fn push_reply(&mut self, data: Vec<u8>) {
+        self.reply.push(data);
+    }
+}
+
+#[wasm_bindgen]
+pub fn sock_send(socket_id: u32, data: &[u8]) -> Frames {
+    process(socket_id, data, Direction::Send)
+}
+
+#[wasm_bindgen]
+pub fn sock_recv(socket_id: u32, data: &[u8]) -> Frames {
+    process(socket_id, data, Direction::Recv)
+}
+
+/// Queues a frame to send to the server, outside of `sock_send`. The page delivers it by calling `next_queued_send`.
+#[wasm_bindgen]
+pub fn queue_send(socket_id: u32, data: &[u8]) {
+    SEND_QUEUE.with(|queue| queue.borrow_mut().push_back((socket_id, Vec::from(data))));
+}
+
+/// Queues a frame to deliver to the game, outside of `sock_recv`. The page delivers it by calling `next_queued_recv`.
+#[wasm_bindgen]
+pub fn queue_recv(socket_id: u32, data: &[u8]) {
+    RECV_QUEUE.with(|queue| queue.borrow_mut().push_back((socket_id, Vec::from(data))));
 }
 
+/// Takes the oldest frame for this socket that was queued using `queue_send`, if any
 #[wasm_bindgen]
-pub fn sock_recv(data: &[u8]) -> Vec<u8> {
-    match Packet::read(&data, Direction::Recv) {
-        Ok(packet) => {
-            debug!("RECV: {:?}", packet);
-            match packet.into_vec() {
-                Ok(vec) => vec,
+pub fn next_queued_send(socket_id: u32) -> Option<Vec<u8>> {
+    SEND_QUEUE.with(|queue| next_queued(&mut queue.borrow_mut(), socket_id))
+}
+
+/// Takes the oldest frame for this socket that was queued using `queue_recv`, if any
+#[wasm_bindgen]
+pub fn next_queued_recv(socket_id: u32) -> Option<Vec<u8>> {
+    RECV_QUEUE.with(|queue| next_queued(&mut queue.borrow_mut(), socket_id))
+}
+
+fn next_queued(queue: &mut VecDeque<(u32, Vec<u8>)>, socket_id: u32) -> Option<Vec<u8>> {
+    let index = queue.iter().position(|(id, _)| *id == socket_id)?;
+    queue.remove(index).map(|(_, data)| data)
+}
+
+/// Describes a connection in logs, such as `#2 MasterServer`
+fn label(socket_id: u32, connection: &Connection) -> String {
+    match connection.server() {
+        Some(server) => format!("#{} {:?}", socket_id, server),
+        None => format!("#{}", socket_id),
+    }
+}
+
+fn process(socket_id: u32, data: &[u8], direction: Direction) -> Frames {
+    let prefix = match direction {
+        Direction::Send => "SEND",
+        Direction::Recv => "RECV",
+    };
+
+    CONNECTIONS.with(|connections| {
+        let mut connections = connections.borrow_mut();
+        let connection = match connections.get_mut(socket_id) {
+            Some(connection) => connection,
+            None => {
+                warn!("{} on unknown socket #{}, data: {:?}", prefix, socket_id, data);
+                return Frames::passthrough(Vec::from(data));
+            }
+        };
+        connection.record_frame(data, direction);
+
+        let decrypted = match connection.encryption().map(|encryption| encryption.decrypt(data, direction)) {
+            Some(Ok(decrypted)) => decrypted,
+            Some(Err(error)) => {
+                // the other side could not decrypt this frame with its own keys either, so it is dropped
+                error!("{} ERR {}: {:?}, dropped: {:?}", prefix, label(socket_id, connection), error, data);
+                connection.record_error();
+                return Frames::dropped();
+            }
+            None => Vec::from(data),
+        };
+
+        // frames that can not be read as a `Packet` still go through the pipeline, so handlers can change their raw parameters
+        let view = PacketView::read(&decrypted, direction, connection.protocol());
+        match &view {
+            PacketView::Packet(_) => (),
+            PacketView::Raw(_, error) => {
+                warn!("{} ERR {}: {:?}, data: {:?}", prefix, label(socket_id, connection), error, data);
+                connection.record_error();
+            }
+            PacketView::Invalid(_, error) => {
+                error!("{} ERR {}: {:?}, data: {:?}", prefix, label(socket_id, connection), error, data);
+                connection.record_error();
+            }
+        }
+
+        let (packets, replies) = SESSION.with(|session| {
+            let mut session = session.borrow_mut();
+            let mut context = Context::new(direction, socket_id, connection.server(), &session);
+            let packets = PIPELINE.with(|pipeline| pipeline.borrow_mut().run(view, &mut context));
+            let replies = context.take_replies();
+            // the state follows what is actually delivered, so a packet that a handler drops or changes is seen that way
+            for packet in packets.iter().filter_map(PacketView::packet).chain(&replies) {
+                session.handle(packet);
+                connection.handle(packet, &session);
+            }
+            (packets, replies)
+        });
+
+        // only encrypt again what was encrypted by the other side
+        let encrypted = is_encrypted(data);
+        let mut frames = Frames::dropped();
+        for packet in packets {
+            let packet = match (connection.encryption_mut(), packet) {
+                (Some(encryption), PacketView::Packet(packet)) => PacketView::Packet(encryption.rekey(packet)),
+                (_, packet) => packet,
+            };
+            match serialize(connection, packet, direction, encrypted) {
+                Ok(vec) => frames.push_forward(vec),
                 Err(error) => {
-                    error!("RECV ERR: {:?}, data: {:?}", error, data);
-                    Vec::from(data)
+                    error!("{} ERR {}: {:?}, data: {:?}", prefix, label(socket_id, connection), error, data);
+                    connection.record_error();
+                    frames.push_forward(encrypt_frame(connection, decrypted.clone(), direction, encrypted));
                 }
             }
         }
-        Err(error) => {
-            error!("RECV ERR: {:?}, data: {:?}", error, data);
-            Vec::from(data)
+        let opposite = match direction {
+            Direction::Send => Direction::Recv,
+            Direction::Recv => Direction::Send,
+        };
+        for packet in replies {
+            match serialize(connection, packet.into(), opposite, encrypted) {
+                Ok(vec) => frames.push_reply(vec),
+                Err(error) => error!("{} ERR {}: could not write reply: {:?}", prefix, label(socket_id, connection), error),
+            }
         }
+        frames
+    })
+}
+
+/// Writes a packet going in `direction`, encrypting it if needed
+fn serialize(connection: &Connection, packet: PacketView, direction: Direction, encrypt: bool) -> PacketWriteResult<Vec<u8>> {
+    let vec = packet.into_vec_with_protocol(connection.protocol())?;
+    Ok(encrypt_frame(connection, vec, direction, encrypt))
+}
+
+/// Encrypts a frame going in `direction` with our own keys for that side, if `encrypt` is set and encryption is enabled
+fn encrypt_frame(connection: &Connection, vec: Vec<u8>, direction: Direction, encrypt: bool) -> Vec<u8> {
+    match connection.encryption().filter(|_| encrypt) {
+        Some(encryption) => encryption.encrypt(&vec, direction).unwrap_or(vec),
+        None => vec,
     }
 }
+
+/// Enables or disables a packet handler by name, see `handlers_snapshot`. Returns false if there is no such handler.
+#[wasm_bindgen]
+pub fn set_handler_enabled(name: &str, enabled: bool) -> bool {
+    PIPELINE.with(|pipeline| pipeline.borrow_mut().set_enabled(name, enabled))
+}
+
+/// Returns the packet handlers as JSON, in the order they see packets, with whether they are enabled.
+#[wasm_bindgen]
+pub fn handlers_snapshot() -> String {
+    PIPELINE.with(|pipeline| {
+        let handlers: Vec<_> = pipeline
+            .borrow()
+            .handlers()
+            .map(|(name, enabled)| serde_json::json!({ "name": name, "enabled": enabled }))
+            .collect();
+        serde_json::Value::from(handlers).to_string()
+    })
+}
+
+/// Replaces the packet rewrite rules with the ones in a TOML or JSON config. See the `rules` module for the format.
+#[wasm_bindgen]
+pub fn load_rules(config: &str) -> Result<(), JsValue> {
+    let rule_set = RuleSet::from_config(config).map_err(|error| JsValue::from_str(&format!("{:?}", error)))?;
+    info!(
+        "Loaded {} rewrite rules{}",
+        rule_set.rules.len(),
+        if rule_set.dry_run { " (dry run)" } else { "" }
+    );
+    RULES.with(|rules| *rules.borrow_mut() = rule_set);
+    Ok(())
+}
+
+/// Only logs what the rewrite rules would change, without changing any packets.
+#[wasm_bindgen]
+pub fn set_rules_dry_run(dry_run: bool) {
+    RULES.with(|rules| rules.borrow_mut().dry_run = dry_run);
+}
+
+/// Returns the current session state as JSON, such as the server we are on, the lobby and the room we are in.
+#[wasm_bindgen]
+pub fn session_snapshot() -> String {
+    SESSION.with(|session| {
+        serde_json::to_string(&*session.borrow()).unwrap_or_else(|error| serde_json::json!({ "error": error.to_string() }).to_string())
+    })
+}
+
+/// Returns the open connections as JSON, with the server they are connected to and how much they sent and received.
+#[wasm_bindgen]
+pub fn connections_snapshot() -> String {
+    CONNECTIONS.with(|connections| {
+        serde_json::to_string(&*connections.borrow()).unwrap_or_else(|error| serde_json::json!({ "error": error.to_string() }).to_string())
+    })
+}
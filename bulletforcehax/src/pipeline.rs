@@ -0,0 +1,237 @@
+//! Handlers that see every packet passing through the hooks, and can change, drop or replace it. This does not depend on
+//! wasm-bindgen, so handlers can be tested natively.
+
+use log::{debug, log_enabled, Level};
+use photon::{Direction, Packet, PacketView, ServerType, Session};
+
+/// What to do with a packet after a handler saw it.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Verdict<'a> {
+    /// Pass the packet on to the next handler, including any changes made to it
+    Pass,
+    /// Do not deliver the packet. Later handlers do not see it.
+    Drop,
+    /// Deliver these packets instead, which are passed on to the next handlers. This can contain the original packet to insert
+    /// packets around it.
+    Replace(Vec<Packet<'a>>),
+}
+
+/// Information about the packet being handled, and a way to respond to it.
+pub struct Context<'c> {
+    direction: Direction,
+    socket_id: u32,
+    server: Option<ServerType>,
+    session: &'c Session,
+    replies: Vec<Packet<'static>>,
+}
+
+/// Sees the packets sent by the client in `on_send`, and the packets received from the server in `on_recv`. Both pass the packet on
+/// by default.
+///
+/// Frames that can not be read as a `Packet` are only seen by `on_send_view` and `on_recv_view`, which can still change their raw
+/// parameters.
+pub trait PacketHandler {
+    /// The name used to enable or disable this handler
+    fn name(&self) -> &str;
+
+    fn on_send<'a>(&mut self, _packet: &mut Packet<'a>, _context: &mut Context) -> Verdict<'a> {
+        Verdict::Pass
+    }
+
+    fn on_recv<'a>(&mut self, _packet: &mut Packet<'a>, _context: &mut Context) -> Verdict<'a> {
+        Verdict::Pass
+    }
+
+    /// Sees every frame sent by the client. By default, this calls `on_send` for a `Packet` and passes on anything else.
+    fn on_send_view<'a>(&mut self, view: &mut PacketView<'a>, context: &mut Context) -> Verdict<'a> {
+        match view {
+            PacketView::Packet(packet) => self.on_send(packet, context),
+            _ => Verdict::Pass,
+        }
+    }
+
+    /// Sees every frame received from the server. By default, this calls `on_recv` for a `Packet` and passes on anything else.
+    fn on_recv_view<'a>(&mut self, view: &mut PacketView<'a>, context: &mut Context) -> Verdict<'a> {
+        match view {
+            PacketView::Packet(packet) => self.on_recv(packet, context),
+            _ => Verdict::Pass,
+        }
+    }
+}
+
+/// An ordered chain of handlers, each of which can be enabled or disabled.
+///
+/// ```rust
+/// # use bulletforcehax::*;
+/// # use photon::*;
+/// /// Stops the client from leaving its room
+/// struct NoLeave;
+///
+/// impl PacketHandler for NoLeave {
+///     fn name(&self) -> &str {
+///         "no_leave"
+///     }
+///
+///     fn on_send<'a>(&mut self, packet: &mut Packet<'a>, _context: &mut Context) -> Verdict<'a> {
+///         match packet {
+///             Packet::OperationRequest(Operation::LeaveRequest { .. }, _) => Verdict::Drop,
+///             _ => Verdict::Pass,
+///         }
+///     }
+/// }
+///
+/// let mut pipeline = Pipeline::new();
+/// pipeline.push(NoLeave);
+///
+/// let session = Session::new();
+/// let mut context = Context::new(Direction::Send, 0, None, &session);
+/// assert!(pipeline.run(Packet::OperationRequest(Operation::LeaveRequest { is_inactive: None }, ParameterOrder::default()), &mut context).is_empty());
+///
+/// pipeline.set_enabled("no_leave", false);
+/// assert_eq!(pipeline.run(Packet::OperationRequest(Operation::LeaveRequest { is_inactive: None }, ParameterOrder::default()), &mut context).len(), 1);
+/// ```
+#[derive(Default)]
+pub struct Pipeline {
+    handlers: Vec<(Box<dyn PacketHandler>, bool)>,
+}
+
+/// Logs every packet on a single line, with the connection it was on and the names of its codes. Packets are only formatted when
+/// debug logging is enabled, as that copies them.
+pub struct Logger;
+
+impl<'c> Context<'c> {
+    pub fn new(direction: Direction, socket_id: u32, server: Option<ServerType>, session: &'c Session) -> Self {
+        Context {
+            direction,
+            socket_id,
+            server,
+            session,
+            replies: vec![],
+        }
+    }
+
+    /// `Send` if the packet comes from the client, `Recv` if it comes from the server
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    /// The socket the packet was on
+    pub fn socket_id(&self) -> u32 {
+        self.socket_id
+    }
+
+    /// The server on the other side of the socket, if known
+    pub fn server(&self) -> Option<ServerType> {
+        self.server
+    }
+
+    /// The state of the client before the packet being handled. It is updated from the packets the pipeline returns, once all
+    /// handlers ran.
+    pub fn session(&self) -> &Session {
+        self.session
+    }
+
+    /// Sends a packet back in the opposite direction, such as a response to a request that is dropped.
+    pub fn reply(&mut self, packet: Packet<'static>) {
+        self.replies.push(packet);
+    }
+
+    /// Takes the packets passed to `reply`
+    pub fn take_replies(&mut self) -> Vec<Packet<'static>> {
+        std::mem::take(&mut self.replies)
+    }
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Pipeline::default()
+    }
+
+    /// Adds an enabled handler at the end of the chain.
+    pub fn push(&mut self, handler: impl PacketHandler + 'static) {
+        self.handlers.push((Box::new(handler), true));
+    }
+
+    /// The names of all handlers in order, and whether they are enabled
+    pub fn handlers(&self) -> impl Iterator<Item = (&str, bool)> {
+        self.handlers.iter().map(|(handler, enabled)| (handler.name(), *enabled))
+    }
+
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.handlers.iter().any(|(handler, enabled)| *enabled && handler.name() == name)
+    }
+
+    /// Enables or disables all handlers with this name. Returns false if there are none.
+    pub fn set_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        let mut found = false;
+        for (handler, handler_enabled) in self.handlers.iter_mut().filter(|(handler, _)| handler.name() == name) {
+            debug!("{} handler {}", if enabled { "Enabling" } else { "Disabling" }, handler.name());
+            *handler_enabled = enabled;
+            found = true;
+        }
+        found
+    }
+
+    /// Passes a packet through all enabled handlers, and returns the packets to deliver in its place. Replacements are always `Packet`s.
+    pub fn run<'a>(&mut self, packet: impl Into<PacketView<'a>>, context: &mut Context) -> Vec<PacketView<'a>> {
+        let mut packets = vec![];
+        self.run_from(0, packet.into(), context, &mut packets);
+        packets
+    }
+
+    fn run_from<'a>(&mut self, index: usize, mut packet: PacketView<'a>, context: &mut Context, packets: &mut Vec<PacketView<'a>>) {
+        let next = self.handlers[index..]
+            .iter()
+            .position(|(_, enabled)| *enabled)
+            .map(|offset| index + offset);
+        let next = match next {
+            Some(next) => next,
+            None => return packets.push(packet),
+        };
+
+        let handler = &mut self.handlers[next].0;
+        let verdict = match context.direction {
+            Direction::Send => handler.on_send_view(&mut packet, context),
+            Direction::Recv => handler.on_recv_view(&mut packet, context),
+        };
+        match verdict {
+            Verdict::Pass => self.run_from(next + 1, packet, context, packets),
+            Verdict::Drop => (),
+            Verdict::Replace(replacements) => {
+                for replacement in replacements {
+                    self.run_from(next + 1, replacement.into(), context, packets);
+                }
+            }
+        }
+    }
+}
+
+impl Logger {
+    fn log(view: &PacketView, context: &Context) {
+        if !log_enabled!(Level::Debug) {
+            return;
+        }
+        let prefix = match context.direction {
+            Direction::Send => "SEND",
+            Direction::Recv => "RECV",
+        };
+        let server = context.server.map(|server| format!(" {:?}", server)).unwrap_or_default();
+        debug!("{} #{}{}: {}", prefix, context.socket_id, server, view);
+    }
+}
+
+impl PacketHandler for Logger {
+    fn name(&self) -> &str {
+        "logger"
+    }
+
+    fn on_send_view<'a>(&mut self, view: &mut PacketView<'a>, context: &mut Context) -> Verdict<'a> {
+        Logger::log(view, context);
+        Verdict::Pass
+    }
+
+    fn on_recv_view<'a>(&mut self, view: &mut PacketView<'a>, context: &mut Context) -> Verdict<'a> {
+        Logger::log(view, context);
+        Verdict::Pass
+    }
+}
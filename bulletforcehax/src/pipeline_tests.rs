@@ -0,0 +1,187 @@
+#![cfg(test)]
+
+use super::*;
+use photon::{InternalOperation, Operation, Packet, ParameterOrder, Protocol, ProtocolValue};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Records the packets it sees, and then does the same as `verdict`
+struct Recorder {
+    name: &'static str,
+    seen: Rc<RefCell<Vec<Packet<'static>>>>,
+    verdict: fn(&mut Packet, &mut Context) -> Verdict<'static>,
+}
+
+impl Recorder {
+    fn new(name: &'static str, verdict: fn(&mut Packet, &mut Context) -> Verdict<'static>) -> (Self, Rc<RefCell<Vec<Packet<'static>>>>) {
+        let seen = Rc::new(RefCell::new(vec![]));
+        let recorder = Recorder {
+            name,
+            seen: seen.clone(),
+            verdict,
+        };
+        (recorder, seen)
+    }
+}
+
+impl PacketHandler for Recorder {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn on_send<'a>(&mut self, packet: &mut Packet<'a>, context: &mut Context) -> Verdict<'a> {
+        self.seen.borrow_mut().push(packet.clone().into_owned());
+        (self.verdict)(packet, context)
+    }
+}
+
+/// `ping_request` from the photon tests
+fn ping() -> Packet<'static> {
+    Packet::read(&[0xf3, 0x6, 0x1, 0x0, 0x1, 0x1, 0x69, 0x0, 0x0, 0x2d, 0x51], Direction::Send)
+        .unwrap()
+        .into_owned()
+}
+
+fn ping_with_time(local_time: i32) -> Packet<'static> {
+    Packet::InternalOperationRequest(InternalOperation::PingRequest { local_time }, ParameterOrder::default())
+}
+
+fn run(pipeline: &mut Pipeline, packet: Packet<'static>) -> (Vec<Packet<'static>>, Vec<Packet<'static>>) {
+    let session = Session::new();
+    let mut context = Context::new(Direction::Send, 1, None, &session);
+    let packets = pipeline
+        .run(packet, &mut context)
+        .into_iter()
+        .map(|view| match view {
+            PacketView::Packet(packet) => packet,
+            view => panic!("expected a packet, got {}", view),
+        })
+        .collect();
+    (packets, context.take_replies())
+}
+
+#[test]
+fn empty_pipeline_passes() {
+    assert_eq!(run(&mut Pipeline::new(), ping()), (vec![ping()], vec![]));
+}
+
+#[test]
+fn modify() {
+    let (first, _) = Recorder::new("first", |packet, _| {
+        if let Packet::InternalOperationRequest(InternalOperation::PingRequest { local_time }, _) = packet {
+            *local_time += 1;
+        }
+        Verdict::Pass
+    });
+    let (second, seen) = Recorder::new("second", |_, _| Verdict::Pass);
+    let mut pipeline = Pipeline::new();
+    pipeline.push(first);
+    pipeline.push(second);
+
+    assert_eq!(run(&mut pipeline, ping()).0, [ping_with_time(11602)]);
+    assert_eq!(*seen.borrow(), [ping_with_time(11602)]);
+}
+
+#[test]
+fn drop_stops_the_chain() {
+    let (first, _) = Recorder::new("first", |_, _| Verdict::Drop);
+    let (second, seen) = Recorder::new("second", |_, _| Verdict::Pass);
+    let mut pipeline = Pipeline::new();
+    pipeline.push(first);
+    pipeline.push(second);
+
+    assert!(run(&mut pipeline, ping()).0.is_empty());
+    assert!(seen.borrow().is_empty());
+}
+
+#[test]
+fn replace_and_reply() {
+    let (first, _) = Recorder::new("first", |packet, context| {
+        context.reply(Packet::InternalOperationResponse(
+            InternalOperation::PingResponse {
+                local_time: 11601,
+                server_time: 0,
+            },
+            0,
+            None,
+            ParameterOrder::default(),
+        ));
+        Verdict::Replace(vec![packet.clone().into_owned(), ping_with_time(1)])
+    });
+    let (second, seen) = Recorder::new("second", |packet, _| match packet {
+        Packet::InternalOperationRequest(InternalOperation::PingRequest { local_time: 1 }, _) => Verdict::Drop,
+        _ => Verdict::Pass,
+    });
+    let mut pipeline = Pipeline::new();
+    pipeline.push(first);
+    pipeline.push(second);
+
+    let (packets, replies) = run(&mut pipeline, ping());
+    assert_eq!(packets, [ping()]);
+    assert_eq!(*seen.borrow(), [ping(), ping_with_time(1)]);
+    assert_eq!(replies.len(), 1);
+}
+
+#[test]
+fn enable_and_disable() {
+    let (first, _) = Recorder::new("drop", |_, _| Verdict::Drop);
+    let (second, seen) = Recorder::new("second", |_, _| Verdict::Pass);
+    let mut pipeline = Pipeline::new();
+    pipeline.push(first);
+    pipeline.push(second);
+    pipeline.push(Logger);
+
+    assert!(pipeline.set_enabled("drop", false));
+    assert!(!pipeline.set_enabled("unknown", false));
+    assert!(!pipeline.is_enabled("drop"));
+    assert_eq!(
+        pipeline.handlers().collect::<Vec<_>>(),
+        [("drop", false), ("second", true), ("logger", true)]
+    );
+
+    let leave = || Packet::OperationRequest(Operation::LeaveRequest { is_inactive: None }, ParameterOrder::default());
+    assert_eq!(run(&mut pipeline, leave()).0, [leave()]);
+    assert_eq!(*seen.borrow(), [leave()]);
+
+    pipeline.set_enabled("drop", true);
+    assert!(run(&mut pipeline, leave()).0.is_empty());
+}
+
+/// Removes group 2 instead of group 1 in raw ChangeGroups requests
+struct OtherGroup;
+
+impl PacketHandler for OtherGroup {
+    fn name(&self) -> &str {
+        "other group"
+    }
+
+    fn on_send_view<'a>(&mut self, view: &mut PacketView<'a>, _context: &mut Context) -> Verdict<'a> {
+        if let Some(params) = view.parameters_mut() {
+            params.insert(239, ProtocolValue::ByteArray(vec![2]));
+        }
+        Verdict::Pass
+    }
+}
+
+#[test]
+fn raw_views() {
+    let (recorder, seen) = Recorder::new("recorder", |_, _| Verdict::Drop);
+    let mut pipeline = Pipeline::new();
+    pipeline.push(OtherGroup);
+    pipeline.push(recorder);
+
+    // a ChangeGroups request that removes group 1, which is not implemented as an `Operation`
+    let data = [0xf3, 0x2, 0xf8, 0x0, 0x1, 0xef, 0x78, 0x0, 0x0, 0x0, 0x1, 0x1];
+    let view = PacketView::read(&data, Direction::Send, Protocol::GpBinaryV16);
+    let session = Session::new();
+    let mut context = Context::new(Direction::Send, 1, None, &session);
+    let views = pipeline.run(view, &mut context);
+
+    // handlers that only look at packets pass it on
+    assert!(seen.borrow().is_empty());
+    assert_eq!(views.len(), 1);
+    assert_eq!(
+        views.into_iter().next().unwrap().into_vec_with_protocol(Protocol::GpBinaryV16).unwrap(),
+        [0xf3, 0x2, 0xf8, 0x0, 0x1, 0xef, 0x78, 0x0, 0x0, 0x0, 0x1, 0x2]
+    );
+}
@@ -0,0 +1,569 @@
+//! Rewrites packets using rules from a TOML or JSON config, so common changes do not need their own `PacketHandler`. Rules also apply
+//! to the raw parameters of packets that can not be read as a `Packet`.
+//!
+//! Each rule matches packets on their direction, kind, operation or event code and parameters, and then changes their parameters.
+//! Parameters are selected with a path of keys separated by `.`, starting with the parameter code. Keys that are numbers up to 255
+//! are bytes, or indices in arrays. Other keys are strings, unless prefixed with `int:` or `string:`. Values are written like
+//! serialized `ProtocolValue`s, such as `{ Byte = 12 }` or `{ "String": "name" }`. Keys and values must have the types of the
+//! `Array` or `Dictionary` they are put in, and the last element of an `Array` can not be removed.
+//!
+//! ```toml
+//! # force max players to 12 when changing the room properties
+//! [[rules]]
+//! name = "max players"
+//! match = { direction = "send", kind = "operation_request", code = 252 }
+//! actions = [{ action = "set", path = "251.255", value = { Byte = 12 } }]
+//! ```
+
+use log::{debug, info, warn};
+use photon::{Direction, Packet, PacketReadError, PacketView, PacketWriteError, ProtocolValue};
+use photon_core::{ParameterTable, PhotonPacket};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::fmt::{Display, Formatter};
+use std::rc::Rc;
+
+/// Rules to apply to every packet, in order.
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct RuleSet {
+    /// Only report what would change, without changing any packets
+    #[serde(default)]
+    pub dry_run: bool,
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Rule {
+    /// Shown when reporting changes
+    #[serde(default)]
+    pub name: String,
+    /// The packets to apply this rule to. Matches all packets by default.
+    #[serde(rename = "match", default)]
+    pub matches: Match,
+    pub actions: Vec<Action>,
+}
+
+/// Selects packets. Properties that are not set match everything.
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Match {
+    pub direction: Option<RuleDirection>,
+    pub kind: Option<PacketKind>,
+    /// The operation or event code
+    pub code: Option<u8>,
+    /// Parameters that must be present, or have a specific value
+    pub params: Vec<Condition>,
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Condition {
+    pub path: Path,
+    /// The value the parameter must have. If not set, it only has to be present.
+    pub equals: Option<ProtocolValue<'static>>,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleDirection {
+    Send,
+    Recv,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PacketKind {
+    Init,
+    InitResponse,
+    OperationRequest,
+    OperationResponse,
+    Event,
+    InternalOperationRequest,
+    InternalOperationResponse,
+    Message,
+    RawMessage,
+}
+
+/// A change to the parameters of a packet.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Action {
+    /// Adds the value, or replaces the existing one
+    Set {
+        path: Path,
+        value: ProtocolValue<'static>,
+    },
+    Remove {
+        path: Path,
+    },
+    /// Adds the value to the end of an array
+    Append {
+        path: Path,
+        value: ProtocolValue<'static>,
+    },
+}
+
+/// The location of a parameter, or of a value nested in it.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Path {
+    code: u8,
+    keys: Vec<Key>,
+}
+
+/// A key in a hashtable or dictionary, or an index in an array.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Key {
+    Byte(u8),
+    Integer(i32),
+    String(String),
+}
+
+/// A change that was made, or would be made in a dry run.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Change {
+    /// The name of the rule that made this change
+    pub rule: String,
+    pub path: String,
+    pub old: Option<ProtocolValue<'static>>,
+    pub new: Option<ProtocolValue<'static>>,
+}
+
+#[derive(Debug)]
+pub enum RuleError {
+    /// The config could not be parsed. Contains the error message.
+    Config(String),
+    InvalidPath(String),
+    PathNotFound(String),
+    /// The value at this path can not hold the new value
+    TypeMismatch(String),
+    Read(PacketReadError),
+    Write(PacketWriteError),
+}
+
+impl From<PacketReadError> for RuleError {
+    fn from(error: PacketReadError) -> Self {
+        RuleError::Read(error)
+    }
+}
+
+impl From<PacketWriteError> for RuleError {
+    fn from(error: PacketWriteError) -> Self {
+        RuleError::Write(error)
+    }
+}
+
+impl RuleSet {
+    pub fn from_toml(config: &str) -> Result<Self, RuleError> {
+        toml::from_str(config).map_err(|error| RuleError::Config(error.to_string()))
+    }
+
+    pub fn from_json(config: &str) -> Result<Self, RuleError> {
+        serde_json::from_str(config).map_err(|error| RuleError::Config(error.to_string()))
+    }
+
+    /// Reads a JSON config if it starts with `{`, or a TOML config otherwise.
+    pub fn from_config(config: &str) -> Result<Self, RuleError> {
+        if config.trim_start().starts_with('{') {
+            RuleSet::from_json(config)
+        } else {
+            RuleSet::from_toml(config)
+        }
+    }
+
+    /// Applies all matching rules to a packet, and returns what changed. In a dry run, the packet is left as-is and the changes that
+    /// would have been made are returned. If a rule fails, the packet is not changed at all.
+    pub fn apply(&self, packet: &mut Packet, direction: Direction) -> Result<Vec<Change>, RuleError> {
+        let (kind, code) = kind_and_code(packet);
+        if !self.rules.iter().any(|rule| rule.matches.matches_packet(direction, kind, code)) {
+            return Ok(vec![]);
+        }
+
+        let mut photon_packet = packet.clone().into_photon_packet()?;
+        let changes = self.apply_matching(&mut photon_packet, direction, kind, code)?;
+        if !changes.is_empty() && !self.dry_run {
+            *packet = Packet::from_photon_packet(photon_packet, direction)?.into_owned();
+        }
+        Ok(changes)
+    }
+
+    /// Like `apply`, for a packet that could only be read into raw parameters.
+    pub fn apply_raw(&self, packet: &mut PhotonPacket, direction: Direction) -> Result<Vec<Change>, RuleError> {
+        let (kind, code) = raw_kind_and_code(packet);
+        if !self.rules.iter().any(|rule| rule.matches.matches_packet(direction, kind, code)) {
+            return Ok(vec![]);
+        }
+
+        let mut changed = packet.clone();
+        let changes = self.apply_matching(&mut changed, direction, kind, code)?;
+        if !changes.is_empty() && !self.dry_run {
+            *packet = changed;
+        }
+        Ok(changes)
+    }
+
+    /// Applies the rules to a `Packet` or to raw parameters. Frames that could not be read at all are not changed.
+    pub fn apply_view(&self, view: &mut PacketView, direction: Direction) -> Result<Vec<Change>, RuleError> {
+        match view {
+            PacketView::Packet(packet) => self.apply(packet, direction),
+            PacketView::Raw(packet, _) => self.apply_raw(packet, direction),
+            PacketView::Invalid(_, _) => Ok(vec![]),
+        }
+    }
+
+    fn apply_matching(&self, packet: &mut PhotonPacket, direction: Direction, kind: PacketKind, code: Option<u8>) -> Result<Vec<Change>, RuleError> {
+        // packets without parameters, such as `Init`, have nothing to change
+        let params = match parameters_mut(packet) {
+            Some(params) => params,
+            None => return Ok(vec![]),
+        };
+
+        let mut changes = vec![];
+        for rule in self.rules.iter().filter(|rule| rule.matches.matches_packet(direction, kind, code)) {
+            if !rule.matches.matches_params(params) {
+                continue;
+            }
+            for action in &rule.actions {
+                changes.push(action.apply(params, &rule.name)?);
+            }
+        }
+        Ok(changes)
+    }
+}
+
+impl Match {
+    fn matches_packet(&self, direction: Direction, kind: PacketKind, code: Option<u8>) -> bool {
+        let direction = match direction {
+            Direction::Send => RuleDirection::Send,
+            Direction::Recv => RuleDirection::Recv,
+        };
+
+        self.direction.is_none_or(|d| d == direction) && self.kind.is_none_or(|k| k == kind) && self.code.is_none_or(|c| Some(c) == code)
+    }
+
+    fn matches_params(&self, params: &ParameterTable) -> bool {
+        self.params.iter().all(|condition| match (condition.path.get(params), &condition.equals) {
+            (Some(value), Some(expected)) => &value == expected,
+            (Some(_), None) => true,
+            (None, _) => false,
+        })
+    }
+}
+
+impl Action {
+    fn apply(&self, params: &mut ParameterTable, rule: &str) -> Result<Change, RuleError> {
+        let (path, old, new) = match self {
+            Action::Set { path, value } => (path, path.set(params, value.clone())?, Some(value.clone())),
+            Action::Remove { path } => (path, path.remove(params)?, None),
+            Action::Append { path, value } => {
+                path.append(params, value.clone())?;
+                (path, None, Some(value.clone()))
+            }
+        };
+
+        Ok(Change {
+            rule: rule.to_string(),
+            path: path.to_string(),
+            old,
+            new,
+        })
+    }
+}
+
+impl Path {
+    /// Returns a copy of the value at this path, if it exists
+    pub fn get(&self, params: &ParameterTable) -> Option<ProtocolValue<'static>> {
+        let (last, keys) = match self.keys.split_last() {
+            Some(split) => split,
+            None => return params.get(&self.code).map(|value| value.clone().into_owned()),
+        };
+
+        let mut value = params.get(&self.code)?;
+        for key in keys {
+            value = key.child(value)?;
+        }
+        key_element(last, value)
+    }
+
+    /// Sets the value at this path, and returns the old value
+    pub fn set<'a>(&self, params: &mut ParameterTable<'a>, new: ProtocolValue<'a>) -> Result<Option<ProtocolValue<'static>>, RuleError> {
+        let (last, container) = match self.container_mut(params)? {
+            Some(container) => container,
+            None => return Ok(params.insert(self.code, new).map(ProtocolValue::into_owned)),
+        };
+
+        let mismatch = || RuleError::TypeMismatch(self.to_string());
+        let old = match (container, new) {
+            (ProtocolValue::Hashtable(map), new) => map.insert(last.to_value(), new),
+            (
+                ProtocolValue::Dictionary {
+                    key_type,
+                    value_type,
+                    entries,
+                },
+                new,
+            ) if is_of_type(&last.to_value(), *key_type) && is_of_type(&new, *value_type) => entries.insert(last.to_value(), new),
+            (ProtocolValue::Array(element_type, vec), new) if new.type_code() == *element_type => Some(std::mem::replace(self.slot(vec, last)?, new)),
+            (ProtocolValue::ObjectArray(vec), new) => Some(std::mem::replace(self.slot(vec, last)?, new)),
+            (ProtocolValue::StringArray(vec), ProtocolValue::String(new)) => {
+                Some(ProtocolValue::String(std::mem::replace(self.slot(vec, last)?, new)))
+            }
+            (ProtocolValue::IntegerArray(vec), ProtocolValue::Integer(new)) => {
+                Some(ProtocolValue::Integer(std::mem::replace(self.slot(vec, last)?, new)))
+            }
+            (ProtocolValue::ByteArray(vec), ProtocolValue::Byte(new)) => Some(ProtocolValue::Byte(std::mem::replace(self.slot(vec, last)?, new))),
+            _ => return Err(mismatch()),
+        };
+        Ok(old.map(ProtocolValue::into_owned))
+    }
+
+    /// Removes the value at this path, and returns it
+    pub fn remove(&self, params: &mut ParameterTable) -> Result<Option<ProtocolValue<'static>>, RuleError> {
+        let (last, container) = match self.container_mut(params)? {
+            Some(container) => container,
+            None => return Ok(params.shift_remove(&self.code).map(ProtocolValue::into_owned)),
+        };
+
+        fn remove_index<T>(vec: &mut Vec<T>, key: &Key) -> Option<T> {
+            key.index().filter(|index| *index < vec.len()).map(|index| vec.remove(index))
+        }
+
+        let old = match container {
+            ProtocolValue::Hashtable(map) | ProtocolValue::Dictionary { entries: map, .. } => map.shift_remove(&last.to_value()),
+            ProtocolValue::Array(_, vec) | ProtocolValue::ObjectArray(vec) => remove_index(vec, last),
+            ProtocolValue::StringArray(vec) => remove_index(vec, last).map(ProtocolValue::String),
+            ProtocolValue::IntegerArray(vec) => remove_index(vec, last).map(ProtocolValue::Integer),
+            ProtocolValue::ByteArray(vec) => remove_index(vec, last).map(ProtocolValue::Byte),
+            _ => return Err(RuleError::TypeMismatch(self.to_string())),
+        };
+        Ok(old.map(ProtocolValue::into_owned))
+    }
+
+    /// Adds a value to the end of the array at this path
+    pub fn append<'a>(&self, params: &mut ParameterTable<'a>, new: ProtocolValue<'a>) -> Result<(), RuleError> {
+        let array = match self.container_mut(params)? {
+            Some((last, container)) => last.child_mut(container),
+            None => params.get_mut(&self.code),
+        };
+
+        match (array.ok_or_else(|| RuleError::PathNotFound(self.to_string()))?, new) {
+            (ProtocolValue::Array(element_type, vec), new) if new.type_code() == *element_type => vec.push(new),
+            (ProtocolValue::ObjectArray(vec), new) => vec.push(new),
+            (ProtocolValue::StringArray(vec), ProtocolValue::String(new)) => vec.push(new),
+            (ProtocolValue::IntegerArray(vec), ProtocolValue::Integer(new)) => vec.push(new),
+            (ProtocolValue::ByteArray(vec), ProtocolValue::Byte(new)) => vec.push(new),
+            _ => return Err(RuleError::TypeMismatch(self.to_string())),
+        }
+        Ok(())
+    }
+
+    /// Returns the last key and the value that contains it, or `None` if this path is a parameter
+    fn container_mut<'t, 'a>(&self, params: &'t mut ParameterTable<'a>) -> Result<Option<(&Key, &'t mut ProtocolValue<'a>)>, RuleError> {
+        let (last, keys) = match self.keys.split_last() {
+            Some(split) => split,
+            None => return Ok(None),
+        };
+
+        let not_found = || RuleError::PathNotFound(self.to_string());
+        let mut value = params.get_mut(&self.code).ok_or_else(not_found)?;
+        for key in keys {
+            value = key.child_mut(value).ok_or_else(not_found)?;
+        }
+        Ok(Some((last, value)))
+    }
+
+    fn slot<'v, T>(&self, vec: &'v mut [T], key: &Key) -> Result<&'v mut T, RuleError> {
+        key.index()
+            .and_then(move |index| vec.get_mut(index))
+            .ok_or_else(|| RuleError::PathNotFound(self.to_string()))
+    }
+}
+
+impl Key {
+    fn to_value(&self) -> ProtocolValue<'static> {
+        match self {
+            Key::Byte(key) => ProtocolValue::Byte(*key),
+            Key::Integer(key) => ProtocolValue::Integer(*key),
+            Key::String(key) => ProtocolValue::String(key.clone().into()),
+        }
+    }
+
+    fn index(&self) -> Option<usize> {
+        match self {
+            Key::Byte(index) => Some(*index as usize),
+            Key::Integer(index) if *index >= 0 => Some(*index as usize),
+            _ => None,
+        }
+    }
+
+    /// The value in a hashtable, dictionary or array of values
+    fn child<'v, 'a>(&self, value: &'v ProtocolValue<'a>) -> Option<&'v ProtocolValue<'a>> {
+        match value {
+            ProtocolValue::Hashtable(map) | ProtocolValue::Dictionary { entries: map, .. } => map.get(&self.to_value()),
+            ProtocolValue::Array(_, vec) | ProtocolValue::ObjectArray(vec) => vec.get(self.index()?),
+            _ => None,
+        }
+    }
+
+    fn child_mut<'v, 'a>(&self, value: &'v mut ProtocolValue<'a>) -> Option<&'v mut ProtocolValue<'a>> {
+        match value {
+            ProtocolValue::Hashtable(map) | ProtocolValue::Dictionary { entries: map, .. } => map.get_mut(&self.to_value()),
+            ProtocolValue::Array(_, vec) | ProtocolValue::ObjectArray(vec) => vec.get_mut(self.index()?),
+            _ => None,
+        }
+    }
+}
+
+/// Whether a value has the given `Dictionary` key or value type, where 0 and 42 mean `object`
+fn is_of_type(value: &ProtocolValue, type_code: u8) -> bool {
+    matches!(type_code, 0 | 42) || value.type_code() == type_code
+}
+
+/// Like `Key::child`, but also returns elements of typed arrays
+fn key_element(key: &Key, value: &ProtocolValue) -> Option<ProtocolValue<'static>> {
+    match value {
+        ProtocolValue::StringArray(vec) => vec.get(key.index()?).map(|s| ProtocolValue::String(s.to_string().into())),
+        ProtocolValue::IntegerArray(vec) => vec.get(key.index()?).map(|i| ProtocolValue::Integer(*i)),
+        ProtocolValue::ByteArray(vec) => vec.get(key.index()?).map(|b| ProtocolValue::Byte(*b)),
+        value => key.child(value).map(|value| value.clone().into_owned()),
+    }
+}
+
+impl std::str::FromStr for Path {
+    type Err = RuleError;
+
+    fn from_str(path: &str) -> Result<Self, Self::Err> {
+        let mut segments = path.split('.');
+        let code = segments
+            .next()
+            .and_then(|code| code.parse().ok())
+            .ok_or_else(|| RuleError::InvalidPath(path.to_string()))?;
+        let keys = segments
+            .map(|segment| {
+                if let Some(key) = segment.strip_prefix("int:") {
+                    key.parse().map(Key::Integer).map_err(|_| RuleError::InvalidPath(path.to_string()))
+                } else if let Some(key) = segment.strip_prefix("string:") {
+                    Ok(Key::String(key.to_string()))
+                } else {
+                    Ok(segment.parse().map(Key::Byte).unwrap_or_else(|_| Key::String(segment.to_string())))
+                }
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Path { code, keys })
+    }
+}
+
+impl Display for Path {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.code)?;
+        for key in &self.keys {
+            match key {
+                Key::Byte(key) => write!(f, ".{}", key)?,
+                Key::Integer(key) => write!(f, ".int:{}", key)?,
+                Key::String(key) if key.parse::<u8>().is_ok() || key.contains(':') => write!(f, ".string:{}", key)?,
+                Key::String(key) => write!(f, ".{}", key)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for Path {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Path {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let path = String::deserialize(deserializer)?;
+        path.parse().map_err(|_| serde::de::Error::custom(format!("invalid path: {}", path)))
+    }
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "rule '{}' changed {}: {:?} -> {:?}", self.rule, self.path, self.old, self.new)
+    }
+}
+
+fn kind_and_code(packet: &Packet) -> (PacketKind, Option<u8>) {
+    match packet {
+        Packet::Init { .. } => (PacketKind::Init, None),
+        Packet::InitResponse(_) => (PacketKind::InitResponse, None),
+        Packet::OperationRequest(operation, _) => (PacketKind::OperationRequest, Some(operation.get_type())),
+        Packet::OperationResponse { parameters, .. } => (PacketKind::OperationResponse, Some(parameters.get_type())),
+        Packet::Event { parameters, .. } => (PacketKind::Event, Some(parameters.get_type())),
+        Packet::InternalOperationRequest(operation, _) => (PacketKind::InternalOperationRequest, Some(operation.get_type())),
+        Packet::InternalOperationResponse(operation, _, _, _) => (PacketKind::InternalOperationResponse, Some(operation.get_type())),
+        Packet::Message(_) => (PacketKind::Message, None),
+        Packet::RawMessage(_) => (PacketKind::RawMessage, None),
+    }
+}
+
+fn raw_kind_and_code(packet: &PhotonPacket) -> (PacketKind, Option<u8>) {
+    match packet {
+        PhotonPacket::Init(_) => (PacketKind::Init, None),
+        PhotonPacket::InitResponse(_) => (PacketKind::InitResponse, None),
+        PhotonPacket::OperationRequest(code, _) => (PacketKind::OperationRequest, Some(*code)),
+        PhotonPacket::OperationResponse(code, _, _, _) => (PacketKind::OperationResponse, Some(*code)),
+        PhotonPacket::Event(code, _) => (PacketKind::Event, Some(*code)),
+        PhotonPacket::InternalOperationRequest(code, _) => (PacketKind::InternalOperationRequest, Some(*code)),
+        PhotonPacket::InternalOperationResponse(code, _, _, _) => (PacketKind::InternalOperationResponse, Some(*code)),
+        PhotonPacket::Message(_) => (PacketKind::Message, None),
+        PhotonPacket::RawMessage(_) => (PacketKind::RawMessage, None),
+    }
+}
+
+fn parameters_mut<'p, 'a>(packet: &'p mut PhotonPacket<'a>) -> Option<&'p mut ParameterTable<'a>> {
+    match packet {
+        PhotonPacket::OperationRequest(_, params)
+        | PhotonPacket::OperationResponse(_, params, _, _)
+        | PhotonPacket::Event(_, params)
+        | PhotonPacket::InternalOperationRequest(_, params)
+        | PhotonPacket::InternalOperationResponse(_, params, _, _) => Some(params),
+        _ => None,
+    }
+}
+
+/// Applies a `RuleSet` that can be replaced while the handler is in a `Pipeline`.
+pub struct RulesHandler {
+    rules: Rc<RefCell<RuleSet>>,
+}
+
+impl RulesHandler {
+    pub fn new(rules: Rc<RefCell<RuleSet>>) -> Self {
+        RulesHandler { rules }
+    }
+
+    fn handle(&self, view: &mut PacketView, direction: Direction) {
+        let rules = self.rules.borrow();
+        match rules.apply_view(view, direction) {
+            Ok(changes) => {
+                for change in changes {
+                    if rules.dry_run {
+                        info!("DRY RUN: {}", change);
+                    } else {
+                        debug!("{}", change);
+                    }
+                }
+            }
+            Err(error) => warn!("Could not apply rules: {:?}, packet: {}", error, view),
+        }
+    }
+}
+
+impl crate::PacketHandler for RulesHandler {
+    fn name(&self) -> &str {
+        "rules"
+    }
+
+    fn on_send_view<'a>(&mut self, view: &mut PacketView<'a>, _context: &mut crate::Context) -> crate::Verdict<'a> {
+        self.handle(view, Direction::Send);
+        crate::Verdict::Pass
+    }
+
+    fn on_recv_view<'a>(&mut self, view: &mut PacketView<'a>, _context: &mut crate::Context) -> crate::Verdict<'a> {
+        self.handle(view, Direction::Recv);
+        crate::Verdict::Pass
+    }
+}
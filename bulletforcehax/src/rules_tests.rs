@@ -0,0 +1,227 @@
+#![cfg(test)]
+
+use super::*;
+use photon::{Operation, Packet, PacketView, ParameterOrder, Protocol, ProtocolValue};
+use photon_core::ParameterTable;
+
+fn authenticate(app_version: &'static str) -> Packet<'static> {
+    Packet::OperationRequest(
+        Operation::AuthenticateRequestNoToken {
+            lobby_stats: false,
+            app_version: app_version.into(),
+            app_id: "8c2cad3e-2e3f-4941-9044-b390ff2c4956".into(),
+            region: Some("eu".into()),
+            user_id: None,
+            client_auth_type: None,
+            client_auth_params: None,
+            client_auth_data: None,
+        },
+        ParameterOrder::default(),
+    )
+}
+
+fn set_properties(properties: Vec<(ProtocolValue<'static>, ProtocolValue<'static>)>) -> Packet<'static> {
+    Packet::OperationRequest(
+        Operation::SetPropertiesGame {
+            properties: properties.into_iter().collect(),
+            expected_properties: None,
+            broadcast: true,
+            event_forward: false,
+        },
+        ParameterOrder::default(),
+    )
+}
+
+fn properties() -> Packet<'static> {
+    set_properties(vec![
+        (ProtocolValue::Byte(255), ProtocolValue::Byte(8)),
+        (ProtocolValue::String("gameMode".into()), ProtocolValue::String("ffa".into())),
+        (ProtocolValue::String("maps".into()), ProtocolValue::StringArray(vec!["Urban".into()])),
+    ])
+}
+
+const RULES: &str = r#"
+[[rules]]
+name = "max players"
+match = { direction = "send", kind = "operation_request", code = 252 }
+actions = [
+    { action = "set", path = "251.255", value = { Byte = 12 } },
+    { action = "remove", path = "251.gameMode" },
+    { action = "append", path = "251.maps", value = { String = "Desert" } },
+]
+"#;
+
+#[test]
+fn set_remove_and_append() {
+    let rules = RuleSet::from_toml(RULES).unwrap();
+    let mut packet = properties();
+
+    let changes = rules.apply(&mut packet, Direction::Send).unwrap();
+    assert_eq!(
+        packet,
+        set_properties(vec![
+            (ProtocolValue::Byte(255), ProtocolValue::Byte(12)),
+            (
+                ProtocolValue::String("maps".into()),
+                ProtocolValue::StringArray(vec!["Urban".into(), "Desert".into()])
+            ),
+        ])
+    );
+    assert_eq!(
+        changes.iter().map(ToString::to_string).collect::<Vec<_>>(),
+        [
+            "rule 'max players' changed 251.255: Some(Byte(8)) -> Some(Byte(12))",
+            "rule 'max players' changed 251.gameMode: Some(String(\"ffa\")) -> None",
+            "rule 'max players' changed 251.maps: None -> Some(String(\"Desert\"))",
+        ]
+    );
+}
+
+#[test]
+fn dry_run() {
+    let mut rules = RuleSet::from_toml(RULES).unwrap();
+    rules.dry_run = true;
+    let mut packet = properties();
+
+    assert_eq!(rules.apply(&mut packet, Direction::Send).unwrap().len(), 3);
+    assert_eq!(packet, properties());
+}
+
+#[test]
+fn not_matching() {
+    let rules = RuleSet::from_toml(RULES).unwrap();
+
+    let mut packet = properties();
+    assert!(rules.apply(&mut packet, Direction::Recv).unwrap().is_empty());
+    assert_eq!(packet, properties());
+
+    let mut packet = authenticate("1.0");
+    assert!(rules.apply(&mut packet, Direction::Send).unwrap().is_empty());
+    assert_eq!(packet, authenticate("1.0"));
+}
+
+#[test]
+fn conditions() {
+    let rules = RuleSet::from_json(
+        r#"{
+            "rules": [{
+                "name": "version",
+                "match": { "params": [{ "path": "220", "equals": { "String": "1.0" } }] },
+                "actions": [{ "action": "set", "path": "220", "value": { "String": "2.0" } }]
+            }]
+        }"#,
+    )
+    .unwrap();
+
+    let mut packet = authenticate("1.0");
+    assert_eq!(rules.apply(&mut packet, Direction::Send).unwrap().len(), 1);
+    assert_eq!(packet, authenticate("2.0"));
+
+    // the condition no longer holds
+    assert!(rules.apply(&mut packet, Direction::Send).unwrap().is_empty());
+}
+
+#[test]
+fn errors() {
+    let rules = RuleSet::from_toml(
+        r#"
+        [[rules]]
+        actions = [{ action = "set", path = "251.gameMode.0", value = { Byte = 1 } }]
+        "#,
+    )
+    .unwrap();
+    let mut packet = properties();
+    assert!(matches!(rules.apply(&mut packet, Direction::Send), Err(RuleError::TypeMismatch(_))));
+    assert_eq!(packet, properties());
+
+    let rules = RuleSet::from_toml(
+        r#"
+        [[rules]]
+        actions = [{ action = "set", path = "251.maps.0", value = { Byte = 1 } }]
+        "#,
+    )
+    .unwrap();
+    assert!(matches!(rules.apply(&mut packet, Direction::Send), Err(RuleError::TypeMismatch(_))));
+
+    assert!(matches!(
+        RuleSet::from_toml("[[rules]]\nactions = [{ action = \"remove\", path = \"256\" }]"),
+        Err(RuleError::Config(_))
+    ));
+    assert!(matches!(RuleSet::from_config("{ \"rules\": 1 }"), Err(RuleError::Config(_))));
+}
+
+#[test]
+fn typed_containers() {
+    fn table() -> ParameterTable<'static> {
+        let entries = vec![(ProtocolValue::String("kills".into()), ProtocolValue::Integer(3))];
+        vec![
+            (1, ProtocolValue::Array(105, vec![ProtocolValue::Integer(1), ProtocolValue::Integer(2)])),
+            (
+                2,
+                ProtocolValue::Dictionary {
+                    key_type: 115,
+                    value_type: 105,
+                    entries: entries.into_iter().collect(),
+                },
+            ),
+        ]
+        .into_iter()
+        .collect()
+    }
+    let path = |path: &str| path.parse::<Path>().unwrap();
+    let mut params = table();
+
+    let string = || ProtocolValue::String("abc".into());
+    assert!(matches!(path("1.0").set(&mut params, string()), Err(RuleError::TypeMismatch(_))));
+    assert!(matches!(path("1").append(&mut params, string()), Err(RuleError::TypeMismatch(_))));
+    assert!(matches!(path("2.kills").set(&mut params, string()), Err(RuleError::TypeMismatch(_))));
+    assert!(matches!(
+        path("2.int:3").set(&mut params, ProtocolValue::Integer(1)),
+        Err(RuleError::TypeMismatch(_))
+    ));
+    assert_eq!(params, table());
+
+    path("1.0").remove(&mut params).unwrap();
+    path("1.0").remove(&mut params).unwrap();
+    // an empty `Array` keeps the type of its elements
+    assert_eq!(params[&1], ProtocolValue::Array(105, vec![]));
+    assert!(matches!(path("1").append(&mut params, string()), Err(RuleError::TypeMismatch(_))));
+    path("1").append(&mut params, ProtocolValue::Integer(2)).unwrap();
+    path("1").append(&mut params, ProtocolValue::Integer(3)).unwrap();
+    path("2.deaths").set(&mut params, ProtocolValue::Integer(1)).unwrap();
+    assert_eq!(
+        params[&1],
+        ProtocolValue::Array(105, vec![ProtocolValue::Integer(2), ProtocolValue::Integer(3)])
+    );
+    assert_eq!(path("2.deaths").get(&params), Some(ProtocolValue::Integer(1)));
+}
+
+#[test]
+fn paths() {
+    let path: Path = "248.255.int:-1.string:12.gameMode".parse().unwrap();
+    assert_eq!(path.to_string(), "248.255.int:-1.string:12.gameMode");
+    assert!("".parse::<Path>().is_err());
+    assert!("name".parse::<Path>().is_err());
+    assert!("1.int:name".parse::<Path>().is_err());
+}
+
+#[test]
+fn raw_parameters() {
+    let rules = RuleSet::from_toml(
+        r#"
+        [[rules]]
+        match = { kind = "operation_request", code = 248 }
+        actions = [{ action = "remove", path = "239" }]
+        "#,
+    )
+    .unwrap();
+
+    // a ChangeGroups request that removes group 1, which is not implemented as an `Operation`
+    let data = [0xf3, 0x2, 0xf8, 0x0, 0x1, 0xef, 0x78, 0x0, 0x0, 0x0, 0x1, 0x1];
+    let mut view = PacketView::read(&data, Direction::Send, Protocol::GpBinaryV16);
+    assert_eq!(rules.apply_view(&mut view, Direction::Send).unwrap().len(), 1);
+    assert_eq!(view.into_vec_with_protocol(Protocol::GpBinaryV16).unwrap(), [0xf3, 0x2, 0xf8, 0x0, 0x0]);
+
+    let mut view = PacketView::read(&[0x12, 0x34], Direction::Send, Protocol::GpBinaryV16);
+    assert!(rules.apply_view(&mut view, Direction::Send).unwrap().is_empty());
+}